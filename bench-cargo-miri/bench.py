@@ -0,0 +1,97 @@
+#!/usr/bin/env python3
+'''
+Run the benchmark programs in this directory through `cargo miri run -Zmiri-stats` and report
+the steps per second and peak memory usage of each.
+Assumes the development version of Miri to be installed (`./miri install`) and the
+`MIRI_SYSROOT` env var to be set appropriately.
+
+Usage: ./bench.py [--save FILE] [--baseline FILE] [--threshold PERCENT] [BENCHMARK...]
+With `--baseline`, the results are compared to the ones saved earlier by `--save`, and the
+script fails if a benchmark got slower or uses more memory by more than the threshold.
+'''
+
+import argparse, json, os, re, subprocess, sys
+
+STATS = re.compile(
+    r"^miri: executed (\d+) steps in ([\d.]+)s \((\d+) steps/s\)(?:, peak memory usage (\d+) KiB)?$",
+    re.MULTILINE,
+)
+
+def benchmarks():
+    here = os.path.dirname(os.path.abspath(__file__))
+    return sorted(
+        name for name in os.listdir(here)
+        if os.path.isfile(os.path.join(here, name, "Cargo.toml"))
+    )
+
+def run(name):
+    here = os.path.dirname(os.path.abspath(__file__))
+    args = ["cargo", "miri", "run", "-q"]
+    if 'MIRI_TEST_TARGET' in os.environ:
+        args += ["--target", os.environ['MIRI_TEST_TARGET']]
+    # Only the Miri invocation that runs the program prints statistics, so building the
+    # dependencies in the same `cargo miri` call does not skew the results.
+    p = subprocess.run(args + ["--", "-Zmiri-stats"], cwd=os.path.join(here, name),
+        stdout=subprocess.DEVNULL, stderr=subprocess.PIPE)
+    stderr = p.stderr.decode("UTF-8")
+    m = STATS.search(stderr)
+    if p.returncode != 0 or m is None:
+        print(stderr, end="")
+        print("\nBENCH FAIL: `{}` did not run successfully".format(name))
+        sys.exit(1)
+    return {
+        "steps": int(m.group(1)),
+        "seconds": float(m.group(2)),
+        "steps_per_sec": int(m.group(3)),
+        "peak_rss_kib": int(m.group(4)) if m.group(4) else None,
+    }
+
+def regressions(name, result, baseline, threshold):
+    old = baseline.get(name)
+    if old is None:
+        return []
+    found = []
+    if result["steps_per_sec"] < old["steps_per_sec"] * (1 - threshold / 100):
+        found.append("{}: {} steps/s, was {}".format(
+            name, result["steps_per_sec"], old["steps_per_sec"]))
+    if result["peak_rss_kib"] and old["peak_rss_kib"] and \
+            result["peak_rss_kib"] > old["peak_rss_kib"] * (1 + threshold / 100):
+        found.append("{}: peak memory usage {} KiB, was {} KiB".format(
+            name, result["peak_rss_kib"], old["peak_rss_kib"]))
+    return found
+
+def main():
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--save", help="write the results to this JSON file")
+    parser.add_argument("--baseline", help="compare the results to this JSON file")
+    parser.add_argument("--threshold", type=float, default=10,
+        help="the slowdown or memory increase in percent that counts as regression")
+    parser.add_argument("benchmarks", nargs="*", help="the benchmarks to run (default: all)")
+    args = parser.parse_args()
+    baseline = {}
+    if args.baseline:
+        with open(args.baseline) as f:
+            baseline = json.load(f)
+
+    results = {}
+    found = []
+    print("{:<16} {:>12} {:>10} {:>12} {:>14}".format(
+        "benchmark", "steps", "seconds", "steps/s", "peak RSS (KiB)"))
+    for name in args.benchmarks or benchmarks():
+        result = run(name)
+        results[name] = result
+        print("{:<16} {:>12} {:>10.3f} {:>12} {:>14}".format(
+            name, result["steps"], result["seconds"], result["steps_per_sec"],
+            result["peak_rss_kib"] or "unknown"))
+        found += regressions(name, result, baseline, args.threshold)
+
+    if args.save:
+        with open(args.save, "w") as f:
+            json.dump(results, f, indent=2, sort_keys=True)
+    if found:
+        print("\nBENCH FAIL: regressions of more than {}%:".format(args.threshold))
+        for regression in found:
+            print("  " + regression)
+        sys.exit(1)
+
+main()
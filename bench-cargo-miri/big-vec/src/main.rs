@@ -0,0 +1,13 @@
+//! Gives every byte of a big buffer its own borrow stack, which stresses the per-byte metadata of
+//! allocations with many distinct ranges.
+
+fn main() {
+    let mut buf = vec![0u8; 1 << 16];
+    // Retag every element separately, in an order that splits ranges in the middle of the buffer.
+    for i in (0..buf.len()).rev().step_by(2).chain((0..buf.len()).step_by(2)) {
+        let elem = &mut buf[i];
+        *elem = i as u8;
+    }
+    let sum: u64 = buf.iter().map(|&b| u64::from(b)).sum();
+    assert_eq!(sum, (1 << 16) / 256 * (255 * 256 / 2));
+}
@@ -0,0 +1,20 @@
+//! Inserts into and removes from a `HashMap` in a loop, which exercises hashing, many small
+//! allocations and the growing and rehashing of the table.
+
+use std::collections::HashMap;
+
+fn main() {
+    let mut map = HashMap::new();
+    for i in 0..2000u32 {
+        map.insert(i, i.to_string());
+        // Keep the map at a few hundred entries, so that removals hit both old and new keys.
+        if i >= 300 {
+            let removed = map.remove(&(i - 300 + (i % 7))).is_some();
+            if !removed {
+                map.remove(&(i - 300));
+            }
+        }
+    }
+    let total: usize = map.values().map(|s| s.len()).sum();
+    println!("{} {}", map.len(), total);
+}
@@ -0,0 +1,19 @@
+//! Compiles a regex and matches it against a text, which exercises a large state machine with
+//! many small, branchy functions.
+
+use regex::Regex;
+
+static TEXT: &str = "\
+Miri can run binary projects and test suites of cargo projects. Contact us at miri@example.org \
+or at interpreter-team@example.com, and see 2020-01-15 and 2019-12-31 for the release notes. \
+Invalid addresses like @example.org or bob@ are not matched.";
+
+fn main() {
+    let email = Regex::new(r"[a-z0-9.-]+@[a-z0-9-]+\.[a-z]{2,}").unwrap();
+    let date = Regex::new(r"(\d{4})-(\d{2})-(\d{2})").unwrap();
+    let emails: Vec<&str> = email.find_iter(TEXT).map(|m| m.as_str()).collect();
+    assert_eq!(emails, ["miri@example.org", "interpreter-team@example.com"]);
+    let years: Vec<&str> = date.captures_iter(TEXT).map(|c| c.get(1).unwrap().as_str()).collect();
+    assert_eq!(years, ["2020", "2019"]);
+    println!("{} {}", emails.len(), years.len());
+}
@@ -0,0 +1,38 @@
+//! Serializes a nested structure to JSON and parses it back, which exercises formatting, string
+//! building and deserialization through generic visitor code.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Point {
+    x: i32,
+    y: i32,
+    label: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Shape {
+    name: String,
+    closed: bool,
+    points: Vec<Point>,
+}
+
+fn main() {
+    let shapes: Vec<Shape> = (0..20)
+        .map(|i| Shape {
+            name: format!("shape {}", i),
+            closed: i % 2 == 0,
+            points: (0..20)
+                .map(|j| Point {
+                    x: i * j,
+                    y: i - j,
+                    label: if j % 3 == 0 { Some(format!("p{}", j)) } else { None },
+                })
+                .collect(),
+        })
+        .collect();
+    let json = serde_json::to_string(&shapes).unwrap();
+    let parsed: Vec<Shape> = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, shapes);
+    println!("{}", json.len());
+}
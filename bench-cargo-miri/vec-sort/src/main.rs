@@ -0,0 +1,22 @@
+//! Sorts vectors with the stable and the unstable sort, which exercises tight loops of reads,
+//! writes and comparisons through references into one allocation.
+
+fn main() {
+    // A simple deterministic pseudo-random sequence, so that no crates are needed.
+    let mut state = 0x2545_f491_u32;
+    let mut data: Vec<u32> = (0..5000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state % 10_000
+        })
+        .collect();
+    let mut copy = data.clone();
+    data.sort();
+    copy.sort_unstable_by(|a, b| b.cmp(a));
+    copy.reverse();
+    assert_eq!(data, copy);
+    assert!(data.windows(2).all(|w| w[0] <= w[1]));
+    println!("{}", data[data.len() / 2]);
+}
@@ -0,0 +1,87 @@
+//! Alignment checks that do not depend on addresses, for `-Zmiri-symbolic-alignment-check`.
+//!
+//! Memory accesses are only checked against the alignment of the accessed allocation and the
+//! address it happens to have. Creating a reference to a field of a packed struct whose type
+//! requires more alignment than the struct guarantees is UB even when the field happens to be
+//! aligned, so that is checked based on the types alone.
+
+use rustc::mir;
+use rustc::ty;
+
+use crate::*;
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// If the next statement creates a reference to a field of a packed struct, checks that the
+    /// struct guarantees the alignment that the field's type requires.
+    fn check_packed_reference(&mut self) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        if !this.machine.symbolic_alignment {
+            return Ok(());
+        }
+        let frame = match this.stack().last() {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+        // Frames that are unwinding have no current block.
+        let block = match frame.block {
+            Some(block) => block,
+            None => return Ok(()),
+        };
+        let place = match frame.body.basic_blocks()[block].statements.get(frame.stmt) {
+            Some(mir::Statement { kind: mir::StatementKind::Assign(assign), .. }) =>
+                match &assign.1 {
+                    mir::Rvalue::Ref(_, _, place) => place.clone(),
+                    _ => return Ok(()),
+                },
+            _ => return Ok(()),
+        };
+
+        // Project to the referenced place, remembering the innermost packed struct on the way.
+        // A dereference starts over: the pointee's alignment is guaranteed by its type.
+        let mut place_ty = this.eval_place(&mir::Place::from(place.local))?;
+        let mut packed = None;
+        for elem in place.projection.iter() {
+            match elem {
+                mir::ProjectionElem::Field(field, _) =>
+                    if let ty::Adt(adt, _) = place_ty.layout.ty.kind {
+                        if adt.repr.packed() {
+                            let name = adt.non_enum_variant().fields[field.index()].ident;
+                            packed = Some((place_ty.layout.ty, name));
+                        }
+                    },
+                mir::ProjectionElem::Deref => packed = None,
+                _ => {}
+            }
+            place_ty = this.place_projection(place_ty, elem)?;
+        }
+        let (struct_ty, field) = match packed {
+            Some(packed) => packed,
+            None => return Ok(()),
+        };
+        let mplace = this.force_allocation(place_ty)?;
+        let required = mplace.layout.align.abi;
+        if mplace.align < required {
+            let notes = vec![
+                "the fields of a `#[repr(packed)]` struct may be unaligned even if they happen to \
+                 be aligned in this execution"
+                    .to_owned(),
+                "copy the field out of the struct instead, or use a raw pointer and \
+                 `ptr::read_unaligned`"
+                    .to_owned(),
+            ];
+            let msg = format!(
+                "creating a reference to field `{}` of packed struct `{}`: its type `{}` requires \
+                 an alignment of {} bytes, but the field is only guaranteed to be {}-byte aligned",
+                field,
+                struct_ty,
+                mplace.layout.ty,
+                required.bytes(),
+                mplace.align.bytes(),
+            );
+            let kind = UbKind::Unaligned;
+            throw_machine_stop!(TerminationInfo::UndefinedBehavior { kind, msg, notes });
+        }
+        Ok(())
+    }
+}
@@ -44,15 +44,7 @@ impl rustc_driver::Callbacks for MiriCompilerCalls {
                         if let hir::ItemKind::Fn(.., body_id) = i.kind {
                             if i.attrs.iter().any(|attr| attr.check_name(rustc_span::symbol::sym::test))
                             {
-                                let config = MiriConfig {
-                                    validate: true,
-                                    communicate: false,
-                                    ignore_leaks: false,
-                                    excluded_env_vars: vec![],
-                                    args: vec![],
-                                    seed: None,
-                                    tracked_pointer_tag: None,
-                                };
+                                let config = MiriConfig::default();
                                 let did = self.0.hir().body_owner_def_id(body_id);
                                 println!("running test: {}", self.0.def_path_debug_str(did));
                                 miri::eval_main(self.0, did, config);
@@ -65,15 +57,7 @@ impl rustc_driver::Callbacks for MiriCompilerCalls {
                 }
                 tcx.hir().krate().visit_all_item_likes(&mut Visitor(tcx));
             } else if let Some((entry_def_id, _)) = tcx.entry_fn(LOCAL_CRATE) {
-                let config = MiriConfig {
-                    validate: true,
-                    communicate: false,
-                    ignore_leaks: false,
-                    excluded_env_vars: vec![],
-                    args: vec![],
-                    seed: None,
-                    tracked_pointer_tag: None,
-                };
+                let config = MiriConfig::default();
                 miri::eval_main(tcx, entry_def_id, config);
 
                 compiler.session().abort_if_errors();
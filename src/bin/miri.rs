@@ -17,6 +17,8 @@ extern crate rustc_span;
 
 use std::convert::TryFrom;
 use std::env;
+use std::ops::Range;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use hex::FromHexError;
@@ -27,6 +29,8 @@ use rustc_interface::{interface, Queries};
 
 struct MiriCompilerCalls {
     miri_config: miri::MiriConfig,
+    /// If set, run the program once for each of these seeds.
+    many_seeds: Option<Range<u64>>,
 }
 
 impl rustc_driver::Callbacks for MiriCompilerCalls {
@@ -45,10 +49,33 @@ impl rustc_driver::Callbacks for MiriCompilerCalls {
             // Add filename to `miri` arguments.
             config.args.insert(0, compiler.input().filestem().to_string());
 
-            if let Some(return_code) = miri::eval_main(tcx, entry_def_id, config) {
-                std::process::exit(
-                    i32::try_from(return_code).expect("Return value was too large!"),
-                );
+            if let Some(seeds) = self.many_seeds.clone() {
+                for seed in seeds {
+                    let mut config = config.clone();
+                    config.seed = Some(seed);
+                    let outcome = miri::eval_main(tcx, entry_def_id, config);
+                    match outcome.return_code {
+                        Some(0) => {}
+                        Some(return_code) => {
+                            eprintln!("the program returned {} with seed {}", return_code, seed);
+                            std::process::exit(
+                                i32::try_from(return_code).expect("Return value was too large!"),
+                            );
+                        }
+                        None => {
+                            eprintln!("the program failed with seed {}", seed);
+                            exit_with_error(outcome.error);
+                        }
+                    }
+                }
+            } else {
+                let outcome = miri::eval_main(tcx, entry_def_id, config);
+                if let Some(return_code) = outcome.return_code {
+                    std::process::exit(
+                        i32::try_from(return_code).expect("Return value was too large!"),
+                    );
+                }
+                exit_with_error(outcome.error);
             }
         });
 
@@ -58,6 +85,12 @@ impl rustc_driver::Callbacks for MiriCompilerCalls {
     }
 }
 
+/// Exits with the exit code for the given kind of evaluation error (see `EvalErrorKind::exit_code`).
+fn exit_with_error(error: Option<miri::EvalErrorKind>) -> ! {
+    let code = error.map_or(1, miri::EvalErrorKind::exit_code);
+    std::process::exit(code)
+}
+
 fn init_early_loggers() {
     // Note that our `extern crate log` is *not* the same as rustc's; as a result, we have to
     // initialize them both, and we always initialize `miri`'s first.
@@ -125,15 +158,74 @@ fn compile_time_sysroot() -> Option<String> {
     })
 }
 
+/// Parses the argument of a flag that takes a probability.
+fn parse_rate(flag: &str, rate: &str) -> f64 {
+    match rate.parse::<f64>() {
+        Ok(rate) if rate >= 0.0 && rate <= 1.0 => rate,
+        _ => panic!("{} requires a number between 0 and 1 as the argument", flag),
+    }
+}
+
+/// Prints the explanation of a Miri error code, like `rustc --explain`.
+fn explain(code: &str) -> ! {
+    match miri::explain(code) {
+        Some(error_code) => {
+            println!("{}: {}\n\n{}", error_code.code, error_code.title, error_code.explanation);
+            std::process::exit(0)
+        }
+        None => {
+            eprintln!("`{}` is not a Miri error code; the codes are:", code);
+            for error_code in miri::ERROR_CODES {
+                eprintln!("    {}: {}", error_code.code, error_code.title);
+            }
+            std::process::exit(1)
+        }
+    }
+}
+
 fn main() {
     init_early_loggers();
 
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("--explain") {
+        explain(&args.next().unwrap_or_default());
+    }
+
     // Parse our arguments and split them across `rustc` and `miri`.
     let mut validate = true;
+    let mut box_uniqueness = true;
     let mut communicate = false;
     let mut ignore_leaks = false;
     let mut seed: Option<u64> = None;
+    let mut many_seeds: Option<Range<u64>> = None;
+    let mut coverage_file: Option<PathBuf> = None;
+    let mut stack_limit: Option<usize> = None;
+    let mut num_cpus = miri::NUM_CPUS;
+    let mut deterministic_hashmap = false;
+    let mut fs_case_insensitive = false;
+    let mut float_jitter = false;
+    let mut cpu_features: Option<Vec<String>> = None;
+    let mut udp_drop_rate = 0.0;
+    let mut udp_reorder_rate = 0.0;
+    let mut validation = miri::ValidationMode::Shallow;
+    let mut ignored_validity = vec![];
+    let mut warning_levels = vec![];
+    let mut track_last_write = false;
+    let mut dealloc_backtraces = false;
+    let mut stats = false;
+    let mut shim_report = false;
+    let mut symbolic_alignment = false;
+    let mut ptr_comparison = miri::PtrComparisonMode::Addresses;
+    let mut address_randomization = true;
+    let mut debug = false;
+    let mut trace_file: Option<PathBuf> = None;
+    let mut trace_terminators = false;
+    let mut tracked_calls = vec![];
+    let mut log_file: Option<String> = None;
+    let mut log_format = miri::LogFormat::Text;
     let mut tracked_pointer_tag: Option<miri::PtrId> = None;
+    let mut break_on_tag: Option<miri::PtrId> = None;
+    let mut break_on_alloc: Option<miri::AllocId> = None;
     let mut rustc_args = vec![];
     let mut miri_args = vec![];
     let mut after_dashdash = false;
@@ -150,12 +242,48 @@ fn main() {
                 "-Zmiri-disable-validation" => {
                     validate = false;
                 }
+                "-Zmiri-disable-box-uniqueness" => {
+                    box_uniqueness = false;
+                }
+                "-Zmiri-no-address-randomization" => {
+                    address_randomization = false;
+                }
+                "-Zmiri-debug" => {
+                    debug = true;
+                }
+                "-Zmiri-trace-terminators" => {
+                    trace_terminators = true;
+                }
+                "-Zmiri-symbolic-alignment-check" => {
+                    symbolic_alignment = true;
+                }
+                "-Zmiri-track-last-write" => {
+                    track_last_write = true;
+                }
+                "-Zmiri-dealloc-backtraces" => {
+                    dealloc_backtraces = true;
+                }
+                "-Zmiri-stats" => {
+                    stats = true;
+                }
+                "-Zmiri-shim-report" => {
+                    shim_report = true;
+                }
                 "-Zmiri-disable-isolation" => {
                     communicate = true;
                 }
                 "-Zmiri-ignore-leaks" => {
                     ignore_leaks = true;
                 }
+                "-Zmiri-deterministic-hashmap" => {
+                    deterministic_hashmap = true;
+                }
+                "-Zmiri-fs-case-insensitive" => {
+                    fs_case_insensitive = true;
+                }
+                "-Zmiri-float-jitter" => {
+                    float_jitter = true;
+                }
                 "--" => {
                     after_dashdash = true;
                 }
@@ -183,6 +311,105 @@ fn main() {
                     bytes[..seed_raw.len()].copy_from_slice(&seed_raw);
                     seed = Some(u64::from_be_bytes(bytes));
                 }
+                arg if arg.starts_with("-Zmiri-many-seeds=") => {
+                    let range = arg.trim_start_matches("-Zmiri-many-seeds=");
+                    let parse = |bound: &str| -> u64 {
+                        bound.parse().unwrap_or_else(|err| panic!(
+                            "-Zmiri-many-seeds requires a range of the form `<from>..<to>`: {}",
+                            err
+                        ))
+                    };
+                    let (from, to) = match range.find("..") {
+                        Some(idx) => (parse(&range[..idx]), parse(&range[idx + 2..])),
+                        None => panic!("-Zmiri-many-seeds requires a range of the form `<from>..<to>`"),
+                    };
+                    many_seeds = Some(from..to);
+                }
+                arg if arg.starts_with("-Zmiri-log-file=") => {
+                    log_file = Some(arg.trim_start_matches("-Zmiri-log-file=").to_owned());
+                }
+                arg if arg.starts_with("-Zmiri-log-format=") => {
+                    let format = arg.trim_start_matches("-Zmiri-log-format=");
+                    log_format = miri::LogFormat::from_str(format).unwrap_or_else(|| {
+                        panic!("-Zmiri-log-format must be `text` or `json`, got `{}`", format)
+                    });
+                }
+                arg if arg.starts_with("-Zmiri-track-call=") => {
+                    tracked_calls.push(arg.trim_start_matches("-Zmiri-track-call=").to_owned());
+                }
+                arg if arg.starts_with("-Zmiri-trace=") => {
+                    trace_file = Some(PathBuf::from(arg.trim_start_matches("-Zmiri-trace=")));
+                }
+                arg if arg.starts_with("-Zmiri-coverage=") => {
+                    coverage_file = Some(PathBuf::from(arg.trim_start_matches("-Zmiri-coverage=")));
+                }
+                arg if arg.starts_with("-Zmiri-stack-limit=") => {
+                    let limit = arg.trim_start_matches("-Zmiri-stack-limit=");
+                    stack_limit = Some(limit.parse().unwrap_or_else(|err| panic!(
+                        "-Zmiri-stack-limit requires a valid `usize` as the argument: {}",
+                        err
+                    )));
+                }
+                arg if arg.starts_with("-Zmiri-num-cpus=") => {
+                    let n = arg.trim_start_matches("-Zmiri-num-cpus=");
+                    num_cpus = match n.parse() {
+                        // `cpu_set_t` has room for 1024 CPUs.
+                        Ok(n) if n >= 1 && n <= 1024 => n,
+                        _ => panic!("-Zmiri-num-cpus requires a number between 1 and 1024: {}", n),
+                    };
+                }
+                arg if arg.starts_with("-Zmiri-cpu-features=") => {
+                    let features = arg.trim_start_matches("-Zmiri-cpu-features=");
+                    cpu_features = Some(
+                        features.split(',').filter(|f| !f.is_empty()).map(str::to_owned).collect(),
+                    );
+                }
+                arg if arg.starts_with("-Zmiri-udp-drop-rate=") => {
+                    let rate = arg.trim_start_matches("-Zmiri-udp-drop-rate=");
+                    udp_drop_rate = parse_rate("-Zmiri-udp-drop-rate", rate);
+                }
+                arg if arg.starts_with("-Zmiri-udp-reorder-rate=") => {
+                    let rate = arg.trim_start_matches("-Zmiri-udp-reorder-rate=");
+                    udp_reorder_rate = parse_rate("-Zmiri-udp-reorder-rate", rate);
+                }
+                arg if arg.starts_with("-Zmiri-recursive-validation=") => {
+                    let mode = arg.trim_start_matches("-Zmiri-recursive-validation=");
+                    validation = miri::ValidationMode::from_str(mode).unwrap_or_else(|| {
+                        panic!("-Zmiri-recursive-validation requires `off`, `shallow` or `full`")
+                    });
+                }
+                arg if arg.starts_with("-Zmiri-ptr-comparison=") => {
+                    let mode = arg.trim_start_matches("-Zmiri-ptr-comparison=");
+                    ptr_comparison = miri::PtrComparisonMode::from_str(mode).unwrap_or_else(|| {
+                        panic!("-Zmiri-ptr-comparison requires `addresses`, `random` or `error`")
+                    });
+                }
+                arg if arg.starts_with("-Zmiri-ignore-validity=") => {
+                    let checks = arg.trim_start_matches("-Zmiri-ignore-validity=");
+                    for check in checks.split(',').filter(|c| !c.is_empty()) {
+                        let check = miri::ValidityCheck::from_str(check).unwrap_or_else(|| {
+                            panic!("-Zmiri-ignore-validity does not know the invariant `{}`", check)
+                        });
+                        ignored_validity.push(check);
+                    }
+                }
+                arg if arg.starts_with("-Zmiri-allow=")
+                    || arg.starts_with("-Zmiri-warn=")
+                    || arg.starts_with("-Zmiri-deny=") =>
+                {
+                    let (flag, categories) = arg.split_at(arg.find('=').unwrap());
+                    let level = match flag {
+                        "-Zmiri-allow" => miri::WarningLevel::Allow,
+                        "-Zmiri-warn" => miri::WarningLevel::Warn,
+                        _ => miri::WarningLevel::Deny,
+                    };
+                    for name in categories[1..].split(',').filter(|c| !c.is_empty()) {
+                        let categories = miri::WarningCategory::from_str(name).unwrap_or_else(|| {
+                            panic!("{} does not know the warning category `{}`", flag, name)
+                        });
+                        warning_levels.extend(categories.iter().map(|&category| (category, level)));
+                    }
+                }
                 arg if arg.starts_with("-Zmiri-env-exclude=") => {
                     excluded_env_vars
                         .push(arg.trim_start_matches("-Zmiri-env-exclude=").to_owned());
@@ -202,6 +429,30 @@ fn main() {
                         panic!("-Zmiri-track-pointer-tag must be a nonzero id");
                     }
                 }
+                arg if arg.starts_with("-Zmiri-break-on-tag=") => {
+                    let id: u64 = match arg.trim_start_matches("-Zmiri-break-on-tag=").parse() {
+                        Ok(id) => id,
+                        Err(err) => panic!(
+                            "-Zmiri-break-on-tag requires a valid `u64` as the argument: {}",
+                            err
+                        ),
+                    };
+                    if let Some(id) = miri::PtrId::new(id) {
+                        break_on_tag = Some(id);
+                    } else {
+                        panic!("-Zmiri-break-on-tag must be a nonzero id");
+                    }
+                }
+                arg if arg.starts_with("-Zmiri-break-on-alloc=") => {
+                    let id: u64 = match arg.trim_start_matches("-Zmiri-break-on-alloc=").parse() {
+                        Ok(id) => id,
+                        Err(err) => panic!(
+                            "-Zmiri-break-on-alloc requires a valid `u64` as the argument: {}",
+                            err
+                        ),
+                    };
+                    break_on_alloc = Some(miri::AllocId(id));
+                }
                 _ => {
                     rustc_args.push(arg);
                 }
@@ -225,20 +476,53 @@ fn main() {
     // Finally, add the default flags all the way in the beginning, but after the binary name.
     rustc_args.splice(1..1, miri::miri_default_args().iter().map(ToString::to_string));
 
+    if seed.is_some() && many_seeds.is_some() {
+        panic!("-Zmiri-seed and -Zmiri-many-seeds cannot be used together!");
+    }
+
     debug!("rustc arguments: {:?}", rustc_args);
     debug!("miri arguments: {:?}", miri_args);
     let miri_config = miri::MiriConfig {
         validate,
+        box_uniqueness,
         communicate,
         ignore_leaks,
         excluded_env_vars,
         seed,
         args: miri_args,
         tracked_pointer_tag,
+        break_on_tag,
+        break_on_alloc,
+        foreign_item_hooks: vec![],
+        coverage_file,
+        stack_limit,
+        num_cpus,
+        deterministic_hashmap,
+        fs_case_insensitive,
+        float_jitter,
+        cpu_features,
+        udp_drop_rate,
+        udp_reorder_rate,
+        validation,
+        ignored_validity,
+        warning_levels,
+        track_last_write,
+        dealloc_backtraces,
+        symbolic_alignment,
+        ptr_comparison,
+        address_randomization,
+        debug,
+        trace_file,
+        trace_terminators,
+        tracked_calls,
+        log_file,
+        log_format,
+        stats,
+        shim_report,
     };
     rustc_driver::install_ice_hook();
     let result = rustc_driver::catch_fatal_errors(move || {
-        rustc_driver::run_compiler(&rustc_args, &mut MiriCompilerCalls { miri_config }, None, None)
+        rustc_driver::run_compiler(&rustc_args, &mut MiriCompilerCalls { miri_config, many_seeds }, None, None)
     })
     .and_then(|result| result);
     std::process::exit(result.is_err() as i32);
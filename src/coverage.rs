@@ -0,0 +1,87 @@
+//! Recording which MIR statements were executed, for `-Zmiri-coverage`.
+
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rustc::mir;
+use rustc::ty;
+use rustc_data_structures::fx::FxHashMap;
+
+use crate::*;
+
+/// Coverage information for a single function.
+#[derive(Debug)]
+struct FunctionCoverage {
+    /// The number of statements (including terminators) in the body of this function.
+    total: usize,
+    /// The locations that were executed at least once.
+    executed: BTreeSet<(mir::BasicBlock, usize)>,
+}
+
+/// Coverage information for the entire program.
+#[derive(Debug, Default)]
+pub struct Coverage<'tcx> {
+    functions: FxHashMap<ty::Instance<'tcx>, FunctionCoverage>,
+}
+
+impl<'tcx> Coverage<'tcx> {
+    fn record(&mut self, instance: ty::Instance<'tcx>, body: &mir::Body<'tcx>, location: (mir::BasicBlock, usize)) {
+        self.functions
+            .entry(instance)
+            .or_insert_with(|| FunctionCoverage {
+                total: body.basic_blocks().iter().map(|block| block.statements.len() + 1).sum(),
+                executed: BTreeSet::new(),
+            })
+            .executed
+            .insert(location);
+    }
+
+    /// Renders the report: one line per function, followed by the list of executed locations.
+    fn report(&self) -> String {
+        let mut functions: Vec<(String, &FunctionCoverage)> =
+            self.functions.iter().map(|(instance, coverage)| (instance.to_string(), coverage)).collect();
+        functions.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut report = String::new();
+        for (name, coverage) in functions {
+            writeln!(report, "{}: {}/{} statements executed", name, coverage.executed.len(), coverage.total)
+                .unwrap();
+            let locations: Vec<String> =
+                coverage.executed.iter().map(|(block, stmt)| format!("{:?}[{}]", block, stmt)).collect();
+            writeln!(report, "    {}", locations.join(" ")).unwrap();
+        }
+        report
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Record the statement that is about to be executed, if coverage is enabled.
+    fn record_coverage(&mut self) {
+        let this = self.eval_context_mut();
+        if this.machine.coverage.is_none() {
+            return;
+        }
+        let (instance, body, location) = match this.stack().last() {
+            // Frames that are unwinding have no current block.
+            Some(frame) => match frame.block {
+                Some(block) => (frame.instance, frame.body, (block, frame.stmt)),
+                None => return,
+            },
+            None => return,
+        };
+        this.machine.coverage.as_mut().unwrap().record(instance, body, location);
+    }
+
+    /// Write the coverage report to `file`, if coverage is enabled.
+    fn write_coverage_report(&self, file: &Path) -> io::Result<()> {
+        let this = self.eval_context_ref();
+        match &this.machine.coverage {
+            Some(coverage) => fs::write(file, coverage.report()),
+            None => Ok(()),
+        }
+    }
+}
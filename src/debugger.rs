@@ -0,0 +1,375 @@
+//! A minimal interactive debugger, for `-Zmiri-debug`.
+//!
+//! Execution stops before the first statement of `main`, at breakpoints, and after each statement
+//! when single-stepping. While stopped, commands are read from stdin; the current frame's locals
+//! and the borrow stacks of the memory a local points to can be inspected. When stdin is closed,
+//! the debugger detaches and the program runs to completion.
+
+use std::io::{self, BufRead, Write};
+
+use rustc::mir;
+use rustc::ty::{self, layout::Variants};
+use rustc_hir::def_id::LOCAL_CRATE;
+
+use crate::*;
+
+const HELP: &str = "\
+commands:
+    s, step              execute one MIR statement (or terminator)
+    c, continue          run until the next breakpoint
+    b, break <fn>        stop when entering a function whose path contains <fn>
+    b, break <file>:<n>  stop at the first statement of line <n> in a file ending in <file>
+    d, delete <i>        remove breakpoint number <i>
+    breakpoints          list the breakpoints
+    w, where             print the stack
+    l, locals            print the locals of the current function
+    borrows <local>      print the borrow stacks of the memory the pointer in <local> points to
+    dump <local>         print the bytes of the memory the pointer in <local> points to
+    dump alloc<id>       print the bytes of the allocation with the given id
+    q, quit              stop the program";
+
+/// Values nested deeper than this are not rendered.
+const MAX_RENDER_DEPTH: usize = 4;
+/// Only this many fields or elements of a value are rendered.
+const MAX_RENDER_FIELDS: u64 = 16;
+
+/// Where execution should stop.
+#[derive(Clone, Debug)]
+enum Breakpoint {
+    /// When entering a function whose path contains this string.
+    Function(String),
+    /// At the first statement of a line, in a file whose name ends in `file`.
+    Line { file: String, line: usize },
+}
+
+impl Breakpoint {
+    fn parse(arg: &str) -> Option<Breakpoint> {
+        if arg.is_empty() {
+            return None;
+        }
+        if let Some(colon) = arg.rfind(':') {
+            if let Ok(line) = arg[colon + 1..].parse() {
+                return Some(Breakpoint::Line { file: arg[..colon].to_owned(), line });
+            }
+        }
+        Some(Breakpoint::Function(arg.to_owned()))
+    }
+}
+
+/// The state of the debugger.
+#[derive(Debug, Default)]
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    /// Whether execution stops at the next statement.
+    stepping: bool,
+    /// Whether `main` was entered already.
+    entered_main: bool,
+    /// Set when stdin is closed; the debugger then lets the program run.
+    detached: bool,
+    /// The file and line of the previous statement, so that a line breakpoint stops only once.
+    last_line: Option<(String, usize)>,
+    /// Why execution should stop at the next statement, when it was paused from elsewhere.
+    paused: Option<String>,
+}
+
+impl Debugger {
+    /// Stops execution at the next statement, e.g. because `-Zmiri-break-on-alloc` triggered.
+    pub fn pause(&mut self, reason: String) {
+        self.paused = Some(reason);
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExtPrivate<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+trait EvalContextExtPrivate<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Determines why execution should stop before the next statement, if it should.
+    fn debugger_stop_reason(&mut self) -> Option<String> {
+        let this = self.eval_context_mut();
+        let frame = this.stack().last()?;
+        // Frames that are unwinding have no current block.
+        let block = frame.block?;
+        let entering = block == mir::START_BLOCK && frame.stmt == 0;
+        let path = frame.instance.to_string();
+        let span = frame.current_source_info()?.span;
+        let loc = this.tcx.sess.source_map().lookup_char_pos(span.lo());
+        let line = (loc.file.name.to_string(), loc.line);
+        let is_main = this
+            .tcx
+            .entry_fn(LOCAL_CRATE)
+            .map_or(false, |(main_id, _)| frame.instance.def_id() == main_id);
+
+        let debugger = this.machine.debugger.as_mut().unwrap();
+        let new_line = debugger.last_line.as_ref() != Some(&line);
+        let reason = if let Some(reason) = debugger.paused.take() {
+            Some(format!("paused because {}", reason))
+        } else if entering && is_main && !debugger.entered_main {
+            debugger.entered_main = true;
+            Some(format!("stopped before `{}`", path))
+        } else if debugger.stepping {
+            Some(format!("stepped to {:?}[{}] of `{}`", block, frame.stmt, path))
+        } else {
+            debugger.breakpoints.iter().enumerate().find_map(|(i, breakpoint)| {
+                let hit = match breakpoint {
+                    Breakpoint::Function(name) => entering && path.contains(name.as_str()),
+                    Breakpoint::Line { file, line: bp_line } =>
+                        new_line && line.0.ends_with(file.as_str()) && line.1 == *bp_line,
+                };
+                if hit { Some(format!("breakpoint {} hit in `{}`", i, path)) } else { None }
+            })
+        };
+        debugger.last_line = Some(line);
+        reason
+    }
+
+    /// Prints the current location, and the source line it is at.
+    fn debugger_print_location(&self) {
+        let this = self.eval_context_ref();
+        if let Some(info) = this.stack().last().and_then(Frame::current_source_info) {
+            let source_map = this.tcx.sess.source_map();
+            eprintln!("  at {}", source_map.span_to_string(info.span));
+            if let Ok(snippet) = source_map.span_to_snippet(info.span) {
+                for text in snippet.lines().take(3) {
+                    eprintln!("  | {}", text);
+                }
+            }
+        }
+    }
+
+    /// Prints the locals of the current frame, with the names they have in the source if any.
+    fn debugger_print_locals(&mut self) {
+        let this = self.eval_context_mut();
+        let body = this.frame().body;
+        for local in body.local_decls.indices() {
+            let name = body
+                .var_debug_info
+                .iter()
+                .find(|info| info.place.projection.is_empty() && info.place.local == local)
+                .map(|info| format!(" ({})", info.name))
+                .unwrap_or_default();
+            let ty = body.local_decls[local].ty;
+            let value = match this.eval_place(&mir::Place::from(local)) {
+                Ok(place) => this.debugger_render_place(place),
+                Err(err) => format!("<{}>", err.kind),
+            };
+            eprintln!("  {:?}{}: {} = {}", local, name, ty, value);
+        }
+    }
+
+    fn debugger_render_place(&mut self, place: PlaceTy<'tcx, Tag>) -> String {
+        let this = self.eval_context_mut();
+        match this.place_to_op(place) {
+            Ok(op) => this.render_value(op, 0),
+            Err(err) => format!("<{}>", err.kind),
+        }
+    }
+
+    /// Reads the pointer stored in `local`, which is either the name of a variable or a MIR
+    /// local like `_3`.
+    fn debugger_read_pointer(&mut self, local: &str) -> InterpResult<'tcx, Pointer<Tag>> {
+        let this = self.eval_context_mut();
+        let body = this.frame().body;
+        let place = body
+            .var_debug_info
+            .iter()
+            .find(|info| &*info.name.as_str() == local)
+            .map(|info| info.place.clone())
+            .or_else(|| {
+                body.local_decls
+                    .indices()
+                    .find(|l| format!("{:?}", l) == local)
+                    .map(mir::Place::from)
+            });
+        let place = match place {
+            Some(place) => this.eval_place(&place)?,
+            None => throw_unsup_format!("no local named `{}`", local),
+        };
+        let op = this.place_to_op(place)?;
+        let ptr = this.read_scalar(op)?.not_undef()?;
+        this.force_ptr(ptr)
+    }
+
+    /// Prints the borrow stacks of the memory the pointer stored in `local` points to.
+    fn debugger_print_borrows(&mut self, local: &str) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let ptr = this.debugger_read_pointer(local)?;
+        let alloc = this.memory.get_raw(ptr.alloc_id)?;
+        match &alloc.extra.stacked_borrows {
+            Some(stacks) => {
+                eprintln!(
+                    "Borrow stacks of {:?} (`{}` is tagged {:?}):",
+                    ptr.alloc_id, local, ptr.tag
+                );
+                stacks.print_stacks();
+            }
+            None => eprintln!(
+                "Borrow stacks of {:?} are not tracked (Stacked Borrows is disabled)",
+                ptr.alloc_id
+            ),
+        }
+        Ok(())
+    }
+
+    /// Prints the contents of an allocation: `arg` is either an allocation id like `alloc42`,
+    /// or a local that holds a pointer into the allocation.
+    fn debugger_dump(&mut self, arg: &str) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let alloc_id = match arg.trim_start_matches("alloc").parse() {
+            Ok(id) if arg.starts_with("alloc") => AllocId(id),
+            _ => this.debugger_read_pointer(arg)?.alloc_id,
+        };
+        eprint!("{}", this.render_allocation(alloc_id)?);
+        Ok(())
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Renders a value based on its type and layout: scalars and pointers directly, structs,
+    /// tuples, arrays and enums field by field. Used by the debugger and `-Zmiri-track-call`.
+    fn render_value(&mut self, op: OpTy<'tcx, Tag>, depth: usize) -> String {
+        let this = self.eval_context_mut();
+        let result: InterpResult<'tcx, String> = (|| {
+            if op.layout.is_zst() && !op.layout.ty.is_unit() {
+                return Ok(format!("{}", op.layout.ty));
+            }
+            match op.layout.ty.kind {
+                ty::Bool
+                | ty::Char
+                | ty::Int(_)
+                | ty::Uint(_)
+                | ty::Float(_)
+                | ty::RawPtr(_)
+                | ty::Ref(..)
+                | ty::FnPtr(_) => Ok(format!("{:?}", *this.read_immediate(op)?)),
+                ty::Adt(adt, _) if adt.is_box() => Ok(format!("{:?}", *this.read_immediate(op)?)),
+                ty::Tuple(_) | ty::Array(..) | ty::Closure(..) | ty::Adt(..)
+                    if depth < MAX_RENDER_DEPTH =>
+                {
+                    let (name, op) = match (&op.layout.ty.kind, &op.layout.variants) {
+                        (ty::Adt(adt, _), _) if adt.is_enum() => {
+                            let (_, variant) = this.read_discriminant(op)?;
+                            let name = adt.variants[variant].ident.to_string();
+                            (Some(name), this.operand_downcast(op, variant)?)
+                        }
+                        (_, Variants::Single { .. }) => (None, op),
+                        _ => return Ok(format!("{}", op.layout.ty)),
+                    };
+                    let field_names: Vec<String> = match &op.layout.ty.kind {
+                        ty::Adt(adt, _) => {
+                            let variant = match op.layout.variants {
+                                Variants::Single { index } => index,
+                                _ => unreachable!(),
+                            };
+                            let fields = &adt.variants[variant].fields;
+                            fields.iter().map(|field| field.ident.to_string()).collect()
+                        }
+                        _ => vec![],
+                    };
+                    let count = op.layout.fields.count() as u64;
+                    let mut fields = vec![];
+                    for i in 0..count.min(MAX_RENDER_FIELDS) {
+                        let field = this.operand_field(op, i)?;
+                        let value = this.render_value(field, depth + 1);
+                        fields.push(match field_names.get(i as usize) {
+                            Some(name) => format!("{}: {}", name, value),
+                            None => value,
+                        });
+                    }
+                    if count > MAX_RENDER_FIELDS {
+                        fields.push("..".to_owned());
+                    }
+                    let fields = fields.join(", ");
+                    Ok(match (name, &op.layout.ty.kind) {
+                        (Some(name), _) => format!("{}({})", name, fields),
+                        (None, ty::Array(..)) => format!("[{}]", fields),
+                        (None, ty::Adt(..)) => format!("{} {{ {} }}", op.layout.ty, fields),
+                        (None, _) => format!("({})", fields),
+                    })
+                }
+                _ => Ok(format!("{}", op.layout.ty)),
+            }
+        })();
+        result.unwrap_or_else(|err| format!("<{}>", err.kind))
+    }
+
+    /// If the debugger is enabled and execution should stop before the next statement, reads
+    /// and executes commands until execution should go on.
+    fn debugger_step(&mut self) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        match &this.machine.debugger {
+            Some(debugger) if !debugger.detached => {}
+            _ => return Ok(()),
+        }
+        let reason = match this.debugger_stop_reason() {
+            Some(reason) => reason,
+            None => return Ok(()),
+        };
+        eprintln!("{}", reason);
+        this.debugger_print_location();
+
+        let stdin = io::stdin();
+        let mut input = stdin.lock();
+        loop {
+            eprint!("(miri) ");
+            io::stderr().flush().unwrap();
+            let mut line = String::new();
+            if input.read_line(&mut line).unwrap_or(0) == 0 {
+                eprintln!();
+                eprintln!("stdin is closed, running the program to completion");
+                this.machine.debugger.as_mut().unwrap().detached = true;
+                return Ok(());
+            }
+            let mut words = line.trim().splitn(2, ' ');
+            let command = words.next().unwrap_or("");
+            let arg = words.next().unwrap_or("").trim();
+            let debugger = this.machine.debugger.as_mut().unwrap();
+            match command {
+                "" => {}
+                "s" | "step" => {
+                    debugger.stepping = true;
+                    return Ok(());
+                }
+                "c" | "continue" => {
+                    debugger.stepping = false;
+                    return Ok(());
+                }
+                "b" | "break" => match Breakpoint::parse(arg) {
+                    Some(breakpoint) => {
+                        eprintln!("breakpoint {}: {:?}", debugger.breakpoints.len(), breakpoint);
+                        debugger.breakpoints.push(breakpoint);
+                    }
+                    None => eprintln!("usage: break <fn> or break <file>:<line>"),
+                },
+                "d" | "delete" => match arg.parse::<usize>() {
+                    Ok(i) if i < debugger.breakpoints.len() => {
+                        debugger.breakpoints.remove(i);
+                    }
+                    _ => eprintln!("no breakpoint number `{}`", arg),
+                },
+                "breakpoints" => {
+                    for (i, breakpoint) in debugger.breakpoints.iter().enumerate() {
+                        eprintln!("breakpoint {}: {:?}", i, breakpoint);
+                    }
+                }
+                "w" | "where" => {
+                    for frame in this.generate_stacktrace(None) {
+                        eprintln!("  {}", frame);
+                    }
+                }
+                "l" | "locals" => this.debugger_print_locals(),
+                "borrows" =>
+                    if let Err(err) = this.debugger_print_borrows(arg) {
+                        eprintln!("cannot print the borrow stacks of `{}`: {}", arg, err.kind);
+                    },
+                "dump" =>
+                    if let Err(err) = this.debugger_dump(arg) {
+                        eprintln!("cannot dump `{}`: {}", arg, err.kind);
+                    },
+                "q" | "quit" => throw_machine_stop!(TerminationInfo::Abort(Some(
+                    "the program was stopped in the debugger".to_owned()
+                ))),
+                "h" | "help" => eprintln!("{}", HELP),
+                _ => eprintln!("unknown command `{}`, try `help`", command),
+            }
+        }
+    }
+}
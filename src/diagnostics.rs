@@ -1,11 +1,304 @@
-use rustc_mir::interpret::InterpErrorInfo;
+use rustc::ty::{self, layout::Size};
+use rustc_data_structures::fx::FxHashMap;
+use rustc_errors::DiagnosticId;
+use rustc_mir::interpret::{AllocCheck, InterpErrorInfo};
+use rustc_span::source_map::Span;
 use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::mem;
 
 use crate::*;
 
+/// A diagnostic as it was reported to the user; collected in `EvalOutcome::diagnostics`.
+#[derive(Clone, Debug)]
+pub struct ReportedDiagnostic {
+    /// Whether this diagnostic was an error (as opposed to a note).
+    pub is_error: bool,
+    pub msg: String,
+    /// The span of the statement that was being executed when the diagnostic was emitted.
+    pub span: Option<Span>,
+    /// The code of the kind of Undefined Behavior, if the diagnostic is about UB.
+    pub code: Option<&'static str>,
+}
+
 /// Miri specific diagnostics
 pub enum NonHaltingDiagnostic {
     PoppedTrackedPointerTag(Item),
+    /// The allocation of `-Zmiri-break-on-alloc` was created, accessed or deallocated.
+    TouchedAllocation(AllocId, &'static str),
+    /// The tag of `-Zmiri-break-on-tag` was created or used.
+    TouchedTag(PtrId, &'static str),
+    /// An integer was used as a pointer to the given allocation.
+    Int2Ptr(AllocId),
+}
+
+impl NonHaltingDiagnostic {
+    /// Identifies what kind of event the diagnostic is about, independently of the pointer or
+    /// allocation involved.
+    fn kind(&self) -> (&'static str, &'static str) {
+        match self {
+            NonHaltingDiagnostic::PoppedTrackedPointerTag(_) => ("popped tag", ""),
+            NonHaltingDiagnostic::TouchedAllocation(_, action) => ("allocation", action),
+            NonHaltingDiagnostic::TouchedTag(_, action) => ("tag", action),
+            NonHaltingDiagnostic::Int2Ptr(_) => ("int2ptr", ""),
+        }
+    }
+}
+
+/// The kinds of warnings whose level `-Zmiri-allow`, `-Zmiri-warn` and `-Zmiri-deny` set.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WarningCategory {
+    /// An integer was used as a pointer. Such pointers are untagged, so Stacked Borrows cannot
+    /// check them.
+    Int2Ptr,
+    /// `read_volatile` read the same value many times in a row, which usually means the program
+    /// waits for something that cannot happen in Miri.
+    VolatileSpin,
+}
+
+/// What happens when a warning is emitted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WarningLevel {
+    Allow,
+    Warn,
+    /// The program is stopped with an error.
+    Deny,
+}
+
+impl WarningCategory {
+    pub const ALL: &'static [WarningCategory] =
+        &[WarningCategory::Int2Ptr, WarningCategory::VolatileSpin];
+
+    /// Parses a category name for the command line. `warnings` stands for all categories.
+    pub fn from_str(name: &str) -> Option<&'static [WarningCategory]> {
+        match name {
+            "warnings" => Some(WarningCategory::ALL),
+            _ => WarningCategory::ALL.iter().find(|category| category.name() == name).map(
+                |category| std::slice::from_ref(category),
+            ),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WarningCategory::Int2Ptr => "int2ptr",
+            WarningCategory::VolatileSpin => "volatile-spin",
+        }
+    }
+
+    /// The level without any command-line flags. Programs often use integers as pointers for good
+    /// reasons, so that is only reported on request.
+    fn default_level(self) -> WarningLevel {
+        match self {
+            WarningCategory::Int2Ptr => WarningLevel::Allow,
+            WarningCategory::VolatileSpin => WarningLevel::Warn,
+        }
+    }
+
+    /// The level of this category, given the levels from the command line in order: later
+    /// flags override earlier ones.
+    pub fn level(self, levels: &[(WarningCategory, WarningLevel)]) -> WarningLevel {
+        levels
+            .iter()
+            .rev()
+            .find(|(category, _)| *category == self)
+            .map_or(self.default_level(), |&(_, level)| level)
+    }
+}
+
+/// Reports a warning of `category` at the current statement, unless the category is allowed. If
+/// it is denied, the program is stopped instead.
+pub fn report_warning<'tcx, 'mir>(
+    ecx: &mut InterpCx<'mir, 'tcx, Evaluator<'tcx>>,
+    category: WarningCategory,
+    msg: String,
+    mut notes: Vec<String>,
+) -> InterpResult<'tcx> {
+    match category.level(&ecx.machine.warning_levels) {
+        WarningLevel::Allow => Ok(()),
+        WarningLevel::Warn => {
+            report_msg(ecx, msg, false, notes);
+            Ok(())
+        }
+        WarningLevel::Deny => {
+            notes.push(format!("this is an error because of `-Zmiri-deny={}`", category.name()));
+            throw_machine_stop!(TerminationInfo::DeniedWarning { msg, notes })
+        }
+    }
+}
+
+/// Counts the non-halting diagnostics that are emitted more than once for the same statement.
+/// Only the first one is shown; a summary of the others is shown when execution ends.
+#[derive(Debug, Default)]
+pub struct RepeatedDiagnostics {
+    /// The index in `repeats` of each kind of diagnostic and statement that was reported.
+    index: FxHashMap<((&'static str, &'static str), Span), usize>,
+    /// The first message of each kind and statement, and how often it was repeated since.
+    repeats: Vec<(Span, String, u64)>,
+}
+
+impl RepeatedDiagnostics {
+    /// Returns whether the diagnostic should be shown, i.e. it is the first of its kind here.
+    fn first(&mut self, kind: (&'static str, &'static str), span: Span, msg: &str) -> bool {
+        let repeats = &mut self.repeats;
+        match self.index.entry((kind, span)) {
+            Entry::Occupied(entry) => {
+                repeats[*entry.get()].2 += 1;
+                false
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(repeats.len());
+                repeats.push((span, msg.to_owned(), 0));
+                true
+            }
+        }
+    }
+}
+
+/// How `-Zmiri-log-file` writes diagnostics.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Like the diagnostics on stderr, but without the source snippets.
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+impl LogFormat {
+    pub fn from_str(format: &str) -> Option<LogFormat> {
+        Some(match format {
+            "text" => LogFormat::Text,
+            "json" => LogFormat::Json,
+            _ => return None,
+        })
+    }
+}
+
+/// The file that all diagnostics are copied to, for `-Zmiri-log-file`.
+pub struct DiagnosticLog {
+    out: BufWriter<File>,
+    format: LogFormat,
+    /// The first error writing the file; later writes are skipped.
+    error: Option<io::Error>,
+}
+
+impl DiagnosticLog {
+    /// Creates the log file. `{crate}` in the path is replaced by the name of the crate, so that
+    /// every test binary of a package can get its own file.
+    pub fn new(path: &str, crate_name: &str, format: LogFormat) -> io::Result<Self> {
+        let path = path.replace("{crate}", crate_name);
+        Ok(DiagnosticLog { out: BufWriter::new(File::create(path)?), format, error: None })
+    }
+
+    fn write(
+        &mut self,
+        is_error: bool,
+        code: Option<&str>,
+        msg: &str,
+        span: Option<&str>,
+        notes: &[String],
+        backtrace: &[String],
+    ) {
+        if self.error.is_some() {
+            return;
+        }
+        let level = if is_error { "error" } else { "note" };
+        let result = match self.format {
+            LogFormat::Text => (|| {
+                match code {
+                    Some(code) => writeln!(self.out, "{}[{}]: {}", level, code, msg)?,
+                    None => writeln!(self.out, "{}: {}", level, msg)?,
+                }
+                if let Some(span) = span {
+                    writeln!(self.out, "  --> {}", span)?;
+                }
+                for note in notes {
+                    writeln!(self.out, "  = note: {}", note)?;
+                }
+                for frame in backtrace {
+                    writeln!(self.out, "  {}", frame)?;
+                }
+                writeln!(self.out)
+            })(),
+            LogFormat::Json => {
+                let list = |items: &[String]| -> String {
+                    let items: Vec<String> = items.iter().map(|item| json_string(item)).collect();
+                    format!("[{}]", items.join(","))
+                };
+                writeln!(
+                    self.out,
+                    concat!(
+                        r#"{{"level":"{}","code":{},"message":{},"#,
+                        r#""span":{},"notes":{},"backtrace":{}}}"#,
+                    ),
+                    level,
+                    code.map_or("null".to_owned(), json_string),
+                    json_string(msg),
+                    span.map_or("null".to_owned(), json_string),
+                    list(notes),
+                    list(backtrace),
+                )
+            }
+        };
+        if let Err(err) = result {
+            self.error = Some(err);
+        }
+    }
+
+    /// Writes everything to the file, and returns the first error that occurred doing so.
+    pub fn finish(mut self) -> io::Result<()> {
+        match self.error {
+            Some(err) => Err(err),
+            None => self.out.flush(),
+        }
+    }
+}
+
+/// Quotes `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Determines which kind of error `e` is, or `None` if it is not an error but a regular
+/// termination of the program (e.g., via `exit`).
+pub fn error_kind(e: &InterpError<'_>) -> Option<EvalErrorKind> {
+    // rustc puts many kinds of UB (such as dangling pointer accesses) into `Unsupported` for now,
+    // so we go by the error codes, which know about all of them.
+    if error_code(e).is_some() {
+        return Some(EvalErrorKind::UndefinedBehavior);
+    }
+    Some(match e {
+        InterpError::MachineStop(info) => {
+            let info = info.downcast_ref::<TerminationInfo>().expect("invalid MachineStop payload");
+            match info {
+                TerminationInfo::Exit(_) => return None,
+                TerminationInfo::Abort(_)
+                | TerminationInfo::Deadlock(_)
+                | TerminationInfo::Misuse(_)
+                | TerminationInfo::DeniedWarning { .. } =>
+                    EvalErrorKind::ProgramError,
+                TerminationInfo::UndefinedBehavior { .. } => EvalErrorKind::UndefinedBehavior,
+            }
+        }
+        InterpError::UndefinedBehavior(_) => EvalErrorKind::UndefinedBehavior,
+        InterpError::Unsupported(_) => EvalErrorKind::Unsupported,
+        InterpError::ResourceExhaustion(_) => EvalErrorKind::ResourceExhaustion,
+        InterpError::InvalidProgram(_) => EvalErrorKind::ProgramError,
+    })
 }
 
 /// Emit a custom diagnostic without going through the miri-engine machinery
@@ -13,44 +306,245 @@ pub fn report_diagnostic<'tcx, 'mir>(
     ecx: &InterpCx<'mir, 'tcx, Evaluator<'tcx>>,
     mut e: InterpErrorInfo<'tcx>,
 ) -> Option<i64> {
+    let mut notes = Vec::new();
     // Special treatment for some error kinds
     let msg = match e.kind {
         InterpError::MachineStop(ref info) => {
             let info = info.downcast_ref::<TerminationInfo>().expect("invalid MachineStop payload");
             match info {
                 TerminationInfo::Exit(code) => return Some(*code),
-                TerminationInfo::Abort => format!("the evaluated program aborted execution"),
+                TerminationInfo::Abort(None) => format!("the evaluated program aborted execution"),
+                TerminationInfo::Abort(Some(msg)) | TerminationInfo::Misuse(msg) => msg.clone(),
+                // The notes that were prepared when raising the error come first.
+                TerminationInfo::DeniedWarning { msg, notes: error_notes }
+                | TerminationInfo::UndefinedBehavior { msg, notes: error_notes, .. } => {
+                    notes.extend(error_notes.iter().cloned());
+                    msg.clone()
+                }
+                TerminationInfo::Deadlock(msg) =>
+                    format!("the evaluated program deadlocked: {}", msg),
             }
         }
         err_unsup!(NoMirFor(..)) => format!(
             "{}. Did you set `MIRI_SYSROOT` to a Miri-enabled sysroot? You can prepare one with `cargo miri setup`.",
             e
         ),
+        err_exhaust!(StackFrameLimitReached) => {
+            let recursion_limit = *ecx.tcx.sess.recursion_limit.get();
+            let limit = ecx.machine.stack_limit.map_or(recursion_limit, |l| l.min(recursion_limit));
+            notes.extend(recursion_note(ecx));
+            notes.push(format!(
+                "the limit can be raised with `-Zmiri-stack-limit` and the crate's `#![recursion_limit]` attribute (currently {})",
+                recursion_limit,
+            ));
+            format!("stack overflow in interpreted program: reached the limit of {} stack frames", limit)
+        }
+        err_unsup!(PointerOutOfBounds { ptr, allocation_size, .. }) => {
+            notes.extend(out_of_bounds_notes(ecx, ptr, allocation_size));
+            e.to_string()
+        }
+        err_unsup!(DanglingPointerDeref) => {
+            notes.extend(dealloc_notes(ecx));
+            e.to_string()
+        }
+        err_unsup!(ValidationFailure(ref msg)) if msg.contains("dangling") => {
+            notes.extend(dealloc_notes(ecx));
+            e.to_string()
+        }
+        err_unsup!(ValidationFailure(_))
+        | err_unsup!(InvalidBool)
+        | err_unsup!(InvalidChar(_))
+        | err_ub!(InvalidDiscriminant(_)) => {
+            notes.extend(ecx.last_write_notes());
+            e.to_string()
+        }
         InterpError::InvalidProgram(_) => bug!("This error should be impossible in Miri: {}", e),
         _ => e.to_string(),
     };
     e.print_backtrace();
-    report_msg(ecx, msg, true)
+    let code = error_code(&e.kind);
+    if let Some(code) = code {
+        notes.push(format!(
+            "this is {} ({}); for more information, try `miri --explain {}`",
+            code.title, code.code, code.code,
+        ));
+    }
+    emit_msg(ecx, msg, true, notes, code.map(|code| code.code))
+}
+
+/// Explains an out-of-bounds access to an allocation of size `alloc_size` that ends at `end`: how
+/// far outside the allocation it is, and which allocation is really at that address (if any).
+fn out_of_bounds_notes<'tcx, 'mir>(
+    ecx: &InterpCx<'mir, 'tcx, Evaluator<'tcx>>,
+    end: Pointer,
+    alloc_size: Size,
+) -> Vec<String> {
+    let mut notes = Vec::new();
+    let end_offset = end.offset.bytes();
+    // Offsets above `isize::MAX` are really negative.
+    let bits = ecx.pointer_size().bits();
+    if end_offset > (1 << (bits - 1)) - 1 {
+        let before = (1u128 << bits) - u128::from(end_offset);
+        notes.push(format!(
+            "the access ends {} byte(s) before the start of {:?}",
+            before,
+            end.alloc_id,
+        ));
+        return notes;
+    }
+    notes.push(format!(
+        "the access ends {} byte(s) past the end of {:?}",
+        end_offset - alloc_size.bytes(),
+        end.alloc_id,
+    ));
+    // If the allocation has an address, see which allocation the last accessed byte is in.
+    let base_addr = ecx.memory.extra.intptrcast.borrow().base_addr.get(&end.alloc_id).copied();
+    if let Some(base_addr) = base_addr {
+        let last = base_addr + end_offset - 1;
+        let neighbour = intptrcast::GlobalState::int_to_ptr(last, &ecx.memory).ok().filter(|ptr| {
+            let size = ecx.memory.get_size_and_align(ptr.alloc_id, AllocCheck::MaybeDead);
+            ptr.alloc_id != end.alloc_id && size.map_or(false, |(size, _)| ptr.offset < size)
+        });
+        match neighbour {
+            Some(ptr) => notes.push(format!(
+                "its last byte is at address {:#x}, which is offset {} of {:?}",
+                last,
+                ptr.offset.bytes(),
+                ptr.alloc_id,
+            )),
+            None => notes.push(format!(
+                "its last byte is at address {:#x}, which is not part of any allocation",
+                last,
+            )),
+        }
+    }
+    notes
+}
+
+/// For an error about a dangling pointer or reference: shows where the deallocated allocations
+/// that the current frame still points to were freed. The error does not say which pointer is
+/// dangling, so this looks at all the pointers stored in the locals of the current frame.
+fn dealloc_notes<'tcx, 'mir>(ecx: &InterpCx<'mir, 'tcx, Evaluator<'tcx>>) -> Vec<String> {
+    let frame = match ecx.stack().last() {
+        Some(frame) => frame,
+        None => return Vec::new(),
+    };
+    let alloc_id = |scalar: ScalarMaybeUndef<Tag>| match scalar {
+        ScalarMaybeUndef::Scalar(Scalar::Ptr(ptr)) => Some(ptr.alloc_id),
+        _ => None,
+    };
+    let mut alloc_ids = Vec::new();
+    for local in frame.locals.iter() {
+        match local.value {
+            LocalValue::Live(Operand::Immediate(Immediate::Scalar(a))) =>
+                alloc_ids.extend(alloc_id(a)),
+            LocalValue::Live(Operand::Immediate(Immediate::ScalarPair(a, b))) => {
+                alloc_ids.extend(alloc_id(a));
+                alloc_ids.extend(alloc_id(b));
+            }
+            // Locals in memory: look at the pointers stored in them.
+            LocalValue::Live(Operand::Indirect(mplace)) =>
+                if let Scalar::Ptr(ptr) = mplace.ptr {
+                    if let Ok(alloc) = ecx.memory.get_raw(ptr.alloc_id) {
+                        alloc_ids.extend(alloc.relocations().iter().map(|&(_, (_, id))| id));
+                    }
+                },
+            LocalValue::Dead | LocalValue::Uninitialized => {}
+        }
+    }
+
+    let mut notes = Vec::new();
+    let mut seen = Vec::new();
+    for alloc_id in alloc_ids {
+        if seen.contains(&alloc_id) {
+            continue;
+        }
+        seen.push(alloc_id);
+        let dealloc = ecx.machine.freed_allocations.describe(
+            alloc_id,
+            "was deallocated here",
+            ecx.tcx.sess.source_map(),
+        );
+        notes.extend(dealloc.into_iter().flatten());
+    }
+    notes
+}
+
+/// If the innermost frames of the stack consist of the same sequence of functions calling each
+/// other over and over, describe that cycle.
+fn recursion_note<'tcx, 'mir>(ecx: &InterpCx<'mir, 'tcx, Evaluator<'tcx>>) -> Option<String> {
+    let instances: Vec<_> = ecx.stack().iter().rev().map(|frame| frame.instance).collect();
+    // Find the shortest period that repeats at least twice at the top of the stack.
+    let period = (1..=instances.len() / 2)
+        .find(|&p| instances[..p] == instances[p..2 * p])?;
+    let repetitions = instances
+        .chunks(period)
+        .take_while(|chunk| *chunk == &instances[..period])
+        .count();
+    // Print the cycle from caller to callee.
+    let cycle: Vec<String> =
+        instances[..period].iter().rev().map(|instance| format!("`{}`", instance)).collect();
+    Some(format!(
+        "the innermost {} frames consist of this cycle of {} function(s), repeated: {}",
+        repetitions * period,
+        period,
+        cycle.join(" -> "),
+    ))
 }
 
 /// Report an error or note (depending on the `error` argument) at the current frame's current statement.
-/// Also emits a full stacktrace of the interpreter stack.
+/// Also emits the given extra notes and a full stacktrace of the interpreter stack.
 pub fn report_msg<'tcx, 'mir>(
     ecx: &InterpCx<'mir, 'tcx, Evaluator<'tcx>>,
     msg: String,
     error: bool,
+    notes: Vec<String>,
 ) -> Option<i64> {
-    if let Some(frame) = ecx.stack().last() {
-        let span = frame.current_source_info().unwrap().span;
+    emit_msg(ecx, msg, error, notes, None)
+}
 
+/// Like `report_msg`, with the error code of the kind of UB the error is about, if any.
+fn emit_msg<'tcx, 'mir>(
+    ecx: &InterpCx<'mir, 'tcx, Evaluator<'tcx>>,
+    msg: String,
+    error: bool,
+    notes: Vec<String>,
+    code: Option<&'static str>,
+) -> Option<i64> {
+    let span = ecx.stack().last().map(|frame| frame.current_source_info().unwrap().span);
+    ecx.machine.reported_diagnostics.borrow_mut().push(ReportedDiagnostic {
+        is_error: error,
+        msg: msg.clone(),
+        span,
+        code,
+    });
+    if let Some(log) = &mut *ecx.machine.diagnostic_log.borrow_mut() {
+        let source_map = ecx.tcx.sess.source_map();
+        let span = span.map(|span| source_map.span_to_string(span));
+        let backtrace: Vec<String> =
+            ecx.generate_stacktrace(None).iter().map(|frame| frame.to_string()).collect();
+        log.write(error, code, &msg, span.as_deref(), &notes, &backtrace);
+    }
+
+    if let Some(span) = span {
         let mut err = if error {
             let msg = format!("Miri evaluation error: {}", msg);
-            ecx.tcx.sess.struct_span_err(span, msg.as_str())
+            match code {
+                Some(code) => ecx.tcx.sess.struct_span_err_with_code(
+                    span,
+                    msg.as_str(),
+                    DiagnosticId::Error(code.to_owned()),
+                ),
+                None => ecx.tcx.sess.struct_span_err(span, msg.as_str()),
+            }
         } else {
             ecx.tcx.sess.diagnostic().span_note_diag(span, msg.as_str())
         };
         let frames = ecx.generate_stacktrace(None);
         err.span_label(span, msg);
+        for note in notes {
+            err.note(&note);
+        }
         // We iterate with indices because we need to look at the next frame (the caller).
         for idx in 0..frames.len() {
             let frame_info = &frames[idx];
@@ -65,7 +559,11 @@ pub fn report_msg<'tcx, 'mir>(
         }
         err.emit();
     } else {
-        ecx.tcx.sess.err(&msg);
+        let mut err = ecx.tcx.sess.struct_err(&msg);
+        for note in notes {
+            err.note(&note);
+        }
+        err.emit();
     }
 
     for (i, frame) in ecx.stack().iter().enumerate() {
@@ -92,17 +590,89 @@ pub fn register_diagnostic(e: NonHaltingDiagnostic) {
 
 impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
 pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
-    /// Emit all diagnostics that were registed with `register_diagnostics`
-    fn process_diagnostics(&self) {
+    /// Emit all diagnostics that were registed with `register_diagnostics`, except for repeats
+    /// of a diagnostic at the same statement. When the debugger is enabled, the breakpoints of
+    /// `-Zmiri-break-on-alloc` and `-Zmiri-break-on-tag` pause it.
+    fn process_diagnostics(&mut self) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        // This runs after every step, and there usually is nothing to do.
+        let diagnostics = DIAGNOSTICS.with(|diagnostics| mem::take(&mut *diagnostics.borrow_mut()));
+        if diagnostics.is_empty() {
+            return Ok(());
+        }
+        let span = this.stack().last().and_then(Frame::current_source_info).map(|info| info.span);
+        for e in diagnostics {
+            let kind = e.kind();
+            let (msg, pause, warning) = match e {
+                NonHaltingDiagnostic::PoppedTrackedPointerTag(item) =>
+                    (format!("popped tracked tag for item {:?}", item), false, None),
+                NonHaltingDiagnostic::TouchedAllocation(alloc_id, action) =>
+                    (format!("{:?} was {}", alloc_id, action), true, None),
+                NonHaltingDiagnostic::TouchedTag(id, action) =>
+                    (format!("tag <{}> was {}", id, action), true, None),
+                NonHaltingDiagnostic::Int2Ptr(alloc_id) => (
+                    format!("an integer was used as a pointer to {:?}", alloc_id),
+                    false,
+                    Some(WarningCategory::Int2Ptr),
+                ),
+            };
+            let first = match span {
+                Some(span) => this.machine.repeated_diagnostics.first(kind, span, &msg),
+                None => true,
+            };
+            if first {
+                match warning {
+                    Some(WarningCategory::Int2Ptr) => {
+                        let notes = vec![
+                            "pointers created from integers are untagged, so Stacked Borrows \
+                            cannot check how they are used"
+                                .to_owned(),
+                        ];
+                        report_warning(this, WarningCategory::Int2Ptr, msg.clone(), notes)?;
+                    }
+                    Some(category) => report_warning(this, category, msg.clone(), vec![])?,
+                    None => {
+                        report_msg(this, msg.clone(), false, vec![]);
+                    }
+                }
+            }
+            if pause {
+                if let Some(debugger) = &mut this.machine.debugger {
+                    debugger.pause(msg);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reports a call to `instance` if `-Zmiri-track-call` asks for it, with the arguments and a
+    /// backtrace.
+    fn track_call(&mut self, instance: ty::Instance<'tcx>, args: &[OpTy<'tcx, Tag>]) {
+        let this = self.eval_context_mut();
+        if this.machine.tracked_calls.is_empty() {
+            return;
+        }
+        let path = this.tcx.def_path_str(instance.def_id());
+        if !this.machine.tracked_calls.contains(&path) {
+            return;
+        }
+        let args: Vec<String> = args.iter().map(|&arg| this.render_value(arg, 0)).collect();
+        let msg = format!("tracked call: `{}`({})", instance, args.join(", "));
+        report_msg(this, msg, false, vec![]);
+    }
+
+    /// Summarizes the diagnostics that were not shown because they repeated an earlier one.
+    fn report_repeated_diagnostics(&self) {
         let this = self.eval_context_ref();
-        DIAGNOSTICS.with(|diagnostics| {
-            for e in diagnostics.borrow_mut().drain(..) {
-                let msg = match e {
-                    NonHaltingDiagnostic::PoppedTrackedPointerTag(item) =>
-                        format!("popped tracked tag for item {:?}", item),
-                };
-                report_msg(this, msg, false);
+        for (span, msg, repeats) in &this.machine.repeated_diagnostics.repeats {
+            if *repeats > 0 {
+                let note = format!("{} (repeated {} more times)", msg, repeats);
+                this.tcx.sess.span_note_without_error(*span, &note);
+                if let Some(log) = &mut *this.machine.diagnostic_log.borrow_mut() {
+                    let span = this.tcx.sess.source_map().span_to_string(*span);
+                    log.write(false, None, &note, Some(&span), &[], &[]);
+                }
             }
-        });
+        }
     }
 }
@@ -0,0 +1,185 @@
+//! Stable codes for the kinds of Undefined Behavior Miri reports, with the explanations that
+//! `miri --explain` prints.
+//!
+//! Codes are never reused or renumbered: tools and documentation refer to them.
+
+use crate::*;
+
+/// A kind of Undefined Behavior, which determines its code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UbKind {
+    Dangling,
+    Aliasing,
+    InvalidValue,
+    Uninit,
+    Unaligned,
+    InvalidDealloc,
+    Unreachable,
+    Other,
+}
+
+/// The code and explanation of a kind of Undefined Behavior.
+#[derive(Debug)]
+pub struct ErrorCode {
+    pub kind: UbKind,
+    pub code: &'static str,
+    pub title: &'static str,
+    /// What the error means, and how it is usually fixed.
+    pub explanation: &'static str,
+}
+
+pub const ERROR_CODES: &[ErrorCode] = &[
+    ErrorCode {
+        kind: UbKind::Dangling,
+        code: "MIRIUB0001",
+        title: "dangling or out-of-bounds pointer",
+        explanation: "\
+A pointer was used to access memory outside of the allocation it points to, or the allocation
+was already deallocated. (Creating a dangling reference is UB even if it is never used to access
+memory; as the reference is an invalid value, that is reported as MIRIUB0003.)
+
+Common causes:
+* using a pointer into a `Vec` or `String` after it reallocated (e.g. after `push`),
+* returning a pointer to a local variable from a function,
+* off-by-one errors in pointer arithmetic,
+* `slice::from_raw_parts` with a length that is too large.
+
+Miri shows where the allocation was deallocated, if it was. Make sure the memory outlives all
+uses of the pointer, e.g. by keeping the owner alive or by using indices instead of pointers.",
+    },
+    ErrorCode {
+        kind: UbKind::Aliasing,
+        code: "MIRIUB0002",
+        title: "aliasing violation (Stacked Borrows)",
+        explanation: "\
+A pointer was used in a way that the aliasing rules do not allow, as checked by the Stacked
+Borrows model. For example, memory was accessed through a raw pointer after a mutable reference
+to it was created, or written through a pointer derived from a shared reference.
+
+Common causes:
+* deriving a raw pointer from `&T` and writing through it (use `&mut T` or `UnsafeCell`),
+* creating a `&mut T` while other pointers to the same memory are still used later,
+* keeping a raw pointer to a field and then taking `&mut` of the whole struct.
+
+`-Zmiri-track-pointer-tag` shows where the tag in the error message became invalid. Derive all
+raw pointers from one pointer and stop creating references in between their uses.",
+    },
+    ErrorCode {
+        kind: UbKind::InvalidValue,
+        code: "MIRIUB0003",
+        title: "invalid value",
+        explanation: "\
+A value that violates the validity invariant of its type was produced: a `bool` that is neither
+0 nor 1, a `char` that is not a Unicode scalar value, an enum with an invalid discriminant, a
+null, unaligned or dangling reference, a wide pointer with invalid metadata, and so on.
+Producing such a value is UB even if it is never used.
+
+Common causes:
+* `mem::transmute` or pointer casts from bytes that do not form a valid value,
+* `mem::uninitialized` or `mem::zeroed` for types that do not allow that,
+* reading a foreign (e.g. C) enum whose value Rust does not know about.
+
+`-Zmiri-track-last-write` shows where the invalid bytes were written. Use `MaybeUninit` for
+memory that is not initialized yet, and validate data before converting it.",
+    },
+    ErrorCode {
+        kind: UbKind::Uninit,
+        code: "MIRIUB0004",
+        title: "use of uninitialized memory",
+        explanation: "\
+Uninitialized memory was read as a value that must be initialized, like an integer, or was used
+to decide control flow.
+
+Common causes:
+* `mem::uninitialized()` or `MaybeUninit::assume_init` before all bytes were written,
+* `Vec::set_len` before the elements were written,
+* reading the padding bytes of a struct, e.g. by viewing it as a byte slice.
+
+Initialize the memory first, or keep it as `MaybeUninit` until it is.",
+    },
+    ErrorCode {
+        kind: UbKind::Unaligned,
+        code: "MIRIUB0005",
+        title: "misaligned pointer",
+        explanation: "\
+Memory was accessed through a pointer that is not aligned enough for the accessed type, or a
+reference to such memory was created.
+
+Common causes:
+* casting a `*const u8` into a byte buffer to `*const u32` and dereferencing it,
+* taking a reference to a field of a `#[repr(packed)]` struct.
+
+Use `ptr::read_unaligned` and `ptr::write_unaligned`, or copy packed fields into a local variable.
+`-Zmiri-symbolic-alignment-check` also reports accesses that just happen to be aligned.",
+    },
+    ErrorCode {
+        kind: UbKind::InvalidDealloc,
+        code: "MIRIUB0006",
+        title: "invalid deallocation",
+        explanation: "\
+Memory was deallocated in a way that does not match how it was allocated: with another
+allocator (e.g. `free` on memory from `Box`), with a different size or alignment than it was
+allocated with, or it was deallocated twice.
+
+Common causes:
+* `Box::from_raw` on a pointer that did not come from `Box::into_raw`,
+* `Vec::from_raw_parts` with a capacity different from the original one,
+* passing memory allocated in Rust to C code that frees it, or the other way around.
+
+Miri shows where the memory was allocated. Always release memory with the API that allocated it.",
+    },
+    ErrorCode {
+        kind: UbKind::Unreachable,
+        code: "MIRIUB0007",
+        title: "unreachable code was reached",
+        explanation: "\
+Code that the program declared to be unreachable was executed, e.g. through
+`hint::unreachable_unchecked`, or a value of an uninhabited type (like `!` or an empty enum) was
+produced.
+
+Make sure the condition that is supposed to make the code unreachable really holds, or use
+`unreachable!()`, which panics instead.",
+    },
+    ErrorCode {
+        kind: UbKind::Other,
+        code: "MIRIUB0008",
+        title: "other Undefined Behavior",
+        explanation: "\
+The program did something else that the Rust language does not allow; the error message says
+what. The documentation of the operation or function involved lists its safety requirements.",
+    },
+];
+
+/// Looks up the explanation of `code`, ignoring case.
+pub fn explain(code: &str) -> Option<&'static ErrorCode> {
+    ERROR_CODES.iter().find(|error_code| error_code.code.eq_ignore_ascii_case(code))
+}
+
+fn by_kind(kind: UbKind) -> &'static ErrorCode {
+    ERROR_CODES.iter().find(|error_code| error_code.kind == kind).unwrap()
+}
+
+/// The code of the Undefined Behavior that `e` is about, or `None` if `e` is not about UB.
+pub fn error_code(e: &InterpError<'_>) -> Option<&'static ErrorCode> {
+    Some(by_kind(match e {
+        err_unsup!(DanglingPointerDeref) | err_unsup!(PointerOutOfBounds { .. }) =>
+            UbKind::Dangling,
+        err_ub!(UbExperimental(_)) => UbKind::Aliasing,
+        err_unsup!(ValidationFailure(_))
+        | err_unsup!(InvalidBool)
+        | err_unsup!(InvalidChar(_))
+        | err_ub!(InvalidDiscriminant(_)) => UbKind::InvalidValue,
+        err_unsup!(ReadUndefBytes(_)) => UbKind::Uninit,
+        err_unsup!(AlignmentCheckFailed { .. }) => UbKind::Unaligned,
+        err_unsup!(DeallocatedWrongMemoryKind(..))
+        | err_unsup!(IncorrectAllocationInformation(..))
+        | err_unsup!(DoubleFree) => UbKind::InvalidDealloc,
+        err_ub!(Unreachable) => UbKind::Unreachable,
+        InterpError::MachineStop(info) => match info.downcast_ref::<TerminationInfo>() {
+            Some(TerminationInfo::UndefinedBehavior { kind, .. }) => *kind,
+            _ => return None,
+        },
+        InterpError::UndefinedBehavior(_) => UbKind::Other,
+        _ => return None,
+    }))
+}
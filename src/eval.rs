@@ -1,21 +1,34 @@
 //! Main evaluator loop and setting up the initial stack frame.
 
 use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use rand::rngs::StdRng;
 use rand::SeedableRng;
 
-use rustc_hir::def_id::DefId;
+use rustc_hir::def_id::{DefId, LOCAL_CRATE};
 use rustc::ty::layout::{LayoutOf, Size};
 use rustc::ty::{self, TyCtxt};
+use rustc_span::symbol::sym;
 
 use crate::*;
 
 /// Configuration needed to spawn a Miri instance.
+///
+/// Tools embedding Miri should start from `MiriConfig::default()` and only override the fields
+/// they care about, so that adding new options does not break them:
+/// ```ignore
+/// let config = miri::MiriConfig { communicate: true, ..Default::default() };
+/// ```
 #[derive(Clone)]
 pub struct MiriConfig {
     /// Determine if validity checking and Stacked Borrows are enabled.
     pub validate: bool,
+    /// Whether Stacked Borrows treats `Box` like `&mut` (if `validate` is set). Otherwise, boxes
+    /// are retagged like mutable raw pointers.
+    pub box_uniqueness: bool,
     /// Determines if communication with the host environment is enabled.
     pub communicate: bool,
     /// Determines if memory leaks should be ignored.
@@ -28,12 +41,186 @@ pub struct MiriConfig {
     pub seed: Option<u64>,
     /// The stacked borrow id to report about
     pub tracked_pointer_tag: Option<PtrId>,
+    /// The stacked borrow id whose creation and uses are reported (and pause the debugger).
+    pub break_on_tag: Option<PtrId>,
+    /// The allocation whose creation and accesses are reported (and pause the debugger).
+    pub break_on_alloc: Option<AllocId>,
+    /// Custom shims for foreign functions, consulted (in order) before Miri's own shims.
+    pub foreign_item_hooks: Vec<ForeignItemHook>,
+    /// If set, a report of all executed MIR statements is written to this file.
+    pub coverage_file: Option<PathBuf>,
+    /// The maximal number of stack frames of the interpreted program.
+    /// The crate's `recursion_limit` also applies.
+    pub stack_limit: Option<usize>,
+    /// The number of CPUs that the program sees.
+    pub num_cpus: u64,
+    /// Whether the keys of `std`'s `RandomState` are derived from the seed even when isolation
+    /// is disabled.
+    pub deterministic_hashmap: bool,
+    /// Whether the file system shims treat names that only differ in case as the same file, like
+    /// the default file systems of macOS and Windows do.
+    pub fs_case_insensitive: bool,
+    /// Whether to randomly perturb the results of inexact floating-point operations by one ULP.
+    pub float_jitter: bool,
+    /// The x86 CPU features that `cpuid` reports. `None` means the features the program was
+    /// compiled for (the target's baseline, plus `-C target-feature`).
+    pub cpu_features: Option<Vec<String>>,
+    /// The probability that an emulated UDP datagram gets lost.
+    pub udp_drop_rate: f64,
+    /// The probability that an emulated UDP datagram overtakes the ones sent before it.
+    pub udp_reorder_rate: f64,
+    /// How deeply assigned values are validated (if `validate` is set).
+    pub validation: ValidationMode,
+    /// The validity invariants that are not checked.
+    pub ignored_validity: Vec<ValidityCheck>,
+    /// The levels of warning categories, in the order of the command-line flags.
+    pub warning_levels: Vec<(WarningCategory, WarningLevel)>,
+    /// Whether to remember where memory was last written, to explain invalid values.
+    pub track_last_write: bool,
+    /// Whether to remember the full backtrace of recent deallocations, to explain errors about
+    /// freed memory.
+    pub dealloc_backtraces: bool,
+    /// Whether to report references to packed fields that are not guaranteed to be aligned,
+    /// independently of their actual address.
+    pub symbolic_alignment: bool,
+    /// How comparisons of pointers into different allocations are evaluated when their result
+    /// depends on where the allocations are placed.
+    pub ptr_comparison: PtrComparisonMode,
+    /// Whether allocations get random base addresses that are not more aligned than required.
+    pub address_randomization: bool,
+    /// Whether to stop before `main` and read debugger commands from stdin.
+    pub debug: bool,
+    /// If set, function entries and exits are logged to this (gzip-compressed) file.
+    pub trace_file: Option<PathBuf>,
+    /// Whether the trace also logs every terminator.
+    pub trace_terminators: bool,
+    /// The paths of the functions whose calls are reported, with their arguments.
+    pub tracked_calls: Vec<String>,
+    /// If set, all diagnostics are also written to this file. `{crate}` is replaced by the name
+    /// of the crate.
+    pub log_file: Option<String>,
+    /// The format of the `log_file`.
+    pub log_format: LogFormat,
+    /// Whether to print the number of executed steps, the time they took and the peak memory
+    /// usage when the program ends, for benchmarking Miri.
+    pub stats: bool,
+    /// Whether to print which foreign functions were called when the program ends.
+    pub shim_report: bool,
+}
+
+/// A custom shim for foreign functions, registered by tools embedding Miri.
+/// It is called with the link name of the function, its arguments and the return place.
+/// If it returns `Ok(true)`, the call is considered handled and execution continues after the
+/// call; if it returns `Ok(false)`, the next hook (and finally Miri's built-in shims) is tried.
+pub type ForeignItemHook = Arc<
+    dyn for<'mir, 'tcx> Fn(
+            &mut MiriEvalContext<'mir, 'tcx>,
+            &str,
+            &[OpTy<'tcx, Tag>],
+            PlaceTy<'tcx, Tag>,
+        ) -> InterpResult<'tcx, bool>
+        + Send
+        + Sync,
+>;
+
+impl Default for MiriConfig {
+    fn default() -> MiriConfig {
+        MiriConfig {
+            validate: true,
+            box_uniqueness: true,
+            communicate: false,
+            ignore_leaks: false,
+            excluded_env_vars: vec![],
+            args: vec![],
+            seed: None,
+            tracked_pointer_tag: None,
+            break_on_tag: None,
+            break_on_alloc: None,
+            foreign_item_hooks: vec![],
+            coverage_file: None,
+            stack_limit: None,
+            num_cpus: NUM_CPUS,
+            deterministic_hashmap: false,
+            fs_case_insensitive: false,
+            float_jitter: false,
+            cpu_features: None,
+            udp_drop_rate: 0.0,
+            udp_reorder_rate: 0.0,
+            validation: ValidationMode::Shallow,
+            ignored_validity: vec![],
+            warning_levels: vec![],
+            track_last_write: false,
+            dealloc_backtraces: false,
+            symbolic_alignment: false,
+            ptr_comparison: PtrComparisonMode::Addresses,
+            address_randomization: true,
+            debug: false,
+            trace_file: None,
+            trace_terminators: false,
+            tracked_calls: vec![],
+            log_file: None,
+            log_format: LogFormat::Text,
+            stats: false,
+            shim_report: false,
+        }
+    }
+}
+
+/// The result of running a program with `eval_main`.
+#[derive(Clone, Debug)]
+pub struct EvalOutcome {
+    /// `Some(return_code)` if the program ran to completion (or called `exit`),
+    /// `None` if Miri stopped the program because of an error.
+    pub return_code: Option<i64>,
+    /// All diagnostics (errors and notes) that Miri reported during execution, in the order they
+    /// were emitted.
+    pub diagnostics: Vec<ReportedDiagnostic>,
+    /// If Miri stopped the program because of an error, the kind of that error.
+    pub error: Option<EvalErrorKind>,
+}
+
+/// The different reasons for Miri to stop a program with an error.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EvalErrorKind {
+    /// The program misbehaved without causing UB, e.g. it aborted or leaked memory.
+    ProgramError,
+    /// The program did something Miri does not support.
+    Unsupported,
+    /// The program caused Undefined Behavior.
+    UndefinedBehavior,
+    /// The program ran into one of Miri's resource limits.
+    ResourceExhaustion,
+}
+
+impl EvalErrorKind {
+    /// The exit code used by the `miri` binary when stopping with this kind of error.
+    /// These codes are stable so that scripts can rely on them.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            EvalErrorKind::ProgramError => 1,
+            EvalErrorKind::Unsupported => 2,
+            EvalErrorKind::UndefinedBehavior => 3,
+            EvalErrorKind::ResourceExhaustion => 4,
+        }
+    }
 }
 
 /// Details of premature program termination.
 pub enum TerminationInfo {
     Exit(i64),
-    Abort,
+    /// The program aborted; the message (if any) explains why.
+    Abort(Option<String>),
+    /// The program is waiting for something that can never happen; the message says what.
+    Deadlock(String),
+    /// The program did something that is not UB, but cannot work as intended; the message says
+    /// what.
+    Misuse(String),
+    /// A warning of a category that `-Zmiri-deny` turned into an error, with its notes.
+    DeniedWarning { msg: String, notes: Vec<String> },
+    /// Undefined Behavior that Miri detected itself (rather than the engine), with notes that
+    /// explain it. The notes are part of the error, so that they get lost with it when the error
+    /// is caught.
+    UndefinedBehavior { kind: UbKind, msg: String, notes: Vec<String> },
 }
 
 /// Returns a freshly created `InterpCx`, along with an `MPlaceTy` representing
@@ -48,39 +235,48 @@ pub fn create_ecx<'mir, 'tcx: 'mir>(
     let mut ecx = InterpCx::new(
         tcx.at(rustc_span::source_map::DUMMY_SP),
         ty::ParamEnv::reveal_all(),
-        Evaluator::new(config.communicate),
+        Evaluator::new(&config),
         MemoryExtra::new(
             StdRng::seed_from_u64(config.seed.unwrap_or(0)),
             config.validate,
             config.tracked_pointer_tag,
+            config.break_on_tag,
+            config.break_on_alloc,
+            config.track_last_write,
+            // The wasm linear memory has to stay compact.
+            config.address_randomization && tcx.sess.target.target.arch != "wasm32",
+            WarningCategory::Int2Ptr.level(&config.warning_levels) != WarningLevel::Allow,
         ),
     );
     // Complete initialization.
     EnvVars::init(&mut ecx, config.excluded_env_vars);
+    if let Some(file) = &config.log_file {
+        let crate_name = tcx.crate_name(LOCAL_CRATE).as_str();
+        let log = DiagnosticLog::new(file, &crate_name, config.log_format).unwrap_or_else(|err| {
+            tcx.sess.fatal(&format!("failed to create log file {}: {}", file, err))
+        });
+        *ecx.machine.diagnostic_log.get_mut() = Some(log);
+    }
+    if let Some(file) = &config.trace_file {
+        let trace = Trace::new(file, config.trace_terminators).unwrap_or_else(|err| {
+            tcx.sess.fatal(&format!("failed to create trace file {}: {}", file.display(), err))
+        });
+        ecx.machine.trace = Some(trace);
+    }
 
     // Setup first stack-frame
     let main_instance = ty::Instance::mono(tcx, main_id);
     let main_mir = ecx.load_mir(main_instance.def, None)?;
-    if main_mir.arg_count != 0 {
+    // A `#[start]` function (as used by `no_std` programs) gets `argc` and `argv` itself.
+    // Otherwise, the `start` lang item of `std` calls `main` with them.
+    let is_start_fn = tcx.has_attr(main_id, sym::start);
+    if !is_start_fn && main_mir.arg_count != 0 {
         bug!("main function must not take any arguments");
     }
 
-    let start_id = tcx.lang_items().start_fn().unwrap();
-    let main_ret_ty = tcx.fn_sig(main_id).output();
-    let main_ret_ty = main_ret_ty.no_bound_vars().unwrap();
-    let start_instance = ty::Instance::resolve(
-        tcx,
-        ty::ParamEnv::reveal_all(),
-        start_id,
-        tcx.mk_substs(::std::iter::once(ty::subst::GenericArg::from(main_ret_ty))),
-    )
-    .unwrap();
-
-    // First argument: pointer to `main()`.
-    let main_ptr = ecx.memory.create_fn_alloc(FnVal::Instance(main_instance));
-    // Second argument (argc): length of `config.args`.
+    // `argc`: length of `config.args`.
     let argc = Scalar::from_uint(config.args.len() as u128, ecx.pointer_size());
-    // Third argument (`argv`): created from `config.args`.
+    // `argv`: created from `config.args`.
     let argv = {
         // Put each argument in memory, collect pointers.
         let mut argvs = Vec::<Scalar<Tag>>::new();
@@ -101,7 +297,7 @@ pub fn create_ecx<'mir, 'tcx: 'mir>(
             ecx.write_scalar(arg, place.into())?;
         }
         ecx.memory.mark_immutable(argvs_place.ptr.assert_ptr().alloc_id)?;
-        // A pointer to that place is the 3rd argument for main.
+        // A pointer to that place is `argv`.
         let argv = argvs_place.ptr;
         // Store `argc` and `argv` for macOS `_NSGetArg{c,v}`.
         {
@@ -147,9 +343,26 @@ pub fn create_ecx<'mir, 'tcx: 'mir>(
     // Return place (in static memory so that it does not count as leak).
     let ret_place = ecx.allocate(ecx.layout_of(tcx.types.isize)?, MiriMemoryKind::Env.into());
     // Call start function.
+    let (start_instance, start_args): (_, Vec<Immediate<Tag>>) = if is_start_fn {
+        (main_instance, vec![argc.into(), argv.into()])
+    } else {
+        let start_id = tcx.lang_items().start_fn().unwrap();
+        let main_ret_ty = tcx.fn_sig(main_id).output();
+        let main_ret_ty = main_ret_ty.no_bound_vars().unwrap();
+        let start_instance = ty::Instance::resolve(
+            tcx,
+            ty::ParamEnv::reveal_all(),
+            start_id,
+            tcx.mk_substs(::std::iter::once(ty::subst::GenericArg::from(main_ret_ty))),
+        )
+        .unwrap();
+        // The first argument is a pointer to `main()`.
+        let main_ptr = ecx.memory.create_fn_alloc(FnVal::Instance(main_instance));
+        (start_instance, vec![main_ptr.into(), argc.into(), argv.into()])
+    };
     ecx.call_function(
         start_instance,
-        &[main_ptr.into(), argc.into(), argv.into()],
+        &start_args,
         Some(ret_place.into()),
         StackPopCleanup::None { cleanup: true },
     )?;
@@ -164,13 +377,15 @@ pub fn create_ecx<'mir, 'tcx: 'mir>(
 }
 
 /// Evaluates the main function specified by `main_id`.
-/// Returns `Some(return_code)` if program executed completed.
-/// Returns `None` if an evaluation error occured.
-pub fn eval_main<'tcx>(tcx: TyCtxt<'tcx>, main_id: DefId, config: MiriConfig) -> Option<i64> {
+/// The `return_code` of the result is `Some` if program execution completed, and `None` if an
+/// evaluation error occured. Either way, all reported diagnostics are also returned.
+pub fn eval_main<'tcx>(tcx: TyCtxt<'tcx>, main_id: DefId, config: MiriConfig) -> EvalOutcome {
     // FIXME: We always ignore leaks on some platforms where we do not
     // correctly implement TLS destructors.
     let target_os = tcx.sess.target.target.target_os.as_str();
     let ignore_leaks = config.ignore_leaks || target_os == "windows" || target_os == "macos";
+    let coverage_file = config.coverage_file.clone();
+    let stats = config.stats;
 
     let (mut ecx, ret_place) = match create_ecx(tcx, main_id, config) {
         Ok(v) => v,
@@ -181,31 +396,116 @@ pub fn eval_main<'tcx>(tcx: TyCtxt<'tcx>, main_id: DefId, config: MiriConfig) ->
     };
 
     // Perform the main execution.
+    let start = Instant::now();
     let res: InterpResult<'_, i64> = (|| {
-        while ecx.step()? {
-            ecx.process_diagnostics();
+        // The hooks before every step all look at the current statement. Most runs enable none
+        // of them, and then we do not have to look at what the next step is going to do at all.
+        // This only removes the overhead of these hooks: `step` itself still looks up the frame,
+        // block and statement anew every time, as that loop lives in the engine, not in Miri.
+        let step_hooks = needs_step_hooks(&ecx);
+        loop {
+            let pending_validation = if step_hooks {
+                ecx.debugger_step()?;
+                ecx.trace_step();
+                ecx.record_coverage();
+                ecx.begin_last_write_step();
+                ecx.check_packed_reference()?;
+                ecx.prepare_validation()
+            } else {
+                None
+            };
+            let running = match ecx.step() {
+                Ok(running) => running,
+                Err(err) => {
+                    ecx.step_inline_asm(err)?;
+                    true
+                }
+            };
+            if !running {
+                break;
+            }
+            ecx.machine.steps += 1;
+            if let Some(pending_validation) = pending_validation {
+                ecx.finish_validation(pending_validation)?;
+            }
+            ecx.process_diagnostics()?;
         }
         // Read the return code pointer *before* we run TLS destructors, to assert
         // that it was written to by the time that `start` lang item returned.
         let return_code = ecx.read_scalar(ret_place.into())?.not_undef()?.to_machine_isize(&ecx)?;
         ecx.run_tls_dtors()?;
+        ecx.machine.file_handler.flush_streams()?;
         Ok(return_code)
     })();
 
     // Process the result.
-    match res {
+    let mut error = None;
+    let return_code = match res {
         Ok(return_code) => {
-            if !ignore_leaks {
-                let leaks = ecx.memory.leak_report();
-                if leaks != 0 {
-                    tcx.sess.err("the evaluated program leaked memory");
-                    // Ignore the provided return code - let the reported error
-                    // determine the return code.
-                    return None;
-                }
+            if !ignore_leaks && ecx.memory.leak_report() != 0 {
+                // Ignore the provided return code - let the reported error
+                // determine the return code.
+                error = Some(EvalErrorKind::ProgramError);
+                let mut notes = ecx.machine.tls.dtor_leak_notes(&ecx);
+                notes.extend(ecx.machine.test_harness.leak_notes(&ecx));
+                report_msg(&ecx, "the evaluated program leaked memory".to_owned(), true, notes)
+            } else {
+                Some(return_code)
             }
-            Some(return_code)
         }
-        Err(e) => report_diagnostic(&ecx, e),
+        Err(e) => {
+            error = error_kind(&e.kind);
+            report_diagnostic(&ecx, e)
+        }
+    };
+    ecx.report_repeated_diagnostics();
+    if stats {
+        print_stats(ecx.machine.steps, start.elapsed());
+    }
+    if let Some(report) = &ecx.machine.shim_report {
+        eprint!("{}", report.report());
+    }
+    if let Some(log) = ecx.machine.diagnostic_log.get_mut().take() {
+        if let Err(err) = log.finish() {
+            tcx.sess.warn(&format!("failed to write the log file: {}", err));
+        }
+    }
+    if let Err(err) = ecx.finish_trace() {
+        tcx.sess.warn(&format!("failed to write the execution trace: {}", err));
+    }
+    if let Some(file) = coverage_file {
+        if let Err(err) = ecx.write_coverage_report(&file) {
+            tcx.sess.warn(&format!("failed to write coverage report to {}: {}", file.display(), err));
+        }
     }
+    EvalOutcome { return_code, diagnostics: ecx.machine.reported_diagnostics.into_inner(), error }
+}
+
+/// Whether any of the hooks that run before every step is enabled. When none is, the main loop
+/// skips them, which makes a run as fast as it was before they were added, but no faster.
+fn needs_step_hooks<'mir, 'tcx>(ecx: &MiriEvalContext<'mir, 'tcx>) -> bool {
+    ecx.machine.debugger.is_some()
+        || ecx.machine.trace.is_some()
+        || ecx.machine.coverage.is_some()
+        || ecx.memory.extra.last_write.is_some()
+        || ecx.machine.symbolic_alignment
+        || ecx.validates_itself()
+}
+
+/// Prints the statistics of `-Zmiri-stats`, in the format that `bench-cargo-miri/bench.py` parses.
+fn print_stats(steps: u64, elapsed: Duration) {
+    let secs = elapsed.as_secs_f64();
+    let rate = if secs > 0.0 { steps as f64 / secs } else { 0.0 };
+    eprint!("miri: executed {} steps in {:.3}s ({:.0} steps/s)", steps, secs, rate);
+    match peak_rss_kib() {
+        Some(kib) => eprintln!(", peak memory usage {} KiB", kib),
+        None => eprintln!(),
+    }
+}
+
+/// The peak resident set size of the Miri process, on hosts where it is easy to find out.
+fn peak_rss_kib() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    line["VmHWM:".len()..].trim().trim_end_matches("kB").trim().parse().ok()
 }
@@ -1,4 +1,9 @@
-use std::ffi::OsStr;
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
 use std::{iter, mem};
 
 use rustc_hir::def_id::{DefId, CRATE_DEF_INDEX};
@@ -10,14 +15,88 @@ use rustc::ty::{
 };
 use rustc_span::source_map::DUMMY_SP;
 
-use rand::RngCore;
+use rand::{Rng, RngCore};
 
 use crate::*;
 
 impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
 
-/// Gets an instance for a path.
-fn resolve_did<'mir, 'tcx>(tcx: TyCtxt<'tcx>, path: &[&str]) -> InterpResult<'tcx, DefId> {
+/// The floating-point intrinsics and foreign functions that compute exact results, which
+/// `-Zmiri-float-jitter` leaves alone.
+#[rustfmt::skip]
+const EXACT_FLOAT_OPS: &[&str] = &[
+    // Intrinsics.
+    "fabsf32", "sqrtf32", "floorf32", "ceilf32", "truncf32", "roundf32",
+    "fabsf64", "sqrtf64", "floorf64", "ceilf64", "truncf64", "roundf64",
+    // Foreign functions.
+    "sqrtf", "fabsf", "floorf", "ceilf", "truncf", "roundf",
+    "fmodf", "fdimf", "fminf", "fmaxf", "copysignf",
+    "sqrt", "fabs", "floor", "ceil", "trunc", "round",
+    "fmod", "fdim", "fmin", "fmax", "copysign", "_copysign",
+];
+
+/// Adds the ranges of the `UnsafeCell`s in a value of the sized type of `layout` at `offset` to
+/// `ranges`, for `unsafe_cell_ranges`.
+fn collect_unsafe_cells<'mir, 'tcx>(
+    ecx: &MiriEvalContext<'mir, 'tcx>,
+    layout: TyLayout<'tcx>,
+    offset: Size,
+    ranges: &mut Vec<(Size, Size)>,
+) -> InterpResult<'tcx> {
+    let is_unsafe_cell = match layout.ty.kind {
+        ty::Adt(adt, _) => Some(adt.did) == ecx.tcx.lang_items().unsafe_cell_type(),
+        _ => false,
+    };
+    if is_unsafe_cell {
+        if layout.size != Size::ZERO {
+            ranges.push((offset, layout.size));
+        }
+        return Ok(());
+    }
+    if ecx.type_is_freeze(layout.ty) {
+        return Ok(());
+    }
+    if let layout::Variants::Multiple { variants, .. } = &layout.variants {
+        // Any of the variants can be stored here.
+        for variant in variants.indices() {
+            collect_unsafe_cells(ecx, layout.for_variant(ecx, variant), offset, ranges)?;
+        }
+        return Ok(());
+    }
+    match layout.fields {
+        layout::FieldPlacement::Array { stride, count } => {
+            let mut element = Vec::new();
+            collect_unsafe_cells(ecx, layout.field(ecx, 0)?, Size::ZERO, &mut element)?;
+            if element == [(Size::ZERO, stride)] {
+                // The elements are entirely `UnsafeCell`s, and so is the array.
+                ranges.push((offset, stride * count));
+            } else if !element.is_empty() {
+                for i in 0..count {
+                    let element_offset = offset + stride * i;
+                    ranges.extend(
+                        element.iter().map(|&(cell, size)| (element_offset + cell, size)),
+                    );
+                }
+            }
+        }
+        _ =>
+            for i in 0..layout.fields.count() {
+                let field_offset = offset + layout.fields.offset(i);
+                collect_unsafe_cells(ecx, layout.field(ecx, i)?, field_offset, ranges)?;
+            },
+    }
+    Ok(())
+}
+
+/// The direction of a path conversion, see `convert_path`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PathConversion {
+    HostToTarget,
+    TargetToHost,
+}
+
+/// Finds the item at a path.
+pub(crate) fn resolve_did<'tcx>(tcx: TyCtxt<'tcx>, path: &[&str]) -> Option<DefId> {
     tcx.crates()
         .iter()
         .find(|&&krate| tcx.original_crate_name(krate).as_str() == path[0])
@@ -40,19 +119,29 @@ fn resolve_did<'mir, 'tcx>(tcx: TyCtxt<'tcx>, path: &[&str]) -> InterpResult<'tc
             }
             None
         })
-        .ok_or_else(|| {
-            let path = path.iter().map(|&s| s.to_owned()).collect();
-            err_unsup!(PathNotFound(path)).into()
-        })
 }
 
 pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
     /// Gets an instance for a path.
     fn resolve_path(&self, path: &[&str]) -> InterpResult<'tcx, ty::Instance<'tcx>> {
-        Ok(ty::Instance::mono(
-            self.eval_context_ref().tcx.tcx,
-            resolve_did(self.eval_context_ref().tcx.tcx, path)?,
-        ))
+        let this = self.eval_context_ref();
+        let key = path.join("::");
+        let cached = this.machine.resolved_paths.borrow().get(&key).copied();
+        let def_id = match cached {
+            Some(def_id) => def_id,
+            None => {
+                let def_id = resolve_did(this.tcx.tcx, path);
+                this.machine.resolved_paths.borrow_mut().insert(key, def_id);
+                def_id
+            }
+        };
+        match def_id {
+            Some(def_id) => Ok(ty::Instance::mono(this.tcx.tcx, def_id)),
+            None => {
+                let path = path.iter().map(|&s| s.to_owned()).collect();
+                throw_unsup!(PathNotFound(path))
+            }
+        }
     }
 
     /// Write a 0 of the appropriate size to `dest`.
@@ -73,6 +162,120 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         Ok(if this.is_null(val)? { None } else { Some(val) })
     }
 
+    /// If `ptr` (a raw pointer operand) is not null, overwrite its entire pointee with zeros.
+    fn write_zeroed_pointee(&mut self, ptr: OpTy<'tcx, Tag>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        if this.is_null(this.read_scalar(ptr)?.not_undef()?)? {
+            return Ok(());
+        }
+        let place = this.deref_operand(ptr)?;
+        this.memory.write_bytes(place.ptr, iter::repeat(0u8).take(place.layout.size.bytes() as usize))
+    }
+
+    /// Zero-initializes a fresh allocation, for `calloc` and friends. Its bytes are already zero
+    /// (just uninitialized), so we only mark them initialized: writing the zeros would make the
+    /// host commit all of the pages, even if the program only uses a small part of a huge buffer.
+    fn zero_fresh_allocation(&mut self, ptr: Pointer<Tag>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let alloc = this.memory.get_raw_mut(ptr.alloc_id)?;
+        assert_eq!(ptr.offset, Size::ZERO, "not the start of an allocation");
+        let size = alloc.size;
+        alloc.mark_definedness(ptr, size, true);
+        Ok(())
+    }
+
+    /// Checks that the `size` bytes at `src` and at `dest` do not overlap, as a copy by `name`
+    /// (like `copy_nonoverlapping` or `memcpy`) requires.
+    fn check_no_overlap(
+        &self,
+        name: &str,
+        src: Pointer<Tag>,
+        dest: Pointer<Tag>,
+        size: Size,
+    ) -> InterpResult<'tcx> {
+        if src.alloc_id != dest.alloc_id {
+            return Ok(());
+        }
+        let (src_start, dest_start) = (src.offset.bytes(), dest.offset.bytes());
+        // Ranges that just touch (`src_start + size == dest_start`) are fine.
+        if src_start < dest_start + size.bytes() && dest_start < src_start + size.bytes() {
+            throw_ub_format!(
+                "{} called on overlapping ranges: source {}..{} and destination {}..{} of {:?}",
+                name,
+                src_start,
+                src_start + size.bytes(),
+                dest_start,
+                dest_start + size.bytes(),
+                src.alloc_id,
+            );
+        }
+        Ok(())
+    }
+
+    /// Reads the null-terminated string at `ptr` for the C function `name`, without the null
+    /// terminator. With `max_len`, at most that many bytes are read, and the string does not have
+    /// to be terminated. The C functions look at one byte after the other, so the first
+    /// uninitialized byte or pointer byte before the end of the string is reported (as UB and as
+    /// unsupported, respectively), instead of failing the read as a whole.
+    fn read_c_str_for(
+        &self,
+        name: &str,
+        ptr: Scalar<Tag>,
+        max_len: Option<u64>,
+    ) -> InterpResult<'tcx, Vec<u8>> {
+        let this = self.eval_context_ref();
+        if max_len == Some(0) {
+            return Ok(Vec::new());
+        }
+        let ptr = this.force_ptr(ptr)?;
+        let pointer_size = this.pointer_size().bytes();
+        let alloc = this.memory.get_raw(ptr.alloc_id)?;
+        let start = ptr.offset.bytes();
+        let end = match max_len {
+            Some(max_len) => alloc.size.bytes().min(start.saturating_add(max_len)),
+            None => alloc.size.bytes(),
+        };
+        let mut len = 0;
+        let mut terminated = false;
+        for offset in start..end {
+            if !alloc.undef_mask().get(Size::from_bytes(offset)) {
+                throw_ub_format!(
+                    "`{}` read uninitialized memory at offset {} of {:?}, before the end of the \
+                     string",
+                    name,
+                    offset,
+                    ptr.alloc_id,
+                );
+            }
+            // The pointers that start up to `pointer_size - 1` bytes earlier cover this byte.
+            let first_covering = Size::from_bytes(offset.saturating_sub(pointer_size - 1));
+            let covering = first_covering..Size::from_bytes(offset + 1);
+            if !alloc.relocations().range(covering).is_empty() {
+                throw_unsup_format!(
+                    "`{}` read a byte of a pointer at offset {} of {:?} as part of a string",
+                    name,
+                    offset,
+                    ptr.alloc_id,
+                );
+            }
+            let byte = alloc.inspect_with_undef_and_ptr_outside_interpreter(
+                offset as usize..offset as usize + 1,
+            )[0];
+            if byte == 0 {
+                terminated = true;
+                break;
+            }
+            len += 1;
+        }
+        // The actual read performs the remaining checks, like that the pointer may be used to
+        // read. If the string does not end inside of the allocation, it reports the
+        // out-of-bounds access to the byte after the end.
+        let read_past_end = !terminated && max_len.map_or(true, |max_len| len < max_len);
+        let read_len = len + (terminated || read_past_end) as u64;
+        let bytes = this.memory.read_bytes(ptr.into(), Size::from_bytes(read_len))?;
+        Ok(bytes[..len as usize].to_vec())
+    }
+
     /// Get the `Place` for a local
     fn local_place(&mut self, local: mir::Local) -> InterpResult<'tcx, PlaceTy<'tcx, Tag>> {
         let this = self.eval_context_mut();
@@ -94,7 +297,14 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
 
         let mut data = vec![0; len];
 
-        if this.machine.communicate {
+        // The keys of `std`'s `RandomState` come from their own rng, seeded like the main one,
+        // so that other uses of randomness do not change the iteration order of hash maps. With
+        // `-Zmiri-deterministic-hashmap`, this also happens when isolation is disabled.
+        if (!this.machine.communicate || this.machine.deterministic_hashmap)
+            && this.generating_hashmap_keys()
+        {
+            this.machine.hashmap_rng.fill_bytes(&mut data);
+        } else if this.machine.communicate {
             // Fill the buffer using the host's rng.
             getrandom::getrandom(&mut data)
                 .map_err(|err| err_unsup_format!("getrandom failed: {}", err))?;
@@ -106,6 +316,71 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         this.memory.write_bytes(ptr, data.iter().copied())
     }
 
+    /// Whether the randomness currently requested is for the keys of `std`'s `RandomState`.
+    fn generating_hashmap_keys(&self) -> bool {
+        let this = self.eval_context_ref();
+        this.stack().iter().any(|frame| {
+            let def_id = frame.instance.def_id();
+            this.tcx.crate_name(def_id.krate).as_str() == "std"
+                && this.tcx.item_name(def_id).as_str() == "hashmap_random_keys"
+        })
+    }
+
+    /// With `-Zmiri-float-jitter`, randomly moves the result `f` of the `f32` operation `name`
+    /// one ULP up or down, or leaves it unchanged. Results of exact operations (such as `sqrt` or
+    /// `floor`, see `EXACT_FLOAT_OPS`) are never changed.
+    fn float_jitter_f32(&mut self, name: &str, f: f32) -> f32 {
+        let this = self.eval_context_mut();
+        if !f.is_finite() || f == 0.0 || EXACT_FLOAT_OPS.contains(&name) {
+            return f;
+        }
+        // Adjusting the bits changes the magnitude, never the sign.
+        let jittered = match this.float_jitter_direction() {
+            0 => return f,
+            1 => f32::from_bits(f.to_bits() + 1),
+            _ => f32::from_bits(f.to_bits() - 1),
+        };
+        // Do not turn finite numbers into infinities, or nonzero numbers into zeros.
+        if jittered.is_finite() && jittered != 0.0 { jittered } else { f }
+    }
+
+    /// Like `float_jitter_f32`, but for `f64`.
+    fn float_jitter_f64(&mut self, name: &str, f: f64) -> f64 {
+        let this = self.eval_context_mut();
+        if !f.is_finite() || f == 0.0 || EXACT_FLOAT_OPS.contains(&name) {
+            return f;
+        }
+        let jittered = match this.float_jitter_direction() {
+            0 => return f,
+            1 => f64::from_bits(f.to_bits() + 1),
+            _ => f64::from_bits(f.to_bits() - 1),
+        };
+        if jittered.is_finite() && jittered != 0.0 { jittered } else { f }
+    }
+
+    /// Picks how to jitter a float: 0 (never with jitter disabled), 1 or -1.
+    fn float_jitter_direction(&mut self) -> i8 {
+        let this = self.eval_context_mut();
+        if !this.machine.float_jitter {
+            return 0;
+        }
+        let rng = this.memory.extra.rng.get_mut();
+        rng.gen_range(-1, 2)
+    }
+
+    /// Implements `getentropy`, which refuses to produce more than 256 bytes at once.
+    /// Returns the value to return to the program (0 on success, -1 with `errno` set on failure).
+    fn getentropy(&mut self, ptr: Scalar<Tag>, len: u64) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+        if len > 256 {
+            let eio = this.eval_libc("EIO")?;
+            this.set_last_error(eio)?;
+            return Ok(-1);
+        }
+        this.gen_random(ptr, len as usize)?;
+        Ok(0)
+    }
+
     /// Call a function: Push the stack frame and pass the arguments.
     /// For now, arguments must be scalars (so that the caller does not have to know the layout).
     fn call_function(
@@ -185,27 +460,16 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             // Done
             Ok(())
         };
-        // Run a visitor
-        {
-            let mut visitor = UnsafeCellVisitor {
-                ecx: this,
-                unsafe_cell_action: |place| {
-                    trace!("unsafe_cell_action on {:?}", place.ptr);
-                    // We need a size to go on.
-                    let unsafe_cell_size = this
-                        .size_and_align_of_mplace(place)?
-                        .map(|(size, _)| size)
-                        // for extern types, just cover what we can
-                        .unwrap_or_else(|| place.layout.size);
-                    // Now handle this `UnsafeCell`, unless it is empty.
-                    if unsafe_cell_size != Size::ZERO {
-                        unsafe_cell_action(place.ptr, unsafe_cell_size)
-                    } else {
-                        Ok(())
-                    }
-                },
-            };
+        if place.layout.is_unsized() {
+            // Where the `UnsafeCell`s of unsized values are depends on their metadata, so we
+            // visit the value.
+            let mut visitor =
+                UnsafeCellVisitor { ecx: this, unsafe_cell_action: &mut unsafe_cell_action };
             visitor.visit_value(place)?;
+        } else {
+            for &(offset, unsafe_cell_size) in this.unsafe_cell_ranges(place.layout)?.iter() {
+                unsafe_cell_action(place.ptr.ptr_wrapping_offset(offset, this), unsafe_cell_size)?;
+            }
         }
         // The part between the end_ptr and the end of the place is also frozen.
         // So pretend there is a 0-sized `UnsafeCell` at the end.
@@ -217,7 +481,7 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         /// whether we are inside an `UnsafeCell` or not.
         struct UnsafeCellVisitor<'ecx, 'mir, 'tcx, F>
         where
-            F: FnMut(MPlaceTy<'tcx, Tag>) -> InterpResult<'tcx>,
+            F: FnMut(Scalar<Tag>, Size) -> InterpResult<'tcx>,
         {
             ecx: &'ecx MiriEvalContext<'mir, 'tcx>,
             unsafe_cell_action: F,
@@ -226,7 +490,7 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         impl<'ecx, 'mir, 'tcx, F> ValueVisitor<'mir, 'tcx, Evaluator<'tcx>>
             for UnsafeCellVisitor<'ecx, 'mir, 'tcx, F>
         where
-            F: FnMut(MPlaceTy<'tcx, Tag>) -> InterpResult<'tcx>,
+            F: FnMut(Scalar<Tag>, Size) -> InterpResult<'tcx>,
         {
             type V = MPlaceTy<'tcx, Tag>;
 
@@ -245,31 +509,36 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 };
                 if is_unsafe_cell {
                     // We do not have to recurse further, this is an `UnsafeCell`.
-                    (self.unsafe_cell_action)(v)
+                    trace!("unsafe_cell_action on {:?}", v.ptr);
+                    // We need a size to go on.
+                    let unsafe_cell_size = self
+                        .ecx
+                        .size_and_align_of_mplace(v)?
+                        .map(|(size, _)| size)
+                        // for extern types, just cover what we can
+                        .unwrap_or_else(|| v.layout.size);
+                    // Now handle this `UnsafeCell`, unless it is empty.
+                    if unsafe_cell_size != Size::ZERO {
+                        (self.unsafe_cell_action)(v.ptr, unsafe_cell_size)
+                    } else {
+                        Ok(())
+                    }
                 } else if self.ecx.type_is_freeze(v.layout.ty) {
                     // This is `Freeze`, there cannot be an `UnsafeCell`
                     Ok(())
-                } else {
-                    // We want to not actually read from memory for this visit. So, before
-                    // walking this value, we have to make sure it is not a
-                    // `Variants::Multiple`.
-                    match v.layout.variants {
-                        layout::Variants::Multiple { .. } => {
-                            // A multi-variant enum, or generator, or so.
-                            // Treat this like a union: without reading from memory,
-                            // we cannot determine the variant we are in. Reading from
-                            // memory would be subject to Stacked Borrows rules, leading
-                            // to all sorts of "funny" recursion.
-                            // We only end up here if the type is *not* freeze, so we just call the
-                            // `UnsafeCell` action.
-                            (self.unsafe_cell_action)(v)
-                        }
-                        layout::Variants::Single { .. } => {
-                            // Proceed further, try to find where exactly that `UnsafeCell`
-                            // is hiding.
-                            self.walk_value(v)
-                        }
+                } else if !v.layout.is_unsized() {
+                    // Sized values have their `UnsafeCell`s where their type says. This also
+                    // covers enums, where we do not want to read the discriminant from memory:
+                    // that would be subject to Stacked Borrows rules, leading to all sorts of
+                    // "funny" recursion.
+                    for &(offset, size) in self.ecx.unsafe_cell_ranges(v.layout)?.iter() {
+                        let ptr = v.ptr.ptr_wrapping_offset(offset, self.ecx);
+                        (self.unsafe_cell_action)(ptr, size)?;
                     }
+                    Ok(())
+                } else {
+                    // Proceed further, try to find where exactly that `UnsafeCell` is hiding.
+                    self.walk_value(v)
                 }
             }
 
@@ -299,13 +568,9 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 }
             }
 
-            // We have to do *something* for unions.
-            fn visit_union(&mut self, v: MPlaceTy<'tcx, Tag>) -> InterpResult<'tcx> {
-                // With unions, we fall back to whatever the type says, to hopefully be consistent
-                // with LLVM IR.
-                // FIXME: are we consistent, and is this really the behavior we want?
-                let frozen = self.ecx.type_is_freeze(v.layout.ty);
-                if frozen { Ok(()) } else { (self.unsafe_cell_action)(v) }
+            // Unions are sized, so `visit_value` already handled them.
+            fn visit_union(&mut self, _v: MPlaceTy<'tcx, Tag>) -> InterpResult<'tcx> {
+                bug!("unions are handled by their type")
             }
 
             // We should never get to a primitive, but always short-circuit somewhere above.
@@ -315,6 +580,36 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         }
     }
 
+    /// The ranges (offset and size) of the `UnsafeCell`s in values of the sized type of `layout`,
+    /// sorted and without overlaps. They only depend on the type: the bytes of an enum are in an
+    /// `UnsafeCell` if they are in one in any of its variants, and its tag never is.
+    fn unsafe_cell_ranges(
+        &self,
+        layout: TyLayout<'tcx>,
+    ) -> InterpResult<'tcx, Rc<[(Size, Size)]>> {
+        let this = self.eval_context_ref();
+        if let Some(ranges) = this.machine.unsafe_cell_ranges.borrow().get(&layout.ty) {
+            return Ok(ranges.clone());
+        }
+        let mut ranges = Vec::new();
+        collect_unsafe_cells(this, layout, Size::ZERO, &mut ranges)?;
+        // The variants of enums and the fields of unions overlap.
+        ranges.sort();
+        let mut merged: Vec<(Size, Size)> = Vec::new();
+        for (offset, size) in ranges {
+            match merged.last_mut() {
+                Some((last_offset, last_size)) if offset <= *last_offset + *last_size => {
+                    let end = (offset + size).max(*last_offset + *last_size);
+                    *last_size = end - *last_offset;
+                }
+                _ => merged.push((offset, size)),
+            }
+        }
+        let ranges: Rc<[(Size, Size)]> = merged.into();
+        this.machine.unsafe_cell_ranges.borrow_mut().insert(layout.ty, ranges.clone());
+        Ok(ranges)
+    }
+
     /// Helper function to get a `libc` constant as a `Scalar`.
     fn eval_libc(&mut self, name: &str) -> InterpResult<'tcx, Scalar<Tag>> {
         self.eval_context_mut()
@@ -331,8 +626,86 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
     /// Helper function to get the `TyLayout` of a `libc` type
     fn libc_ty_layout(&mut self, name: &str) -> InterpResult<'tcx, TyLayout<'tcx>> {
         let this = self.eval_context_mut();
+        if let Some(&layout) = this.machine.libc_layouts.get(name) {
+            return Ok(layout);
+        }
         let ty = this.resolve_path(&["libc", name])?.monomorphic_ty(*this.tcx);
-        this.layout_of(ty)
+        let layout = this.layout_of(ty)?;
+        this.machine.libc_layouts.insert(name.to_owned(), layout);
+        Ok(layout)
+    }
+
+    /// The index of the field called `name` in the struct at `place`, if there is one.
+    fn field_index(&self, place: MPlaceTy<'tcx, Tag>, name: &str) -> Option<usize> {
+        match place.layout.ty.kind {
+            ty::Adt(adt, _) =>
+                adt.non_enum_variant().fields.iter().position(|f| &*f.ident.as_str() == name),
+            _ => None,
+        }
+    }
+
+    /// Whether the struct at `place` has a field called `name`.
+    fn has_field(&self, place: MPlaceTy<'tcx, Tag>, name: &str) -> bool {
+        self.field_index(place, name).is_some()
+    }
+
+    /// The place of the field called `name` in the struct at `place`.
+    fn field_by_name(
+        &self,
+        place: MPlaceTy<'tcx, Tag>,
+        name: &str,
+    ) -> InterpResult<'tcx, MPlaceTy<'tcx, Tag>> {
+        let this = self.eval_context_ref();
+        let index = this.field_index(place, name);
+        let index = index.unwrap_or_else(|| bug!("no field {} in {:?}", name, place.layout.ty));
+        this.mplace_field(place, index as u64)
+    }
+
+    /// Writes `value` to the integer field called `name` in the struct at `place`.
+    fn write_int_field(
+        &mut self,
+        place: MPlaceTy<'tcx, Tag>,
+        name: &str,
+        value: i64,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let field = this.field_by_name(place, name)?;
+        this.write_scalar(Scalar::from_int(value, field.layout.size), field.into())
+    }
+
+    /// Makes `ptr..ptr+size` accessible again or inaccessible. Any access to an inaccessible
+    /// range, like to a page protected with `PROT_NONE`, is UB.
+    fn set_accessible(
+        &mut self,
+        ptr: Pointer<Tag>,
+        size: Size,
+        accessible: bool,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let alloc = this.memory.get_raw_mut(ptr.alloc_id)?;
+        let alloc_size = alloc.size;
+        let ranges =
+            alloc.extra.inaccessible.get_or_insert_with(|| RangeMap::new(alloc_size, false));
+        for inaccessible in ranges.iter_mut(ptr.offset, size) {
+            *inaccessible = !accessible;
+        }
+        Ok(())
+    }
+
+    /// Encodes `value` as an unsigned integer of `size` bytes in the byte order of the target.
+    /// Shims that assemble raw bytes (instead of writing typed places) must use this, or they
+    /// would silently assume the host's byte order.
+    fn uint_to_target_bytes(&self, value: u128, size: Size) -> Vec<u8> {
+        let this = self.eval_context_ref();
+        let mut bytes = vec![0; size.bytes() as usize];
+        write_target_uint(this.tcx.data_layout.endian, &mut bytes, value).unwrap();
+        bytes
+    }
+
+    /// Decodes `bytes` as an unsigned integer in the byte order of the target.
+    fn uint_from_target_bytes(&self, bytes: &[u8]) -> u128 {
+        let this = self.eval_context_ref();
+        read_target_uint(this.tcx.data_layout.endian, bytes).unwrap()
     }
 
     // Writes several `ImmTy`s contiguosly into memory. This is useful when you have to pack
@@ -421,8 +794,28 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     throw_unsup_format!("The {} error cannot be transformed into a raw os error", e)
                 }
             })?
+        } else if target.target_os == "windows" {
+            // These are the codes that `std` maps back to the same `ErrorKind`.
+            Scalar::from_u32(match e.kind() {
+                NotFound => 2, // ERROR_FILE_NOT_FOUND
+                PermissionDenied => 5, // ERROR_ACCESS_DENIED
+                BrokenPipe => 109, // ERROR_BROKEN_PIPE
+                AlreadyExists => 183, // ERROR_ALREADY_EXISTS
+                TimedOut => 10060, // WSAETIMEDOUT
+                WouldBlock => 10035, // WSAEWOULDBLOCK
+                InvalidInput => 10022, // WSAEINVAL
+                Interrupted => 10004, // WSAEINTR
+                AddrInUse => 10048, // WSAEADDRINUSE
+                AddrNotAvailable => 10049, // WSAEADDRNOTAVAIL
+                ConnectionAborted => 10053, // WSAECONNABORTED
+                ConnectionReset => 10054, // WSAECONNRESET
+                NotConnected => 10057, // WSAENOTCONN
+                ConnectionRefused => 10061, // WSAECONNREFUSED
+                _ => {
+                    throw_unsup_format!("The {} error cannot be transformed into a raw os error", e)
+                }
+            })
         } else {
-            // FIXME: we have to implement the Windows equivalent of this.
             throw_unsup_format!(
                 "Setting the last OS error from an io::Error is unsupported for {}.",
                 target.target_os
@@ -473,6 +866,26 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         bytes_to_os_str(bytes)
     }
 
+    /// Helper function to read an OsString from a null-terminated sequence of `u16`, which is what
+    /// the Windows APIs usually handle.
+    fn read_os_str_from_wide_str(&self, scalar: Scalar<Tag>) -> InterpResult<'tcx, OsString> {
+        let this = self.eval_context_ref();
+        let mut ptr = this.memory.force_ptr(scalar)?;
+        let mut wide = Vec::new();
+        loop {
+            let bytes = this.memory.read_bytes(ptr.into(), Size::from_bytes(2))?;
+            let unit = this.uint_from_target_bytes(bytes) as u16;
+            if unit == 0 {
+                break;
+            }
+            wide.push(unit);
+            ptr = ptr.offset(Size::from_bytes(2), this)?;
+        }
+        let s = String::from_utf16(&wide)
+            .map_err(|_| err_unsup_format!("{:?} is not a valid utf-16 string", wide))?;
+        Ok(OsString::from(s))
+    }
+
     /// Helper function to write an OsStr as a null-terminated sequence of bytes, which is what
     /// the Unix APIs usually handle. This function returns `Ok(false)` without trying to write if
     /// `size` is not large enough to fit the contents of `os_string` plus a null terminator. It
@@ -523,6 +936,112 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         self.write_os_str_to_c_str(os_str, arg_place.ptr, size).unwrap();
         arg_place.ptr.assert_ptr()
     }
+
+    /// Returns a pointer to a null-terminated copy of `s` that lives until the program ends, for
+    /// shims that return pointers to static strings. Every string is only allocated once.
+    fn static_c_str(&mut self, s: &'static str) -> Pointer<Tag> {
+        let this = self.eval_context_mut();
+        if let Some(&ptr) = this.machine.static_c_strs.get(s) {
+            return ptr;
+        }
+        let ptr = this.alloc_os_str_as_c_str(s.as_ref(), MiriMemoryKind::Env.into());
+        this.machine.static_c_strs.insert(s, ptr);
+        ptr
+    }
+
+    /// Converts `os_str` between the path syntax of the host and that of the target. Windows
+    /// paths are translated for a Unix host by `WindowsPath`; otherwise, only the path separators
+    /// change if exactly one of host and target is Windows. Paths that are not valid UTF-8 are
+    /// returned unchanged.
+    fn convert_path<'a>(
+        &self,
+        os_str: &'a OsStr,
+        direction: PathConversion,
+    ) -> InterpResult<'tcx, Cow<'a, OsStr>> {
+        let this = self.eval_context_ref();
+        let target_windows = this.tcx.sess.target.target.target_os == "windows";
+        if cfg!(windows) == target_windows {
+            return Ok(Cow::Borrowed(os_str));
+        }
+        let s = match os_str.to_str() {
+            Some(s) => s,
+            None => return Ok(Cow::Borrowed(os_str)),
+        };
+        let converted = match direction {
+            PathConversion::HostToTarget if target_windows => WindowsPath::from_unix_host(s),
+            PathConversion::TargetToHost if target_windows =>
+                WindowsPath::parse(s).to_unix_host().map_err(|reason| {
+                    err_unsup_format!("cannot access the Windows path {:?}: {}", s, reason)
+                })?,
+            // The target is Unix and the host is Windows.
+            PathConversion::HostToTarget => s.replace('\\', "/"),
+            PathConversion::TargetToHost => s.replace('/', "\\"),
+        };
+        Ok(Cow::Owned(OsString::from(converted)))
+    }
+
+    /// Reads a path that the program passes to a file system shim, and returns the host path it
+    /// names: the path is converted from the target's syntax, relative paths start at the
+    /// program's current directory, and with `-Zmiri-fs-case-insensitive`, its components name
+    /// existing files up to case. Empty paths stay empty.
+    fn read_path_from_c_str(&self, scalar: Scalar<Tag>) -> InterpResult<'tcx, PathBuf> {
+        let this = self.eval_context_ref();
+        let path = this.read_os_str_from_c_str(scalar)?;
+        this.target_path_to_host(path)
+    }
+
+    /// Like `read_path_from_c_str`, for the paths that the Windows APIs take as `u16` strings.
+    fn read_path_from_wide_str(&self, scalar: Scalar<Tag>) -> InterpResult<'tcx, PathBuf> {
+        let this = self.eval_context_ref();
+        let path = this.read_os_str_from_wide_str(scalar)?;
+        this.target_path_to_host(&path)
+    }
+
+    /// The host path that the target path `path` names, see `read_path_from_c_str`.
+    fn target_path_to_host(&self, path: &OsStr) -> InterpResult<'tcx, PathBuf> {
+        let this = self.eval_context_ref();
+        let mut path = PathBuf::from(this.convert_path(path, PathConversion::TargetToHost)?);
+        if !path.as_os_str().is_empty() {
+            path = this.machine.current_dir.join(path);
+        }
+        Ok(if this.machine.fs_case_insensitive { resolve_case_insensitive(&path) } else { path })
+    }
+
+    /// Renders the contents of an allocation for `miri_dump_alloc` and the debugger: its bytes in
+    /// hex, 16 per line, with `__` for uninitialized bytes, followed by the pointers it contains.
+    fn render_allocation(&self, alloc_id: AllocId) -> InterpResult<'tcx, String> {
+        let this = self.eval_context_ref();
+        let alloc = this.memory.get_raw(alloc_id)?;
+        let size = alloc.size.bytes() as usize;
+        let bytes = alloc.inspect_with_undef_and_ptr_outside_interpreter(0..size);
+        let mut out = String::new();
+        writeln!(out, "{:?} (size {}, align {}):", alloc_id, size, alloc.align.bytes()).unwrap();
+        for (line, chunk) in bytes.chunks(16).enumerate() {
+            write!(out, "  0x{:04x}:", line * 16).unwrap();
+            for (i, byte) in chunk.iter().enumerate() {
+                let offset = Size::from_bytes((line * 16 + i) as u64);
+                if alloc.undef_mask().get(offset) {
+                    write!(out, " {:02x}", byte).unwrap();
+                } else {
+                    write!(out, " __").unwrap();
+                }
+            }
+            writeln!(out).unwrap();
+        }
+        let pointer_size = this.pointer_size().bytes();
+        for (offset, (tag, target)) in alloc.relocations().iter() {
+            writeln!(
+                out,
+                "  0x{:04x}..0x{:04x}: pointer to {:?} (tag {:?})",
+                offset.bytes(),
+                offset.bytes() + pointer_size,
+                target,
+                tag,
+            )
+            .unwrap();
+        }
+        Ok(out)
+    }
 }
 
 pub fn immty_from_int_checked<'tcx>(
@@ -544,3 +1063,257 @@ pub fn immty_from_uint_checked<'tcx>(
         err_unsup_format!("Signed value {:#x} does not fit in {} bits", int, layout.size.bits())
     )?)
 }
+
+/// Replaces each component of `path` that does not exist with the name of an existing file in the
+/// same directory that only differs in case, if there is one. If there are several, the first one
+/// in alphabetical order is used, so that the result does not depend on the order of the
+/// directory entries.
+fn resolve_case_insensitive(path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        let name = match component {
+            Component::Normal(name) => name,
+            _ => {
+                resolved.push(component);
+                continue;
+            }
+        };
+        let dir = if resolved.as_os_str().is_empty() { Path::new(".") } else { &resolved };
+        let lowercase = name.to_str().map(str::to_lowercase);
+        let found = if fs::symlink_metadata(dir.join(name)).is_ok() {
+            None
+        } else {
+            fs::read_dir(dir).ok().and_then(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                    .filter(|entry| Some(entry.to_lowercase()) == lowercase)
+                    .min()
+            })
+        };
+        match found {
+            Some(found) => resolved.push(found),
+            None => resolved.push(name),
+        }
+    }
+    resolved
+}
+
+/// The part of a Windows path before its root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WindowsPrefix {
+    /// A drive letter, like `C:`, always stored in upper case.
+    Disk(char),
+    /// A network share, `\\server\share`.
+    Unc { server: String, share: String },
+    /// A device or another object in the Win32 namespace, like `\\.\COM1` or `\\?\Volume{..}`.
+    Device(String),
+}
+
+/// A Windows path, split into its parts like the Win32 file APIs do. When the target is Windows
+/// and the host is not, the paths that the program passes to Miri are translated to host paths
+/// with `to_unix_host`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowsPath {
+    pub prefix: Option<WindowsPrefix>,
+    /// Whether the path starts at the root of its prefix, or of the current drive.
+    pub has_root: bool,
+    pub components: Vec<String>,
+    /// Whether the path had the `\\?\` prefix, which turns off all normalization: `/` is not a
+    /// separator and `.` and `..` are names.
+    pub verbatim: bool,
+}
+
+impl WindowsPath {
+    pub fn parse(path: &str) -> WindowsPath {
+        if path.starts_with(r"\\?\") {
+            return WindowsPath::parse_verbatim(&path[4..]);
+        }
+        let is_separator = |c: char| c == '\\' || c == '/';
+        let (prefix, has_root, rest) = if path.starts_with(is_separator)
+            && path[1..].starts_with(is_separator)
+        {
+            let mut parts = path[2..].splitn(3, is_separator);
+            let first = parts.next().unwrap().to_owned();
+            let second = parts.next().unwrap_or("").to_owned();
+            let device = first == "." || first == "?";
+            let drive = match parse_drive(&second) {
+                Some((drive, "")) if device => Some(drive),
+                _ => None,
+            };
+            let prefix = match drive {
+                // `\\.\C:\` is another way to write `C:\`.
+                Some(drive) => WindowsPrefix::Disk(drive),
+                None if device => WindowsPrefix::Device(second),
+                None => WindowsPrefix::Unc { server: first, share: second },
+            };
+            (Some(prefix), true, parts.next().unwrap_or(""))
+        } else if let Some((drive, rest)) = parse_drive(path) {
+            // Paths like `C:file` are relative to the current directory on that drive.
+            (Some(WindowsPrefix::Disk(drive)), rest.starts_with(is_separator), rest)
+        } else {
+            (None, path.starts_with(is_separator), path)
+        };
+        let mut components: Vec<String> = Vec::new();
+        for component in rest.split(is_separator) {
+            match component {
+                "" | "." => {}
+                // `..` at the root stays at the root.
+                ".." if has_root || components.last().map_or(false, |last| last != "..") => {
+                    components.pop();
+                }
+                _ => components.push(component.to_owned()),
+            }
+        }
+        WindowsPath { prefix, has_root, components, verbatim: false }
+    }
+
+    /// Parses what follows the `\\?\` of a verbatim path.
+    fn parse_verbatim(path: &str) -> WindowsPath {
+        let (prefix, rest) = if let Some(rest) = strip_prefix_ignore_case(path, r"UNC\") {
+            let mut parts = rest.splitn(3, '\\');
+            let server = parts.next().unwrap().to_owned();
+            let share = parts.next().unwrap_or("").to_owned();
+            (WindowsPrefix::Unc { server, share }, parts.next().unwrap_or(""))
+        } else if let Some((drive, rest)) = parse_drive(path) {
+            (WindowsPrefix::Disk(drive), rest)
+        } else {
+            let mut parts = path.splitn(2, '\\');
+            (WindowsPrefix::Device(parts.next().unwrap().to_owned()), parts.next().unwrap_or(""))
+        };
+        let has_root = match prefix {
+            WindowsPrefix::Disk(_) => rest.starts_with('\\'),
+            _ => true,
+        };
+        WindowsPath {
+            prefix: Some(prefix),
+            has_root,
+            // Verbatim paths only ever use `\`.
+            components: rest
+                .split('\\')
+                .filter(|component| !component.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            verbatim: true,
+        }
+    }
+
+    /// The path on a Unix host. The host's root is drive `C:`; other drives, network shares and
+    /// devices cannot be accessed.
+    pub fn to_unix_host(&self) -> Result<String, String> {
+        match &self.prefix {
+            None | Some(WindowsPrefix::Disk('C')) => {}
+            Some(WindowsPrefix::Disk(drive)) =>
+                return Err(format!(
+                    "drive `{}:` does not exist, the host file system is `C:`",
+                    drive
+                )),
+            Some(WindowsPrefix::Unc { server, share }) =>
+                return Err(format!("network share `\\\\{}\\{}` cannot be accessed", server, share)),
+            Some(WindowsPrefix::Device(device)) =>
+                return Err(format!("device `{}` cannot be accessed", device)),
+        }
+        for component in &self.components {
+            // In verbatim paths, `/`, `.` and `..` are part of file names. Unix does not allow such
+            // names (and neither does NTFS).
+            let dots = component == "." || component == "..";
+            if component.contains('/') || (dots && self.verbatim) {
+                return Err(format!("`{}` is not a valid file name on the host", component));
+            }
+        }
+        let path = self.components.join("/");
+        Ok(match (self.has_root, path.is_empty()) {
+            (true, _) => format!("/{}", path),
+            (false, true) => ".".to_owned(),
+            (false, false) => path,
+        })
+    }
+
+    /// The Windows path for a path on a Unix host, see `to_unix_host`.
+    pub fn from_unix_host(path: &str) -> String {
+        let path = path.replace('/', "\\");
+        if path.starts_with('\\') { format!("C:{}", path) } else { path }
+    }
+}
+
+/// Splits a drive letter, like `C:`, off the beginning of `path`.
+fn parse_drive(path: &str) -> Option<(char, &str)> {
+    let mut chars = path.chars();
+    match (chars.next(), chars.next()) {
+        (Some(drive), Some(':')) if drive.is_ascii_alphabetic() =>
+            Some((drive.to_ascii_uppercase(), &path[2..])),
+        _ => None,
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    match s.get(..prefix.len()) {
+        Some(start) if start.eq_ignore_ascii_case(prefix) => Some(&s[prefix.len()..]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(path: &str) -> Result<String, String> {
+        WindowsPath::parse(path).to_unix_host()
+    }
+
+    #[test]
+    fn test_windows_path_drives() {
+        assert_eq!(host(r"C:\Users\miri\file.txt").unwrap(), "/Users/miri/file.txt");
+        assert_eq!(host("c:/Users//miri/./file.txt").unwrap(), "/Users/miri/file.txt");
+        assert_eq!(host(r"C:\a\..\..\b").unwrap(), "/b");
+        assert_eq!(host(r"\tmp\file").unwrap(), "/tmp/file");
+        assert_eq!(host(r"C:").unwrap(), ".");
+        assert_eq!(host(r"C:dir\file").unwrap(), "dir/file");
+        assert_eq!(host(r"..\..\a\..\b").unwrap(), "../../b");
+        assert!(host(r"D:\file").is_err());
+    }
+
+    #[test]
+    fn test_windows_path_verbatim() {
+        let path = WindowsPath::parse(r"\\?\C:\Users\.\file");
+        assert_eq!(path.prefix, Some(WindowsPrefix::Disk('C')));
+        assert!(path.has_root && path.verbatim);
+        assert_eq!(path.components, ["Users", ".", "file"]);
+        assert!(path.to_unix_host().is_err());
+
+        assert_eq!(host(r"\\?\C:\Users\miri\file.txt").unwrap(), "/Users/miri/file.txt");
+        assert_eq!(host(r"\\?\c:\").unwrap(), "/");
+        // `/` is not a separator in verbatim paths.
+        assert!(host(r"\\?\C:\a/b").is_err());
+        assert!(host(r"\\?\D:\file").is_err());
+        assert_eq!(
+            WindowsPath::parse(r"\\?\UNC\server\share\file").prefix,
+            Some(WindowsPrefix::Unc { server: "server".to_owned(), share: "share".to_owned() }),
+        );
+        assert_eq!(
+            WindowsPath::parse(r"\\?\Volume{1234}\file").prefix,
+            Some(WindowsPrefix::Device("Volume{1234}".to_owned())),
+        );
+    }
+
+    #[test]
+    fn test_windows_path_unc_and_devices() {
+        let path = WindowsPath::parse(r"\\server\share\dir\file");
+        assert_eq!(
+            path.prefix,
+            Some(WindowsPrefix::Unc { server: "server".to_owned(), share: "share".to_owned() }),
+        );
+        assert_eq!(path.components, ["dir", "file"]);
+        assert!(path.to_unix_host().is_err());
+        assert_eq!(host(r"\\.\C:\file").unwrap(), "/file");
+        let com1 = WindowsPrefix::Device("COM1".to_owned());
+        assert_eq!(WindowsPath::parse(r"\\.\COM1").prefix, Some(com1));
+        assert!(host(r"\\.\COM1").is_err());
+    }
+
+    #[test]
+    fn test_windows_path_from_unix_host() {
+        assert_eq!(WindowsPath::from_unix_host("/home/miri/file"), r"C:\home\miri\file");
+        assert_eq!(WindowsPath::from_unix_host("dir/file"), r"dir\file");
+        assert_eq!(host(&WindowsPath::from_unix_host("/home/miri")).unwrap(), "/home/miri");
+    }
+}
@@ -0,0 +1,63 @@
+//! Inline assembly.
+//!
+//! Miri cannot run machine code, so inline assembly is reported as unsupported, pointing at the
+//! `asm!`. A few instructions that do not touch any state are still executed, so that crates
+//! using them keep working: `nop` does nothing, and `int3` behaves like a breakpoint.
+
+use rustc::mir;
+
+use crate::*;
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Called when a step failed with `err`. If the step was an inline assembly statement that
+    /// Miri can execute, executes it; otherwise fails, with a better error for inline assembly.
+    ///
+    /// The engine refuses to execute inline assembly without looking at it, so this runs after
+    /// it did, instead of looking at every statement before it is executed.
+    fn step_inline_asm(&mut self, err: InterpErrorInfo<'tcx>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        // A step that fails does not advance the current frame, so this is the failed statement.
+        let asm = match this.stack().last().and_then(current_statement) {
+            Some(mir::Statement { kind: mir::StatementKind::InlineAsm(asm), .. }) => asm,
+            _ => return Err(err),
+        };
+        let template = asm.asm.asm.as_str();
+        let instruction = template.trim();
+        // Only instructions without operands can be executed.
+        let executable = asm.outputs.is_empty() && asm.inputs.is_empty();
+        match instruction {
+            "" | "nop" if executable => {}
+            "int3" if executable => this.breakpoint_instruction("`int3`")?,
+            _ => throw_unsup_format!("inline assembly is not supported: `{}`", instruction),
+        }
+        this.frame_mut().stmt += 1;
+        Ok(())
+    }
+
+    /// Executes a breakpoint instruction: the debugger stops before the next statement, and
+    /// without the debugger, the program is aborted like it would be without a debugger attached.
+    fn breakpoint_instruction(&mut self, what: &str) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        match &mut this.machine.debugger {
+            Some(debugger) => {
+                debugger.pause(format!("the program executed a breakpoint instruction ({})", what));
+                Ok(())
+            }
+            None =>
+                throw_machine_stop!(TerminationInfo::Abort(Some(format!(
+                    "the evaluated program executed a breakpoint instruction ({}) without a \
+                     debugger attached",
+                    what
+                )))),
+        }
+    }
+}
+
+fn current_statement<'a, 'mir, 'tcx>(
+    frame: &'a Frame<'mir, 'tcx, Tag, FrameData<'tcx>>,
+) -> Option<&'a mir::Statement<'tcx>> {
+    // Frames that are unwinding have no current block.
+    let block = frame.block?;
+    frame.body.basic_blocks()[block].statements.get(frame.stmt)
+}
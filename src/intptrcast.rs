@@ -4,7 +4,7 @@ use std::collections::{hash_map::Entry, HashMap};
 
 use rand::Rng;
 
-use rustc::ty::layout::HasDataLayout;
+use rustc::ty::layout::{Align, HasDataLayout};
 use rustc_mir::interpret::{AllocCheck, AllocId, InterpResult, Memory, Pointer, PointerArithmetic};
 use rustc_target::abi::Size;
 
@@ -25,18 +25,28 @@ pub struct GlobalState {
     /// This is used as a memory address when a new pointer is casted to an integer. It
     /// is always larger than any address that was previously made part of a block.
     pub next_base_addr: u64,
+    /// Whether base addresses are picked at random. Otherwise, they increase with every
+    /// allocation that gets one.
+    pub randomize: bool,
 }
 
-impl Default for GlobalState {
-    fn default() -> Self {
+impl GlobalState {
+    pub fn new(randomize: bool) -> Self {
         GlobalState {
             int_to_ptr_map: Vec::default(),
             base_addr: HashMap::default(),
             next_base_addr: STACK_ADDR,
+            randomize,
         }
     }
 }
 
+impl Default for GlobalState {
+    fn default() -> Self {
+        GlobalState::new(false)
+    }
+}
+
 impl<'mir, 'tcx> GlobalState {
     pub fn int_to_ptr(
         int: u64,
@@ -89,30 +99,45 @@ impl<'mir, 'tcx> GlobalState {
             Entry::Occupied(entry) => *entry.get(),
             Entry::Vacant(entry) => {
                 // This allocation does not have a base address yet, pick one.
-                // Leave some space to the previous allocation, to give it some chance to be less aligned.
-                let slack = {
-                    let mut rng = memory.extra.rng.borrow_mut();
-                    // This means that `(global_state.next_base_addr + slack) % 16` is uniformly distributed.
-                    rng.gen_range(0, 16)
+                // If this allocation is zero-sized, it still occupies 1 byte to avoid two
+                // allocations having the same base address.
+                let occupied = max(size.bytes(), 1);
+                let random_base_addr = if global_state.randomize {
+                    Self::random_base_addr(&global_state.int_to_ptr_map, occupied, align, memory)
+                } else {
+                    None
+                };
+                let base_addr = match random_base_addr {
+                    Some(base_addr) => base_addr,
+                    None => {
+                        // Leave some space to the previous allocation, to give it some chance to be less aligned.
+                        let slack = {
+                            let mut rng = memory.extra.rng.borrow_mut();
+                            // This means that `(global_state.next_base_addr + slack) % 16` is uniformly distributed.
+                            rng.gen_range(0, 16)
+                        };
+                        // From next_base_addr + slack, round up to adjust for alignment.
+                        let base_addr = global_state.next_base_addr.checked_add(slack).unwrap();
+                        Self::align_addr(base_addr, align.bytes())
+                    }
                 };
-                // From next_base_addr + slack, round up to adjust for alignment.
-                let base_addr = global_state.next_base_addr.checked_add(slack).unwrap();
-                let base_addr = Self::align_addr(base_addr, align.bytes());
                 entry.insert(base_addr);
                 trace!(
-                    "Assigning base address {:#x} to allocation {:?} (slack: {}, align: {})",
+                    "Assigning base address {:#x} to allocation {:?} (align: {})",
                     base_addr,
                     ptr.alloc_id,
-                    slack,
                     align.bytes(),
                 );
 
-                // Remember next base address.  If this allocation is zero-sized, leave a gap
-                // of at least 1 to avoid two allocations having the same base address.
-                global_state.next_base_addr = base_addr.checked_add(max(size.bytes(), 1)).unwrap();
-                // Given that `next_base_addr` increases in each allocation, pushing the
-                // corresponding tuple keeps `int_to_ptr_map` sorted
-                global_state.int_to_ptr_map.push((base_addr, ptr.alloc_id));
+                // Remember next base address.
+                let end = base_addr.checked_add(occupied).unwrap();
+                global_state.next_base_addr = max(global_state.next_base_addr, end);
+                // Keep `int_to_ptr_map` sorted.
+                let pos = global_state
+                    .int_to_ptr_map
+                    .binary_search_by_key(&base_addr, |(addr, _)| *addr)
+                    .unwrap_err();
+                global_state.int_to_ptr_map.insert(pos, (base_addr, ptr.alloc_id));
 
                 base_addr
             }
@@ -125,6 +150,78 @@ impl<'mir, 'tcx> GlobalState {
         Ok(dl.overflowing_offset(base_addr, ptr.offset.bytes()).0)
     }
 
+    /// Picks a random base address for an allocation that occupies `size` bytes and has the given
+    /// alignment, which does not overlap with any allocation in `int_to_ptr_map`. The address is
+    /// not more aligned than required, so that code relying on more alignment fails. Returns
+    /// `None` if no such address was found.
+    fn random_base_addr(
+        int_to_ptr_map: &[(u64, AllocId)],
+        size: u64,
+        align: Align,
+        memory: &Memory<'mir, 'tcx, Evaluator<'tcx>>,
+    ) -> Option<u64> {
+        // Stay well below `isize::MAX`, so that pointer arithmetic does not overflow.
+        let space: u64 = if memory.pointer_size().bits() > 32 { 1 << 40 } else { 1 << 28 };
+        let align = align.bytes();
+        let start = Self::align_addr(STACK_ADDR, align.checked_mul(2)?);
+        // Candidates are the odd multiples of `align`.
+        let slots = space.checked_sub(size)? / align / 2;
+        if slots == 0 {
+            return None;
+        }
+        let mut rng = memory.extra.rng.borrow_mut();
+        // Give up after a few collisions; the space is large enough to make them rare.
+        for _ in 0..16 {
+            let base_addr = start + (2 * rng.gen_range(0, slots) + 1) * align;
+            let end = base_addr + size;
+            let pos = match int_to_ptr_map.binary_search_by_key(&base_addr, |(addr, _)| *addr) {
+                Ok(_) => continue,
+                Err(pos) => pos,
+            };
+            // The previous allocation must end before `base_addr`, and the next one must start
+            // after `end`.
+            let prev_is_free = pos == 0 || {
+                let (prev_addr, prev_id) = int_to_ptr_map[pos - 1];
+                memory.get_size_and_align(prev_id, AllocCheck::MaybeDead).map_or(
+                    false,
+                    |(prev_size, _)| prev_addr + max(prev_size.bytes(), 1) <= base_addr,
+                )
+            };
+            let next_is_free =
+                int_to_ptr_map.get(pos).map_or(true, |&(next_addr, _)| end <= next_addr);
+            if prev_is_free && next_is_free {
+                return Some(base_addr);
+            }
+        }
+        None
+    }
+
+    /// Returns the lowest address above all the addresses assigned so far.
+    pub fn next_base_addr(memory: &Memory<'mir, 'tcx, Evaluator<'tcx>>) -> u64 {
+        memory.extra.intptrcast.borrow().next_base_addr
+    }
+
+    /// Assigns `base_addr` to a fresh allocation, for memory that the program expects at a
+    /// fixed address. `base_addr` must not be below `next_base_addr`.
+    pub fn assign_base_addr(
+        alloc_id: AllocId,
+        base_addr: u64,
+        memory: &Memory<'mir, 'tcx, Evaluator<'tcx>>,
+    ) -> InterpResult<'tcx> {
+        let (size, align) = memory.get_size_and_align(alloc_id, AllocCheck::Live)?;
+        let mut global_state = memory.extra.intptrcast.borrow_mut();
+        assert!(base_addr >= global_state.next_base_addr);
+        assert_eq!(base_addr % align.bytes(), 0);
+        global_state
+            .base_addr
+            .insert(alloc_id, base_addr)
+            .expect_none("allocation already has a base address");
+        global_state.next_base_addr = base_addr.checked_add(max(size.bytes(), 1)).unwrap();
+        // `base_addr` is the largest address so far, so this keeps `int_to_ptr_map` sorted.
+        global_state.int_to_ptr_map.push((base_addr, alloc_id));
+        Ok(())
+    }
+
     /// Shifts `addr` to make it aligned with `align` by rounding `addr` to the smallest multiple
     /// of `align` that is larger or equal to `addr`
     fn align_addr(addr: u64, align: u64) -> u64 {
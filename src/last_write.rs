@@ -0,0 +1,127 @@
+//! Tracking of where memory was last written, for `-Zmiri-track-last-write`.
+//!
+//! Every allocation remembers, for each byte, the statement that last wrote it. When a value
+//! turns out to be invalid, the error can then point to the statement that produced its bytes.
+//! To find those bytes, we remember the last memory read of each step that saw bytes written by an
+//! earlier step: when a step copies a value out of memory and validates it, that read is the one
+//! that fetched the invalid bytes.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rustc::ty::layout::Size;
+use rustc_span::source_map::Span;
+
+use crate::*;
+
+pub type MemoryExtra = Option<Rc<RefCell<GlobalState>>>;
+
+/// The state shared by all allocations.
+#[derive(Debug, Default)]
+pub struct GlobalState {
+    /// Counts the steps of the program; bytes written in the current step are not interesting.
+    step: u64,
+    /// The statement of the current step.
+    span: Option<Span>,
+    /// The last read in the current step of bytes that were written in an earlier step: the
+    /// allocation, the offset and the size.
+    last_read: Option<(AllocId, Size, Size)>,
+}
+
+/// A write to a range of bytes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct LastWrite {
+    step: u64,
+    span: Span,
+}
+
+/// The last writes of the bytes of an allocation.
+#[derive(Clone, Debug)]
+pub struct LastWrites {
+    /// `None` for bytes that were not written since the allocation was created.
+    writes: RangeMap<Option<LastWrite>>,
+    global: Rc<RefCell<GlobalState>>,
+}
+
+impl LastWrites {
+    pub fn new(size: Size, global: Rc<RefCell<GlobalState>>) -> Self {
+        LastWrites { writes: RangeMap::new(size, None), global }
+    }
+
+    pub fn memory_read(&self, ptr: Pointer<Tag>, size: Size) {
+        let mut global = self.global.borrow_mut();
+        let step = global.step;
+        if self.writes.iter(ptr.offset, size).any(|write| write.map_or(true, |w| w.step != step)) {
+            global.last_read = Some((ptr.alloc_id, ptr.offset, size));
+        }
+    }
+
+    pub fn memory_written(&mut self, ptr: Pointer<Tag>, size: Size) {
+        let global = self.global.borrow();
+        let write = global.span.map(|span| LastWrite { step: global.step, span });
+        for last_write in self.writes.iter_mut(ptr.offset, size) {
+            *last_write = write;
+        }
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Starts tracking the writes of the next step.
+    fn begin_last_write_step(&self) {
+        let this = self.eval_context_ref();
+        if let Some(global) = &this.memory.extra.last_write {
+            let mut global = global.borrow_mut();
+            global.step += 1;
+            global.span = this
+                .stack()
+                .last()
+                .and_then(Frame::current_source_info)
+                .map(|source_info| source_info.span);
+            global.last_read = None;
+        }
+    }
+
+    /// Describes where the bytes of the last interesting read of the current step were written.
+    fn last_write_notes(&self) -> Vec<String> {
+        let this = self.eval_context_ref();
+        let global = match &this.memory.extra.last_write {
+            Some(global) => global.borrow(),
+            None => return Vec::new(),
+        };
+        let (alloc_id, offset, size) = match global.last_read {
+            Some(last_read) => last_read,
+            None => return Vec::new(),
+        };
+        let writes = match this.memory.get_raw(alloc_id) {
+            Ok(alloc) => match &alloc.extra.last_write {
+                Some(last_writes) => &last_writes.writes,
+                None => return Vec::new(),
+            },
+            Err(_) => return Vec::new(),
+        };
+        let source_map = this.tcx.sess.source_map();
+        let start = offset.bytes();
+        let end = start + size.bytes();
+        writes
+            .iter_all()
+            .filter(|(range, _)| range.start < end && start < range.end)
+            .map(|(range, write)| {
+                let bytes = format!(
+                    "bytes {}..{} of {:?}",
+                    range.start.max(start),
+                    range.end.min(end),
+                    alloc_id,
+                );
+                match write {
+                    Some(write) => format!(
+                        "{} were last written at {}",
+                        bytes,
+                        source_map.span_to_string(write.span),
+                    ),
+                    None => format!("{} were not written since the allocation was created", bytes),
+                }
+            })
+            .collect()
+    }
+}
@@ -14,51 +14,93 @@ extern crate rustc;
 extern crate rustc_hir;
 extern crate rustc_span;
 extern crate rustc_data_structures;
+extern crate rustc_errors;
 extern crate rustc_mir;
 extern crate rustc_target;
 
+mod alignment;
+mod coverage;
+mod debugger;
 mod diagnostics;
+mod error_codes;
 mod eval;
 mod helpers;
+mod inline_asm;
 mod intptrcast;
+mod last_write;
 mod machine;
 mod mono_hash_map;
 mod operator;
 mod range_map;
+mod shim_report;
 mod shims;
 mod stacked_borrows;
+mod test_harness;
+mod trace;
+mod validation;
+mod wide_ptr;
 
 // Make all those symbols available in the same place as our own.
 pub use rustc_mir::interpret::*;
 // Resolve ambiguity.
 pub use rustc_mir::interpret::{self, AllocMap, PlaceTy};
 
-pub use crate::shims::dlsym::{Dlsym, EvalContextExt as DlsymEvalContextExt};
+pub use crate::shims::dlsym::{Dlsym, DynamicLinker, EvalContextExt as DlsymEvalContextExt};
 pub use crate::shims::env::{EnvVars, EvalContextExt as EnvEvalContextExt};
+pub use crate::shims::epoll::{Epoll, EvalContextExt as EpollEvalContextExt};
 pub use crate::shims::foreign_items::EvalContextExt as ForeignItemsEvalContextExt;
-pub use crate::shims::fs::{EvalContextExt as FileEvalContextExt, FileHandler};
+pub use crate::shims::fs::{
+    EvalContextExt as FileEvalContextExt, FileDescriptor, FileHandler, Readiness,
+};
 pub use crate::shims::intrinsics::EvalContextExt as IntrinsicsEvalContextExt;
+pub use crate::shims::kqueue::{EvalContextExt as KqueueEvalContextExt, Kqueue};
+pub use crate::shims::miri_extern::EvalContextExt as MiriExternEvalContextExt;
 pub use crate::shims::panic::{CatchUnwindData, EvalContextExt as PanicEvalContextExt};
+pub use crate::shims::poll::EvalContextExt as PollEvalContextExt;
+pub use crate::shims::socket::{EvalContextExt as SocketEvalContextExt, Socket, SocketHandler};
 pub use crate::shims::time::EvalContextExt as TimeEvalContextExt;
 pub use crate::shims::tls::{EvalContextExt as TlsEvalContextExt, TlsData};
 pub use crate::shims::EvalContextExt as ShimsEvalContextExt;
 
+pub use crate::alignment::EvalContextExt as AlignmentEvalContextExt;
+pub use crate::coverage::{Coverage, EvalContextExt as CoverageEvalContextExt};
+pub use crate::debugger::{Debugger, EvalContextExt as DebuggerEvalContextExt};
 pub use crate::diagnostics::{
-    register_diagnostic, report_diagnostic, EvalContextExt as DiagnosticsEvalContextExt, NonHaltingDiagnostic,
+    error_kind, register_diagnostic, report_diagnostic, report_msg, report_warning, DiagnosticLog,
+    EvalContextExt as DiagnosticsEvalContextExt, LogFormat, NonHaltingDiagnostic,
+    RepeatedDiagnostics, ReportedDiagnostic, WarningCategory, WarningLevel,
+};
+pub use crate::error_codes::{error_code, explain, ErrorCode, UbKind, ERROR_CODES};
+pub use crate::eval::{
+    create_ecx, eval_main, EvalErrorKind, EvalOutcome, ForeignItemHook, MiriConfig, TerminationInfo,
+};
+pub use crate::helpers::{
+    EvalContextExt as HelpersEvalContextExt, PathConversion, WindowsPath, WindowsPrefix,
 };
-pub use crate::eval::{create_ecx, eval_main, MiriConfig, TerminationInfo};
-pub use crate::helpers::EvalContextExt as HelpersEvalContextExt;
+pub use crate::inline_asm::EvalContextExt as InlineAsmEvalContextExt;
+pub use crate::last_write::{EvalContextExt as LastWriteEvalContextExt, LastWrites};
 pub use crate::machine::{
-    AllocExtra, Evaluator, FrameData, MemoryExtra, MiriEvalContext, MiriEvalContextExt,
-    MiriMemoryKind, NUM_CPUS, PAGE_SIZE, STACK_ADDR, STACK_SIZE,
+    AllocExtra, AllocSite, DeallocHistory, Evaluator, FrameData, MemoryExtra, MiriEvalContext, MiriEvalContextExt,
+    MiriMemoryKind, VolatileSpin, NUM_CPUS, PAGE_SIZE, STACK_ADDR, STACK_SIZE,
+    VOLATILE_SPIN_THRESHOLD,
 };
 pub use crate::mono_hash_map::MonoHashMap;
-pub use crate::operator::EvalContextExt as OperatorEvalContextExt;
+pub use crate::operator::{
+    EvalContextExt as OperatorEvalContextExt, PtrComparisonMode, RandomPtrOrder,
+};
 pub use crate::range_map::RangeMap;
+pub use crate::shim_report::{EvalContextExt as ShimReportEvalContextExt, ShimReport};
 pub use crate::stacked_borrows::{
     EvalContextExt as StackedBorEvalContextExt, GlobalState, Item, Permission, PtrId, Stack,
     Stacks, Tag,
 };
+pub use crate::test_harness::{EvalContextExt as TestHarnessEvalContextExt, TestHarness};
+pub use crate::trace::{EvalContextExt as TraceEvalContextExt, Trace};
+pub use crate::validation::{
+    EvalContextExt as ValidationEvalContextExt, KnownValid, PendingValidation, ValidationMode,
+    ValidityCheck,
+};
+pub use crate::wide_ptr::EvalContextExt as WidePtrEvalContextExt;
 
 /// Insert rustc arguments at the beginning of the argument list that Miri wants to be
 /// set per default, for maximal validation power.
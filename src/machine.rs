@@ -3,18 +3,25 @@
 
 use std::borrow::Cow;
 use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::env;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use rand::rngs::StdRng;
+use rand::SeedableRng;
 
 use rustc_hir::def_id::DefId;
 use rustc::mir;
 use rustc::ty::{
     self,
-    layout::{LayoutOf, Size},
+    layout::{LayoutOf, Size, TyLayout},
     Ty, TyCtxt,
 };
-use rustc_span::{source_map::Span, symbol::sym};
+use rustc_span::{
+    source_map::{SourceMap, Span},
+    symbol::{sym, Symbol},
+};
 use syntax::attr;
 
 use crate::*;
@@ -23,7 +30,97 @@ use crate::*;
 pub const PAGE_SIZE: u64 = 4 * 1024; // FIXME: adjust to target architecture
 pub const STACK_ADDR: u64 = 32 * PAGE_SIZE; // not really about the "stack", but where we start assigning integer addresses to allocations
 pub const STACK_SIZE: u64 = 16 * PAGE_SIZE; // whatever
-pub const NUM_CPUS: u64 = 1;
+pub const NUM_CPUS: u64 = 1; // the default of `-Zmiri-num-cpus`
+
+/// After this many volatile reads in a row that return the same value from the same location,
+/// we suspect the program of waiting for a change that will never happen.
+pub const VOLATILE_SPIN_THRESHOLD: u64 = 1 << 16;
+
+/// Consecutive volatile reads of the same location that all returned the same value.
+#[derive(Debug)]
+pub struct VolatileSpin {
+    pub location: (AllocId, Size),
+    pub value: ScalarMaybeUndef<Tag>,
+    pub reads: u64,
+}
+
+/// Where the most recent deallocations happened, to explain later uses of the freed memory.
+///
+/// For every deallocation, the statement in the innermost frame of the local crate that caused it
+/// is remembered. Full backtraces are only generated with `-Zmiri-dealloc-backtraces`, as that
+/// would have to look at the whole stack for every deallocation. Either way, only the most recent
+/// deallocations are remembered, so that programs that allocate and free all the time do not
+/// accumulate history without bound.
+#[derive(Debug, Default)]
+pub struct DeallocHistory<'tcx> {
+    backtraces: HashMap<AllocId, Vec<FrameInfo<'tcx>>>,
+    /// The allocations in `backtraces`, oldest first.
+    order: VecDeque<AllocId>,
+    /// The statement that caused the deallocation: in the innermost frame in the local crate, or
+    /// in the innermost frame if there is none in the local crate.
+    sites: HashMap<AllocId, Option<Span>>,
+    /// The allocations in `sites`, oldest first.
+    site_order: VecDeque<AllocId>,
+}
+
+impl<'tcx> DeallocHistory<'tcx> {
+    /// How many deallocations are remembered with their full backtrace.
+    const CAPACITY: usize = 4096;
+    /// How many deallocations are remembered with the statement that caused them.
+    const SITE_CAPACITY: usize = 1 << 16;
+
+    pub fn record(
+        &mut self,
+        alloc_id: AllocId,
+        site: Option<Span>,
+        backtrace: Option<Vec<FrameInfo<'tcx>>>,
+    ) {
+        if self.site_order.len() == Self::SITE_CAPACITY {
+            let oldest = self.site_order.pop_front().unwrap();
+            self.sites.remove(&oldest);
+        }
+        if self.sites.insert(alloc_id, site).is_none() {
+            self.site_order.push_back(alloc_id);
+        }
+        if let Some(backtrace) = backtrace {
+            if self.order.len() == Self::CAPACITY {
+                let oldest = self.order.pop_front().unwrap();
+                self.backtraces.remove(&oldest);
+            }
+            if self.backtraces.insert(alloc_id, backtrace).is_none() {
+                self.order.push_back(alloc_id);
+            }
+        }
+    }
+
+    /// Describes where `alloc_id` was deallocated, if that was recent enough: a line saying that
+    /// it `what` (like "was deallocated here"), followed by the backtrace if there is one.
+    pub fn describe(
+        &self,
+        alloc_id: AllocId,
+        what: &str,
+        source_map: &SourceMap,
+    ) -> Option<Vec<String>> {
+        if let Some(backtrace) = self.backtraces.get(&alloc_id) {
+            let mut notes = vec![format!("{:?} {}:", alloc_id, what)];
+            notes.extend(backtrace.iter().map(|frame| format!("  {}", frame)));
+            return Some(notes);
+        }
+        let note = match self.sites.get(&alloc_id)? {
+            Some(site) => format!("{:?} {}: {}", alloc_id, what, source_map.span_to_string(*site)),
+            None => format!("{:?} {}", alloc_id, what),
+        };
+        Some(vec![note])
+    }
+}
+
+/// How and where a heap allocation was created.
+#[derive(Debug, Copy, Clone)]
+pub struct AllocSite {
+    pub kind: MiriMemoryKind,
+    /// The statement in the innermost frame of the local crate that allocated it.
+    pub span: Option<Span>,
+}
 
 /// Extra data stored with each stack frame
 #[derive(Debug)]
@@ -47,6 +144,8 @@ pub enum MiriMemoryKind {
     C,
     /// Windows `HeapAlloc` memory.
     WinHeap,
+    /// Wasm linear memory obtained with `memory.grow`.
+    WasmMemory,
     /// Memory for env vars and args, errno and other parts of the machine-managed environment.
     Env,
     /// Rust statics.
@@ -65,6 +164,39 @@ impl Into<MemoryKind<MiriMemoryKind>> for MiriMemoryKind {
 pub struct AllocExtra {
     /// Stacked Borrows state is only added if validation is enabled.
     pub stacked_borrows: Option<stacked_borrows::AllocExtra>,
+    /// The ranges that must not be accessed at all, like pages protected with `PROT_NONE`.
+    /// This is only created once part of the allocation is made inaccessible.
+    pub inaccessible: Option<RangeMap<bool>>,
+    /// Where the bytes were last written, if `-Zmiri-track-last-write` is set.
+    pub last_write: Option<LastWrites>,
+    /// Whether accesses are reported, because `-Zmiri-break-on-alloc` is about this allocation.
+    pub break_on_access: bool,
+}
+
+impl AllocExtra {
+    /// Checks that no byte of `ptr..ptr+size` is inaccessible.
+    fn check_accessible<'tcx>(&self, ptr: Pointer<Tag>, size: Size) -> InterpResult<'tcx> {
+        let ranges = match &self.inaccessible {
+            Some(ranges) => ranges,
+            None => return Ok(()),
+        };
+        let start = ptr.offset.bytes();
+        let end = start + size.bytes();
+        let hit = ranges
+            .iter_all()
+            .find(|(range, inaccessible)| **inaccessible && range.start < end && start < range.end);
+        if let Some((range, _)) = hit {
+            throw_ub_format!(
+                "accessing {} byte(s) at offset {} of {:?}, but bytes {}..{} are inaccessible",
+                size.bytes(),
+                start,
+                ptr.alloc_id,
+                range.start.max(start),
+                range.end.min(end),
+            );
+        }
+        Ok(())
+    }
 }
 
 /// Extra global memory data
@@ -72,21 +204,45 @@ pub struct AllocExtra {
 pub struct MemoryExtra {
     pub stacked_borrows: stacked_borrows::MemoryExtra,
     pub intptrcast: intptrcast::MemoryExtra,
+    pub last_write: last_write::MemoryExtra,
 
     /// The random number generator used for resolving non-determinism.
     pub(crate) rng: RefCell<StdRng>,
 
     /// Whether to enforce the validity invariant.
     pub(crate) validate: bool,
+
+    /// The allocation whose creation and accesses are reported, for `-Zmiri-break-on-alloc`.
+    pub(crate) break_on_alloc: Option<AllocId>,
+
+    /// Whether integers that are used as pointers are reported, see `WarningCategory::Int2Ptr`.
+    pub(crate) report_int2ptr: bool,
+
+    /// The allocations of the vtables that were created while the program runs.
+    pub(crate) vtables: RefCell<HashSet<AllocId>>,
 }
 
 impl MemoryExtra {
-    pub fn new(rng: StdRng, validate: bool, tracked_pointer_tag: Option<PtrId>) -> Self {
+    pub fn new(
+        rng: StdRng,
+        validate: bool,
+        tracked_pointer_tag: Option<PtrId>,
+        break_on_tag: Option<PtrId>,
+        break_on_alloc: Option<AllocId>,
+        track_last_write: bool,
+        randomize_addresses: bool,
+        report_int2ptr: bool,
+    ) -> Self {
+        let stacked_borrows = GlobalState::new(tracked_pointer_tag, break_on_tag);
         MemoryExtra {
-            stacked_borrows: Rc::new(RefCell::new(GlobalState::new(tracked_pointer_tag))),
-            intptrcast: Default::default(),
+            stacked_borrows: Rc::new(RefCell::new(stacked_borrows)),
+            intptrcast: RefCell::new(intptrcast::GlobalState::new(randomize_addresses)),
+            last_write: if track_last_write { Some(Default::default()) } else { None },
             rng: RefCell::new(rng),
             validate,
+            break_on_alloc,
+            report_int2ptr,
+            vtables: RefCell::default(),
         }
     }
 }
@@ -104,25 +260,165 @@ pub struct Evaluator<'tcx> {
     pub(crate) argv: Option<Scalar<Tag>>,
     pub(crate) cmd_line: Option<Scalar<Tag>>,
 
-    /// Last OS error location in memory. It is a 32-bit integer.
+    /// The program's current directory. It starts out as the host's (or `/` under isolation),
+    /// but `chdir` only changes this, not the current directory of Miri itself.
+    pub(crate) current_dir: PathBuf,
+
+    /// Last OS error location in memory. It is a 32-bit integer: `errno` on Unix targets, and
+    /// what `GetLastError` returns on Windows (there is only one thread, so one of them suffices).
     pub(crate) last_error: Option<MPlaceTy<'tcx, Tag>>,
 
     /// TLS state.
     pub(crate) tls: TlsData<'tcx>,
 
+    /// The tests of the libtest harness, if the program was built with one.
+    pub(crate) test_harness: TestHarness,
+
     /// If enabled, the `env_vars` field is populated with the host env vars during initialization
     /// and random number generation is delegated to the host.
     pub(crate) communicate: bool,
 
     pub(crate) file_handler: FileHandler,
+    pub(crate) socket_handler: SocketHandler,
 
     /// The temporary used for storing the argument of
     /// the call to `miri_start_panic` (the panic payload) when unwinding.
     pub(crate) panic_payload: Option<ImmTy<'tcx, Tag>>,
+
+    /// All diagnostics reported so far, for `EvalOutcome`.
+    pub(crate) reported_diagnostics: RefCell<Vec<ReportedDiagnostic>>,
+
+    /// Custom foreign function shims provided by the embedder.
+    pub(crate) foreign_item_hooks: Vec<ForeignItemHook>,
+
+    /// The maximal number of stack frames, if `-Zmiri-stack-limit` is set.
+    /// (rustc's `recursion_limit` is enforced in addition to this.)
+    pub(crate) stack_limit: Option<usize>,
+
+    /// The number of CPUs that the program sees, from `-Zmiri-num-cpus`.
+    pub(crate) num_cpus: u64,
+
+    /// Whether `-Zmiri-deterministic-hashmap` is set.
+    pub(crate) deterministic_hashmap: bool,
+
+    /// The random number generator for the keys of `std`'s `RandomState`, see `gen_random`.
+    pub(crate) hashmap_rng: StdRng,
+
+    /// The seed of Miri's random number generators (`-Zmiri-seed`, or 0).
+    pub(crate) seed: u64,
+
+    /// Whether `-Zmiri-fs-case-insensitive` is set.
+    pub(crate) fs_case_insensitive: bool,
+
+    /// Whether `-Zmiri-float-jitter` is set.
+    pub(crate) float_jitter: bool,
+
+    /// The x86 CPU features reported by `cpuid`, if `-Zmiri-cpu-features` is set.
+    pub(crate) cpu_features: Option<Vec<String>>,
+
+    /// The signals for which the program installed a handler. These handlers never run, but we
+    /// mention them in the error when the program tries to send a signal.
+    pub(crate) signal_handlers: BTreeSet<i32>,
+
+    /// For recently deallocated heap allocations: where they were freed. Used to explain double
+    /// frees and dangling references.
+    pub(crate) freed_allocations: DeallocHistory<'tcx>,
+
+    /// Whether to record the full backtrace of deallocations in `freed_allocations`, and not only
+    /// the statement that caused them.
+    pub(crate) dealloc_backtraces: bool,
+
+    /// For live heap allocations: how and where (in the local crate) they were allocated. Used to
+    /// explain deallocations with the wrong layout or by the wrong allocator.
+    pub(crate) alloc_sites: HashMap<AllocId, AllocSite>,
+
+    /// The functions of the `#[global_allocator]` that implement `__rust_alloc` and friends, once
+    /// they were looked up.
+    pub(crate) global_allocator_fns: HashMap<String, Option<ty::Instance<'tcx>>>,
+
+    /// The items found by `resolve_path`, keyed by their path joined with `::`. Finding an item
+    /// walks the children of every module on the way, and shims look up `libc` items all the time.
+    pub(crate) resolved_paths: RefCell<HashMap<String, Option<DefId>>>,
+
+    /// The values of the constants read by `eval_path_scalar`, keyed like `resolved_paths`.
+    pub(crate) path_scalars: HashMap<String, Option<ScalarMaybeUndef<Tag>>>,
+
+    /// The layouts of the `libc` types used by shims, keyed by their name.
+    pub(crate) libc_layouts: HashMap<String, TyLayout<'tcx>>,
+
+    /// The `UnsafeCell`s of the types that were retagged, see `unsafe_cell_ranges`.
+    pub(crate) unsafe_cell_ranges: RefCell<HashMap<Ty<'tcx>, Rc<[(Size, Size)]>>>,
+
+    /// The symbol names of the foreign functions that were called, which are looked up in their
+    /// attributes otherwise.
+    pub(crate) link_names: HashMap<DefId, Symbol>,
+
+    /// The strings returned by `static_c_str`, so that shims returning the same static string
+    /// again (like `gai_strerror`) do not allocate a new copy every time.
+    pub(crate) static_c_strs: HashMap<&'static str, Pointer<Tag>>,
+
+    /// The state of `dlopen`, `dlsym` and `dlerror`.
+    pub(crate) dynamic_linker: DynamicLinker,
+
+    /// The volatile reads that might be a spin loop waiting for a change that can never happen.
+    pub(crate) volatile_spin: Option<VolatileSpin>,
+
+    /// How deeply assigned values are validated.
+    pub(crate) validation: ValidationMode,
+
+    /// Whether Stacked Borrows retags boxes like `&mut`, see `-Zmiri-disable-box-uniqueness`.
+    pub(crate) box_uniqueness: bool,
+
+    /// The validity invariants that are not checked, from `-Zmiri-ignore-validity`.
+    pub(crate) ignored_validity: Vec<ValidityCheck>,
+
+    /// The levels of the warning categories set with `-Zmiri-allow`, `-Zmiri-warn` and
+    /// `-Zmiri-deny`, see `WarningCategory::level`.
+    pub(crate) warning_levels: Vec<(WarningCategory, WarningLevel)>,
+
+    /// The values in immutable memory that recursive validation found to be valid. They do not
+    /// have to be validated again.
+    pub(crate) known_valid: HashSet<KnownValid<'tcx>>,
+
+    /// Whether to check the alignment of references to packed fields based on types alone.
+    pub(crate) symbolic_alignment: bool,
+
+    /// How comparisons of pointers into different allocations are evaluated.
+    pub(crate) ptr_comparison: PtrComparisonMode,
+    /// The placement of allocations that `-Zmiri-ptr-comparison=random` picked so far.
+    pub(crate) random_ptr_order: RefCell<RandomPtrOrder>,
+
+    /// The executed statements, if `-Zmiri-coverage` is enabled.
+    pub(crate) coverage: Option<Coverage<'tcx>>,
+
+    /// The state of the interactive debugger, if `-Zmiri-debug` is enabled.
+    pub(crate) debugger: Option<Debugger>,
+
+    /// The execution trace, if `-Zmiri-trace` is enabled. It is opened by `create_ecx`.
+    pub(crate) trace: Option<Trace<'tcx>>,
+
+    /// The non-halting diagnostics that were suppressed because they repeated an earlier one.
+    pub(crate) repeated_diagnostics: RepeatedDiagnostics,
+
+    /// The number of steps (statements and terminators) executed so far.
+    pub(crate) steps: u64,
+
+    /// Whether `-Zmiri-stats` is set.
+    pub(crate) stats: bool,
+
+    /// The calls of foreign functions, if `-Zmiri-shim-report` is set.
+    pub(crate) shim_report: Option<ShimReport>,
+
+    /// The paths of the functions whose calls are reported, from `-Zmiri-track-call`.
+    pub(crate) tracked_calls: Vec<String>,
+
+    /// The file all diagnostics are copied to, if `-Zmiri-log-file` is set. It is opened by
+    /// `create_ecx`.
+    pub(crate) diagnostic_log: RefCell<Option<DiagnosticLog>>,
 }
 
 impl<'tcx> Evaluator<'tcx> {
-    pub(crate) fn new(communicate: bool) -> Self {
+    pub(crate) fn new(config: &MiriConfig) -> Self {
         Evaluator {
             // `env_vars` could be initialized properly here if `Memory` were available before
             // calling this method.
@@ -130,11 +426,58 @@ impl<'tcx> Evaluator<'tcx> {
             argc: None,
             argv: None,
             cmd_line: None,
+            current_dir: if config.communicate {
+                env::current_dir().unwrap_or_else(|_| PathBuf::from("/"))
+            } else {
+                PathBuf::from("/")
+            },
             last_error: None,
             tls: TlsData::default(),
-            communicate,
+            test_harness: TestHarness::default(),
+            communicate: config.communicate,
             file_handler: Default::default(),
+            socket_handler: SocketHandler::new(config.udp_drop_rate, config.udp_reorder_rate),
             panic_payload: None,
+            reported_diagnostics: RefCell::new(Vec::new()),
+            foreign_item_hooks: config.foreign_item_hooks.clone(),
+            stack_limit: config.stack_limit,
+            num_cpus: config.num_cpus,
+            deterministic_hashmap: config.deterministic_hashmap,
+            hashmap_rng: StdRng::seed_from_u64(config.seed.unwrap_or(0)),
+            seed: config.seed.unwrap_or(0),
+            fs_case_insensitive: config.fs_case_insensitive,
+            float_jitter: config.float_jitter,
+            cpu_features: config.cpu_features.clone(),
+            signal_handlers: BTreeSet::new(),
+            freed_allocations: DeallocHistory::default(),
+            dealloc_backtraces: config.dealloc_backtraces,
+            alloc_sites: HashMap::new(),
+            global_allocator_fns: HashMap::new(),
+            resolved_paths: RefCell::new(HashMap::new()),
+            path_scalars: HashMap::new(),
+            libc_layouts: HashMap::new(),
+            unsafe_cell_ranges: RefCell::new(HashMap::new()),
+            link_names: HashMap::new(),
+            static_c_strs: HashMap::new(),
+            dynamic_linker: DynamicLinker::default(),
+            volatile_spin: None,
+            coverage: if config.coverage_file.is_some() { Some(Coverage::default()) } else { None },
+            validation: if config.validate { config.validation } else { ValidationMode::Off },
+            box_uniqueness: config.box_uniqueness,
+            ignored_validity: config.ignored_validity.clone(),
+            warning_levels: config.warning_levels.clone(),
+            known_valid: HashSet::new(),
+            symbolic_alignment: config.symbolic_alignment,
+            ptr_comparison: config.ptr_comparison,
+            random_ptr_order: RefCell::new(RandomPtrOrder::default()),
+            debugger: if config.debug { Some(Debugger::default()) } else { None },
+            trace: None,
+            repeated_diagnostics: RepeatedDiagnostics::default(),
+            steps: 0,
+            stats: config.stats,
+            shim_report: if config.shim_report { Some(ShimReport::default()) } else { None },
+            tracked_calls: config.tracked_calls.clone(),
+            diagnostic_log: RefCell::new(None),
         }
     }
 }
@@ -177,7 +520,8 @@ impl<'mir, 'tcx> Machine<'mir, 'tcx> for Evaluator<'tcx> {
 
     #[inline(always)]
     fn enforce_validity(ecx: &InterpCx<'mir, 'tcx, Self>) -> bool {
-        ecx.memory.extra.validate
+        // If Miri validates values itself, the engine must not do it as well.
+        ecx.machine.validation != ValidationMode::Off && !ecx.validates_itself()
     }
 
     #[inline(always)]
@@ -189,6 +533,8 @@ impl<'mir, 'tcx> Machine<'mir, 'tcx> for Evaluator<'tcx> {
         ret: Option<(PlaceTy<'tcx, Tag>, mir::BasicBlock)>,
         unwind: Option<mir::BasicBlock>,
     ) -> InterpResult<'tcx, Option<&'mir mir::Body<'tcx>>> {
+        ecx.track_call(instance, args);
+        ecx.begin_test(instance, args)?;
         ecx.find_mir_or_eval_fn(instance, args, ret, unwind)
     }
 
@@ -298,6 +644,9 @@ impl<'mir, 'tcx> Machine<'mir, 'tcx> for Evaluator<'tcx> {
         kind: Option<MemoryKind<Self::MemoryKinds>>,
     ) -> (Cow<'b, Allocation<Self::PointerTag, Self::AllocExtra>>, Self::PointerTag) {
         let kind = kind.expect("we set our STATIC_KIND so this cannot be None");
+        if kind == MemoryKind::Vtable {
+            memory_extra.vtables.borrow_mut().insert(id);
+        }
         let alloc = alloc.into_owned();
         let (stacks, base_tag) = if memory_extra.validate {
             let (stacks, base_tag) = Stacks::new_allocation(
@@ -311,6 +660,14 @@ impl<'mir, 'tcx> Machine<'mir, 'tcx> for Evaluator<'tcx> {
             // No stacks, no tag.
             (None, Tag::Untagged)
         };
+        let last_write = memory_extra
+            .last_write
+            .as_ref()
+            .map(|global| LastWrites::new(alloc.size, Rc::clone(global)));
+        let break_on_access = memory_extra.break_on_alloc == Some(id);
+        if break_on_access {
+            register_diagnostic(NonHaltingDiagnostic::TouchedAllocation(id, "created"));
+        }
         let mut stacked_borrows = memory_extra.stacked_borrows.borrow_mut();
         let alloc: Allocation<Tag, Self::AllocExtra> = alloc.with_tags_and_extra(
             |alloc| {
@@ -322,7 +679,7 @@ impl<'mir, 'tcx> Machine<'mir, 'tcx> for Evaluator<'tcx> {
                     stacked_borrows.static_base_ptr(alloc)
                 }
             },
-            AllocExtra { stacked_borrows: stacks },
+            AllocExtra { stacked_borrows: stacks, inaccessible: None, last_write, break_on_access },
         );
         (Cow::Owned(alloc), base_tag)
     }
@@ -342,7 +699,7 @@ impl<'mir, 'tcx> Machine<'mir, 'tcx> for Evaluator<'tcx> {
         kind: mir::RetagKind,
         place: PlaceTy<'tcx, Tag>,
     ) -> InterpResult<'tcx> {
-        if !Self::enforce_validity(ecx) {
+        if !ecx.memory.extra.validate {
             // No tracking.
             Ok(())
         } else {
@@ -352,6 +709,11 @@ impl<'mir, 'tcx> Machine<'mir, 'tcx> for Evaluator<'tcx> {
 
     #[inline(always)]
     fn stack_push(ecx: &mut InterpCx<'mir, 'tcx, Self>) -> InterpResult<'tcx, FrameData<'tcx>> {
+        if let Some(limit) = ecx.machine.stack_limit {
+            if ecx.stack().len() >= limit {
+                throw_exhaust!(StackFrameLimitReached);
+            }
+        }
         Ok(FrameData {
             call_id: ecx.memory.extra.stacked_borrows.borrow_mut().new_call(),
             catch_panic: None,
@@ -364,7 +726,9 @@ impl<'mir, 'tcx> Machine<'mir, 'tcx> for Evaluator<'tcx> {
         extra: FrameData<'tcx>,
         unwinding: bool,
     ) -> InterpResult<'tcx, StackPopInfo> {
-        ecx.handle_stack_pop(extra, unwinding)
+        let res = ecx.handle_stack_pop(extra, unwinding)?;
+        ecx.end_test();
+        Ok(res)
     }
 
     #[inline(always)]
@@ -372,7 +736,11 @@ impl<'mir, 'tcx> Machine<'mir, 'tcx> for Evaluator<'tcx> {
         memory: &Memory<'mir, 'tcx, Self>,
         int: u64,
     ) -> InterpResult<'tcx, Pointer<Self::PointerTag>> {
-        intptrcast::GlobalState::int_to_ptr(int, memory)
+        let ptr = intptrcast::GlobalState::int_to_ptr(int, memory)?;
+        if memory.extra.report_int2ptr {
+            register_diagnostic(NonHaltingDiagnostic::Int2Ptr(ptr.alloc_id));
+        }
+        Ok(ptr)
     }
 
     #[inline(always)]
@@ -391,6 +759,13 @@ impl AllocationExtra<Tag> for AllocExtra {
         ptr: Pointer<Tag>,
         size: Size,
     ) -> InterpResult<'tcx> {
+        alloc.extra.check_accessible(ptr, size)?;
+        if alloc.extra.break_on_access {
+            register_diagnostic(NonHaltingDiagnostic::TouchedAllocation(ptr.alloc_id, "read"));
+        }
+        if let Some(ref last_write) = alloc.extra.last_write {
+            last_write.memory_read(ptr, size);
+        }
         if let Some(ref stacked_borrows) = alloc.extra.stacked_borrows {
             stacked_borrows.memory_read(ptr, size)
         } else {
@@ -404,6 +779,13 @@ impl AllocationExtra<Tag> for AllocExtra {
         ptr: Pointer<Tag>,
         size: Size,
     ) -> InterpResult<'tcx> {
+        alloc.extra.check_accessible(ptr, size)?;
+        if alloc.extra.break_on_access {
+            register_diagnostic(NonHaltingDiagnostic::TouchedAllocation(ptr.alloc_id, "written"));
+        }
+        if let Some(ref mut last_write) = alloc.extra.last_write {
+            last_write.memory_written(ptr, size);
+        }
         if let Some(ref mut stacked_borrows) = alloc.extra.stacked_borrows {
             stacked_borrows.memory_written(ptr, size)
         } else {
@@ -417,6 +799,10 @@ impl AllocationExtra<Tag> for AllocExtra {
         ptr: Pointer<Tag>,
         size: Size,
     ) -> InterpResult<'tcx> {
+        if alloc.extra.break_on_access {
+            let diagnostic = NonHaltingDiagnostic::TouchedAllocation(ptr.alloc_id, "deallocated");
+            register_diagnostic(diagnostic);
+        }
         if let Some(ref mut stacked_borrows) = alloc.extra.stacked_borrows {
             stacked_borrows.memory_deallocated(ptr, size)
         } else {
@@ -431,7 +817,7 @@ impl MayLeak for MiriMemoryKind {
         use self::MiriMemoryKind::*;
         match self {
             Rust | C | WinHeap => false,
-            Env | Static => true,
+            Env | Static | WasmMemory => true,
         }
     }
 }
@@ -1,5 +1,9 @@
+use std::collections::HashMap;
 use std::convert::TryFrom;
 
+use rand::rngs::StdRng;
+use rand::Rng;
+
 use rustc::mir;
 use rustc::ty::{
     layout::{LayoutOf, Size},
@@ -8,6 +12,74 @@ use rustc::ty::{
 
 use crate::*;
 
+/// How comparisons of pointers into different allocations are evaluated when their result depends
+/// on where the allocations are placed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PtrComparisonMode {
+    /// Compare the addresses that Miri picked for the allocations.
+    Addresses,
+    /// Pick the result at random (based on the seed).
+    Random,
+    /// Stop with an error.
+    Error,
+}
+
+impl PtrComparisonMode {
+    pub fn from_str(mode: &str) -> Option<PtrComparisonMode> {
+        Some(match mode {
+            "addresses" => PtrComparisonMode::Addresses,
+            "random" => PtrComparisonMode::Random,
+            "error" => PtrComparisonMode::Error,
+            _ => return None,
+        })
+    }
+}
+
+/// The placement that `-Zmiri-ptr-comparison=random` picks for the allocations whose pointers
+/// are compared. Everything is picked once, so that the same comparison always has the same
+/// result, and the allocations are ordered consistently, as if they were placed in that order.
+#[derive(Debug, Default)]
+pub struct RandomPtrOrder {
+    /// The random key of each compared allocation. Allocations are ordered by their keys.
+    keys: HashMap<AllocId, u64>,
+    /// Whether the pointers of each ambiguous equality comparison are equal, with the smaller
+    /// pointer first.
+    equalities: HashMap<((AllocId, Size), (AllocId, Size)), bool>,
+}
+
+impl RandomPtrOrder {
+    fn key(&mut self, alloc_id: AllocId, rng: &mut StdRng) -> (u64, AllocId) {
+        (*self.keys.entry(alloc_id).or_insert_with(|| rng.gen()), alloc_id)
+    }
+
+    /// The result of comparing `left` and `right`, which point into different allocations, with
+    /// `bin_op`. For `Ne`, this is the result of `Eq`, like for `ptr_eq`.
+    fn compare(
+        &mut self,
+        bin_op: mir::BinOp,
+        left: Pointer<Tag>,
+        right: Pointer<Tag>,
+        rng: &mut StdRng,
+    ) -> bool {
+        use rustc::mir::BinOp::*;
+
+        let before = |this: &mut Self, rng: &mut StdRng| {
+            this.key(left.alloc_id, rng) < this.key(right.alloc_id, rng)
+        };
+        match bin_op {
+            Eq | Ne => {
+                let left = (left.alloc_id, left.offset);
+                let right = (right.alloc_id, right.offset);
+                let pair = if left < right { (left, right) } else { (right, left) };
+                *self.equalities.entry(pair).or_insert_with(|| rng.gen())
+            }
+            Lt | Le => before(self, rng),
+            Gt | Ge => !before(self, rng),
+            _ => bug!("{:?} is not a comparison", bin_op),
+        }
+    }
+}
+
 pub trait EvalContextExt<'tcx> {
     fn binary_ptr_op(
         &self,
@@ -18,12 +90,26 @@ pub trait EvalContextExt<'tcx> {
 
     fn ptr_eq(&self, left: Scalar<Tag>, right: Scalar<Tag>) -> InterpResult<'tcx, bool>;
 
+    fn ambiguous_ptr_comparison(
+        &self,
+        bin_op: mir::BinOp,
+        left: Scalar<Tag>,
+        right: Scalar<Tag>,
+    ) -> InterpResult<'tcx, Option<bool>>;
+
     fn pointer_offset_inbounds(
         &self,
         ptr: Scalar<Tag>,
         pointee_ty: Ty<'tcx>,
         offset: i64,
     ) -> InterpResult<'tcx, Scalar<Tag>>;
+
+    fn check_offset_in_bounds(
+        &self,
+        min_ptr: Pointer<Tag>,
+        size: u64,
+        offset_ptr: Scalar<Tag>,
+    ) -> InterpResult<'tcx>;
 }
 
 impl<'mir, 'tcx> EvalContextExt<'tcx> for super::MiriEvalContext<'mir, 'tcx> {
@@ -43,11 +129,19 @@ impl<'mir, 'tcx> EvalContextExt<'tcx> for super::MiriEvalContext<'mir, 'tcx> {
                 #[rustfmt::skip]
                 let eq = match (*left, *right) {
                     (Immediate::Scalar(left), Immediate::Scalar(right)) => {
-                        self.ptr_eq(left.not_undef()?, right.not_undef()?)?
+                        let (left, right) = (left.not_undef()?, right.not_undef()?);
+                        match self.ambiguous_ptr_comparison(Eq, left, right)? {
+                            Some(eq) => eq,
+                            None => self.ptr_eq(left, right)?,
+                        }
                     }
                     (Immediate::ScalarPair(left1, left2), Immediate::ScalarPair(right1, right2)) => {
-                        self.ptr_eq(left1.not_undef()?, right1.not_undef()?)?
-                            && self.ptr_eq(left2.not_undef()?, right2.not_undef()?)?
+                        let (left1, right1) = (left1.not_undef()?, right1.not_undef()?);
+                        let eq1 = match self.ambiguous_ptr_comparison(Eq, left1, right1)? {
+                            Some(eq) => eq,
+                            None => self.ptr_eq(left1, right1)?,
+                        };
+                        eq1 && self.ptr_eq(left2.not_undef()?, right2.not_undef()?)?
                     }
                     _ => bug!("Type system should not allow comparing Scalar with ScalarPair"),
                 };
@@ -55,6 +149,11 @@ impl<'mir, 'tcx> EvalContextExt<'tcx> for super::MiriEvalContext<'mir, 'tcx> {
             }
 
             Lt | Le | Gt | Ge => {
+                if let Some(res) =
+                    self.ambiguous_ptr_comparison(bin_op, left.to_scalar()?, right.to_scalar()?)?
+                {
+                    return Ok((Scalar::from_bool(res), false, self.tcx.types.bool));
+                }
                 // Just compare the integers.
                 // TODO: Do we really want to *always* do that, even when comparing two live in-bounds pointers?
                 let left = self.force_bits(left.to_scalar()?, left.layout.size)?;
@@ -93,6 +192,58 @@ impl<'mir, 'tcx> EvalContextExt<'tcx> for super::MiriEvalContext<'mir, 'tcx> {
         Ok(left == right)
     }
 
+    /// If `left` and `right` point into different allocations and the result of comparing them
+    /// with `bin_op` depends on where these allocations are placed, determines the result as
+    /// configured with `-Zmiri-ptr-comparison`. Returns `None` if the addresses are to be compared.
+    fn ambiguous_ptr_comparison(
+        &self,
+        bin_op: mir::BinOp,
+        left: Scalar<Tag>,
+        right: Scalar<Tag>,
+    ) -> InterpResult<'tcx, Option<bool>> {
+        use rustc::mir::BinOp::*;
+
+        let mode = self.machine.ptr_comparison;
+        if mode == PtrComparisonMode::Addresses {
+            return Ok(None);
+        }
+        let (left, right) = match (left, right) {
+            (Scalar::Ptr(left), Scalar::Ptr(right)) if left.alloc_id != right.alloc_id =>
+                (left, right),
+            _ => return Ok(None),
+        };
+        let in_bounds = |ptr: Pointer<Tag>| {
+            self.memory
+                .get_size_and_align(ptr.alloc_id, AllocCheck::Live)
+                .map_or(false, |(size, _)| ptr.offset < size)
+        };
+        let ambiguous = match bin_op {
+            // Pointers into two live allocations are never equal, but a pointer that is dangling or
+            // out of bounds (even just one past the end) could be equal to any other pointer.
+            Eq | Ne => !in_bounds(left) || !in_bounds(right),
+            // Allocations can be placed in any order.
+            _ => true,
+        };
+        if !ambiguous {
+            return Ok(None);
+        }
+        match mode {
+            PtrComparisonMode::Random => {
+                let rng = &mut *self.memory.extra.rng.borrow_mut();
+                let mut order = self.machine.random_ptr_order.borrow_mut();
+                Ok(Some(order.compare(bin_op, left, right, rng)))
+            }
+            PtrComparisonMode::Error => throw_unsup_format!(
+                "comparing pointers into {:?} and {:?} with `{:?}`: \
+                the result depends on where the allocations are placed",
+                left.alloc_id,
+                right.alloc_id,
+                bin_op,
+            ),
+            PtrComparisonMode::Addresses => bug!("addresses are compared without asking"),
+        }
+    }
+
     /// Raises an error if the offset moves the pointer outside of its allocation.
     /// For integers, we consider each of them their own tiny allocation of size 0,
     /// so offset-by-0 is okay for them -- except for NULL, which we rule out entirely.
@@ -117,6 +268,9 @@ impl<'mir, 'tcx> EvalContextExt<'tcx> for super::MiriEvalContext<'mir, 'tcx> {
             // If the negation overflows, the result will be negative so the try_from will fail.
             (offset_ptr, u64::try_from(-offset).unwrap())
         };
+        if let Scalar::Ptr(min_ptr) = min_ptr {
+            self.check_offset_in_bounds(min_ptr, abs_offset, offset_ptr)?;
+        }
         self.memory.check_ptr_access_align(
             min_ptr,
             Size::from_bytes(abs_offset),
@@ -126,4 +280,49 @@ impl<'mir, 'tcx> EvalContextExt<'tcx> for super::MiriEvalContext<'mir, 'tcx> {
         // That's it!
         Ok(offset_ptr)
     }
+
+    /// Checks that `min_ptr..min_ptr+size` (the range covered by an inbounds offset) stays inside
+    /// the allocation of `min_ptr`. The error mentions the allocation that the offset pointer
+    /// (`offset_ptr`) would end up in, if that is known.
+    /// Dangling pointers are left to `check_ptr_access_align`.
+    fn check_offset_in_bounds(
+        &self,
+        min_ptr: Pointer<Tag>,
+        size: u64,
+        offset_ptr: Scalar<Tag>,
+    ) -> InterpResult<'tcx> {
+        let alloc_size = match self.memory.get_size_and_align(min_ptr.alloc_id, AllocCheck::Live) {
+            Ok((alloc_size, _)) => alloc_size.bytes(),
+            Err(_) => return Ok(()),
+        };
+        let end = min_ptr.offset.bytes() + size;
+        if end <= alloc_size {
+            return Ok(());
+        }
+        // If the allocation has an address, see if the result lands in some other allocation.
+        let base_addr =
+            self.memory.extra.intptrcast.borrow().base_addr.get(&min_ptr.alloc_id).copied();
+        let target = match (base_addr, offset_ptr) {
+            (Some(base_addr), Scalar::Ptr(offset_ptr)) => {
+                let addr = base_addr + offset_ptr.offset.bytes();
+                intptrcast::GlobalState::int_to_ptr(addr, &self.memory)
+                    .ok()
+                    .map(|target| target.alloc_id)
+                    .filter(|&id| id != min_ptr.alloc_id)
+            }
+            _ => None,
+        };
+        let target = match target {
+            Some(id) => format!("; the resulting pointer would point into {:?}", id),
+            None => String::new(),
+        };
+        throw_ub_format!(
+            "out-of-bounds pointer arithmetic: pointer must be in-bounds at offset {}, \
+            but is outside bounds of allocation {:?} which has size {}{}",
+            end,
+            min_ptr.alloc_id,
+            alloc_size,
+            target,
+        )
+    }
 }
@@ -5,10 +5,16 @@
 //! Users must not depend on whether a range is coalesced or not, even though this is observable
 //! via the iteration APIs.
 
+use std::cmp::Ordering;
 use std::ops;
 
 use rustc::ty::layout::Size;
 
+/// The most elements a chunk holds; a chunk that grows beyond this is split in two. Inserting an
+/// element has to move the elements after it in its chunk, so this bounds the cost of a split,
+/// while still keeping the per-chunk overhead small.
+const CHUNK_CAPACITY: usize = 256;
+
 #[derive(Clone, Debug)]
 struct Elem<T> {
     /// The range covered by this element; never empty.
@@ -16,9 +22,12 @@ struct Elem<T> {
     /// The data stored for this element.
     data: T,
 }
+/// The elements are sorted and cover the entire range without gaps. They are stored in chunks
+/// (none of which are empty), so that allocations with millions of distinct ranges do not have
+/// to move all of them whenever a range is split.
 #[derive(Clone, Debug)]
 pub struct RangeMap<T> {
-    v: Vec<Elem<T>>,
+    chunks: Vec<Vec<Elem<T>>>,
 }
 
 impl<T> RangeMap<T> {
@@ -27,35 +36,41 @@ impl<T> RangeMap<T> {
     #[inline(always)]
     pub fn new(size: Size, init: T) -> RangeMap<T> {
         let size = size.bytes();
-        let mut map = RangeMap { v: Vec::new() };
+        let mut map = RangeMap { chunks: Vec::new() };
         if size > 0 {
-            map.v.push(Elem { range: 0..size, data: init });
+            map.chunks.push(vec![Elem { range: 0..size, data: init }]);
         }
         map
     }
 
-    /// Finds the index containing the given offset.
-    fn find_offset(&self, offset: u64) -> usize {
-        // We do a binary search.
-        let mut left = 0usize; // inclusive
-        let mut right = self.v.len(); // exclusive
-        loop {
-            debug_assert!(left < right, "find_offset: offset {} is out-of-bounds", offset);
-            let candidate = left.checked_add(right).unwrap() / 2;
-            let elem = &self.v[candidate];
-            if offset < elem.range.start {
-                // We are too far right (offset is further left).
-                debug_assert!(candidate < right); // we are making progress
-                right = candidate;
-            } else if offset >= elem.range.end {
-                // We are too far left (offset is further right).
-                debug_assert!(candidate >= left); // we are making progress
-                left = candidate + 1;
-            } else {
-                // This is it!
-                return candidate;
-            }
-        }
+    /// The size of the map, i.e., the end of the last range.
+    fn size(&self) -> u64 {
+        self.chunks.last().map_or(0, |chunk| chunk.last().unwrap().range.end)
+    }
+
+    /// Finds the chunk index and the index in that chunk of the element containing the given
+    /// offset.
+    fn find_offset(&self, offset: u64) -> (usize, usize) {
+        // We do a binary search over the chunks, and then over the elements of the chunk.
+        let chunk_idx = match self.chunks.binary_search_by_key(&offset, |c| c[0].range.start) {
+            Ok(idx) => idx,
+            Err(idx) => idx.checked_sub(1).unwrap(),
+        };
+        let elem_idx = self.chunks[chunk_idx]
+            .binary_search_by(|elem| {
+                if offset < elem.range.start {
+                    // The element is further right than the offset.
+                    Ordering::Greater
+                } else if offset >= elem.range.end {
+                    // The element is further left than the offset.
+                    Ordering::Less
+                } else {
+                    // This is it!
+                    Ordering::Equal
+                }
+            })
+            .unwrap_or_else(|_| panic!("find_offset: offset {} is out-of-bounds", offset));
+        (chunk_idx, elem_idx)
     }
 
     /// Provides read-only iteration over everything in the given range. This does
@@ -64,48 +79,98 @@ impl<T> RangeMap<T> {
     pub fn iter<'a>(&'a self, offset: Size, len: Size) -> impl Iterator<Item = &'a T> + 'a {
         let offset = offset.bytes();
         let len = len.bytes();
-        // Compute a slice starting with the elements we care about.
-        let slice: &[Elem<T>] = if len == 0 {
+        // Compute the elements of the first chunk we care about, and the chunks after it.
+        let (first, rest): (&[Elem<T>], &[Vec<Elem<T>>]) = if len == 0 {
             // We just need any empty iterator. We don't even want to
             // yield the element that surrounds this position.
-            &[]
+            (&[], &[])
         } else {
-            let first_idx = self.find_offset(offset);
-            &self.v[first_idx..]
+            let (chunk_idx, elem_idx) = self.find_offset(offset);
+            (&self.chunks[chunk_idx][elem_idx..], &self.chunks[chunk_idx + 1..])
         };
         // The first offset that is not included any more.
         let end = offset + len;
-        slice.iter().take_while(move |elem| elem.range.start < end).map(|elem| &elem.data)
+        first
+            .iter()
+            .chain(rest.iter().flatten())
+            .take_while(move |elem| elem.range.start < end)
+            .map(|elem| &elem.data)
+    }
+
+    /// Provides read-only iteration over all elements, together with the range they cover.
+    pub fn iter_all<'a>(&'a self) -> impl Iterator<Item = (ops::Range<u64>, &'a T)> + 'a {
+        self.chunks.iter().flatten().map(|elem| (elem.range.clone(), &elem.data))
     }
 
     pub fn iter_mut_all<'a>(&'a mut self) -> impl Iterator<Item = &'a mut T> + 'a {
-        self.v.iter_mut().map(|elem| &mut elem.data)
+        self.chunks.iter_mut().flatten().map(|elem| &mut elem.data)
     }
 
-    // Splits the element situated at the given `index`, such that the 2nd one starts at offset
-    // `split_offset`. Do nothing if the element already starts there.
-    // Returns whether a split was necessary.
-    fn split_index(&mut self, index: usize, split_offset: u64) -> bool
+    // Splits the element containing `split_offset`, such that the 2nd one starts at offset
+    // `split_offset`. Do nothing if the element already starts there, or if `split_offset` is
+    // the end of the map.
+    fn split_at(&mut self, split_offset: u64)
     where
         T: Clone,
     {
-        let elem = &mut self.v[index];
-        if split_offset == elem.range.start || split_offset == elem.range.end {
+        if split_offset == self.size() {
             // Nothing to do.
-            return false;
+            return;
+        }
+        let (chunk_idx, elem_idx) = self.find_offset(split_offset);
+        let chunk = &mut self.chunks[chunk_idx];
+        let elem = &mut chunk[elem_idx];
+        if split_offset == elem.range.start {
+            // Nothing to do.
+            return;
         }
-        debug_assert!(
-            elem.range.contains(&split_offset),
-            "the `split_offset` is not in the element to be split"
-        );
 
         // Now we really have to split. Reduce length of first element.
         let second_range = split_offset..elem.range.end;
         elem.range.end = split_offset;
         // Copy the data, and insert second element.
         let second = Elem { range: second_range, data: elem.data.clone() };
-        self.v.insert(index + 1, second);
-        return true;
+        chunk.insert(elem_idx + 1, second);
+        // Split the chunk if it got too big.
+        if chunk.len() > CHUNK_CAPACITY {
+            let second_half = chunk.split_off(chunk.len() / 2);
+            self.chunks.insert(chunk_idx + 1, second_half);
+        }
+    }
+
+    /// Merges neighbouring elements with equal data among the elements in `offset..end`, which
+    /// must start at `offset`. Elements are only merged with others in the same chunk.
+    fn merge_equal(&mut self, offset: u64, end: u64)
+    where
+        T: PartialEq,
+    {
+        let (mut chunk_idx, mut elem_idx) = self.find_offset(offset);
+        while let Some(chunk) = self.chunks.get_mut(chunk_idx) {
+            if chunk[elem_idx].range.start >= end {
+                break;
+            }
+            // The elements from `elem_idx` until `range_end` (exclusive) are in the range.
+            let in_range = chunk[elem_idx..].iter().take_while(|e| e.range.start < end).count();
+            let range_end = elem_idx + in_range;
+            // Compact the elements in place: each one is either merged into the last element we
+            // keep, or moved right behind it. The elements left over at the end are removed.
+            let mut last_kept = elem_idx;
+            for idx in elem_idx + 1..range_end {
+                if chunk[idx].data == chunk[last_kept].data {
+                    chunk[last_kept].range.end = chunk[idx].range.end;
+                } else {
+                    last_kept += 1;
+                    chunk.swap(last_kept, idx);
+                }
+            }
+            chunk.drain(last_kept + 1..range_end);
+            if last_kept + 1 < chunk.len() {
+                // The range ends in this chunk.
+                break;
+            }
+            chunk_idx += 1;
+            elem_idx = 0;
+        }
     }
 
     /// Provides mutable iteration over everything in the given range. As a side-effect,
@@ -122,82 +187,30 @@ impl<T> RangeMap<T> {
     {
         let offset = offset.bytes();
         let len = len.bytes();
-        // Compute a slice containing exactly the elements we care about
-        let slice: &mut [Elem<T>] = if len == 0 {
+        // The first offset that is not included any more.
+        let end = offset + len;
+        // Compute the elements of the first chunk we care about, and the chunks after it.
+        let (first, rest): (&mut [Elem<T>], &mut [Vec<Elem<T>>]) = if len == 0 {
             // We just need any empty iterator. We don't even want to
             // yield the element that surrounds this position, nor do
             // any splitting.
-            &mut []
+            (&mut [], &mut [])
         } else {
-            // Make sure we got a clear beginning
-            let mut first_idx = self.find_offset(offset);
-            if self.split_index(first_idx, offset) {
-                // The newly created 2nd element is ours
-                first_idx += 1;
-            }
-            // No more mutation.
-            let first_idx = first_idx;
-            // Find our end. Linear scan, but that's ok because the iteration
-            // is doing the same linear scan anyway -- no increase in complexity.
-            // We combine this scan with a scan for duplicates that we can merge, to reduce
-            // the number of elements.
-            // We stop searching after the first "block" of size 1, to avoid spending excessive
-            // amounts of time on the merging.
-            let mut equal_since_idx = first_idx;
-            // Once we see too many non-mergeable blocks, we stop.
-            // The initial value is chosen via... magic. Benchmarking and magic.
-            let mut successful_merge_count = 3usize;
-            // When the loop is done, this is the first excluded element.
-            let mut end_idx = first_idx;
-            loop {
-                // Compute if `end` is the last element we need to look at.
-                let done = self.v[end_idx].range.end >= offset + len;
-                // We definitely need to include `end`, so move the index.
-                end_idx += 1;
-                debug_assert!(
-                    done || end_idx < self.v.len(),
-                    "iter_mut: end-offset {} is out-of-bounds",
-                    offset + len
-                );
-                // see if we want to merge everything in `equal_since..end` (exclusive at the end!)
-                if successful_merge_count > 0 {
-                    if done || self.v[end_idx].data != self.v[equal_since_idx].data {
-                        // Everything in `equal_since..end` was equal. Make them just one element covering
-                        // the entire range.
-                        let removed_elems = end_idx - equal_since_idx - 1; // number of elements that we would remove
-                        if removed_elems > 0 {
-                            // Adjust the range of the first element to cover all of them.
-                            let equal_until = self.v[end_idx - 1].range.end; // end of range of last of the equal elements
-                            self.v[equal_since_idx].range.end = equal_until;
-                            // Delete the rest of them.
-                            self.v.splice(equal_since_idx + 1..end_idx, std::iter::empty());
-                            // Adjust `end_idx` because we made the list shorter.
-                            end_idx -= removed_elems;
-                            // Adjust the count for the cutoff.
-                            successful_merge_count += removed_elems;
-                        } else {
-                            // Adjust the count for the cutoff.
-                            successful_merge_count -= 1;
-                        }
-                        // Go on scanning for the next block starting here.
-                        equal_since_idx = end_idx;
-                    }
-                }
-                // Leave loop if this is the last element.
-                if done {
-                    break;
-                }
-            }
-            // Move to last included instead of first excluded index.
-            let end_idx = end_idx - 1;
-            // We need to split the end as well. Even if this performs a
-            // split, we don't have to adjust our index as we only care about
-            // the first part of the split.
-            self.split_index(end_idx, offset + len);
-            // Now we yield the slice. `end` is inclusive.
-            &mut self.v[first_idx..=end_idx]
+            // Make sure we got a clear beginning and end.
+            self.split_at(offset);
+            self.split_at(end);
+            // Scanning for duplicates costs no more than iterating over the range, and keeps
+            // the number of elements down.
+            self.merge_equal(offset, end);
+            let (chunk_idx, elem_idx) = self.find_offset(offset);
+            let (first, rest) = self.chunks[chunk_idx..].split_first_mut().unwrap();
+            (&mut first[elem_idx..], rest)
         };
-        slice.iter_mut().map(|elem| &mut elem.data)
+        first
+            .iter_mut()
+            .chain(rest.iter_mut().flatten())
+            .take_while(move |elem| elem.range.start < end)
+            .map(|elem| &mut elem.data)
     }
 }
 
@@ -222,7 +235,7 @@ mod tests {
         }
         // Check.
         assert_eq!(to_vec(&map, 10, 1), vec![42]);
-        assert_eq!(map.v.len(), 3);
+        assert_eq!(map.iter_all().count(), 3);
 
         // Insert with size 0.
         for x in map.iter_mut(Size::from_bytes(10), Size::from_bytes(0)) {
@@ -232,7 +245,7 @@ mod tests {
             *x = 19;
         }
         assert_eq!(to_vec(&map, 10, 2), vec![42, -1]);
-        assert_eq!(map.v.len(), 3);
+        assert_eq!(map.iter_all().count(), 3);
     }
 
     #[test]
@@ -244,7 +257,7 @@ mod tests {
         for x in map.iter_mut(Size::from_bytes(15), Size::from_bytes(1)) {
             *x = 43;
         }
-        assert_eq!(map.v.len(), 5);
+        assert_eq!(map.iter_all().count(), 5);
         assert_eq!(to_vec(&map, 10, 10), vec![-1, 42, -1, -1, -1, 43, -1, -1, -1, -1]);
 
         for x in map.iter_mut(Size::from_bytes(10), Size::from_bytes(10)) {
@@ -252,14 +265,14 @@ mod tests {
                 *x = 23;
             }
         }
-        assert_eq!(map.v.len(), 6);
+        assert_eq!(map.iter_all().count(), 6);
         assert_eq!(to_vec(&map, 10, 10), vec![23, 42, 23, 23, 23, 43, 23, 23, 23, 23]);
         assert_eq!(to_vec(&map, 13, 5), vec![23, 23, 43, 23, 23]);
 
         for x in map.iter_mut(Size::from_bytes(15), Size::from_bytes(5)) {
             *x = 19;
         }
-        assert_eq!(map.v.len(), 6);
+        assert_eq!(map.iter_all().count(), 6);
         assert_eq!(to_vec(&map, 10, 10), vec![23, 42, 23, 23, 23, 19, 19, 19, 19, 19]);
         // Should be seeing two blocks with 19.
         assert_eq!(
@@ -269,7 +282,38 @@ mod tests {
 
         // A NOP `iter_mut` should trigger merging.
         for _ in map.iter_mut(Size::from_bytes(15), Size::from_bytes(5)) {}
-        assert_eq!(map.v.len(), 5);
+        assert_eq!(map.iter_all().count(), 5);
         assert_eq!(to_vec(&map, 10, 10), vec![23, 42, 23, 23, 23, 19, 19, 19, 19, 19]);
     }
+
+    #[test]
+    fn many_ranges() {
+        let size = 4 * CHUNK_CAPACITY as u64;
+        let mut map = RangeMap::<u64>::new(Size::from_bytes(size), 0);
+        // Give every byte its own value, in an order that splits chunks in the middle.
+        for i in (0..size).rev().step_by(2).chain((0..size).step_by(2)) {
+            for x in map.iter_mut(Size::from_bytes(i), Size::from_bytes(1)) {
+                *x = i;
+            }
+        }
+        assert_eq!(map.iter_all().count(), size as usize);
+        assert!(map.chunks.len() > 1);
+        assert!(map.chunks.iter().all(|chunk| chunk.len() <= CHUNK_CAPACITY));
+        assert_eq!(to_vec(&map, 0, size), (0..size).collect::<Vec<_>>());
+        // Reading and writing across chunk boundaries.
+        let len = 2 * CHUNK_CAPACITY as u64;
+        assert_eq!(
+            map.iter(Size::from_bytes(100), Size::from_bytes(len)).copied().collect::<Vec<_>>(),
+            (100..100 + len).collect::<Vec<_>>()
+        );
+        for x in map.iter_mut(Size::from_bytes(100), Size::from_bytes(len)) {
+            *x = 0;
+        }
+        assert_eq!(to_vec(&map, 99, 2), vec![99, 0]);
+        assert_eq!(to_vec(&map, 99 + len, 2), vec![0, 100 + len]);
+        // Merging only happens within chunks, but it does happen.
+        for _ in map.iter_mut(Size::from_bytes(100), Size::from_bytes(len)) {}
+        assert!(map.iter_all().count() < size as usize - 100);
+        assert!(map.iter(Size::from_bytes(100), Size::from_bytes(len)).all(|&x| x == 0));
+    }
 }
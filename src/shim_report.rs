@@ -0,0 +1,122 @@
+//! Counting the calls of foreign functions, for `-Zmiri-shim-report`.
+//!
+//! Every call of a foreign function is recorded with how its shim handled it: the shim emulated
+//! the function, it emulated the function only approximately (the shim says so by calling
+//! `note_partial_emulation`), the function is not available under isolation, or Miri does not
+//! support it (or the arguments it was called with).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+use rustc_hir::def_id::DefId;
+
+use crate::*;
+
+/// The calls of a single foreign function.
+#[derive(Debug, Default)]
+struct ShimCalls {
+    emulated: u64,
+    partially_emulated: u64,
+    rejected_by_isolation: u64,
+    unsupported: u64,
+    /// What the shim did not emulate, for the partially emulated calls.
+    approximations: BTreeSet<&'static str>,
+}
+
+impl ShimCalls {
+    fn total(&self) -> u64 {
+        self.emulated + self.partially_emulated + self.rejected_by_isolation + self.unsupported
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ShimReport {
+    calls: BTreeMap<String, ShimCalls>,
+    /// What the shim that is currently running did not emulate, if anything.
+    approximation: Option<&'static str>,
+}
+
+impl ShimReport {
+    fn record<T>(&mut self, link_name: &str, res: &InterpResult<'_, T>) {
+        let approximation = self.approximation.take();
+        let calls = self.calls.entry(link_name.to_owned()).or_default();
+        match res {
+            Err(err) => match &err.kind {
+                err_unsup!(Unsupported(msg)) if msg.contains("when isolation is enabled") =>
+                    calls.rejected_by_isolation += 1,
+                InterpError::Unsupported(_) => calls.unsupported += 1,
+                // The shim found the program to misbehave (or it stopped the program, like
+                // `exit`), so it did its job.
+                _ => calls.emulated += 1,
+            },
+            Ok(_) =>
+                if let Some(approximation) = approximation {
+                    calls.partially_emulated += 1;
+                    calls.approximations.insert(approximation);
+                } else {
+                    calls.emulated += 1;
+                },
+        }
+    }
+
+    /// Renders the report: one line per foreign function, with its calls by outcome.
+    pub fn report(&self) -> String {
+        let mut report = format!("miri: {} foreign functions were called:\n", self.calls.len());
+        for (name, calls) in &self.calls {
+            let total = calls.total();
+            let outcomes: Vec<String> = [
+                (calls.emulated, "emulated"),
+                (calls.partially_emulated, "partially emulated"),
+                (calls.rejected_by_isolation, "rejected by isolation"),
+                (calls.unsupported, "unsupported"),
+            ]
+            .iter()
+            .filter(|&&(count, _)| count > 0)
+            .map(|&(count, outcome)| {
+                if count == total { outcome.to_owned() } else { format!("{} {}", count, outcome) }
+            })
+            .collect();
+            write!(
+                report,
+                "    {}: {} call{}, {}",
+                name,
+                total,
+                if total == 1 { "" } else { "s" },
+                outcomes.join(", ")
+            )
+            .unwrap();
+            if !calls.approximations.is_empty() {
+                let approximations: Vec<&str> = calls.approximations.iter().copied().collect();
+                write!(report, " ({})", approximations.join("; ")).unwrap();
+            }
+            report.push('\n');
+        }
+        report
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Records the call of the foreign item `def_id`, which was emulated with result `res`.
+    fn record_shim_call<T>(&mut self, def_id: DefId, res: &InterpResult<'tcx, T>) {
+        let this = self.eval_context_mut();
+        let report = match &mut this.machine.shim_report {
+            Some(report) => report,
+            None => return,
+        };
+        // `emulate_foreign_item` looked up the link name.
+        let link_name = match this.machine.link_names.get(&def_id) {
+            Some(name) => name.as_str(),
+            None => return,
+        };
+        report.record(link_name.trim_end_matches("$UNIX2003"), res);
+    }
+
+    /// Called by shims that do not emulate everything the function does, with what is missing.
+    fn note_partial_emulation(&mut self, approximation: &'static str) {
+        let this = self.eval_context_mut();
+        if let Some(report) = &mut this.machine.shim_report {
+            report.approximation = Some(approximation);
+        }
+    }
+}
@@ -1,27 +1,145 @@
 use rustc::mir;
+use rustc::ty::layout::{Align, Size};
 
+use crate::shims::foreign_items::windows;
 use crate::*;
 
 #[derive(Debug, Copy, Clone)]
 pub enum Dlsym {
     GetEntropy,
+    Accept4,
+    // Returned by `GetProcAddress` on Windows.
+    GetSystemTimePreciseAsFileTime,
+    WaitOnAddress,
+    WakeByAddressSingle,
+    WakeByAddressAll,
 }
 
 impl Dlsym {
     // Returns an error for unsupported symbols, and None if this symbol
     // should become a NULL pointer (pretend it does not exist).
-    pub fn from_str(name: &str) -> InterpResult<'static, Option<Dlsym>> {
+    pub fn from_str(name: &str, target_os: &str) -> InterpResult<'static, Option<Dlsym>> {
         use self::Dlsym::*;
+        if target_os == "windows" {
+            // `std` looks up optional APIs this way, and falls back to something else if they
+            // do not exist.
+            return Ok(match name {
+                "GetSystemTimePreciseAsFileTime" => Some(GetSystemTimePreciseAsFileTime),
+                "WaitOnAddress" => Some(WaitOnAddress),
+                "WakeByAddressSingle" => Some(WakeByAddressSingle),
+                "WakeByAddressAll" => Some(WakeByAddressAll),
+                _ => None,
+            });
+        }
         Ok(match name {
             "getentropy" => Some(GetEntropy),
-            "__pthread_get_minstack" => None,
-            _ => throw_unsup_format!("Unsupported dlsym: {}", name),
+            "accept4" => Some(Accept4),
+            // Everything else does not exist, like in a program that is linked statically. This
+            // includes `__pthread_get_minstack` and `gnu_get_libc_version` (which `std` calls
+            // when name resolution fails), and whatever feature-probing code looks for.
+            _ => None,
         })
     }
 }
 
+/// The state of the `dlopen` family of functions. Miri cannot load shared libraries, so the only
+/// handle there is is the one for the program itself.
+#[derive(Debug, Default)]
+pub struct DynamicLinker {
+    /// The handle returned by `dlopen(NULL, ..)`, once it was called.
+    handle: Option<Pointer<Tag>>,
+    /// The message describing the last error, until `dlerror` returns it.
+    error: Option<String>,
+    /// The string that `dlerror` returned last. Like in glibc, it is freed by the next call.
+    error_buf: Option<Pointer<Tag>>,
+}
+
 impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
 pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    fn dlopen(
+        &mut self,
+        filename_op: OpTy<'tcx, Tag>,
+        _flag_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, Scalar<Tag>> {
+        let this = self.eval_context_mut();
+        let filename = this.read_scalar(filename_op)?.not_undef()?;
+        if this.is_null(filename)? {
+            let handle = match this.machine.dynamic_linker.handle {
+                Some(handle) => handle,
+                None => {
+                    // The handle is opaque to the program, the allocation only gives it a unique
+                    // address.
+                    let handle = this.memory.allocate(
+                        Size::from_bytes(1),
+                        Align::from_bytes(1).unwrap(),
+                        MiriMemoryKind::Env.into(),
+                    );
+                    this.machine.dynamic_linker.handle = Some(handle);
+                    handle
+                }
+            };
+            return Ok(handle.into());
+        }
+        let filename = this.read_os_str_from_c_str(filename)?;
+        this.machine.dynamic_linker.error = Some(format!(
+            "{}: cannot open shared object file: Miri does not support loading shared libraries",
+            filename.to_string_lossy(),
+        ));
+        Ok(Scalar::ptr_null(&*this.tcx))
+    }
+
+    fn dlsym(
+        &mut self,
+        handle_op: OpTy<'tcx, Tag>,
+        symbol_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, Scalar<Tag>> {
+        let this = self.eval_context_mut();
+        // Integers are pseudo-handles like `RTLD_DEFAULT` and `RTLD_NEXT`, whose values differ
+        // between targets. All of them look up symbols in the program, like our handle.
+        if let Scalar::Ptr(handle) = this.read_scalar(handle_op)?.not_undef()? {
+            if Some(handle) != this.machine.dynamic_linker.handle {
+                throw_ub_format!("`dlsym` called with a handle that was not returned by `dlopen`");
+            }
+        }
+        let symbol = this.read_scalar(symbol_op)?.not_undef()?;
+        let symbol_name = this.memory.read_c_str(symbol)?;
+        let symbol_name = String::from_utf8_lossy(symbol_name).into_owned();
+        let target_os = &this.tcx.sess.target.target.target_os;
+        match Dlsym::from_str(&symbol_name, target_os)? {
+            Some(dlsym) => Ok(this.memory.create_fn_alloc(FnVal::Other(dlsym)).into()),
+            None => {
+                this.machine.dynamic_linker.error =
+                    Some(format!("undefined symbol: {}", symbol_name));
+                Ok(Scalar::ptr_null(&*this.tcx))
+            }
+        }
+    }
+
+    fn dlerror(&mut self) -> InterpResult<'tcx, Scalar<Tag>> {
+        let this = self.eval_context_mut();
+        if let Some(buf) = this.machine.dynamic_linker.error_buf.take() {
+            this.memory.deallocate(buf, None, MiriMemoryKind::Env.into())?;
+        }
+        match this.machine.dynamic_linker.error.take() {
+            Some(error) => {
+                let buf = this.alloc_os_str_as_c_str(error.as_ref(), MiriMemoryKind::Env.into());
+                this.machine.dynamic_linker.error_buf = Some(buf);
+                Ok(buf.into())
+            }
+            None => Ok(Scalar::ptr_null(&*this.tcx)),
+        }
+    }
+
+    fn dlclose(&mut self, handle_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+        let handle = this.read_scalar(handle_op)?.not_undef()?;
+        if this.machine.dynamic_linker.handle.map(Scalar::from) != Some(handle) {
+            throw_ub_format!("`dlclose` called with a handle that was not returned by `dlopen`");
+        }
+        // The program cannot be unloaded, so the handle stays valid.
+        Ok(0)
+    }
+
     fn call_dlsym(
         &mut self,
         dlsym: Dlsym,
@@ -37,8 +155,26 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             GetEntropy => {
                 let ptr = this.read_scalar(args[0])?.not_undef()?;
                 let len = this.read_scalar(args[1])?.to_machine_usize(this)?;
-                this.gen_random(ptr, len as usize)?;
-                this.write_null(dest)?;
+                let result = this.getentropy(ptr, len)?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+            Accept4 => {
+                let result = this.accept(args[0], args[1], args[2], Some(args[3]))?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+            GetSystemTimePreciseAsFileTime | WaitOnAddress | WakeByAddressSingle
+            | WakeByAddressAll => {
+                // These are regular foreign functions as well.
+                let link_name = match dlsym {
+                    GetSystemTimePreciseAsFileTime => "GetSystemTimePreciseAsFileTime",
+                    WaitOnAddress => "WaitOnAddress",
+                    WakeByAddressSingle => "WakeByAddressSingle",
+                    WakeByAddressAll => "WakeByAddressAll",
+                    _ => unreachable!(),
+                };
+                windows::EvalContextExt::emulate_foreign_item_by_name(
+                    this, link_name, args, dest, ret,
+                )?;
             }
         }
 
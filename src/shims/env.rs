@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 use std::ffi::{OsString, OsStr};
 use std::env;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
 
 use crate::stacked_borrows::Tag;
 use crate::*;
@@ -8,6 +11,10 @@ use crate::*;
 use rustc::ty::layout::Size;
 use rustc_mir::interpret::Pointer;
 
+// Windows error codes of `SetCurrentDirectoryW`.
+const ERROR_INVALID_PARAMETER: u32 = 87;
+const ERROR_DIRECTORY: u32 = 267;
+
 #[derive(Default)]
 pub struct EnvVars {
     /// Stores pointers to the environment variables. These variables must be stored as
@@ -117,37 +124,100 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
     ) -> InterpResult<'tcx, Scalar<Tag>> {
         let this = self.eval_context_mut();
 
-        this.check_no_isolation("getcwd")?;
-
         let buf = this.read_scalar(buf_op)?.not_undef()?;
         let size = this.read_scalar(size_op)?.to_machine_usize(&*this.tcx)?;
-        // If we cannot get the current directory, we return null
-        match env::current_dir() {
-            Ok(cwd) => {
-                if this.write_os_str_to_c_str(&OsString::from(cwd), buf, size)? {
-                    return Ok(buf);
-                }
-                let erange = this.eval_libc("ERANGE")?;
-                this.set_last_error(erange)?;
-            }
-            Err(e) => this.set_last_error_from_io_error(e)?,
+        let cwd = this.machine.current_dir.clone();
+        let cwd = this.convert_path(cwd.as_os_str(), PathConversion::HostToTarget)?;
+        if this.write_os_str_to_c_str(&cwd, buf, size)? {
+            return Ok(buf);
         }
+        let erange = this.eval_libc("ERANGE")?;
+        this.set_last_error(erange)?;
         Ok(Scalar::ptr_null(&*this.tcx))
     }
 
     fn chdir(&mut self, path_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, i32> {
         let this = self.eval_context_mut();
 
-        this.check_no_isolation("chdir")?;
-
-        let path = this.read_os_str_from_c_str(this.read_scalar(path_op)?.not_undef()?)?;
+        let path = this.read_path_from_c_str(this.read_scalar(path_op)?.not_undef()?)?;
+        if path.as_os_str().is_empty() {
+            let enoent = this.eval_libc("ENOENT")?;
+            this.set_last_error(enoent)?;
+            return Ok(-1);
+        }
 
-        match env::set_current_dir(path) {
-            Ok(()) => Ok(0),
+        match this.change_current_dir(path) {
+            Ok(true) => Ok(0),
+            Ok(false) => {
+                let enotdir = this.eval_libc("ENOTDIR")?;
+                this.set_last_error(enotdir)?;
+                Ok(-1)
+            }
             Err(e) => {
                 this.set_last_error_from_io_error(e)?;
                 Ok(-1)
             }
         }
     }
+
+    #[allow(non_snake_case)]
+    fn SetCurrentDirectoryW(&mut self, path_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let path = this.read_path_from_wide_str(this.read_scalar(path_op)?.not_undef()?)?;
+        if path.as_os_str().is_empty() {
+            this.set_last_error(Scalar::from_u32(ERROR_INVALID_PARAMETER))?;
+            return Ok(0);
+        }
+
+        match this.change_current_dir(path) {
+            Ok(true) => Ok(1),
+            Ok(false) => {
+                this.set_last_error(Scalar::from_u32(ERROR_DIRECTORY))?;
+                Ok(0)
+            }
+            Err(e) => {
+                this.set_last_error_from_io_error(e)?;
+                Ok(0)
+            }
+        }
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExtPrivate<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+trait EvalContextExtPrivate<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Makes the host path `path` the current directory of the program, and returns whether it
+    /// is a directory. Under isolation, there is no file system to check the path against, so
+    /// every directory exists. Otherwise, the path has to name a directory on the host, like for
+    /// the real `chdir`.
+    fn change_current_dir(&mut self, path: PathBuf) -> io::Result<bool> {
+        let this = self.eval_context_mut();
+        let path = if this.machine.communicate {
+            let path = fs::canonicalize(&path)?;
+            if !path.is_dir() {
+                return Ok(false);
+            }
+            path
+        } else {
+            normalize_lexically(&path)
+        };
+        // The host's current directory stays the same, see `Evaluator::current_dir`.
+        this.machine.current_dir = path;
+        Ok(true)
+    }
+}
+
+/// Removes the `.` and `..` components of an absolute path, without looking at the file system.
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
 }
@@ -0,0 +1,273 @@
+//! Emulation of Linux's `epoll` readiness notifications over Miri's emulated file descriptors.
+//!
+//! Readiness is computed on demand from `FileDescriptor::readiness`, so all notifications are
+//! level-triggered. Edge-triggered interests (`EPOLLET`) are accepted as well: reporting an event
+//! again while the descriptor stays ready is a spurious wakeup that correct programs must handle
+//! anyway.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io;
+
+use rustc::ty::layout::{LayoutOf, Size};
+
+use crate::*;
+
+/// The events and user data registered for one file descriptor.
+#[derive(Debug, Clone, Copy)]
+struct Interest {
+    events: u32,
+    data: u64,
+}
+
+/// An epoll instance.
+#[derive(Debug, Default)]
+pub struct Epoll {
+    interests: BTreeMap<i32, Interest>,
+}
+
+impl FileDescriptor for Epoll {
+    fn as_epoll_mut(&mut self) -> Option<&mut Epoll> {
+        Some(self)
+    }
+
+    fn read<'tcx>(&mut self, _bytes: &mut [u8]) -> InterpResult<'tcx, io::Result<usize>> {
+        Ok(Err(io::ErrorKind::InvalidInput.into()))
+    }
+
+    fn write<'tcx>(&mut self, _bytes: &[u8]) -> InterpResult<'tcx, io::Result<usize>> {
+        Ok(Err(io::ErrorKind::InvalidInput.into()))
+    }
+
+    fn close<'tcx>(self: Box<Self>) -> InterpResult<'tcx, io::Result<i32>> {
+        Ok(Ok(0))
+    }
+
+    fn dup<'tcx>(&mut self) -> InterpResult<'tcx, io::Result<Box<dyn FileDescriptor>>> {
+        throw_unsup_format!("duplicating epoll instances is not supported")
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExtPrivate<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+trait EvalContextExtPrivate<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    fn epoll_error<T: From<i32>>(&mut self, errno: &str) -> InterpResult<'tcx, T> {
+        let this = self.eval_context_mut();
+        let errno = this.eval_libc(errno)?;
+        this.set_last_error(errno)?;
+        Ok((-1).into())
+    }
+
+    /// Computes the `epoll_event::events` to report for `interest`, given the readiness of its
+    /// file descriptor. `EPOLLHUP` is always reported, even if it was not requested.
+    fn epoll_events(
+        &mut self,
+        interest: Interest,
+        readiness: Readiness,
+    ) -> InterpResult<'tcx, u32> {
+        let this = self.eval_context_mut();
+        let mut events = 0;
+        for &(flag, ready) in &[
+            ("EPOLLIN", readiness.readable),
+            ("EPOLLOUT", readiness.writable),
+            ("EPOLLRDHUP", readiness.read_hangup),
+        ] {
+            let flag = this.eval_libc(flag)?.to_u32()?;
+            if ready && interest.events & flag != 0 {
+                events |= flag;
+            }
+        }
+        if readiness.hangup {
+            events |= this.eval_libc("EPOLLHUP")?.to_u32()?;
+        }
+        Ok(events)
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    fn epoll_create1(&mut self, flags_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let flags = this.read_scalar(flags_op)?.to_i32()?;
+        // We ignore `EPOLL_CLOEXEC` because `exec` is not supported anyway.
+        if flags & !this.eval_libc_i32("EPOLL_CLOEXEC")? != 0 {
+            return this.epoll_error("EINVAL");
+        }
+
+        Ok(this.machine.file_handler.insert_fd(Box::new(Epoll::default())))
+    }
+
+    fn epoll_ctl(
+        &mut self,
+        epfd_op: OpTy<'tcx, Tag>,
+        op_op: OpTy<'tcx, Tag>,
+        fd_op: OpTy<'tcx, Tag>,
+        event_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let epfd = this.read_scalar(epfd_op)?.to_i32()?;
+        let op = this.read_scalar(op_op)?.to_i32()?;
+        let fd = this.read_scalar(fd_op)?.to_i32()?;
+
+        let epoll_ctl_add = this.eval_libc_i32("EPOLL_CTL_ADD")?;
+        let epoll_ctl_mod = this.eval_libc_i32("EPOLL_CTL_MOD")?;
+        let epoll_ctl_del = this.eval_libc_i32("EPOLL_CTL_DEL")?;
+
+        // The event is ignored (and may be null) for `EPOLL_CTL_DEL`.
+        let interest = if op == epoll_ctl_add || op == epoll_ctl_mod {
+            let event = this.deref_operand(event_op)?;
+            let events = this.read_scalar(this.mplace_field(event, 0)?.into())?.to_u32()?;
+            let data = this.read_scalar(this.mplace_field(event, 1)?.into())?.to_u64()?;
+            let mut supported = 0;
+            for flag in &[
+                "EPOLLIN",
+                "EPOLLOUT",
+                "EPOLLRDHUP",
+                "EPOLLPRI",
+                "EPOLLERR",
+                "EPOLLHUP",
+                "EPOLLET",
+                "EPOLLONESHOT",
+            ] {
+                supported |= this.eval_libc(flag)?.to_u32()?;
+            }
+            if events & !supported != 0 {
+                throw_unsup_format!("unsupported epoll events {:#x}", events & !supported);
+            }
+            Some(Interest { events, data })
+        } else if op == epoll_ctl_del {
+            None
+        } else {
+            return this.epoll_error("EINVAL");
+        };
+
+        let handles = &mut this.machine.file_handler.handles;
+        let pollable = match handles.get(&fd) {
+            Some(file_descriptor) => file_descriptor.readiness().is_some(),
+            None => return this.epoll_error("EBADF"),
+        };
+        let epoll = match handles.get_mut(&epfd) {
+            Some(file_descriptor) =>
+                match file_descriptor.as_epoll_mut() {
+                    Some(epoll) if fd != epfd => epoll,
+                    _ => return this.epoll_error("EINVAL"),
+                },
+            None => return this.epoll_error("EBADF"),
+        };
+        if !pollable {
+            return this.epoll_error("EPERM");
+        }
+
+        let result = match interest {
+            Some(interest) if op == epoll_ctl_add =>
+                if epoll.interests.contains_key(&fd) {
+                    Err("EEXIST")
+                } else {
+                    epoll.interests.insert(fd, interest);
+                    Ok(0)
+                },
+            Some(interest) =>
+                match epoll.interests.get_mut(&fd) {
+                    Some(old) => {
+                        *old = interest;
+                        Ok(0)
+                    }
+                    None => Err("ENOENT"),
+                },
+            None =>
+                match epoll.interests.remove(&fd) {
+                    Some(_) => Ok(0),
+                    None => Err("ENOENT"),
+                },
+        };
+        match result {
+            Ok(ok) => Ok(ok),
+            Err(errno) => this.epoll_error(errno),
+        }
+    }
+
+    fn epoll_wait(
+        &mut self,
+        epfd_op: OpTy<'tcx, Tag>,
+        events_op: OpTy<'tcx, Tag>,
+        maxevents_op: OpTy<'tcx, Tag>,
+        timeout_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let epfd = this.read_scalar(epfd_op)?.to_i32()?;
+        let events_ptr = this.read_scalar(events_op)?.not_undef()?;
+        let maxevents = this.read_scalar(maxevents_op)?.to_i32()?;
+        let timeout = this.read_scalar(timeout_op)?.to_i32()?;
+
+        let maxevents = match usize::try_from(maxevents) {
+            Ok(maxevents) if maxevents > 0 => maxevents,
+            _ => return this.epoll_error("EINVAL"),
+        };
+        let interests = match this.machine.file_handler.handles.get_mut(&epfd) {
+            Some(file_descriptor) =>
+                match file_descriptor.as_epoll_mut() {
+                    Some(epoll) => epoll.interests.clone(),
+                    None => return this.epoll_error("EINVAL"),
+                },
+            None => return this.epoll_error("EBADF"),
+        };
+
+        // Collect the ready events. Interests whose file descriptor was closed are dropped (Linux
+        // only does that once every duplicate of the descriptor is closed, but we do not support
+        // duplicating pollable descriptors).
+        let epoll_oneshot = this.eval_libc("EPOLLONESHOT")?.to_u32()?;
+        let mut ready = Vec::new();
+        let mut closed = Vec::new();
+        for (&fd, &interest) in &interests {
+            if ready.len() == maxevents {
+                break;
+            }
+            let readiness = match this.machine.file_handler.handles.get(&fd) {
+                Some(file_descriptor) => file_descriptor.readiness().unwrap(),
+                None => {
+                    closed.push(fd);
+                    continue;
+                }
+            };
+            let events = this.epoll_events(interest, readiness)?;
+            if events != 0 {
+                ready.push((fd, events, interest));
+            }
+        }
+        let epoll = this.machine.file_handler.handles.get_mut(&epfd).unwrap();
+        let epoll = epoll.as_epoll_mut().unwrap();
+        for fd in closed {
+            epoll.interests.remove(&fd);
+        }
+        // One-shot interests are disabled once they reported an event, until `EPOLL_CTL_MOD`.
+        for &(fd, _, interest) in &ready {
+            if interest.events & epoll_oneshot != 0 {
+                epoll.interests.get_mut(&fd).unwrap().events = 0;
+            }
+        }
+
+        if ready.is_empty() && timeout < 0 {
+            // Nothing can become ready while we are waiting: there are no other threads.
+            throw_machine_stop!(TerminationInfo::Deadlock(
+                "waiting without timeout on an epoll instance with no ready file descriptors"
+                    .to_owned()
+            ));
+        }
+        // With a timeout, waiting would just let the time run out.
+
+        let event_layout = this.libc_ty_layout("epoll_event")?;
+        let event_ptr_layout = this.layout_of(this.tcx.mk_mut_ptr(event_layout.ty))?;
+        for (i, &(_, events, interest)) in ready.iter().enumerate() {
+            let offset = Size::from_bytes(i as u64 * event_layout.size.bytes());
+            let ptr = events_ptr.ptr_offset(offset, this)?;
+            let event = this.ref_to_mplace(ImmTy::from_scalar(ptr, event_ptr_layout))?;
+            let events_place = this.mplace_field(event, 0)?;
+            this.write_scalar(Scalar::from_u32(events), events_place.into())?;
+            let data_place = this.mplace_field(event, 1)?;
+            this.write_scalar(Scalar::from_u64(interest.data), data_place.into())?;
+        }
+        Ok(ready.len() as i32)
+    }
+}
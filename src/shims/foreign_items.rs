@@ -1,14 +1,17 @@
-mod windows;
+pub mod windows;
 mod posix;
+mod wasm;
+pub mod x86;
 
 use std::{convert::TryInto, iter};
 
-use rustc_hir::def_id::DefId;
+use rustc_hir::def_id::{DefId, LOCAL_CRATE};
 use rustc::mir;
 use rustc::ty;
 use rustc::ty::layout::{Align, Size};
 use rustc_apfloat::Float;
-use rustc_span::symbol::sym;
+use rustc_span::{source_map::Span, symbol::sym};
+use rustc_target::spec::PanicStrategy;
 use syntax::attr;
 
 use crate::*;
@@ -46,24 +49,43 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
 
     fn malloc(&mut self, size: u64, zero_init: bool, kind: MiriMemoryKind) -> Scalar<Tag> {
         let this = self.eval_context_mut();
-        if size == 0 {
-            Scalar::from_int(0, this.pointer_size())
+        // `malloc(0)` returns a unique pointer that must be passed to `free`; we use a
+        // zero-sized allocation for that. (The Windows heap aligns those as well.)
+        let align = if size == 0 && kind != MiriMemoryKind::WinHeap {
+            Align::from_bytes(1).unwrap()
         } else {
-            let align = this.min_align(size, kind);
-            let ptr = this.memory.allocate(Size::from_bytes(size), align, kind.into());
-            if zero_init {
-                // We just allocated this, the access is definitely in-bounds.
-                this.memory.write_bytes(ptr.into(), iter::repeat(0u8).take(size as usize)).unwrap();
-            }
-            Scalar::Ptr(ptr)
+            this.min_align(size, kind)
+        };
+        let ptr = this.memory.allocate(Size::from_bytes(size), align, kind.into());
+        if zero_init {
+            // We just allocated this, the access is definitely in-bounds.
+            this.zero_fresh_allocation(ptr).unwrap();
         }
+        this.record_alloc_site(ptr.alloc_id, kind);
+        Scalar::Ptr(ptr)
+    }
+
+    /// Allocates C heap memory with the given alignment (which must be a power of two), as done
+    /// by `posix_memalign` and friends. The memory can be released with `free`.
+    fn aligned_malloc(&mut self, size: u64, align: u64) -> InterpResult<'tcx, Scalar<Tag>> {
+        let this = self.eval_context_mut();
+        // Never less aligned than `malloc` would be for this size.
+        let align = Align::from_bytes(align)
+            .map_err(|err| err_unsup_format!("unsupported allocation alignment: {}", err))?
+            .max(this.min_align(size, MiriMemoryKind::C));
+        let ptr = this.memory.allocate(Size::from_bytes(size), align, MiriMemoryKind::C.into());
+        this.record_alloc_site(ptr.alloc_id, MiriMemoryKind::C);
+        Ok(Scalar::Ptr(ptr))
     }
 
     fn free(&mut self, ptr: Scalar<Tag>, kind: MiriMemoryKind) -> InterpResult<'tcx> {
         let this = self.eval_context_mut();
         if !this.is_null(ptr)? {
             let ptr = this.force_ptr(ptr)?;
+            this.check_double_free(ptr)?;
+            this.check_dealloc_kind(ptr, kind)?;
             this.memory.deallocate(ptr, None, kind.into())?;
+            this.record_free(ptr.alloc_id);
         }
         Ok(())
     }
@@ -75,21 +97,20 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         kind: MiriMemoryKind,
     ) -> InterpResult<'tcx, Scalar<Tag>> {
         let this = self.eval_context_mut();
-        let new_align = this.min_align(new_size, kind);
         if this.is_null(old_ptr)? {
-            if new_size == 0 {
-                Ok(Scalar::from_int(0, this.pointer_size()))
-            } else {
-                let new_ptr =
-                    this.memory.allocate(Size::from_bytes(new_size), new_align, kind.into());
-                Ok(Scalar::Ptr(new_ptr))
-            }
+            // `realloc(NULL, n)` is `malloc(n)`.
+            Ok(this.malloc(new_size, /*zero_init:*/ false, kind))
         } else {
             let old_ptr = this.force_ptr(old_ptr)?;
+            this.check_double_free(old_ptr)?;
+            this.check_dealloc_kind(old_ptr, kind)?;
             if new_size == 0 {
+                // `realloc(ptr, 0)` frees `ptr` (and returns `NULL`, like glibc does).
                 this.memory.deallocate(old_ptr, None, kind.into())?;
+                this.record_free(old_ptr.alloc_id);
                 Ok(Scalar::from_int(0, this.pointer_size()))
             } else {
+                let new_align = this.min_align(new_size, kind);
                 let new_ptr = this.memory.reallocate(
                     old_ptr,
                     None,
@@ -97,11 +118,179 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     new_align,
                     kind.into(),
                 )?;
+                this.record_free(old_ptr.alloc_id);
+                this.record_alloc_site(new_ptr.alloc_id, kind);
                 Ok(Scalar::Ptr(new_ptr))
             }
         }
     }
 
+    /// Remembers where `alloc_id` was (successfully) deallocated by `free`, `__rust_dealloc` and
+    /// similar functions, so that a later double free or dangling reference can point to it.
+    fn record_free(&mut self, alloc_id: AllocId) {
+        let this = self.eval_context_mut();
+        let site = this.local_call_site();
+        let backtrace = if this.machine.dealloc_backtraces {
+            Some(this.generate_stacktrace(None))
+        } else {
+            None
+        };
+        this.machine.freed_allocations.record(alloc_id, site, backtrace);
+        this.machine.alloc_sites.remove(&alloc_id);
+        this.machine.test_harness.note_free(alloc_id);
+    }
+
+    /// Reports a double free if `ptr` points to an allocation that was already freed via
+    /// `free` or similar functions.
+    fn check_double_free(&mut self, ptr: Pointer<Tag>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        if let Some(notes) =
+            this.machine.freed_allocations.describe(
+                ptr.alloc_id,
+                "was already freed",
+                this.tcx.sess.source_map(),
+            )
+        {
+            let msg = format!("double free of {:?}", ptr.alloc_id);
+            let kind = UbKind::InvalidDealloc;
+            throw_machine_stop!(TerminationInfo::UndefinedBehavior { kind, msg, notes });
+        }
+        Ok(())
+    }
+
+    /// The statement that is being executed in the innermost frame of the local crate (or in the
+    /// innermost frame, if there is no local one).
+    fn local_call_site(&self) -> Option<Span> {
+        let this = self.eval_context_ref();
+        let frame = this
+            .stack()
+            .iter()
+            .rev()
+            .find(|frame| frame.instance.def_id().is_local())
+            .or_else(|| this.stack().last());
+        frame.and_then(Frame::current_source_info).map(|source_info| source_info.span)
+    }
+
+    /// Whether a frame of the foreign item `def_id` is already on the stack. For the items that
+    /// are forwarded to a handler of the program, that means the handler failed again.
+    fn is_running(&self, def_id: DefId) -> bool {
+        let this = self.eval_context_ref();
+        this.stack().iter().any(|frame| frame.instance.def_id() == def_id)
+    }
+
+    /// Remembers how and where the heap allocation `alloc_id` was created.
+    fn record_alloc_site(&mut self, alloc_id: AllocId, kind: MiriMemoryKind) {
+        let this = self.eval_context_mut();
+        let span = this.local_call_site();
+        this.machine.alloc_sites.insert(alloc_id, AllocSite { kind, span });
+        this.machine.tls.note_allocation(alloc_id);
+        this.machine.test_harness.note_allocation(alloc_id);
+    }
+
+    /// Checks that the heap allocation `ptr` points to is deallocated by the allocator that
+    /// created it (like `free` for `malloc`, and `__rust_dealloc` for `__rust_alloc`).
+    fn check_dealloc_kind(
+        &mut self,
+        ptr: Pointer<Tag>,
+        kind: MiriMemoryKind,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let site = match this.machine.alloc_sites.get(&ptr.alloc_id) {
+            Some(&site) if site.kind != kind => site,
+            _ => return Ok(()),
+        };
+        let source_map = this.tcx.sess.source_map();
+        let mut notes = Vec::new();
+        if let Some(span) = site.span {
+            notes.push(format!(
+                "{:?} was allocated here: {}",
+                ptr.alloc_id,
+                source_map.span_to_string(span)
+            ));
+        }
+        if let Some(span) = this.local_call_site() {
+            notes.push(format!("and it is deallocated here: {}", source_map.span_to_string(span)));
+        }
+        let msg = format!(
+            "{:?} was allocated with {}, but it is deallocated with {}",
+            ptr.alloc_id,
+            heap_allocator_name(site.kind),
+            heap_allocator_name(kind),
+        );
+        throw_machine_stop!(TerminationInfo::UndefinedBehavior {
+            kind: UbKind::InvalidDealloc,
+            msg,
+            notes,
+        })
+    }
+
+    /// If the program has a `#[global_allocator]`, returns its function that implements the
+    /// allocator function `link_name` (like `__rg_alloc` for `__rust_alloc`).
+    fn global_allocator_fn(&mut self, link_name: &str) -> Option<ty::Instance<'tcx>> {
+        let this = self.eval_context_mut();
+        let impl_name = match link_name {
+            "__rust_alloc" => "__rg_alloc",
+            "__rust_alloc_zeroed" => "__rg_alloc_zeroed",
+            "__rust_dealloc" => "__rg_dealloc",
+            "__rust_realloc" => "__rg_realloc",
+            _ => return None,
+        };
+        if let Some(&instance) = this.machine.global_allocator_fns.get(link_name) {
+            return instance;
+        }
+        let tcx = this.tcx.tcx;
+        // The allocator functions are generated inside an anonymous constant, so we cannot find
+        // them by their path; they are however always reachable from other crates.
+        let instance = iter::once(LOCAL_CRATE)
+            .chain(tcx.crates().iter().copied())
+            .find(|&cnum| tcx.has_global_allocator(cnum))
+            .and_then(|cnum| {
+                tcx.reachable_non_generics(cnum)
+                    .keys()
+                    .copied()
+                    .find(|&def_id| tcx.item_name(def_id).as_str() == impl_name)
+            })
+            .map(|def_id| ty::Instance::mono(tcx, def_id));
+        this.machine.global_allocator_fns.insert(link_name.to_owned(), instance);
+        instance
+    }
+
+    /// Checks that `__rust_dealloc`/`__rust_realloc` are passed the same layout that the
+    /// allocation was created with.
+    fn check_rust_dealloc_layout(
+        &mut self,
+        ptr: Pointer<Tag>,
+        size: Size,
+        align: Align,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        // Dangling pointers are reported by `deallocate` itself.
+        let (alloc_size, alloc_align) =
+            match this.memory.get_size_and_align(ptr.alloc_id, AllocCheck::Live) {
+                Ok(layout) => layout,
+                Err(_) => return Ok(()),
+            };
+        if (alloc_size, alloc_align) != (size, align) {
+            let mut notes = Vec::new();
+            if let Some(span) = this.machine.alloc_sites.get(&ptr.alloc_id).and_then(|s| s.span) {
+                let site = this.tcx.sess.source_map().span_to_string(span);
+                notes.push(format!("{:?} was allocated here: {}", ptr.alloc_id, site));
+            }
+            let msg = format!(
+                "incorrect layout on deallocation: {:?} has size {} and alignment {}, \
+                but it was deallocated with size {} and alignment {}",
+                ptr.alloc_id,
+                alloc_size.bytes(),
+                alloc_align.bytes(),
+                size.bytes(),
+                align.bytes(),
+            );
+            let kind = UbKind::InvalidDealloc;
+            throw_machine_stop!(TerminationInfo::UndefinedBehavior { kind, msg, notes });
+        }
+        Ok(())
+    }
+
     /// Emulates calling a foreign item, failing if the item is not supported.
     /// This function will handle `goto_block` if needed.
     /// Returns Ok(None) if the foreign item was completely handled
@@ -117,15 +306,26 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         _unwind: Option<mir::BasicBlock>,
     ) -> InterpResult<'tcx, Option<&'mir mir::Body<'tcx>>> {
         let this = self.eval_context_mut();
-        let attrs = this.tcx.get_attrs(def_id);
-        let link_name = match attr::first_attr_value_str_by_name(&attrs, sym::link_name) {
-            Some(name) => name.as_str(),
-            None => this.tcx.item_name(def_id).as_str(),
+        let link_name = match this.machine.link_names.get(&def_id) {
+            Some(&name) => name,
+            None => {
+                let attrs = this.tcx.get_attrs(def_id);
+                let name = attr::first_attr_value_str_by_name(&attrs, sym::link_name)
+                    .unwrap_or_else(|| this.tcx.item_name(def_id));
+                this.machine.link_names.insert(def_id, name);
+                name
+            }
         };
+        let link_name = link_name.as_str();
         // Strip linker suffixes (seen on 32-bit macOS).
         let link_name = link_name.trim_end_matches("$UNIX2003");
         let tcx = &{ this.tcx.tcx };
 
+        // With a `#[global_allocator]`, the Rust heap is implemented by the program itself.
+        if let Some(instance) = this.global_allocator_fn(link_name) {
+            return Ok(Some(&*this.load_mir(instance.def, None)?));
+        }
+
         // First: functions that diverge.
         let (dest, ret) = match link_name {
             // Note that this matches calls to the *foreign* item `__rust_start_panic* -
@@ -134,6 +334,13 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             // Normally, this will be either `libpanic_unwind` or `libpanic_abort`, but it could
             // also be a custom user-provided implementation via `#![feature(panic_runtime)]`
             "__rust_start_panic" => {
+                // With `-C panic=abort`, the panic message has already been printed by the panic
+                // hook, so we can stop right here.
+                if tcx.sess.panic_strategy() == PanicStrategy::Abort {
+                    throw_machine_stop!(TerminationInfo::Abort(Some(
+                        "the evaluated program panicked".to_owned()
+                    )));
+                }
                 // FIXME we might want to cache this... but it's not really performance-critical.
                 let panic_runtime = tcx
                     .crates()
@@ -149,10 +356,37 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             // The implementation is provided by the function with the `#[panic_handler]` attribute.
             "panic_impl" => {
                 let panic_impl_id = this.tcx.lang_items().panic_impl().unwrap();
+                // The handler of `std` detects panics while panicking and aborts. A custom
+                // handler that panics would recurse until the stack overflows instead.
+                if tcx.crate_name(panic_impl_id.krate) != sym::std && this.is_running(def_id) {
+                    throw_machine_stop!(TerminationInfo::Abort(Some(
+                        "the evaluated program aborted execution: panicked inside of its \
+                         `#[panic_handler]`"
+                            .to_owned()
+                    )));
+                }
                 let panic_impl_instance = ty::Instance::mono(*this.tcx, panic_impl_id);
                 return Ok(Some(&*this.load_mir(panic_impl_instance.def, None)?));
             }
+            // Likewise, `handle_alloc_error` calls the `oom_impl` foreign item, which is provided
+            // by the function with the `#[alloc_error_handler]` attribute. The one of `std` calls
+            // the hook set by `set_alloc_error_hook` and aborts.
+            "oom_impl" => {
+                let oom_id = this.tcx.lang_items().oom().unwrap();
+                if tcx.crate_name(oom_id.krate) != sym::std && this.is_running(def_id) {
+                    throw_machine_stop!(TerminationInfo::Abort(Some(
+                        "the evaluated program aborted execution: allocation failed inside of \
+                         its `#[alloc_error_handler]`"
+                            .to_owned()
+                    )));
+                }
+                let oom_instance = ty::Instance::mono(*this.tcx, oom_id);
+                return Ok(Some(&*this.load_mir(oom_instance.def, None)?));
+            }
 
+            "abort" => {
+                throw_machine_stop!(TerminationInfo::Abort(None));
+            }
             | "exit"
             | "ExitProcess"
             => {
@@ -169,7 +403,15 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             }
         };
 
-        // Next: functions that return.
+        // Next: functions that return. Custom shims registered by the embedder take precedence.
+        // We clone the list (it only contains `Arc`s) so that the hooks can borrow the machine.
+        for hook in this.machine.foreign_item_hooks.clone() {
+            if hook(this, link_name, args, dest)? {
+                this.dump_place(*dest);
+                this.go_to_block(ret);
+                return Ok(None);
+            }
+        }
         if this.emulate_foreign_item_by_name(link_name, args, dest, ret)? {
             this.dump_place(*dest);
             this.go_to_block(ret);
@@ -231,6 +473,7 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     Align::from_bytes(align).unwrap(),
                     MiriMemoryKind::Rust.into(),
                 );
+                this.record_alloc_site(ptr.alloc_id, MiriMemoryKind::Rust);
                 this.write_scalar(ptr, dest)?;
             }
             "__rust_alloc_zeroed" => {
@@ -248,7 +491,8 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     MiriMemoryKind::Rust.into(),
                 );
                 // We just allocated this, the access is definitely in-bounds.
-                this.memory.write_bytes(ptr.into(), iter::repeat(0u8).take(size as usize)).unwrap();
+                this.zero_fresh_allocation(ptr).unwrap();
+                this.record_alloc_site(ptr.alloc_id, MiriMemoryKind::Rust);
                 this.write_scalar(ptr, dest)?;
             }
             "__rust_dealloc" => {
@@ -262,11 +506,12 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     throw_unsup!(HeapAllocNonPowerOfTwoAlignment(align));
                 }
                 let ptr = this.force_ptr(ptr)?;
-                this.memory.deallocate(
-                    ptr,
-                    Some((Size::from_bytes(old_size), Align::from_bytes(align).unwrap())),
-                    MiriMemoryKind::Rust.into(),
-                )?;
+                let size = Size::from_bytes(old_size);
+                let align = Align::from_bytes(align).unwrap();
+                this.check_dealloc_kind(ptr, MiriMemoryKind::Rust)?;
+                this.check_rust_dealloc_layout(ptr, size, align)?;
+                this.memory.deallocate(ptr, Some((size, align)), MiriMemoryKind::Rust.into())?;
+                this.record_free(ptr.alloc_id);
             }
             "__rust_realloc" => {
                 let old_size = this.read_scalar(args[1])?.to_machine_usize(this)?;
@@ -280,6 +525,8 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 }
                 let ptr = this.force_ptr(this.read_scalar(args[0])?.not_undef()?)?;
                 let align = Align::from_bytes(align).unwrap();
+                this.check_dealloc_kind(ptr, MiriMemoryKind::Rust)?;
+                this.check_rust_dealloc_layout(ptr, Size::from_bytes(old_size), align)?;
                 let new_ptr = this.memory.reallocate(
                     ptr,
                     Some((Size::from_bytes(old_size), align)),
@@ -287,6 +534,8 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     align,
                     MiriMemoryKind::Rust.into(),
                 )?;
+                this.record_free(ptr.alloc_id);
+                this.record_alloc_site(new_ptr.alloc_id, MiriMemoryKind::Rust);
                 this.write_scalar(new_ptr, dest)?;
             }
 
@@ -315,6 +564,35 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 this.write_scalar(Scalar::from_int(result, Size::from_bits(32)), dest)?;
             }
 
+            // The copies are done on entire ranges of bytes, initialization masks and borrow
+            // stacks at once, instead of one byte at a time by the C implementations.
+            | "memcpy"
+            | "memmove"
+            => {
+                let to = this.read_scalar(args[0])?.not_undef()?;
+                let from = this.read_scalar(args[1])?.not_undef()?;
+                let n = Size::from_bytes(this.read_scalar(args[2])?.to_machine_usize(this)?);
+                let one = Align::from_bytes(1).unwrap();
+                let from_ptr = this.memory.check_ptr_access(from, n, one)?;
+                let to_ptr = this.memory.check_ptr_access(to, n, one)?;
+                if let (Some(from_ptr), Some(to_ptr)) = (from_ptr, to_ptr) {
+                    let nonoverlapping = link_name == "memcpy";
+                    if nonoverlapping {
+                        this.check_no_overlap(link_name, from_ptr, to_ptr, n)?;
+                    }
+                    this.memory.copy(from_ptr, to_ptr, n, nonoverlapping)?;
+                }
+                this.write_scalar(to, dest)?;
+            }
+
+            "memset" => {
+                let ptr = this.read_scalar(args[0])?.not_undef()?;
+                let val = this.read_scalar(args[1])?.to_i32()? as u8;
+                let n = this.read_scalar(args[2])?.to_machine_usize(this)?;
+                this.memory.write_bytes(ptr, iter::repeat(val).take(n as usize))?;
+                this.write_scalar(ptr, dest)?;
+            }
+
             "memrchr" => {
                 let ptr = this.read_scalar(args[0])?.not_undef()?;
                 let val = this.read_scalar(args[1])?.to_i32()? as u8;
@@ -352,18 +630,37 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
 
             "strlen" => {
                 let ptr = this.read_scalar(args[0])?.not_undef()?;
-                let n = this.memory.read_c_str(ptr)?.len();
+                let n = this.read_c_str_for(link_name, ptr, None)?.len();
                 this.write_scalar(Scalar::from_uint(n as u64, dest.layout.size), dest)?;
             }
 
             // math functions
+            // These are implemented using the host's float methods, so for the transcendental
+            // functions (where the platform `libm` is not required to round correctly) the
+            // last bits of the result can differ between hosts (and from the target's `libm`).
             | "cbrtf"
             | "coshf"
             | "sinhf"
+            | "tanhf"
             | "tanf"
             | "acosf"
             | "asinf"
             | "atanf"
+            | "sinf"
+            | "cosf"
+            | "expf"
+            | "exp2f"
+            | "expm1f"
+            | "logf"
+            | "log2f"
+            | "log10f"
+            | "log1pf"
+            | "sqrtf"
+            | "fabsf"
+            | "floorf"
+            | "ceilf"
+            | "truncf"
+            | "roundf"
             => {
                 // FIXME: Using host floats.
                 let f = f32::from_bits(this.read_scalar(args[0])?.to_u32()?);
@@ -371,18 +668,41 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     "cbrtf" => f.cbrt(),
                     "coshf" => f.cosh(),
                     "sinhf" => f.sinh(),
+                    "tanhf" => f.tanh(),
                     "tanf" => f.tan(),
                     "acosf" => f.acos(),
                     "asinf" => f.asin(),
                     "atanf" => f.atan(),
+                    "sinf" => f.sin(),
+                    "cosf" => f.cos(),
+                    "expf" => f.exp(),
+                    "exp2f" => f.exp2(),
+                    "expm1f" => f.exp_m1(),
+                    "logf" => f.ln(),
+                    "log2f" => f.log2(),
+                    "log10f" => f.log10(),
+                    "log1pf" => f.ln_1p(),
+                    "sqrtf" => f.sqrt(),
+                    "fabsf" => f.abs(),
+                    "floorf" => f.floor(),
+                    "ceilf" => f.ceil(),
+                    "truncf" => f.trunc(),
+                    "roundf" => f.round(),
                     _ => bug!(),
                 };
+                let f = this.float_jitter_f32(link_name, f);
                 this.write_scalar(Scalar::from_u32(f.to_bits()), dest)?;
             }
             // underscore case for windows
             | "_hypotf"
             | "hypotf"
             | "atan2f"
+            | "powf"
+            | "fmodf"
+            | "fdimf"
+            | "fminf"
+            | "fmaxf"
+            | "copysignf"
             => {
                 // FIXME: Using host floats.
                 let f1 = f32::from_bits(this.read_scalar(args[0])?.to_u32()?);
@@ -390,18 +710,42 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 let n = match link_name {
                     "_hypotf" | "hypotf" => f1.hypot(f2),
                     "atan2f" => f1.atan2(f2),
+                    "powf" => f1.powf(f2),
+                    // Rust's `%` on floats has the semantics of C's `fmod`.
+                    "fmodf" => f1 % f2,
+                    "fdimf" => if f1.is_nan() || f2.is_nan() { f32::NAN } else { (f1 - f2).max(0.0) },
+                    "fminf" => f1.min(f2),
+                    "fmaxf" => f1.max(f2),
+                    "copysignf" => f1.copysign(f2),
                     _ => bug!(),
                 };
+                let n = this.float_jitter_f32(link_name, n);
                 this.write_scalar(Scalar::from_u32(n.to_bits()), dest)?;
             }
 
             | "cbrt"
             | "cosh"
             | "sinh"
+            | "tanh"
             | "tan"
             | "acos"
             | "asin"
             | "atan"
+            | "sin"
+            | "cos"
+            | "exp"
+            | "exp2"
+            | "expm1"
+            | "log"
+            | "log2"
+            | "log10"
+            | "log1p"
+            | "sqrt"
+            | "fabs"
+            | "floor"
+            | "ceil"
+            | "trunc"
+            | "round"
             => {
                 // FIXME: Using host floats.
                 let f = f64::from_bits(this.read_scalar(args[0])?.to_u64()?);
@@ -409,12 +753,29 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     "cbrt" => f.cbrt(),
                     "cosh" => f.cosh(),
                     "sinh" => f.sinh(),
+                    "tanh" => f.tanh(),
                     "tan" => f.tan(),
                     "acos" => f.acos(),
                     "asin" => f.asin(),
                     "atan" => f.atan(),
+                    "sin" => f.sin(),
+                    "cos" => f.cos(),
+                    "exp" => f.exp(),
+                    "exp2" => f.exp2(),
+                    "expm1" => f.exp_m1(),
+                    "log" => f.ln(),
+                    "log2" => f.log2(),
+                    "log10" => f.log10(),
+                    "log1p" => f.ln_1p(),
+                    "sqrt" => f.sqrt(),
+                    "fabs" => f.abs(),
+                    "floor" => f.floor(),
+                    "ceil" => f.ceil(),
+                    "trunc" => f.trunc(),
+                    "round" => f.round(),
                     _ => bug!(),
                 };
+                let f = this.float_jitter_f64(link_name, f);
                 this.write_scalar(Scalar::from_u64(f.to_bits()), dest)?;
             }
             // underscore case for windows, here and below
@@ -422,6 +783,13 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             | "_hypot"
             | "hypot"
             | "atan2"
+            | "pow"
+            | "fmod"
+            | "fdim"
+            | "fmin"
+            | "fmax"
+            | "copysign"
+            | "_copysign"
             => {
                 // FIXME: Using host floats.
                 let f1 = f64::from_bits(this.read_scalar(args[0])?.to_u64()?);
@@ -429,8 +797,16 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 let n = match link_name {
                     "_hypot" | "hypot" => f1.hypot(f2),
                     "atan2" => f1.atan2(f2),
+                    "pow" => f1.powf(f2),
+                    // Rust's `%` on floats has the semantics of C's `fmod`.
+                    "fmod" => f1 % f2,
+                    "fdim" => if f1.is_nan() || f2.is_nan() { f64::NAN } else { (f1 - f2).max(0.0) },
+                    "fmin" => f1.min(f2),
+                    "fmax" => f1.max(f2),
+                    "copysign" | "_copysign" => f1.copysign(f2),
                     _ => bug!(),
                 };
+                let n = this.float_jitter_f64(link_name, n);
                 this.write_scalar(Scalar::from_u64(n.to_bits()), dest)?;
             }
             // For radix-2 (binary) systems, `ldexp` and `scalbn` are the same.
@@ -455,9 +831,14 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 this.write_scalar(Scalar::from_f64(res), dest)?;
             }
 
+            _ if link_name.starts_with("miri_") => this.emulate_miri_extern(link_name, args, dest)?,
+            _ if link_name.starts_with("llvm.x86.") => return x86::EvalContextExt::emulate_x86_intrinsic(this, link_name, args, dest),
+            _ if link_name.starts_with("llvm.wasm.") => return wasm::EvalContextExt::emulate_wasm_intrinsic(this, link_name, args, dest),
+
             _ => match this.tcx.sess.target.target.target_os.as_str() {
-                "linux" | "macos" => return posix::EvalContextExt::emulate_foreign_item_by_name(this, link_name, args, dest, ret),
+                "linux" | "macos" | "freebsd" | "android" => return posix::EvalContextExt::emulate_foreign_item_by_name(this, link_name, args, dest, ret),
                 "windows" => return windows::EvalContextExt::emulate_foreign_item_by_name(this, link_name, args, dest, ret),
+                "unknown" if this.tcx.sess.target.target.arch == "wasm32" => return wasm::EvalContextExt::emulate_foreign_item_by_name(this, link_name, args, dest, ret),
                 target => throw_unsup_format!("The {} target platform is not supported", target),
             }
         };
@@ -472,12 +853,29 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         path: &[&str],
     ) -> InterpResult<'tcx, Option<ScalarMaybeUndef<Tag>>> {
         let this = self.eval_context_mut();
-        if let Ok(instance) = this.resolve_path(path) {
-            let cid = GlobalId { instance, promoted: None };
-            let const_val = this.const_eval_raw(cid)?;
-            let const_val = this.read_scalar(const_val.into())?;
-            return Ok(Some(const_val));
+        let key = path.join("::");
+        if let Some(&scalar) = this.machine.path_scalars.get(&key) {
+            return Ok(scalar);
         }
-        return Ok(None);
+        let scalar = match this.resolve_path(path) {
+            Ok(instance) => {
+                let cid = GlobalId { instance, promoted: None };
+                let const_val = this.const_eval_raw(cid)?;
+                Some(this.read_scalar(const_val.into())?)
+            }
+            Err(_) => None,
+        };
+        this.machine.path_scalars.insert(key, scalar);
+        Ok(scalar)
+    }
+}
+
+/// Describes the allocator that manages heap memory of the given kind.
+fn heap_allocator_name(kind: MiriMemoryKind) -> &'static str {
+    match kind {
+        MiriMemoryKind::Rust => "the Rust allocator (`__rust_alloc`)",
+        MiriMemoryKind::C => "the C heap (`malloc`)",
+        MiriMemoryKind::WinHeap => "the Windows heap (`HeapAlloc`)",
+        kind => bug!("{:?} memory is not managed by a heap allocator", kind),
     }
 }
@@ -1,8 +1,13 @@
+mod android;
+mod freebsd;
 mod linux;
 mod macos;
 
+use std::iter;
+
 use crate::*;
 use rustc::mir;
+use rustc_mir::interpret::AllocCheck;
 use rustc::ty::layout::{Align, LayoutOf, Size};
 
 impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
@@ -46,7 +51,7 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
 
             // File related shims
             "open" | "open64" => {
-                let result = this.open(args[0], args[1])?;
+                let result = this.open(args[0], args[1], args.get(2).cloned())?;
                 this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
             }
 
@@ -55,6 +60,21 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
             }
 
+            "poll" => {
+                let result = this.poll(args[0], args[1], args[2])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "select" => {
+                let result = this.select(args[0], args[1], args[2], args[3], args[4])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "ioctl" => {
+                let result = this.ioctl(args[0], args[1], args.get(2).cloned())?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
             "read" => {
                 let result = this.read(args[0], args[1], args[2])?;
                 this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
@@ -104,6 +124,21 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
             }
 
+            "umask" => {
+                let result = this.umask(args[0])?;
+                this.write_scalar(Scalar::from_uint(result, dest.layout.size), dest)?;
+            }
+
+            "mkdir" => {
+                let result = this.mkdir(args[0], args[1])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "rmdir" => {
+                let result = this.rmdir(args[0])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
             "rename" => {
                 let result = this.rename(args[0], args[1])?;
                 this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
@@ -114,49 +149,307 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
             }
 
+            // C stdio shims
+            "fopen" | "fopen64" => {
+                let result = this.fopen(args[0], args[1])?;
+                this.write_scalar(result, dest)?;
+            }
+
+            "fread" => {
+                let result = this.fread(args[0], args[1], args[2], args[3])?;
+                this.write_scalar(Scalar::from_uint(result, dest.layout.size), dest)?;
+            }
+
+            "fwrite" => {
+                let result = this.fwrite(args[0], args[1], args[2], args[3])?;
+                this.write_scalar(Scalar::from_uint(result, dest.layout.size), dest)?;
+            }
+
+            "fseek" | "fseeko" | "fseeko64" => {
+                let result = this.fseek(args[0], args[1], args[2])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "ftell" | "ftello" | "ftello64" => {
+                let result = this.ftell(args[0], dest.layout.size)?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "fflush" => {
+                let result = this.fflush(args[0])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "fclose" => {
+                let result = this.fclose(args[0])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            // C string shims
+            "strnlen" => {
+                let ptr = this.read_scalar(args[0])?.not_undef()?;
+                let max_len = this.read_scalar(args[1])?.to_machine_usize(tcx)?;
+                let n = this.read_c_str_for(link_name, ptr, Some(max_len))?.len();
+                this.write_scalar(Scalar::from_uint(n as u64, dest.layout.size), dest)?;
+            }
+
+            "strcmp" | "strncmp" => {
+                let left = this.read_scalar(args[0])?.not_undef()?;
+                let right = this.read_scalar(args[1])?.not_undef()?;
+                let max_len = match link_name {
+                    "strncmp" => Some(this.read_scalar(args[2])?.to_machine_usize(tcx)?),
+                    _ => None,
+                };
+                let left = this.read_c_str_for(link_name, left, max_len)?;
+                // The comparison stops at the end of the left string at the latest, so only that
+                // much (and the byte after it) of the right string is looked at.
+                let right_len = left.len() as u64 + 1;
+                let right_len = max_len.map_or(right_len, |max_len| max_len.min(right_len));
+                let right = this.read_c_str_for(link_name, right, Some(right_len))?;
+
+                // The terminating null byte is smaller than all others, just like the end of a
+                // slice.
+                use std::cmp::Ordering::*;
+                let result = match left.cmp(&right) {
+                    Less => -1i32,
+                    Equal => 0,
+                    Greater => 1,
+                };
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "strchr" | "strrchr" => {
+                let ptr = this.read_scalar(args[0])?.not_undef()?;
+                let val = this.read_scalar(args[1])?.to_i32()? as u8;
+                let bytes = this.read_c_str_for(link_name, ptr, None)?;
+                // Searching for the null byte finds the terminator.
+                let idx = if val == 0 {
+                    Some(bytes.len())
+                } else if link_name == "strchr" {
+                    bytes.iter().position(|&c| c == val)
+                } else {
+                    bytes.iter().rposition(|&c| c == val)
+                };
+                if let Some(idx) = idx {
+                    let new_ptr = ptr.ptr_offset(Size::from_bytes(idx as u64), this)?;
+                    this.write_scalar(new_ptr, dest)?;
+                } else {
+                    this.write_null(dest)?;
+                }
+            }
+
+            "strstr" => {
+                let haystack = this.read_scalar(args[0])?.not_undef()?;
+                let needle = this.read_scalar(args[1])?.not_undef()?;
+                let haystack_bytes = this.read_c_str_for(link_name, haystack, None)?;
+                let needle_bytes = this.read_c_str_for(link_name, needle, None)?;
+                let idx = if needle_bytes.is_empty() {
+                    Some(0)
+                } else {
+                    haystack_bytes.windows(needle_bytes.len()).position(|w| w == &needle_bytes[..])
+                };
+                if let Some(idx) = idx {
+                    let new_ptr = haystack.ptr_offset(Size::from_bytes(idx as u64), this)?;
+                    this.write_scalar(new_ptr, dest)?;
+                } else {
+                    this.write_null(dest)?;
+                }
+            }
+
+            "strcpy" | "stpcpy" => {
+                let to = this.read_scalar(args[0])?.not_undef()?;
+                let from = this.read_scalar(args[1])?.not_undef()?;
+                let len = this.read_c_str_for(link_name, from, None)?.len() as u64;
+                this.copy_c_str_bytes(link_name, from, to, len + 1)?;
+                // `stpcpy` returns a pointer to the terminator it wrote.
+                let result = match link_name {
+                    "stpcpy" => to.ptr_offset(Size::from_bytes(len), this)?,
+                    _ => to,
+                };
+                this.write_scalar(result, dest)?;
+            }
+
+            "strncpy" => {
+                let to = this.read_scalar(args[0])?.not_undef()?;
+                let from = this.read_scalar(args[1])?.not_undef()?;
+                let n = this.read_scalar(args[2])?.to_machine_usize(tcx)?;
+                let len = this.read_c_str_for(link_name, from, Some(n))?.len() as u64;
+                this.copy_c_str_bytes(link_name, from, to, len)?;
+                // The rest of the destination is filled with null bytes; if the string is `n`
+                // bytes or longer, the destination is not terminated at all.
+                let rest = to.ptr_offset(Size::from_bytes(len), this)?;
+                this.memory.write_bytes(rest, iter::repeat(0u8).take((n - len) as usize))?;
+                this.write_scalar(to, dest)?;
+            }
+
+            "strcat" => {
+                let to = this.read_scalar(args[0])?.not_undef()?;
+                let from = this.read_scalar(args[1])?.not_undef()?;
+                let to_len = this.read_c_str_for(link_name, to, None)?.len() as u64;
+                let from_len = this.read_c_str_for(link_name, from, None)?.len() as u64;
+                let end = to.ptr_offset(Size::from_bytes(to_len), this)?;
+                this.copy_c_str_bytes(link_name, from, end, from_len + 1)?;
+                this.write_scalar(to, dest)?;
+            }
+
+            "strdup" | "strndup" => {
+                let ptr = this.read_scalar(args[0])?.not_undef()?;
+                let max_len = match link_name {
+                    "strndup" => Some(this.read_scalar(args[1])?.to_machine_usize(tcx)?),
+                    _ => None,
+                };
+                let mut bytes = this.read_c_str_for(link_name, ptr, max_len)?;
+                // `strndup` terminates the copy even if it stopped before the end.
+                bytes.push(0);
+                let copy = this.malloc(bytes.len() as u64, /*zero_init:*/ false, MiriMemoryKind::C);
+                this.memory.write_bytes(copy, bytes)?;
+                this.write_scalar(copy, dest)?;
+            }
+
+            // Socket related shims
+            "socket" => {
+                let result = this.socket(args[0], args[1], args[2])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "bind" => {
+                let result = this.bind(args[0], args[1], args[2])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "listen" => {
+                let result = this.listen(args[0], args[1])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "connect" => {
+                let result = this.connect(args[0], args[1], args[2])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "accept" => {
+                let result = this.accept(args[0], args[1], args[2], None)?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "getsockname" | "getpeername" => {
+                let peer = link_name == "getpeername";
+                let result = this.getsockname(args[0], args[1], args[2], peer)?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "setsockopt" => {
+                let result = this.setsockopt(args[0], args[1], args[2])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "shutdown" => {
+                let result = this.shutdown(args[0], args[1])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "getaddrinfo" => {
+                let result = this.getaddrinfo(args[0], args[1], args[2], args[3])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "freeaddrinfo" => {
+                this.freeaddrinfo(args[0])?;
+            }
+
+            "gai_strerror" => {
+                let result = this.gai_strerror(args[0])?;
+                this.write_scalar(result, dest)?;
+            }
+
+            "send" => {
+                let result = this.send(args[0], args[1], args[2], args[3], None)?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "sendto" => {
+                let addr = Some((args[4], args[5]));
+                let result = this.send(args[0], args[1], args[2], args[3], addr)?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "recv" => {
+                let result = this.recv(args[0], args[1], args[2], args[3], None)?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "recvfrom" => {
+                let addr = Some((args[4], args[5]));
+                let result = this.recv(args[0], args[1], args[2], args[3], addr)?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
             // Other shims
             "posix_memalign" => {
                 let ret = this.deref_operand(args[0])?;
                 let align = this.read_scalar(args[1])?.to_machine_usize(this)?;
                 let size = this.read_scalar(args[2])?.to_machine_usize(this)?;
                 // Align must be power of 2, and also at least ptr-sized (POSIX rules).
-                if !align.is_power_of_two() {
-                    throw_unsup!(HeapAllocNonPowerOfTwoAlignment(align));
-                }
-                if align < this.pointer_size().bytes() {
-                    throw_ub_format!(
-                        "posix_memalign: alignment must be at least the size of a pointer, but is {}",
-                        align,
-                    );
-                }
-
-                if size == 0 {
-                    this.write_null(ret.into())?;
+                // Unlike the other allocation functions, the error code is returned directly.
+                if !align.is_power_of_two() || align < this.pointer_size().bytes() {
+                    let einval = this.eval_libc_i32("EINVAL")?;
+                    this.write_scalar(Scalar::from_i32(einval), dest)?;
                 } else {
-                    let ptr = this.memory.allocate(
-                        Size::from_bytes(size),
-                        Align::from_bytes(align).unwrap(),
-                        MiriMemoryKind::C.into(),
-                    );
+                    let ptr = this.aligned_malloc(size, align)?;
                     this.write_scalar(ptr, ret.into())?;
+                    this.write_null(dest)?;
                 }
-                this.write_null(dest)?;
             }
-
-            "dlsym" => {
-                let _handle = this.read_scalar(args[0])?;
-                let symbol = this.read_scalar(args[1])?.not_undef()?;
-                let symbol_name = this.memory.read_c_str(symbol)?;
-                let err = format!("bad c unicode symbol: {:?}", symbol_name);
-                let symbol_name = ::std::str::from_utf8(symbol_name).unwrap_or(&err);
-                if let Some(dlsym) = Dlsym::from_str(symbol_name)? {
-                    let ptr = this.memory.create_fn_alloc(FnVal::Other(dlsym));
-                    this.write_scalar(Scalar::from(ptr), dest)?;
-                } else {
+            "aligned_alloc" => {
+                let align = this.read_scalar(args[0])?.to_machine_usize(this)?;
+                let size = this.read_scalar(args[1])?.to_machine_usize(this)?;
+                // C11 also requires `size` to be a multiple of `align`, but glibc and macOS do not
+                // enforce that, so neither do we.
+                if !align.is_power_of_two() {
+                    let einval = this.eval_libc("EINVAL")?;
+                    this.set_last_error(einval)?;
                     this.write_null(dest)?;
+                } else {
+                    let ptr = this.aligned_malloc(size, align)?;
+                    this.write_scalar(ptr, dest)?;
                 }
             }
 
+            // `std` captures backtraces by walking the machine stack with `_Unwind_Backtrace`.
+            // There is no such stack in Miri, so we report its end right away, which results in
+            // an empty backtrace. Programs can use `miri_get_backtrace` instead.
+            "_Unwind_Backtrace" => {
+                // This is `_URC_END_OF_STACK`.
+                this.write_scalar(Scalar::from_int(5, dest.layout.size), dest)?;
+            }
+
+            "getentropy" => {
+                let ptr = this.read_scalar(args[0])?.not_undef()?;
+                let len = this.read_scalar(args[1])?.to_machine_usize(this)?;
+                let result = this.getentropy(ptr, len)?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "dlopen" => {
+                let result = this.dlopen(args[0], args[1])?;
+                this.write_scalar(result, dest)?;
+            }
+
+            "dlsym" => {
+                let result = this.dlsym(args[0], args[1])?;
+                this.write_scalar(result, dest)?;
+            }
+
+            "dlerror" => {
+                let result = this.dlerror()?;
+                this.write_scalar(result, dest)?;
+            }
+
+            "dlclose" => {
+                let result = this.dlclose(args[0])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
             // Hook pthread calls that go to the thread-local storage memory subsystem.
             "pthread_key_create" => {
                 let key_place = this.deref_operand(args[0])?;
@@ -264,12 +557,82 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 this.write_null(dest)?;
             }
 
-            // Some things needed for `sys::thread` initialization to go through.
-            | "signal"
-            | "sigaction"
-            | "sigaltstack"
+            // Signals. We accept (and record) handler installation, but never deliver any signals.
+            // libstd needs some of these for `sys::thread` initialization to go through.
+            "signal" => {
+                let signum = this.read_scalar(args[0])?.to_i32()?;
+                this.machine.signal_handlers.insert(signum);
+                this.note_partial_emulation("signal handlers never run");
+                // Return the previous handler, which is always `SIG_DFL` (0) as far as the program
+                // can tell.
+                this.write_null(dest)?;
+            }
+            "sigaction" => {
+                let signum = this.read_scalar(args[0])?.to_i32()?;
+                let act = this.read_scalar(args[1])?.not_undef()?;
+                if !this.is_null(act)? {
+                    this.machine.signal_handlers.insert(signum);
+                    this.note_partial_emulation("signal handlers never run");
+                }
+                // The old action is always the default one: all fields zero.
+                this.write_zeroed_pointee(args[2])?;
+                this.write_null(dest)?;
+            }
+            "sigaltstack" => {
+                // The old alternate stack is reported as all-zero. libstd takes this to mean
+                // that an alternate stack is already installed, and skips installing its own.
+                this.write_zeroed_pointee(args[1])?;
+                this.write_null(dest)?;
+            }
+            | "sigprocmask"
+            | "pthread_sigmask"
+            => {
+                // There is only one thread and no signals are ever delivered, so the mask does not
+                // matter. The old mask is reported as empty.
+                this.write_zeroed_pointee(args[2])?;
+                this.write_null(dest)?;
+            }
+            "sigemptyset" => {
+                this.write_zeroed_pointee(args[0])?;
+                this.write_null(dest)?;
+            }
+            | "raise"
+            | "kill"
+            | "pthread_kill"
+            => {
+                let signum = match link_name {
+                    "raise" => this.read_scalar(args[0])?.to_i32()?,
+                    _ => this.read_scalar(args[1])?.to_i32()?,
+                };
+                let handler = if this.machine.signal_handlers.contains(&signum) {
+                    " (the program installed a handler for it, but Miri never runs signal handlers)"
+                } else {
+                    ""
+                };
+                throw_unsup_format!(
+                    "Miri does not support sending signals: `{}` called with signal {}{}",
+                    link_name,
+                    signum,
+                    handler,
+                );
+            }
+
+            // `setjmp` returns twice, and `longjmp` skips over all frames in between, neither of
+            // which a call to a shim can do. Catch them here, as the generic "can't call foreign
+            // function" error is rather confusing for a function that the program did not define.
+            | "setjmp"
+            | "_setjmp"
+            | "sigsetjmp"
+            | "__sigsetjmp"
+            | "longjmp"
+            | "_longjmp"
+            | "siglongjmp"
             => {
-                this.write_scalar(Scalar::from_int(0, dest.layout.size), dest)?;
+                throw_unsup_format!(
+                    "Miri does not support setjmp/longjmp: `{}` was called, but Miri cannot \
+                     return twice from a function or jump across stack frames",
+                    link_name,
+                );
             }
 
             "sysconf" => {
@@ -282,7 +645,7 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     (&["libc", "_SC_GETPW_R_SIZE_MAX"], Scalar::from_int(-1, dest.layout.size)),
                     (
                         &["libc", "_SC_NPROCESSORS_ONLN"],
-                        Scalar::from_int(NUM_CPUS, dest.layout.size),
+                        Scalar::from_uint(this.machine.num_cpus, dest.layout.size),
                     ),
                 ];
                 let mut result = None;
@@ -303,6 +666,7 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             }
 
             "isatty" => {
+                this.note_partial_emulation("no descriptor is a terminal");
                 this.write_null(dest)?;
             }
 
@@ -314,10 +678,40 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             "mmap" => {
                 // This is a horrible hack, but since the guard page mechanism calls mmap and expects a particular return value, we just give it that value.
                 let addr = this.read_scalar(args[0])?.not_undef()?;
+                this.note_partial_emulation("no memory is mapped");
                 this.write_scalar(addr, dest)?;
             }
 
             "mprotect" => {
+                let addr = this.read_scalar(args[0])?.not_undef()?;
+                let len = this.read_scalar(args[1])?.to_machine_usize(this)?;
+                let prot = this.read_scalar(args[2])?.to_i32()?;
+                // The guard page mechanism protects memory that Miri does not know about, we just
+                // let that succeed. In Miri's allocations, `PROT_NONE` makes the pages
+                // inaccessible; read-only pages are not enforced.
+                if let Scalar::Ptr(ptr) = addr {
+                    if this.force_bits(addr, this.pointer_size())? % u128::from(PAGE_SIZE) != 0 {
+                        let einval = this.eval_libc("EINVAL")?;
+                        this.set_last_error(einval)?;
+                        this.write_scalar(Scalar::from_int(-1, dest.layout.size), dest)?;
+                        return Ok(true);
+                    }
+                    let (alloc_size, _) =
+                        this.memory.get_size_and_align(ptr.alloc_id, AllocCheck::Live)?;
+                    let available = alloc_size.bytes() - ptr.offset.bytes();
+                    if len > available {
+                        throw_unsup_format!("`mprotect` beyond the end of an allocation");
+                    }
+                    // The last page is protected as a whole, but we only have to care for the
+                    // part within the allocation.
+                    let len = (len + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE;
+                    let len = Size::from_bytes(len.min(available));
+                    let accessible = prot != this.eval_libc_i32("PROT_NONE")?;
+                    if accessible && prot & this.eval_libc_i32("PROT_WRITE")? == 0 {
+                        this.note_partial_emulation("read-only pages are not enforced");
+                    }
+                    this.set_accessible(ptr, len, accessible)?;
+                }
                 this.write_null(dest)?;
             }
 
@@ -325,6 +719,8 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 match this.tcx.sess.target.target.target_os.as_str() {
                     "linux" => return linux::EvalContextExt::emulate_foreign_item_by_name(this, link_name, args, dest, ret),
                     "macos" => return macos::EvalContextExt::emulate_foreign_item_by_name(this, link_name, args, dest, ret),
+                    "android" => return android::EvalContextExt::emulate_foreign_item_by_name(this, link_name, args, dest, ret),
+                    "freebsd" => return freebsd::EvalContextExt::emulate_foreign_item_by_name(this, link_name, args, dest, ret),
                     _ => unreachable!(),
                 }
             }
@@ -333,3 +729,27 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         Ok(true)
     }
 }
+
+impl<'mir, 'tcx> EvalContextExtPrivate<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+trait EvalContextExtPrivate<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Copies `size` bytes of a string from `from` to `to`, for the C function `name`. Like for
+    /// `memcpy`, the ranges must not overlap.
+    fn copy_c_str_bytes(
+        &mut self,
+        name: &str,
+        from: Scalar<Tag>,
+        to: Scalar<Tag>,
+        size: u64,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let size = Size::from_bytes(size);
+        let one = Align::from_bytes(1).unwrap();
+        let from_ptr = this.memory.check_ptr_access(from, size, one)?;
+        let to_ptr = this.memory.check_ptr_access(to, size, one)?;
+        if let (Some(from_ptr), Some(to_ptr)) = (from_ptr, to_ptr) {
+            this.check_no_overlap(name, from_ptr, to_ptr, size)?;
+            this.memory.copy(from_ptr, to_ptr, size, /*nonoverlapping:*/ true)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use crate::*;
+use rustc::mir;
+
+use super::linux;
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    fn emulate_foreign_item_by_name(
+        &mut self,
+        link_name: &str,
+        args: &[OpTy<'tcx, Tag>],
+        dest: PlaceTy<'tcx, Tag>,
+        ret: mir::BasicBlock,
+    ) -> InterpResult<'tcx, bool> {
+        let this = self.eval_context_mut();
+
+        match link_name {
+            // Bionic names this differently than glibc.
+            "__errno" => {
+                let errno_place = this.machine.last_error.unwrap();
+                this.write_scalar(errno_place.to_ref().to_scalar()?, dest)?;
+            }
+
+            // File related shims
+
+            // `std` uses these instead of `statx` on Android.
+            "stat" => {
+                let result = this.stat_by_name(true, args[0], args[1])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "lstat" => {
+                let result = this.stat_by_name(false, args[0], args[1])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "fstat" => {
+                let result = this.fstat_by_name(args[0], args[1])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            // Other shims
+            "__system_property_get" => {
+                // There are no system properties; report every property as empty.
+                let value = this.read_scalar(args[1])?.not_undef()?;
+                this.memory.write_bytes(value, Some(0u8))?;
+                this.write_null(dest)?;
+            }
+
+            // Bionic runs on the Linux kernel, and implements most of the Linux-specific API.
+            _ => return linux::EvalContextExt::emulate_foreign_item_by_name(this, link_name, args, dest, ret),
+        };
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,108 @@
+use crate::*;
+use rustc::mir;
+use rustc::ty::layout::LayoutOf;
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    fn emulate_foreign_item_by_name(
+        &mut self,
+        link_name: &str,
+        args: &[OpTy<'tcx, Tag>],
+        dest: PlaceTy<'tcx, Tag>,
+        _ret: mir::BasicBlock,
+    ) -> InterpResult<'tcx, bool> {
+        let this = self.eval_context_mut();
+
+        match link_name {
+            "__error" => {
+                let errno_place = this.machine.last_error.unwrap();
+                this.write_scalar(errno_place.to_ref().to_scalar()?, dest)?;
+            }
+
+            // File related shims
+
+            // The only reason this is not in the `posix` module is because the `macos` item has a
+            // different name.
+            "close" => {
+                let result = this.close(args[0])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            // The `libc` crate links to the symbols of the FreeBSD 11 ABI, with or without the
+            // symbol version.
+            "stat" | "stat@FBSD_1.0" => {
+                let result = this.stat_by_name(true, args[0], args[1])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "lstat" | "lstat@FBSD_1.0" => {
+                let result = this.stat_by_name(false, args[0], args[1])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "fstat" | "fstat@FBSD_1.0" => {
+                let result = this.fstat_by_name(args[0], args[1])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            // Time related shims
+            "clock_gettime" => {
+                let result = this.clock_gettime(args[0], args[1])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            // Other shims
+            "sysctl" => {
+                let result = this.freebsd_sysctl(args)?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "thr_self" => {
+                // There is only the main thread.
+                let id_place = this.deref_operand(args[0])?;
+                this.write_scalar(Scalar::from_int(1, id_place.layout.size), id_place.into())?;
+                this.write_null(dest)?;
+            }
+
+            "pthread_attr_get_np" => {
+                // The attributes are only used for `pthread_attr_getstack`, which ignores them.
+                this.write_null(dest)?;
+            }
+
+            _ => throw_unsup_format!("can't call foreign function: {}", link_name),
+        };
+
+        Ok(true)
+    }
+
+    /// Implements `sysctl` for `kern.arandom`, which `std` uses for randomness. All other names
+    /// are unsupported.
+    fn freebsd_sysctl(&mut self, args: &[OpTy<'tcx, Tag>]) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let name = this.read_scalar(args[0])?.not_undef()?;
+        let namelen = this.read_scalar(args[1])?.to_u32()?;
+        let oldp = this.read_scalar(args[2])?.not_undef()?;
+        let oldlenp = this.deref_operand(args[3])?;
+        let newp = this.read_scalar(args[4])?.not_undef()?;
+
+        let int_layout = this.libc_ty_layout("c_int")?;
+        let int_ptr_layout = this.layout_of(this.tcx.mk_imm_ptr(int_layout.ty))?;
+        let mut mib = Vec::new();
+        for i in 0..u64::from(namelen) {
+            let ptr = name.ptr_offset(int_layout.size * i, this)?;
+            let place = this.ref_to_mplace(ImmTy::from_scalar(ptr, int_ptr_layout))?;
+            mib.push(this.read_scalar(place.into())?.to_i32()?);
+        }
+        let kern_arandom = [this.eval_libc_i32("CTL_KERN")?, this.eval_libc_i32("KERN_ARND")?];
+        if mib != kern_arandom || !this.is_null(newp)? {
+            throw_unsup_format!("unsupported sysctl name {:?}", mib);
+        }
+
+        // Like FreeBSD, we return at most 256 bytes at a time.
+        let len = this.read_scalar(oldlenp.into())?.to_machine_usize(this)?.min(256);
+        this.gen_random(oldp, len as usize)?;
+        this.write_scalar(Scalar::from_uint(len, oldlenp.layout.size), oldlenp.into())?;
+        Ok(0)
+    }
+}
@@ -1,5 +1,6 @@
 use crate::*;
 use rustc::mir;
+use rustc::ty::layout;
 
 impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
 pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
@@ -27,6 +28,27 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
             }
 
+            // Socket related shims
+            "accept4" => {
+                let result = this.accept(args[0], args[1], args[2], Some(args[3]))?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "epoll_create1" => {
+                let result = this.epoll_create1(args[0])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "epoll_ctl" => {
+                let result = this.epoll_ctl(args[0], args[1], args[2], args[3])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
+            "epoll_wait" => {
+                let result = this.epoll_wait(args[0], args[1], args[2], args[3])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
             // Time related shims
 
             // This is a POSIX function but it has only been tested on linux.
@@ -36,6 +58,20 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             }
 
             // Other shims
+            // `memalign` is obsolete and does not exist on macOS, but some crates still use it.
+            "memalign" => {
+                let align = this.read_scalar(args[0])?.to_machine_usize(this)?;
+                let size = this.read_scalar(args[1])?.to_machine_usize(this)?;
+                if !align.is_power_of_two() {
+                    let einval = this.eval_libc("EINVAL")?;
+                    this.set_last_error(einval)?;
+                    this.write_null(dest)?;
+                } else {
+                    let ptr = this.aligned_malloc(size, align)?;
+                    this.write_scalar(ptr, dest)?;
+                }
+            }
+
             "pthread_getattr_np" => {
                 this.write_null(dest)?;
             }
@@ -46,10 +82,11 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     .expect("Failed to get libc::SYS_getrandom")
                     .to_machine_usize(this)?;
 
+                // Not every `libc` we support (e.g. the one for Android) defines this.
                 let sys_statx = this
                     .eval_path_scalar(&["libc", "SYS_statx"])?
-                    .expect("Failed to get libc::SYS_statx")
-                    .to_machine_usize(this)?;
+                    .map(|sys_statx| sys_statx.to_machine_usize(this))
+                    .transpose()?;
 
                 match this.read_scalar(args[0])?.to_machine_usize(this)? {
                     // `libc::syscall(NR_GETRANDOM, buf.as_mut_ptr(), buf.len(), GRND_NONBLOCK)`
@@ -61,7 +98,7 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     }
                     // `statx` is used by `libstd` to retrieve metadata information on `linux`
                     // instead of using `stat`,`lstat` or `fstat` as on `macos`.
-                    id if id == sys_statx => {
+                    id if Some(id) == sys_statx => {
                         // The first argument is the syscall id,
                         // so skip over it.
                         let result = this.linux_statx(args[1], args[2], args[3], args[4], args[5])?;
@@ -75,9 +112,21 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 getrandom(this, args, dest)?;
             }
 
+            // Used for CPU feature detection on ARM. We report the baseline features only.
+            "getauxval" => {
+                let kind = this.read_scalar(args[0])?.to_machine_usize(this)?;
+                let at_hwcap = this.eval_libc("AT_HWCAP")?.to_machine_usize(this)?;
+                let at_hwcap2 = this.eval_libc("AT_HWCAP2")?.to_machine_usize(this)?;
+                if kind != at_hwcap && kind != at_hwcap2 {
+                    let enoent = this.eval_libc("ENOENT")?;
+                    this.set_last_error(enoent)?;
+                }
+                this.write_null(dest)?;
+            }
+
             "sched_getaffinity" => {
-                // Return an error; `num_cpus` then falls back to `sysconf`.
-                this.write_scalar(Scalar::from_int(-1, dest.layout.size), dest)?;
+                let result = sched_getaffinity(this, args)?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
             }
 
             _ => throw_unsup_format!("can't call foreign function: {}", link_name),
@@ -104,3 +153,43 @@ fn getrandom<'tcx>(
     this.write_scalar(Scalar::from_uint(len, dest.layout.size), dest)?;
     Ok(())
 }
+
+// Shims `sched_getaffinity`: the current thread may run on all of the `-Zmiri-num-cpus` CPUs.
+fn sched_getaffinity<'tcx>(
+    this: &mut MiriEvalContext<'_, 'tcx>,
+    args: &[OpTy<'tcx, Tag>],
+) -> InterpResult<'tcx, i32> {
+    let pid = this.read_scalar(args[0])?.to_i32()?;
+    let size = this.read_scalar(args[1])?.to_machine_usize(this)?;
+    let mask = this.read_scalar(args[2])?.not_undef()?;
+
+    // There are no other processes whose affinity we could report.
+    if pid != 0 {
+        let esrch = this.eval_libc("ESRCH")?;
+        this.set_last_error(esrch)?;
+        return Ok(-1);
+    }
+    // `cpu_set_t` is an array of `unsigned long`, and the kernel requires the mask to be large
+    // enough for all CPUs.
+    let word_size = this.pointer_size().bytes();
+    let num_cpus = this.machine.num_cpus;
+    if size % word_size != 0 || size * 8 < num_cpus {
+        let einval = this.eval_libc("EINVAL")?;
+        this.set_last_error(einval)?;
+        return Ok(-1);
+    }
+    let endian = this.tcx.data_layout.endian;
+    let mut bytes = Vec::with_capacity(size as usize);
+    for word in 0..size / word_size {
+        let first_cpu = word * word_size * 8;
+        let cpus = num_cpus.saturating_sub(first_cpu).min(word_size * 8);
+        let bits = if cpus == 64 { u64::max_value() } else { (1u64 << cpus) - 1 };
+        let word_bytes = match endian {
+            layout::Endian::Little => bits.to_le_bytes()[..word_size as usize].to_vec(),
+            layout::Endian::Big => bits.to_be_bytes()[8 - word_size as usize..].to_vec(),
+        };
+        bytes.extend(word_bytes);
+    }
+    this.memory.write_bytes(mask, bytes)?;
+    Ok(0)
+}
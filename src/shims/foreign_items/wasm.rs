@@ -0,0 +1,97 @@
+use rustc::mir;
+use rustc::ty::layout::{Align, Size};
+
+use crate::intptrcast::GlobalState;
+use crate::*;
+
+/// The size of a wasm page.
+const WASM_PAGE_SIZE: u64 = 64 * 1024;
+/// The largest number of pages a wasm32 linear memory can have (4 GiB).
+const WASM_MAX_PAGES: u64 = 64 * 1024;
+
+impl<'mir, 'tcx> EvalContextExtPrivate<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+trait EvalContextExtPrivate<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// The current size of the linear memory in pages. We pretend that everything below the
+    /// highest integer address handed out so far was already part of the linear memory.
+    fn wasm_memory_pages(&self) -> u64 {
+        let this = self.eval_context_ref();
+        let next_base_addr = GlobalState::next_base_addr(&this.memory);
+        (next_base_addr + WASM_PAGE_SIZE - 1) / WASM_PAGE_SIZE
+    }
+
+    /// Checks that a `memory.*` instruction operates on memory 0, the only one wasm32 has.
+    fn check_wasm_memory_index(&mut self, mem_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let mem = this.read_scalar(mem_op)?.to_u32()?;
+        if mem != 0 {
+            throw_ub_format!("wasm memory index {} does not exist", mem);
+        }
+        Ok(())
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Emulates the LLVM intrinsics used by `core::arch::wasm32`.
+    fn emulate_wasm_intrinsic(
+        &mut self,
+        link_name: &str,
+        args: &[OpTy<'tcx, Tag>],
+        dest: PlaceTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, bool> {
+        let this = self.eval_context_mut();
+
+        match link_name {
+            // `memory_size`: the size of the linear memory in pages.
+            "llvm.wasm.memory.size.i32" => {
+                this.check_wasm_memory_index(args[0])?;
+                let pages = this.wasm_memory_pages();
+                this.write_scalar(Scalar::from_uint(pages, dest.layout.size), dest)?;
+            }
+
+            // `memory_grow`: every call makes a new allocation whose address is where the linear
+            // memory used to end. This means that the memory returned by two calls is not one
+            // allocation, but allocators (like `dlmalloc`) treat it as separate regions anyway.
+            "llvm.wasm.memory.grow.i32" => {
+                this.check_wasm_memory_index(args[0])?;
+                let delta = this.read_scalar(args[1])?.to_u32()?;
+                let pages = this.wasm_memory_pages();
+                let delta = u64::from(delta);
+                if pages + delta > WASM_MAX_PAGES {
+                    // Growing failed.
+                    this.write_scalar(Scalar::from_int(-1, dest.layout.size), dest)?;
+                    return Ok(true);
+                }
+                if delta > 0 {
+                    // Fresh linear memory is zeroed.
+                    let size = Size::from_bytes(delta * WASM_PAGE_SIZE);
+                    let align = Align::from_bytes(WASM_PAGE_SIZE).unwrap();
+                    let ptr = this.memory.allocate(size, align, MiriMemoryKind::WasmMemory.into());
+                    this.zero_fresh_allocation(ptr)?;
+                    let base_addr = pages * WASM_PAGE_SIZE;
+                    GlobalState::assign_base_addr(ptr.alloc_id, base_addr, &this.memory)?;
+                }
+                this.write_scalar(Scalar::from_uint(pages, dest.layout.size), dest)?;
+            }
+
+            _ => throw_unsup_format!("can't call foreign function: {}", link_name),
+        }
+
+        Ok(true)
+    }
+
+    /// There is no libc on `wasm32-unknown-unknown`: the standard library is self-contained, and
+    /// every other foreign function is provided by the wasm host, which Miri does not have.
+    fn emulate_foreign_item_by_name(
+        &mut self,
+        link_name: &str,
+        _args: &[OpTy<'tcx, Tag>],
+        _dest: PlaceTy<'tcx, Tag>,
+        _ret: mir::BasicBlock,
+    ) -> InterpResult<'tcx, bool> {
+        throw_unsup_format!(
+            "can't call `{}`: it is imported from the wasm host, which does not exist under Miri",
+            link_name
+        )
+    }
+}
@@ -1,8 +1,96 @@
 use crate::*;
 use rustc::mir;
 use rustc::ty::layout::Size;
+use rustc_mir::interpret::AllocCheck;
+use std::io::{self, Write};
 use std::iter;
 
+// The pseudo-handles returned by `GetStdHandle`.
+const STD_INPUT_HANDLE: i64 = -10;
+const STD_OUTPUT_HANDLE: i64 = -11;
+const STD_ERROR_HANDLE: i64 = -12;
+
+const ERROR_INVALID_HANDLE: u32 = 6;
+const ERROR_PROC_NOT_FOUND: u32 = 127;
+const ERROR_TIMEOUT: u32 = 1460;
+
+const INFINITE: u32 = 0xFFFFFFFF;
+const WAIT_TIMEOUT: u32 = 0x00000102;
+
+/// The pseudo-handle returned by `GetCurrentThread`.
+const CURRENT_THREAD: i64 = -2;
+/// The ID of the only thread.
+const MAIN_THREAD_ID: u32 = 1;
+
+/// The handle of every module; see `GetModuleHandleW`.
+const MODULE_HANDLE: i64 = 0x10000;
+
+/// The handle returned by `GetProcessHeap`. There are no other heaps, as `HeapCreate` is not
+/// supported.
+const PROCESS_HEAP: i64 = 1;
+
+// Flags of the heap functions.
+const HEAP_NO_SERIALIZE: u32 = 0x00000001;
+const HEAP_GENERATE_EXCEPTIONS: u32 = 0x00000004;
+const HEAP_ZERO_MEMORY: u32 = 0x00000008;
+const HEAP_REALLOC_IN_PLACE_ONLY: u32 = 0x00000010;
+
+/// Returns the host stream that `handle` stands for, if it is the stdout or stderr handle.
+fn host_stream(handle: i64) -> Option<Box<dyn Write>> {
+    match handle {
+        STD_OUTPUT_HANDLE => Some(Box::new(io::stdout())),
+        STD_ERROR_HANDLE => Some(Box::new(io::stderr())),
+        _ => None,
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExtPrivate<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+trait EvalContextExtPrivate<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Turns the result of writing to `host_stream(handle)` into the number of bytes written, or
+    /// sets the last error and returns `None`.
+    fn host_stream_result(
+        &mut self,
+        result: Option<io::Result<usize>>,
+    ) -> InterpResult<'tcx, Option<usize>> {
+        let this = self.eval_context_mut();
+        match result {
+            Some(Ok(n)) => Ok(Some(n)),
+            Some(Err(e)) => {
+                this.set_last_error_from_io_error(e)?;
+                Ok(None)
+            }
+            None => {
+                // `CreateFileW` is not supported, so there are no other handles to write to.
+                this.set_last_error(Scalar::from_u32(ERROR_INVALID_HANDLE))?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Checks the heap handle and flags passed to one of the heap functions, and returns whether
+    /// `HEAP_ZERO_MEMORY` is set.
+    fn check_heap_args(
+        &mut self,
+        handle_op: OpTy<'tcx, Tag>,
+        flags_op: OpTy<'tcx, Tag>,
+        allowed_flags: u32,
+    ) -> InterpResult<'tcx, bool> {
+        let this = self.eval_context_mut();
+        let handle = this.read_scalar(handle_op)?.to_machine_isize(this)?;
+        let flags = this.read_scalar(flags_op)?.to_u32()?;
+        if handle != PROCESS_HEAP {
+            throw_ub_format!("invalid heap handle {:#x}", handle);
+        }
+        // Serialization does not matter without threads, and since we never fail to allocate,
+        // there are no exceptions to generate.
+        let unsupported = flags & !(allowed_flags | HEAP_NO_SERIALIZE | HEAP_GENERATE_EXCEPTIONS);
+        if unsupported != 0 {
+            throw_unsup_format!("unsupported heap flags {:#x}", unsupported);
+        }
+        Ok(flags & HEAP_ZERO_MEMORY != 0)
+    }
+}
+
 impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
 pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
     fn emulate_foreign_item_by_name(
@@ -41,33 +129,30 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 throw_unsup_format!("can't set environment variable on Windows");
             }
 
+            "SetCurrentDirectoryW" => {
+                let result = this.SetCurrentDirectoryW(args[0])?;
+                this.write_scalar(Scalar::from_int(result, dest.layout.size), dest)?;
+            }
+
             // File related shims
             "WriteFile" => {
                 let handle = this.read_scalar(args[0])?.to_machine_isize(this)?;
                 let buf = this.read_scalar(args[1])?.not_undef()?;
                 let n = this.read_scalar(args[2])?.to_u32()?;
+                let overlapped = this.read_scalar(args[4])?.not_undef()?;
+                if !this.is_null(overlapped)? {
+                    throw_unsup_format!("overlapped I/O is not supported");
+                }
+                // Without overlapped I/O, the pointer to the number of bytes written is mandatory.
                 let written_place = this.deref_operand(args[3])?;
                 // Spec says to always write `0` first.
                 this.write_null(written_place.into())?;
-                let written = if handle == -11 || handle == -12 {
-                    // stdout/stderr
-                    use std::io::{self, Write};
-
-                    let buf_cont = this.memory.read_bytes(buf, Size::from_bytes(u64::from(n)))?;
-                    let res = if handle == -11 {
-                        io::stdout().write(buf_cont)
-                    } else {
-                        io::stderr().write(buf_cont)
-                    };
-                    res.ok().map(|n| n as u32)
-                } else {
-                    eprintln!("Miri: Ignored output to handle {}", handle);
-                    // Pretend it all went well.
-                    Some(n)
-                };
+                let bytes = this.memory.read_bytes(buf, Size::from_bytes(u64::from(n)))?;
+                let result = host_stream(handle).map(|mut stream| stream.write(bytes));
+                let written = this.host_stream_result(result)?;
                 // If there was no error, write back how much was written.
                 if let Some(n) = written {
-                    this.write_scalar(Scalar::from_u32(n), written_place.into())?;
+                    this.write_scalar(Scalar::from_u32(n as u32), written_place.into())?;
                 }
                 // Return whether this was a success.
                 this.write_scalar(
@@ -75,34 +160,99 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     dest,
                 )?;
             }
+            "WriteConsoleW" => {
+                let handle = this.read_scalar(args[0])?.to_machine_isize(this)?;
+                let buf = this.read_scalar(args[1])?.not_undef()?;
+                let n = this.read_scalar(args[2])?.to_u32()?;
+                let written_ptr = this.read_scalar(args[3])?.not_undef()?;
+                // The console takes UTF-16, but the host streams take UTF-8.
+                let bytes = this.memory.read_bytes(buf, Size::from_bytes(u64::from(n) * 2))?;
+                let wide: Vec<u16> =
+                    bytes.chunks(2).map(|c| this.uint_from_target_bytes(c) as u16).collect();
+                let text = String::from_utf16_lossy(&wide);
+                // We always write the whole text, so that we can report the number of characters.
+                let result = host_stream(handle)
+                    .map(|mut stream| stream.write_all(text.as_bytes()).map(|()| n as usize));
+                let written = this.host_stream_result(result)?;
+                if let Some(n) = written {
+                    if !this.is_null(written_ptr)? {
+                        let written_place = this.deref_operand(args[3])?;
+                        this.write_scalar(Scalar::from_u32(n as u32), written_place.into())?;
+                    }
+                }
+                this.write_scalar(
+                    Scalar::from_int(if written.is_some() { 1 } else { 0 }, dest.layout.size),
+                    dest,
+                )?;
+            }
 
             // Other shims
             "GetProcessHeap" => {
                 // Just fake a HANDLE
-                this.write_scalar(Scalar::from_int(1, this.pointer_size()), dest)?;
+                this.write_scalar(Scalar::from_int(PROCESS_HEAP, this.pointer_size()), dest)?;
             }
             "HeapAlloc" => {
-                let _handle = this.read_scalar(args[0])?.to_machine_isize(this)?;
-                let flags = this.read_scalar(args[1])?.to_u32()?;
+                let zero_init = this.check_heap_args(args[0], args[1], HEAP_ZERO_MEMORY)?;
                 let size = this.read_scalar(args[2])?.to_machine_usize(this)?;
-                let zero_init = (flags & 0x00000008) != 0; // HEAP_ZERO_MEMORY
+                // Like `malloc(0)`, `HeapAlloc` with size 0 returns a unique pointer.
                 let res = this.malloc(size, zero_init, MiriMemoryKind::WinHeap);
                 this.write_scalar(res, dest)?;
             }
             "HeapFree" => {
-                let _handle = this.read_scalar(args[0])?.to_machine_isize(this)?;
-                let _flags = this.read_scalar(args[1])?.to_u32()?;
+                this.check_heap_args(args[0], args[1], 0)?;
                 let ptr = this.read_scalar(args[2])?.not_undef()?;
+                // Freeing `NULL` does nothing (and succeeds).
                 this.free(ptr, MiriMemoryKind::WinHeap)?;
                 this.write_scalar(Scalar::from_int(1, Size::from_bytes(4)), dest)?;
             }
             "HeapReAlloc" => {
-                let _handle = this.read_scalar(args[0])?.to_machine_isize(this)?;
-                let _flags = this.read_scalar(args[1])?.to_u32()?;
+                let allowed_flags = HEAP_ZERO_MEMORY | HEAP_REALLOC_IN_PLACE_ONLY;
+                let zero_init = this.check_heap_args(args[0], args[1], allowed_flags)?;
+                let flags = this.read_scalar(args[1])?.to_u32()?;
                 let ptr = this.read_scalar(args[2])?.not_undef()?;
                 let size = this.read_scalar(args[3])?.to_machine_usize(this)?;
-                let res = this.realloc(ptr, size, MiriMemoryKind::WinHeap)?;
-                this.write_scalar(res, dest)?;
+                if this.is_null(ptr)? {
+                    // Unlike `realloc`, this does not allocate a new block.
+                    throw_ub_format!("HeapReAlloc called with a null pointer");
+                }
+                let ptr = this.force_ptr(ptr)?;
+                this.check_double_free(ptr)?;
+                this.check_dealloc_kind(ptr, MiriMemoryKind::WinHeap)?;
+                let old_size = this.memory.get_size_and_align(ptr.alloc_id, AllocCheck::Live)?.0;
+                let in_place_only = flags & HEAP_REALLOC_IN_PLACE_ONLY != 0;
+                let new_ptr = if in_place_only && size > old_size.bytes() {
+                    // We never grow allocations in place. Moving them when shrinking is fine, as
+                    // that cannot be observed without using the old, now dangling, pointer.
+                    None
+                } else {
+                    // Unlike `realloc(ptr, 0)`, this keeps a (zero-sized) block around.
+                    let new_align = this.min_align(size, MiriMemoryKind::WinHeap);
+                    let new_ptr = this.memory.reallocate(
+                        ptr,
+                        None,
+                        Size::from_bytes(size),
+                        new_align,
+                        MiriMemoryKind::WinHeap.into(),
+                    )?;
+                    this.record_free(ptr.alloc_id);
+                    this.record_alloc_site(new_ptr.alloc_id, MiriMemoryKind::WinHeap);
+                    Some(new_ptr)
+                };
+                match new_ptr {
+                    Some(new_ptr) => {
+                        // `HEAP_ZERO_MEMORY` only applies to the added bytes.
+                        if zero_init && size > old_size.bytes() {
+                            let grown = iter::repeat(0u8).take((size - old_size.bytes()) as usize);
+                            let grown_ptr = new_ptr.offset(old_size, this)?;
+                            this.memory.write_bytes(grown_ptr.into(), grown)?;
+                        }
+                        this.write_scalar(Scalar::Ptr(new_ptr), dest)?;
+                    }
+                    None => {
+                        this.set_last_error(Scalar::from_u32(8))?; // ERROR_NOT_ENOUGH_MEMORY
+                        this.write_null(dest)?;
+                    }
+                }
             }
 
             "SetLastError" => {
@@ -126,13 +276,75 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 // Nothing to do, not even a return value.
             }
 
+            // Dynamic symbol lookup. We pretend every module is loaded; which symbols are found
+            // is decided by `Dlsym`.
             | "GetModuleHandleW"
-            | "GetProcAddress"
+            | "LoadLibraryW"
+            | "LoadLibraryExW"
+            => {
+                this.write_scalar(Scalar::from_int(MODULE_HANDLE, dest.layout.size), dest)?;
+            }
+            "GetProcAddress" => {
+                let _module = this.read_scalar(args[0])?.not_undef()?;
+                let symbol = this.read_scalar(args[1])?.not_undef()?;
+                if symbol.to_machine_usize(this).map_or(false, |ordinal| ordinal < 0x10000) {
+                    throw_unsup_format!("GetProcAddress with an ordinal is not supported");
+                }
+                let symbol_name = this.memory.read_c_str(symbol)?;
+                let symbol_name = String::from_utf8_lossy(symbol_name).into_owned();
+                let target_os = &this.tcx.sess.target.target.target_os;
+                if let Some(dlsym) = Dlsym::from_str(&symbol_name, target_os)? {
+                    let ptr = this.memory.create_fn_alloc(FnVal::Other(dlsym));
+                    this.write_scalar(Scalar::from(ptr), dest)?;
+                } else {
+                    this.set_last_error(Scalar::from_u32(ERROR_PROC_NOT_FOUND))?;
+                    this.write_null(dest)?;
+                }
+            }
+            "FreeLibrary" => {
+                this.write_scalar(Scalar::from_int(1, dest.layout.size), dest)?;
+            }
+
+            // Address-based waiting. There are no other threads, so nobody ever wakes us up.
+            "WaitOnAddress" => {
+                let addr = this.read_scalar(args[0])?.not_undef()?;
+                let compare = this.read_scalar(args[1])?.not_undef()?;
+                let size = this.read_scalar(args[2])?.to_machine_usize(this)?;
+                let timeout = this.read_scalar(args[3])?.to_u32()?;
+                if ![1, 2, 4, 8].contains(&size) {
+                    throw_ub_format!("WaitOnAddress called with invalid size {}", size);
+                }
+                let size = Size::from_bytes(size);
+                let current = this.memory.read_bytes(addr, size)?.to_owned();
+                let expected = this.memory.read_bytes(compare, size)?;
+                if current != expected {
+                    this.write_scalar(Scalar::from_int(1, dest.layout.size), dest)?;
+                } else if timeout == INFINITE {
+                    throw_machine_stop!(TerminationInfo::Deadlock(
+                        "waiting without timeout for a value to change in WaitOnAddress".to_owned()
+                    ));
+                } else {
+                    // With a timeout, waiting would just let the time run out.
+                    this.set_last_error(Scalar::from_u32(ERROR_TIMEOUT))?;
+                    this.write_null(dest)?;
+                }
+            }
+            "WakeByAddressSingle" | "WakeByAddressAll" => {
+                let _addr = this.read_scalar(args[0])?.not_undef()?;
+                // Nobody is waiting.
+            }
+
+            // Time related shims
+            "GetSystemTimeAsFileTime" | "GetSystemTimePreciseAsFileTime" => {
+                this.GetSystemTimeAsFileTime(args[0])?;
+            }
+
             | "TryEnterCriticalSection"
             | "GetConsoleScreenBufferInfo"
             | "SetConsoleTextAttribute"
             => {
                 // Pretend these do not exist / nothing happened, by returning zero.
+                this.note_partial_emulation("the call has no effect");
                 this.write_null(dest)?;
             }
 
@@ -143,10 +355,32 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     system_info.ptr,
                     iter::repeat(0u8).take(system_info.layout.size.bytes() as usize),
                 )?;
-                // Set number of processors.
-                let dword_size = Size::from_bytes(4);
-                let num_cpus = this.mplace_field(system_info, 6)?;
-                this.write_scalar(Scalar::from_int(NUM_CPUS, dword_size), num_cpus.into())?;
+                let processor_architecture = match this.tcx.sess.target.target.arch.as_str() {
+                    "x86_64" => 9, // PROCESSOR_ARCHITECTURE_AMD64
+                    "x86" => 0, // PROCESSOR_ARCHITECTURE_INTEL
+                    "aarch64" => 12, // PROCESSOR_ARCHITECTURE_ARM64
+                    _ => 0xffff, // PROCESSOR_ARCHITECTURE_UNKNOWN
+                };
+                // The mask only has room for as many processors as its bits.
+                let num_cpus = this.machine.num_cpus;
+                let mask_bits = this.pointer_size().bits();
+                let active_processor_mask = if num_cpus >= mask_bits {
+                    u64::max_value() >> (64 - mask_bits)
+                } else {
+                    (1u64 << num_cpus) - 1
+                };
+                // Set the fields that `std` and friends look at, by index.
+                let fields = [
+                    (0, processor_architecture), // wProcessorArchitecture
+                    (2, PAGE_SIZE), // dwPageSize
+                    (5, active_processor_mask), // dwActiveProcessorMask
+                    (6, num_cpus), // dwNumberOfProcessors
+                    (8, 64 * 1024), // dwAllocationGranularity
+                ];
+                for &(field, value) in &fields {
+                    let place = this.mplace_field(system_info, field)?;
+                    this.write_scalar(Scalar::from_uint(value, place.layout.size), place.into())?;
+                }
             }
 
             "TlsAlloc" => {
@@ -177,10 +411,17 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 this.write_scalar(Scalar::from_int(1, dest.layout.size), dest)?;
             }
             "GetStdHandle" => {
-                let which = this.read_scalar(args[0])?.to_i32()?;
+                let which = i64::from(this.read_scalar(args[0])?.to_i32()?);
                 // We just make this the identity function, so we know later in `WriteFile`
                 // which one it is.
-                this.write_scalar(Scalar::from_int(which, this.pointer_size()), dest)?;
+                let handle = match which {
+                    STD_INPUT_HANDLE | STD_OUTPUT_HANDLE | STD_ERROR_HANDLE => which,
+                    _ => {
+                        this.set_last_error(Scalar::from_u32(ERROR_INVALID_HANDLE))?;
+                        -1 // INVALID_HANDLE_VALUE
+                    }
+                };
+                this.write_scalar(Scalar::from_int(handle, this.pointer_size()), dest)?;
             }
             "GetConsoleMode" => {
                 // Everything is a pipe.
@@ -199,10 +440,58 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 this.gen_random(ptr, len as usize)?;
                 this.write_scalar(Scalar::from_bool(true), dest)?;
             }
-            // We don't support threading.
+            "BCryptGenRandom" => {
+                let algorithm = this.read_scalar(args[0])?.not_undef()?;
+                let ptr = this.read_scalar(args[1])?.not_undef()?;
+                let len = this.read_scalar(args[2])?.to_u32()?;
+                let flags = this.read_scalar(args[3])?.to_u32()?;
+                // We only support the system RNG, which is what `BCRYPT_USE_SYSTEM_PREFERRED_RNG`
+                // selects (and then no algorithm handle may be given).
+                if flags != 2 || !this.is_null(algorithm)? {
+                    throw_unsup_format!(
+                        "BCryptGenRandom is only supported with BCRYPT_USE_SYSTEM_PREFERRED_RNG"
+                    );
+                }
+                this.gen_random(ptr, len as usize)?;
+                this.write_null(dest)?; // STATUS_SUCCESS
+            }
+            // We don't support threading. There is just the main thread, but the thread APIs
+            // that only look at the current thread work.
             "CreateThread" => {
                 throw_unsup_format!("Miri does not support threading");
             }
+            "GetCurrentThreadId" => {
+                this.write_scalar(Scalar::from_u32(MAIN_THREAD_ID), dest)?;
+            }
+            "GetCurrentThread" => {
+                this.write_scalar(Scalar::from_int(CURRENT_THREAD, dest.layout.size), dest)?;
+            }
+            "WaitForSingleObject" => {
+                let handle = this.read_scalar(args[0])?.to_machine_isize(this)?;
+                let timeout = this.read_scalar(args[1])?.to_u32()?;
+                if handle != CURRENT_THREAD {
+                    throw_unsup_format!("WaitForSingleObject is only supported on thread handles");
+                }
+                // The current thread never terminates while it is waiting for itself.
+                if timeout == INFINITE {
+                    throw_machine_stop!(TerminationInfo::Deadlock(
+                        "the current thread is waiting for itself to terminate".to_owned()
+                    ));
+                }
+                this.write_scalar(Scalar::from_u32(WAIT_TIMEOUT), dest)?;
+            }
+            "CloseHandle" => {
+                let handle = this.read_scalar(args[0])?.to_machine_isize(this)?;
+                // Closing a pseudo-handle (like the current thread's) does nothing. There are no
+                // real handles that could be closed.
+                let success = if handle == CURRENT_THREAD {
+                    true
+                } else {
+                    this.set_last_error(Scalar::from_u32(ERROR_INVALID_HANDLE))?;
+                    false
+                };
+                this.write_scalar(Scalar::from_int(success as i32, dest.layout.size), dest)?;
+            }
             _ => throw_unsup_format!("can't call foreign function: {}", link_name),
         }
 
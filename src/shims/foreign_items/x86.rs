@@ -0,0 +1,280 @@
+use crate::*;
+use rustc::mir;
+use rustc::ty::layout::Align;
+
+/// The CPU features that our `cpuid` can report: the name (as used by `target_feature`), the
+/// leaf, the register (0 to 3 for `eax`, `ebx`, `ecx`, `edx`) and the bit.
+const CPUID_FEATURES: &[(&str, u32, usize, u32)] = &[
+    ("sse3", 1, 2, 0),
+    ("pclmulqdq", 1, 2, 1),
+    ("ssse3", 1, 2, 9),
+    ("fma", 1, 2, 12),
+    ("cmpxchg16b", 1, 2, 13),
+    ("sse4.1", 1, 2, 19),
+    ("sse4.2", 1, 2, 20),
+    ("movbe", 1, 2, 22),
+    ("popcnt", 1, 2, 23),
+    ("aes", 1, 2, 25),
+    ("xsave", 1, 2, 26),
+    ("avx", 1, 2, 28),
+    ("f16c", 1, 2, 29),
+    ("rdrand", 1, 2, 30),
+    ("tsc", 1, 3, 4),
+    ("mmx", 1, 3, 23),
+    ("fxsr", 1, 3, 24),
+    ("sse", 1, 3, 25),
+    ("sse2", 1, 3, 26),
+    ("bmi1", 7, 1, 3),
+    ("avx2", 7, 1, 5),
+    ("bmi2", 7, 1, 8),
+    ("avx512f", 7, 1, 16),
+    ("rdseed", 7, 1, 18),
+    ("adx", 7, 1, 19),
+    ("sha", 7, 1, 29),
+    ("lzcnt", 0x8000_0001, 2, 5),
+    ("sse4a", 0x8000_0001, 2, 6),
+    ("tbm", 0x8000_0001, 2, 21),
+];
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Emulates the LLVM intrinsics used by `core::arch::{x86, x86_64}`. Most `_mm_*`
+    /// functions are implemented in terms of the generic `simd_*` intrinsics and plain memory
+    /// accesses; here we handle the most common ones that need target-specific LLVM intrinsics.
+    fn emulate_x86_intrinsic(
+        &mut self,
+        link_name: &str,
+        args: &[OpTy<'tcx, Tag>],
+        dest: PlaceTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, bool> {
+        let this = self.eval_context_mut();
+        let tcx = &{ this.tcx.tcx };
+
+        match link_name {
+            // `_mm_pause`: a spin-loop hint.
+            "llvm.x86.sse2.pause" => {}
+
+            // `_xgetbv`: we only know about the state components for SSE and (if enabled) AVX.
+            "llvm.x86.xgetbv" => {
+                let xcr = this.read_scalar(args[0])?.to_u32()?;
+                if xcr != 0 {
+                    throw_unsup_format!("`_xgetbv` is only supported for XCR0");
+                }
+                let avx = this.cpu_features().iter().any(|f| f == "avx");
+                // x87, SSE and (maybe) AVX state.
+                let xcr0 = if avx { 0b111 } else { 0b11 };
+                this.write_scalar(Scalar::from_u64(xcr0), dest)?;
+            }
+
+            // `_mm_movemask_*`: collect the most significant bit of every lane.
+            #[rustfmt::skip]
+            | "llvm.x86.sse2.pmovmskb.128"
+            | "llvm.x86.avx2.pmovmskb"
+            | "llvm.x86.sse.movmsk.ps"
+            | "llvm.x86.sse2.movmsk.pd"
+            | "llvm.x86.avx.movmsk.ps.256"
+            | "llvm.x86.avx.movmsk.pd.256"
+            => {
+                let lanes = args[0].layout.fields.count() as u64;
+                let mut mask = 0u64;
+                for i in 0..lanes {
+                    let lane = this.operand_field(args[0], i)?;
+                    let size = lane.layout.size;
+                    let bits = this.read_scalar(lane)?.not_undef()?.to_bits(size)?;
+                    if bits >> (size.bits() - 1) != 0 {
+                        mask |= 1 << i;
+                    }
+                }
+                this.write_scalar(Scalar::from_uint(mask, dest.layout.size), dest)?;
+            }
+
+            // `_mm_shuffle_epi8`: pick bytes by index within each 128-bit lane; indices with the
+            // high bit set produce 0.
+            "llvm.x86.ssse3.pshuf.b.128" | "llvm.x86.avx2.pshuf.b" => {
+                let lanes = dest.layout.fields.count() as u64;
+                for i in 0..lanes {
+                    let idx = this.read_scalar(this.operand_field(args[1], i)?)?.to_u8()?;
+                    let val = if idx & 0x80 != 0 {
+                        Scalar::from_u8(0).into()
+                    } else {
+                        let src = (i & !15) + u64::from(idx & 15);
+                        this.read_scalar(this.operand_field(args[0], src)?)?
+                    };
+                    let dest = this.place_field(dest, i)?;
+                    this.write_scalar(val, dest)?;
+                }
+            }
+
+            // `_mm_storeu_*`: store a vector to a possibly unaligned address.
+            #[rustfmt::skip]
+            | "llvm.x86.sse2.storeu.dq"
+            | "llvm.x86.sse2.storeu.pd"
+            | "llvm.x86.sse.storeu.ps"
+            | "llvm.x86.avx.storeu.dq.256"
+            | "llvm.x86.avx.storeu.pd.256"
+            | "llvm.x86.avx.storeu.ps.256"
+            => {
+                let ptr = this.read_scalar(args[0])?.not_undef()?;
+                let size = args[1].layout.size;
+                let ptr = this.memory.check_ptr_access(ptr, size, Align::from_bytes(1).unwrap())?;
+                if let Some(ptr) = ptr {
+                    let lanes = args[1].layout.fields.count() as u64;
+                    for i in 0..lanes {
+                        let lane = this.operand_field(args[1], i)?;
+                        let lane_size = lane.layout.size;
+                        let val = this.read_scalar(lane)?;
+                        let lane_ptr = ptr.offset(lane_size * i, tcx)?;
+                        this.memory
+                            .get_raw_mut(ptr.alloc_id)?
+                            .write_scalar(tcx, lane_ptr, val, lane_size)?;
+                    }
+                }
+            }
+
+            // `_mm_cmp*_ps`/`_mm_cmp*_pd`: compare lanes according to the predicate in the third
+            // argument, producing a mask.
+            "llvm.x86.sse.cmp.ps" | "llvm.x86.sse2.cmp.pd" => {
+                use mir::BinOp;
+                let predicate = this.read_scalar(args[2])?.to_i8()?;
+                let lanes = dest.layout.fields.count() as u64;
+                for i in 0..lanes {
+                    let left = this.read_immediate(this.operand_field(args[0], i)?)?;
+                    let right = this.read_immediate(this.operand_field(args[1], i)?)?;
+                    // All of these are `false` if one of the operands is NaN.
+                    let eq = this.overflowing_binary_op(BinOp::Eq, left, right)?.0.to_bool()?;
+                    let lt = this.overflowing_binary_op(BinOp::Lt, left, right)?.0.to_bool()?;
+                    let le = this.overflowing_binary_op(BinOp::Le, left, right)?.0.to_bool()?;
+                    let gt = this.overflowing_binary_op(BinOp::Gt, left, right)?.0.to_bool()?;
+                    let unordered = !eq && !lt && !gt;
+                    let res = match predicate {
+                        0 => eq,
+                        1 => lt,
+                        2 => le,
+                        3 => unordered,
+                        4 => !eq,
+                        5 => !lt,
+                        6 => !le,
+                        7 => !unordered,
+                        _ => throw_unsup_format!(
+                            "unsupported predicate {} for `{}`",
+                            predicate,
+                            link_name
+                        ),
+                    };
+                    let dest = this.place_field(dest, i)?;
+                    let mask = if res { -1 } else { 0 };
+                    this.write_scalar(Scalar::from_int(mask, dest.layout.size), dest)?;
+                }
+            }
+
+            // `_mm_{min,max}_ep{u8,i16}`.
+            #[rustfmt::skip]
+            | "llvm.x86.sse2.pmaxu.b"
+            | "llvm.x86.sse2.pminu.b"
+            | "llvm.x86.sse2.pmaxs.w"
+            | "llvm.x86.sse2.pmins.w"
+            => {
+                let op = if link_name.contains("max") { mir::BinOp::Gt } else { mir::BinOp::Lt };
+                let lanes = dest.layout.fields.count() as u64;
+                for i in 0..lanes {
+                    let left = this.read_immediate(this.operand_field(args[0], i)?)?;
+                    let right = this.read_immediate(this.operand_field(args[1], i)?)?;
+                    let left_wins = this.overflowing_binary_op(op, left, right)?.0.to_bool()?;
+                    let val = if left_wins { left } else { right };
+                    let dest = this.place_field(dest, i)?;
+                    this.write_immediate(*val, dest)?;
+                }
+            }
+
+            // `_mm_{slli,srli,srai}_epi{16,32,64}`: shift all lanes by an immediate. Unlike the
+            // generic shifts, shifting by the lane width or more is fine: logical shifts then
+            // produce 0, arithmetic shifts replicate the sign bit.
+            #[rustfmt::skip]
+            | "llvm.x86.sse2.pslli.w"
+            | "llvm.x86.sse2.pslli.d"
+            | "llvm.x86.sse2.pslli.q"
+            | "llvm.x86.sse2.psrli.w"
+            | "llvm.x86.sse2.psrli.d"
+            | "llvm.x86.sse2.psrli.q"
+            | "llvm.x86.sse2.psrai.w"
+            | "llvm.x86.sse2.psrai.d"
+            => {
+                let shift = u64::from(this.read_scalar(args[1])?.to_u32()?);
+                let lanes = dest.layout.fields.count() as u64;
+                for i in 0..lanes {
+                    let lane = this.operand_field(args[0], i)?;
+                    let size = lane.layout.size;
+                    let bits = this.read_scalar(lane)?.not_undef()?.to_bits(size)?;
+                    let res = if link_name.contains(".psrai.") {
+                        let signed = this.sign_extend(bits, lane.layout) as i128;
+                        (signed >> shift.min(size.bits() - 1)) as u128
+                    } else if shift >= size.bits() {
+                        0
+                    } else if link_name.contains(".pslli.") {
+                        bits << shift
+                    } else {
+                        bits >> shift
+                    };
+                    let res = this.truncate(res, lane.layout);
+                    let dest = this.place_field(dest, i)?;
+                    this.write_scalar(Scalar::from_uint(res, size), dest)?;
+                }
+            }
+
+            _ => throw_unsup_format!("unsupported x86 vendor intrinsic: {}", link_name),
+        }
+
+        Ok(true)
+    }
+
+    /// Emulates `core::arch`'s `__cpuid_count` and `has_cpuid`, reporting the CPU features from
+    /// `-Zmiri-cpu-features` (or the features the program was compiled for). Unknown feature
+    /// names are ignored.
+    fn emulate_cpuid_fn(
+        &mut self,
+        name: &str,
+        args: &[OpTy<'tcx, Tag>],
+        dest: PlaceTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        if name == "has_cpuid" {
+            return this.write_scalar(Scalar::from_bool(true), dest);
+        }
+        let leaf = this.read_scalar(args[0])?.to_u32()?;
+        let sub_leaf = this.read_scalar(args[1])?.to_u32()?;
+        let features = this.cpu_features();
+        let vendor = u32::from_le_bytes(*b"Miri");
+        let mut regs = match (leaf, sub_leaf) {
+            // Highest basic leaf, and the vendor string ("MiriMiriMiri" in `ebx`, `edx`, `ecx`).
+            (0, _) => [7, vendor, vendor, vendor],
+            // Highest extended leaf.
+            (0x8000_0000, _) => [0x8000_0001, 0, 0, 0],
+            _ => [0; 4],
+        };
+        if sub_leaf == 0 {
+            for &(feature, feature_leaf, reg, bit) in CPUID_FEATURES {
+                if feature_leaf == leaf && features.iter().any(|f| f == feature) {
+                    regs[reg] |= 1 << bit;
+                }
+            }
+        }
+        // Announce that the OS supports `xgetbv`, so that AVX can be detected.
+        if leaf == 1 && features.iter().any(|f| f == "avx") {
+            regs[2] |= 1 << 27;
+        }
+        for (i, &reg) in regs.iter().enumerate() {
+            let field = this.place_field(dest, i as u64)?;
+            this.write_scalar(Scalar::from_u32(reg), field)?;
+        }
+        Ok(())
+    }
+
+    /// The CPU features to report to the program.
+    fn cpu_features(&self) -> Vec<String> {
+        let this = self.eval_context_ref();
+        match &this.machine.cpu_features {
+            Some(features) => features.clone(),
+            None => this.tcx.sess.target_features.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+}
@@ -1,37 +1,380 @@
 use std::collections::BTreeMap;
 use std::convert::{TryFrom, TryInto};
-use std::fs::{remove_file, rename, File, OpenOptions};
-use std::io::{Read, Seek, SeekFrom, Write};
-use std::path::PathBuf;
+use std::fs::{create_dir, remove_dir, remove_file, rename, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 use rustc::ty::layout::{Align, LayoutOf, Size};
+use rustc_hir::def_id::LOCAL_CRATE;
 
 use crate::stacked_borrows::Tag;
 use crate::*;
 use helpers::immty_from_uint_checked;
 use shims::time::system_time_to_duration;
 
+/// Which I/O operations on a file descriptor would currently succeed without blocking.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Readiness {
+    pub readable: bool,
+    pub writable: bool,
+    /// The peer will not send any more data; reads report the end of the stream.
+    pub read_hangup: bool,
+    /// Neither reading nor writing will ever make progress again.
+    pub hangup: bool,
+}
+
+/// Something the program can refer to with a file descriptor: a host file, or one of the objects
+/// that Miri emulates in memory (like sockets).
+pub trait FileDescriptor: std::fmt::Debug {
+    /// The host file behind this descriptor, for operations that only make sense on files.
+    fn as_file_handle<'tcx>(&self) -> InterpResult<'tcx, &FileHandle> {
+        throw_unsup_format!("{:?} is not a file", self)
+    }
+
+    /// The emulated socket behind this descriptor, if any.
+    fn as_socket_mut(&mut self) -> Option<&mut Socket> {
+        None
+    }
+
+    /// The emulated epoll instance behind this descriptor, if any.
+    fn as_epoll_mut(&mut self) -> Option<&mut Epoll> {
+        None
+    }
+
+    /// The emulated kqueue behind this descriptor, if any.
+    fn as_kqueue_mut(&mut self) -> Option<&mut Kqueue> {
+        None
+    }
+
+    /// The synthetic file behind this descriptor, if any.
+    fn as_synthetic_file(&self) -> Option<&SyntheticFile> {
+        None
+    }
+
+    /// Whether this descriptor is emulated in memory, and can therefore be used when isolation is
+    /// enabled.
+    fn is_emulated(&self) -> bool {
+        true
+    }
+
+    /// The current readiness of this descriptor, or `None` if it does not support readiness
+    /// notifications (like regular files).
+    fn readiness(&self) -> Option<Readiness> {
+        None
+    }
+
+    /// Whether this descriptor was opened for reading and for writing, for `F_GETFL`.
+    fn access(&self) -> (bool, bool) {
+        (true, true)
+    }
+
+    /// Whether operations that would wait fail with `EWOULDBLOCK` instead (`O_NONBLOCK`).
+    fn nonblocking(&self) -> bool {
+        false
+    }
+
+    /// Sets `O_NONBLOCK`, for `F_SETFL` and `FIONBIO`. Descriptors that never wait can ignore it.
+    fn set_nonblocking(&mut self, _nonblocking: bool) {}
+
+    fn read<'tcx>(&mut self, bytes: &mut [u8]) -> InterpResult<'tcx, io::Result<usize>>;
+
+    fn write<'tcx>(&mut self, bytes: &[u8]) -> InterpResult<'tcx, io::Result<usize>>;
+
+    fn seek<'tcx>(&mut self, _offset: SeekFrom) -> InterpResult<'tcx, io::Result<u64>> {
+        throw_unsup_format!("cannot seek on {:?}", self)
+    }
+
+    fn close<'tcx>(self: Box<Self>) -> InterpResult<'tcx, io::Result<i32>>;
+
+    fn dup<'tcx>(&mut self) -> InterpResult<'tcx, io::Result<Box<dyn FileDescriptor>>>;
+}
+
 #[derive(Debug)]
 pub struct FileHandle {
     file: File,
+    readable: bool,
     writable: bool,
+    /// Reads and writes on host files never wait, but programs can still set and get the flag.
+    nonblocking: bool,
+}
+
+impl FileDescriptor for FileHandle {
+    fn as_file_handle<'tcx>(&self) -> InterpResult<'tcx, &FileHandle> {
+        Ok(self)
+    }
+
+    fn is_emulated(&self) -> bool {
+        false
+    }
+
+    fn access(&self) -> (bool, bool) {
+        (self.readable, self.writable)
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+
+    fn read<'tcx>(&mut self, bytes: &mut [u8]) -> InterpResult<'tcx, io::Result<usize>> {
+        Ok(self.file.read(bytes))
+    }
+
+    fn write<'tcx>(&mut self, bytes: &[u8]) -> InterpResult<'tcx, io::Result<usize>> {
+        Ok(self.file.write(bytes))
+    }
+
+    fn seek<'tcx>(&mut self, offset: SeekFrom) -> InterpResult<'tcx, io::Result<u64>> {
+        Ok(self.file.seek(offset))
+    }
+
+    fn close<'tcx>(self: Box<Self>) -> InterpResult<'tcx, io::Result<i32>> {
+        // We sync the file if it was opened in a mode different than read-only.
+        if self.writable {
+            // `File::sync_all` does the checks that are done when closing a file. We do this to
+            // to handle possible errors correctly.
+            let result = self.file.sync_all().map(|_| 0i32);
+            // Now we actually close the file.
+            drop(self);
+            // And return the result.
+            Ok(result)
+        } else {
+            // We drop the file, this closes it but ignores any errors produced when closing
+            // it. This is done because `File::sync_all` cannot be done over files like
+            // `/dev/urandom` which are read-only. Check
+            // https://github.com/rust-lang/miri/issues/999#issuecomment-568920439 for a deeper
+            // discussion.
+            drop(self);
+            Ok(Ok(0))
+        }
+    }
+
+    fn dup<'tcx>(&mut self) -> InterpResult<'tcx, io::Result<Box<dyn FileDescriptor>>> {
+        let FileHandle { readable, writable, nonblocking, .. } = *self;
+        Ok(self.file.try_clone().map(|duplicated| -> Box<dyn FileDescriptor> {
+            Box::new(FileHandle { file: duplicated, readable, writable, nonblocking })
+        }))
+    }
+}
+
+/// A read-only file whose contents Miri makes up: one of the files in `/proc` that programs read to
+/// learn about their process and the machine. These do not depend on the host, so they can also
+/// be opened under isolation.
+#[derive(Debug, Clone)]
+pub struct SyntheticFile {
+    contents: Vec<u8>,
+    pos: u64,
+}
+
+impl FileDescriptor for SyntheticFile {
+    fn as_synthetic_file(&self) -> Option<&SyntheticFile> {
+        Some(self)
+    }
+
+    fn access(&self) -> (bool, bool) {
+        (true, false)
+    }
+
+    fn read<'tcx>(&mut self, bytes: &mut [u8]) -> InterpResult<'tcx, io::Result<usize>> {
+        let start = self.contents.len().min(self.pos as usize);
+        let n = bytes.len().min(self.contents.len() - start);
+        bytes[..n].copy_from_slice(&self.contents[start..start + n]);
+        self.pos += n as u64;
+        Ok(Ok(n))
+    }
+
+    fn write<'tcx>(&mut self, _bytes: &[u8]) -> InterpResult<'tcx, io::Result<usize>> {
+        // These files can only be opened for reading.
+        Ok(Err(io::Error::from(io::ErrorKind::PermissionDenied)))
+    }
+
+    fn seek<'tcx>(&mut self, offset: SeekFrom) -> InterpResult<'tcx, io::Result<u64>> {
+        let pos = match offset {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => i64::try_from(self.pos)
+                .ok()
+                .and_then(|pos| pos.checked_add(offset))
+                .and_then(|pos| u64::try_from(pos).ok()),
+            SeekFrom::End(offset) => i64::try_from(self.contents.len())
+                .ok()
+                .and_then(|len| len.checked_add(offset))
+                .and_then(|pos| u64::try_from(pos).ok()),
+        };
+        Ok(match pos {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::from(io::ErrorKind::InvalidInput)),
+        })
+    }
+
+    fn close<'tcx>(self: Box<Self>) -> InterpResult<'tcx, io::Result<i32>> {
+        Ok(Ok(0))
+    }
+
+    fn dup<'tcx>(&mut self) -> InterpResult<'tcx, io::Result<Box<dyn FileDescriptor>>> {
+        Ok(Ok(Box::new(self.clone())))
+    }
+}
+
+/// A C `FILE` stream opened with `fopen`. The program only gets a pointer to an opaque
+/// allocation; the stream's buffers live here.
+#[derive(Debug)]
+pub struct Stream {
+    fd: i32,
+    /// Bytes written to the stream that were not passed on to the file yet.
+    write_buffer: Vec<u8>,
+    /// Bytes read from the file ahead of time, of which the first `read_pos` were consumed.
+    read_buffer: Vec<u8>,
+    read_pos: usize,
+}
+
+/// The size of the buffers of `Stream`s, like `BUFSIZ` in glibc.
+const STREAM_BUFFER_SIZE: usize = 8192;
+
+impl Stream {
+    fn new(fd: i32) -> Self {
+        Stream { fd, write_buffer: Vec::new(), read_buffer: Vec::new(), read_pos: 0 }
+    }
+
+    /// Reads up to `count` bytes into `out`, refilling the read buffer from `file` as needed.
+    /// On an error, `out` holds the bytes read before it.
+    fn read<'tcx>(
+        &mut self,
+        file: &mut dyn FileDescriptor,
+        count: usize,
+        out: &mut Vec<u8>,
+    ) -> InterpResult<'tcx, io::Result<()>> {
+        // Pending writes have to reach the file before reading from it.
+        if let Err(e) = self.flush_writes(file)? {
+            return Ok(Err(e));
+        }
+        while out.len() < count {
+            if self.read_pos == self.read_buffer.len() {
+                self.read_buffer.resize(STREAM_BUFFER_SIZE, 0);
+                self.read_pos = 0;
+                match file.read(&mut self.read_buffer)? {
+                    Ok(read_bytes) => self.read_buffer.truncate(read_bytes),
+                    Err(e) => {
+                        self.read_buffer.clear();
+                        return Ok(Err(e));
+                    }
+                }
+                if self.read_buffer.is_empty() {
+                    // End of file.
+                    break;
+                }
+            }
+            let available = &self.read_buffer[self.read_pos..];
+            let n = available.len().min(count - out.len());
+            out.extend_from_slice(&available[..n]);
+            self.read_pos += n;
+        }
+        Ok(Ok(()))
+    }
+
+    /// Buffers `bytes` for writing, and passes the buffer on to `file` once it is full.
+    fn write<'tcx>(
+        &mut self,
+        file: &mut dyn FileDescriptor,
+        bytes: &[u8],
+    ) -> InterpResult<'tcx, io::Result<()>> {
+        if let Err(e) = self.discard_read_ahead(file)? {
+            return Ok(Err(e));
+        }
+        self.write_buffer.extend_from_slice(bytes);
+        if self.write_buffer.len() >= STREAM_BUFFER_SIZE {
+            return self.flush_writes(file);
+        }
+        Ok(Ok(()))
+    }
+
+    /// The position in the file as the program sees it.
+    fn position<'tcx>(
+        &mut self,
+        file: &mut dyn FileDescriptor,
+    ) -> InterpResult<'tcx, io::Result<u64>> {
+        let unread = (self.read_buffer.len() - self.read_pos) as u64;
+        let pending = self.write_buffer.len() as u64;
+        Ok(file.seek(SeekFrom::Current(0))?.map(|offset| offset + pending - unread))
+    }
+
+    /// Passes the pending writes on to `file` and gives back the bytes that were read ahead, so
+    /// that the position of the file is the one the program expects.
+    fn sync<'tcx>(&mut self, file: &mut dyn FileDescriptor) -> InterpResult<'tcx, io::Result<()>> {
+        if let Err(e) = self.discard_read_ahead(file)? {
+            return Ok(Err(e));
+        }
+        self.flush_writes(file)
+    }
+
+    fn flush_writes<'tcx>(
+        &mut self,
+        file: &mut dyn FileDescriptor,
+    ) -> InterpResult<'tcx, io::Result<()>> {
+        while !self.write_buffer.is_empty() {
+            match file.write(&self.write_buffer)? {
+                Ok(0) => return Ok(Err(io::ErrorKind::WriteZero.into())),
+                Ok(written) => drop(self.write_buffer.drain(..written)),
+                Err(e) => return Ok(Err(e)),
+            }
+        }
+        Ok(Ok(()))
+    }
+
+    fn discard_read_ahead<'tcx>(
+        &mut self,
+        file: &mut dyn FileDescriptor,
+    ) -> InterpResult<'tcx, io::Result<()>> {
+        let unread = self.read_buffer.len() - self.read_pos;
+        self.read_buffer.clear();
+        self.read_pos = 0;
+        if unread == 0 {
+            return Ok(Ok(()));
+        }
+        Ok(file.seek(SeekFrom::Current(-(unread as i64)))?.map(|_| ()))
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct FileHandler {
-    handles: BTreeMap<i32, FileHandle>,
+    pub(crate) handles: BTreeMap<i32, Box<dyn FileDescriptor>>,
+    /// The C streams opened with `fopen`, by the allocation the program's `FILE *` points to.
+    streams: BTreeMap<AllocId, Stream>,
+    /// The buffer `read` reads into before copying to the program's memory, kept between calls
+    /// so that programs reading in small pieces do not allocate a fresh one every time.
+    read_buffer: Vec<u8>,
+    /// The file mode creation mask set with `umask`, or `None` if the program did not set one.
+    umask: Option<u32>,
 }
 
 // fd numbers 0, 1, and 2 are reserved for stdin, stdout, and stderr
 const MIN_NORMAL_FILE_FD: i32 = 3;
 
+/// The largest `read` buffer that is kept for later calls; buffers for bigger reads are freed.
+const MAX_KEPT_READ_BUFFER: usize = 64 * 1024;
+
+/// The file mode creation mask of a new process on most systems.
+const DEFAULT_UMASK: u32 = 0o022;
+
 impl FileHandler {
-    fn insert_fd(&mut self, file_handle: FileHandle) -> i32 {
+    /// The file mode creation mask: the permission bits that files and directories the program
+    /// creates do not get.
+    fn umask(&self) -> u32 {
+        self.umask.unwrap_or(DEFAULT_UMASK)
+    }
+
+    pub(crate) fn insert_fd(&mut self, file_handle: Box<dyn FileDescriptor>) -> i32 {
         self.insert_fd_with_min_fd(file_handle, 0)
     }
 
-    fn insert_fd_with_min_fd(&mut self, file_handle: FileHandle, min_fd: i32) -> i32 {
+    fn insert_fd_with_min_fd(&mut self, file_handle: Box<dyn FileDescriptor>, min_fd: i32) -> i32 {
         let min_fd = std::cmp::max(min_fd, MIN_NORMAL_FILE_FD);
 
         // Find the lowest unused FD, starting from min_fd. If the first such unused FD is in
@@ -61,10 +404,72 @@ impl FileHandler {
         self.handles.insert(new_fd, file_handle).unwrap_none();
         new_fd
     }
+
+    /// The stream `id` and the file descriptor behind it, or `None` if the program closed the
+    /// descriptor behind the stream's back.
+    fn stream_and_file(&mut self, id: AllocId) -> Option<(&mut Stream, &mut dyn FileDescriptor)> {
+        let stream = self.streams.get_mut(&id).unwrap();
+        let file = self.handles.get_mut(&stream.fd)?;
+        Some((stream, &mut **file))
+    }
+
+    /// Writes out what the program wrote to the streams it did not close, as C does when the
+    /// program exits. Errors can no longer be reported to the program, so they are ignored.
+    pub(crate) fn flush_streams<'tcx>(&mut self) -> InterpResult<'tcx> {
+        let ids: Vec<AllocId> = self.streams.keys().copied().collect();
+        for id in ids {
+            if let Some((stream, file)) = self.stream_and_file(id) {
+                let _ = stream.sync(file)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<'mir, 'tcx> EvalContextExtPrivate<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
 trait EvalContextExtPrivate<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// The contents of the file at `path`, if it is one of the `/proc` files that Miri makes up.
+    /// They describe the machine that Miri emulates, and are the same in every run.
+    fn synthetic_file_contents(&self, path: &Path) -> Option<String> {
+        let this = self.eval_context_ref();
+        if this.tcx.sess.target.target.target_os != "linux" {
+            return None;
+        }
+        let num_cpus = this.machine.num_cpus;
+        let contents = match path.to_str()? {
+            "/proc/cpuinfo" => {
+                let flags = match &this.machine.cpu_features {
+                    Some(features) => features.join(" "),
+                    None => String::new(),
+                };
+                (0..num_cpus)
+                    .map(|cpu| {
+                        format!(
+                            "processor\t: {}\nvendor_id\t: Miri\nmodel name\t: Miri virtual CPU\n\
+                             cpu cores\t: {}\nflags\t\t: {}\n\n",
+                            cpu, num_cpus, flags,
+                        )
+                    })
+                    .collect()
+            }
+            // Miri does not know how much memory the interpreted program uses, so all the sizes
+            // are 0.
+            "/proc/self/status" | "/proc/thread-self/status" => format!(
+                "Name:\t{}\nState:\tR (running)\nVmPeak:\t       0 kB\nVmSize:\t       0 kB\n\
+                 VmHWM:\t       0 kB\nVmRSS:\t       0 kB\nThreads:\t1\n",
+                this.tcx.crate_name(LOCAL_CRATE),
+            ),
+            // The addresses of allocations start at `STACK_ADDR`.
+            "/proc/self/maps" => format!(
+                "{:x}-{:x} rw-p 00000000 00:00 0                          [stack]\n",
+                STACK_ADDR,
+                STACK_ADDR + STACK_SIZE,
+            ),
+            _ => return None,
+        };
+        Some(contents)
+    }
+
     /// Emulate `stat` or `lstat` on the `macos` platform. This function is not intended to be
     /// called directly from `emulate_foreign_item_by_name`, so it does not check if isolation is
     /// disabled or if the target platform is the correct one. Please use `macos_stat` or
@@ -78,7 +483,7 @@ trait EvalContextExtPrivate<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, '
         let this = self.eval_context_mut();
 
         let path_scalar = this.read_scalar(path_op)?.not_undef()?;
-        let path: PathBuf = this.read_os_str_from_c_str(path_scalar)?.into();
+        let path = this.read_path_from_c_str(path_scalar)?;
 
         let metadata = match FileMetadata::from_path(this, path, follow_symlink)? {
             Some(metadata) => metadata,
@@ -154,12 +559,127 @@ trait EvalContextExtPrivate<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, '
     /// the last OS error to `libc::EBADF` (invalid file descriptor). This function uses
     /// `T: From<i32>` instead of `i32` directly because some fs functions return different integer
     /// types (like `read`, that returns an `i64`).
+    /// Writes `metadata` to a `stat` buffer, for the platforms other than `macos` that use `stat`
+    /// (`freebsd` and `android`). Unlike on `macos`, the fields are found by name, so this works
+    /// with every layout of the struct. Fields we do not know anything about are zeroed.
+    fn stat_write_buf_by_name(
+        &mut self,
+        metadata: FileMetadata,
+        buf_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        this.write_zeroed_pointee(buf_op)?;
+        let buf = this.deref_operand(buf_op)?;
+        let mode = metadata.mode.to_u16()?;
+        this.write_int_field(buf, "st_mode", mode.into())?;
+        this.write_int_field(buf, "st_size", metadata.size.try_into().unwrap())?;
+        for &(time, sec_field, nsec_field) in &[
+            (metadata.accessed, "st_atime", "st_atime_nsec"),
+            (metadata.modified, "st_mtime", "st_mtime_nsec"),
+            (metadata.created, "st_birthtime", "st_birthtime_nsec"),
+        ] {
+            if !this.has_field(buf, sec_field) {
+                // Not every platform records the creation time.
+                continue;
+            }
+            let (sec, nsec) = time.unwrap_or((0, 0));
+            this.write_int_field(buf, sec_field, sec.try_into().unwrap())?;
+            this.write_int_field(buf, nsec_field, nsec.into())?;
+        }
+
+        Ok(0)
+    }
+
+    /// Checks that isolation is disabled, unless `fd` refers to a descriptor that Miri emulates in
+    /// memory. Host files and the standard streams need `-Zmiri-disable-isolation`.
+    fn check_fd_isolation(&self, name: &str, fd: i32) -> InterpResult<'tcx> {
+        let this = self.eval_context_ref();
+        match this.machine.file_handler.handles.get(&fd) {
+            Some(file_descriptor) if file_descriptor.is_emulated() => Ok(()),
+            _ => this.check_no_isolation(name),
+        }
+    }
+
     fn handle_not_found<T: From<i32>>(&mut self) -> InterpResult<'tcx, T> {
         let this = self.eval_context_mut();
         let ebadf = this.eval_libc("EBADF")?;
         this.set_last_error(ebadf)?;
         Ok((-1).into())
     }
+
+    /// Turns the `offset` and `whence` arguments of `lseek` and `fseek` into a `SeekFrom`. If
+    /// `whence` is invalid, the last OS error is set to `EINVAL` and `None` is returned.
+    fn seek_from(&mut self, offset: i64, whence: i32) -> InterpResult<'tcx, Option<SeekFrom>> {
+        let this = self.eval_context_mut();
+
+        Ok(Some(if whence == this.eval_libc_i32("SEEK_SET")? {
+            SeekFrom::Start(offset as u64)
+        } else if whence == this.eval_libc_i32("SEEK_CUR")? {
+            SeekFrom::Current(offset)
+        } else if whence == this.eval_libc_i32("SEEK_END")? {
+            SeekFrom::End(offset)
+        } else {
+            let einval = this.eval_libc("EINVAL")?;
+            this.set_last_error(einval)?;
+            return Ok(None);
+        }))
+    }
+
+    /// Reads an `off_t` or `c_long` file offset, which only has 32 bits on 32-bit targets.
+    fn read_file_offset(&mut self, offset_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, i64> {
+        let this = self.eval_context_mut();
+
+        Ok(match offset_op.layout.size.bytes() {
+            4 => i64::from(this.read_scalar(offset_op)?.to_i32()?),
+            _ => this.read_scalar(offset_op)?.to_i64()?,
+        })
+    }
+
+    /// The stream that `stream_op`, a `FILE *`, points to. Passing anything else than a stream
+    /// returned by `fopen` to the stdio functions is UB.
+    fn stream_id(&mut self, name: &str, stream_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, AllocId> {
+        let this = self.eval_context_mut();
+
+        let stream = this.read_scalar(stream_op)?.not_undef()?;
+        let ptr = this.force_ptr(stream)?;
+        if ptr.offset.bytes() != 0 || !this.machine.file_handler.streams.contains_key(&ptr.alloc_id)
+        {
+            throw_ub_format!(
+                "`{}` called on {:?}, which is not a stream opened by `fopen`",
+                name,
+                ptr
+            );
+        }
+        Ok(ptr.alloc_id)
+    }
+
+    /// Runs `f` on the stream `id` and its file descriptor. If that fails, the last OS error is
+    /// set and `None` is returned.
+    fn with_stream<T>(
+        &mut self,
+        id: AllocId,
+        f: impl FnOnce(&mut Stream, &mut dyn FileDescriptor) -> InterpResult<'tcx, io::Result<T>>,
+    ) -> InterpResult<'tcx, Option<T>> {
+        let this = self.eval_context_mut();
+
+        let result = match this.machine.file_handler.stream_and_file(id) {
+            Some((stream, file)) => f(stream, file)?,
+            None => {
+                // The program closed the file descriptor behind the stream's back.
+                let ebadf = this.eval_libc("EBADF")?;
+                this.set_last_error(ebadf)?;
+                return Ok(None);
+            }
+        };
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) => {
+                this.set_last_error_from_io_error(e)?;
+                Ok(None)
+            }
+        }
+    }
 }
 
 impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
@@ -168,12 +688,25 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         &mut self,
         path_op: OpTy<'tcx, Tag>,
         flag_op: OpTy<'tcx, Tag>,
+        mode_op: Option<OpTy<'tcx, Tag>>,
     ) -> InterpResult<'tcx, i32> {
         let this = self.eval_context_mut();
 
-        this.check_no_isolation("open")?;
-
         let flag = this.read_scalar(flag_op)?.to_i32()?;
+        let path = this.read_path_from_c_str(this.read_scalar(path_op)?.not_undef()?)?;
+
+        if let Some(contents) = this.synthetic_file_contents(&path) {
+            // Like the real files, these cannot be written to.
+            if flag & 0b11 != this.eval_libc_i32("O_RDONLY")? {
+                let eacces = this.eval_libc("EACCES")?;
+                this.set_last_error(eacces)?;
+                return Ok(-1);
+            }
+            let file = SyntheticFile { contents: contents.into_bytes(), pos: 0 };
+            return Ok(this.machine.file_handler.insert_fd(Box::new(file)));
+        }
+
+        this.check_no_isolation("open")?;
 
         let mut options = OpenOptions::new();
 
@@ -186,6 +719,7 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         if (o_rdonly | o_wronly | o_rdwr) & !0b11 != 0 {
             throw_unsup_format!("Access mode flags on this platform are unsupported");
         }
+        let mut readable = true;
         let mut writable = true;
 
         // Now we check the access mode
@@ -195,6 +729,7 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             writable = false;
             options.read(true);
         } else if access_mode == o_wronly {
+            readable = false;
             options.write(true);
         } else if access_mode == o_rdwr {
             options.read(true).write(true);
@@ -217,9 +752,21 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             mirror |= o_trunc;
         }
         let o_creat = this.eval_libc_i32("O_CREAT")?;
+        let mut mode = None;
         if flag & o_creat != 0 {
             options.create(true);
             mirror |= o_creat;
+            let mode_op = mode_op.ok_or_else(|| {
+                err_ub_format!("`open` with `O_CREAT` requires a third argument (the mode)")
+            })?;
+            // The mode is a `mode_t` that was promoted to an `int`.
+            let bits = this.read_scalar(mode_op)?.to_bits(mode_op.layout.size)? as u32;
+            mode = Some(bits & 0o7777 & !this.machine.file_handler.umask());
+        }
+        let o_excl = this.eval_libc_i32("O_EXCL")?;
+        if flag & o_excl != 0 && flag & o_creat != 0 {
+            options.create_new(true);
+            mirror |= o_excl;
         }
         let o_cloexec = this.eval_libc_i32("O_CLOEXEC")?;
         if flag & o_cloexec != 0 {
@@ -227,17 +774,29 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             // (Technically we do not support *not* setting this flag, but we ignore that.)
             mirror |= o_cloexec;
         }
+        let o_nonblock = this.eval_libc_i32("O_NONBLOCK")?;
+        let nonblocking = flag & o_nonblock != 0;
+        if nonblocking {
+            // Host files never block, so we only need to remember this for `F_GETFL`.
+            mirror |= o_nonblock;
+        }
         // If `flag` is not equal to `mirror`, there is an unsupported option enabled in `flag`,
         // then we throw an error.
         if flag != mirror {
             throw_unsup_format!("unsupported flags {:#x}", flag & !mirror);
         }
 
-        let path = this.read_os_str_from_c_str(this.read_scalar(path_op)?.not_undef()?)?;
-
-        let fd = options.open(&path).map(|file| {
+        // Only files that do not exist yet get the mode.
+        let mode = mode.filter(|_| std::fs::symlink_metadata(&path).is_err());
+        let file = options.open(&path).and_then(|file| {
+            if let Some(mode) = mode {
+                set_permissions(&path, mode)?;
+            }
+            Ok(file)
+        });
+        let fd = file.map(|file| {
             let fh = &mut this.machine.file_handler;
-            fh.insert_fd(FileHandle { file, writable })
+            fh.insert_fd(Box::new(FileHandle { file, readable, writable, nonblocking }))
         });
 
         this.try_unwrap_io_result(fd)
@@ -251,12 +810,57 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
     ) -> InterpResult<'tcx, i32> {
         let this = self.eval_context_mut();
 
-        this.check_no_isolation("fcntl")?;
-
         let fd = this.read_scalar(fd_op)?.to_i32()?;
+        this.check_fd_isolation("fcntl", fd)?;
+
         let cmd = this.read_scalar(cmd_op)?.to_i32()?;
-        // We only support getting the flags for a descriptor.
-        if cmd == this.eval_libc_i32("F_GETFD")? {
+        if cmd == this.eval_libc_i32("F_GETFL")? {
+            let (readable, writable, nonblocking) = match fd {
+                0 => (true, false, false),
+                1 | 2 => (false, true, false),
+                _ => match this.machine.file_handler.handles.get(&fd) {
+                    Some(file_descriptor) => {
+                        let (readable, writable) = file_descriptor.access();
+                        (readable, writable, file_descriptor.nonblocking())
+                    }
+                    None => return this.handle_not_found(),
+                },
+            };
+            let mut flags = match (readable, writable) {
+                (true, false) => this.eval_libc_i32("O_RDONLY")?,
+                (false, true) => this.eval_libc_i32("O_WRONLY")?,
+                _ => this.eval_libc_i32("O_RDWR")?,
+            };
+            if nonblocking {
+                flags |= this.eval_libc_i32("O_NONBLOCK")?;
+            }
+            Ok(flags)
+        } else if cmd == this.eval_libc_i32("F_SETFL")? {
+            let flag_op = start_op.ok_or_else(|| {
+                err_unsup_format!("fcntl with command F_SETFL requires a third argument")
+            })?;
+            let flag = this.read_scalar(flag_op)?.to_i32()?;
+            // The access mode and the file creation flags are ignored, like Linux does.
+            if flag & this.eval_libc_i32("O_APPEND")? != 0 {
+                throw_unsup_format!("setting `O_APPEND` with `fcntl` is not supported");
+            }
+            let nonblocking = flag & this.eval_libc_i32("O_NONBLOCK")? != 0;
+            if fd < MIN_NORMAL_FILE_FD {
+                if nonblocking {
+                    throw_unsup_format!(
+                        "non-blocking mode for stdin, stdout, or stderr is not supported"
+                    );
+                }
+                return Ok(0);
+            }
+            match this.machine.file_handler.handles.get_mut(&fd) {
+                Some(file_descriptor) => {
+                    file_descriptor.set_nonblocking(nonblocking);
+                    Ok(0)
+                }
+                None => this.handle_not_found(),
+            }
+        } else if cmd == this.eval_libc_i32("F_GETFD")? {
             // Currently this is the only flag that `F_GETFD` returns. It is OK to just return the
             // `FD_CLOEXEC` value without checking if the flag is set for the file because `std`
             // always sets this flag when opening a file. However we still need to check that the
@@ -283,45 +887,100 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             })?;
             let start = this.read_scalar(start_op)?.to_i32()?;
             let fh = &mut this.machine.file_handler;
-            let (file_result, writable) = match fh.handles.get(&fd) {
-                Some(FileHandle { file, writable }) => (file.try_clone(), *writable),
+            let dup_result = match fh.handles.get_mut(&fd) {
+                Some(file_descriptor) => file_descriptor.dup()?,
                 None => return this.handle_not_found(),
             };
-            let fd_result = file_result.map(|duplicated| {
-                fh.insert_fd_with_min_fd(FileHandle { file: duplicated, writable }, start)
-            });
+            let fd_result =
+                dup_result.map(|duplicated| fh.insert_fd_with_min_fd(duplicated, start));
             this.try_unwrap_io_result(fd_result)
         } else {
             throw_unsup_format!("The {:#x} command is not supported for `fcntl`)", cmd);
         }
     }
 
-    fn close(&mut self, fd_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, i32> {
+    fn ioctl(
+        &mut self,
+        fd_op: OpTy<'tcx, Tag>,
+        request_op: OpTy<'tcx, Tag>,
+        arg_op: Option<OpTy<'tcx, Tag>>,
+    ) -> InterpResult<'tcx, i32> {
         let this = self.eval_context_mut();
 
-        this.check_no_isolation("close")?;
-
         let fd = this.read_scalar(fd_op)?.to_i32()?;
+        // The request is an `unsigned long` on some targets and an `int` on others.
+        let request = this.read_scalar(request_op)?.to_bits(request_op.layout.size)?;
+        let mut request_name = None;
+        for &name in &["FIONBIO", "FIOCLEX", "FIONCLEX", "FIONREAD", "TIOCGWINSZ"] {
+            let value = match this.eval_path_scalar(&["libc", name])? {
+                Some(value) => value.not_undef()?,
+                None => continue,
+            };
+            // The constants do not necessarily have the type of the argument.
+            if let Scalar::Raw { data, .. } = value {
+                if data == request {
+                    request_name = Some(name);
+                }
+            }
+        }
+        let request_name = match request_name {
+            Some(name) => name,
+            None => throw_unsup_format!("`ioctl` request {:#x} is not supported", request),
+        };
 
-        if let Some(FileHandle { file, writable }) = this.machine.file_handler.handles.remove(&fd) {
-            // We sync the file if it was opened in a mode different than read-only.
-            if writable {
-                // `File::sync_all` does the checks that are done when closing a file. We do this to
-                // to handle possible errors correctly.
-                let result = this.try_unwrap_io_result(file.sync_all().map(|_| 0i32));
-                // Now we actually close the file.
-                drop(file);
-                // And return the result.
-                result
-            } else {
-                // We drop the file, this closes it but ignores any errors produced when closing
-                // it. This is done because `File::sync_all` cannot be done over files like
-                // `/dev/urandom` which are read-only. Check
-                // https://github.com/rust-lang/miri/issues/999#issuecomment-568920439 for a deeper
-                // discussion.
-                drop(file);
-                Ok(0)
+        // The standard streams are not in the handles.
+        if fd >= MIN_NORMAL_FILE_FD && !this.machine.file_handler.handles.contains_key(&fd) {
+            return this.handle_not_found();
+        }
+        // `FIONBIO` and `FIONREAD` take a pointer to an `int`.
+        let int_place = match (request_name, arg_op) {
+            ("FIONBIO", Some(arg_op)) | ("FIONREAD", Some(arg_op)) => {
+                let ptr = this.read_scalar(arg_op)?.not_undef()?;
+                let int_ptr_layout = this.layout_of(this.tcx.mk_mut_ptr(this.tcx.types.i32))?;
+                Some(this.ref_to_mplace(ImmTy::from_scalar(ptr, int_ptr_layout))?)
+            }
+            ("FIONBIO", None) | ("FIONREAD", None) =>
+                throw_ub_format!("`ioctl` request `{}` requires an argument", request_name),
+            _ => None,
+        };
+        match request_name {
+            // `exec` is not supported, so close-on-exec has no effect.
+            "FIOCLEX" | "FIONCLEX" => {}
+            "FIONBIO" => {
+                let nonblocking = this.read_scalar(int_place.unwrap().into())?.to_i32()? != 0;
+                // The standard streams never wait, so they ignore the flag.
+                if let Some(file_descriptor) = this.machine.file_handler.handles.get_mut(&fd) {
+                    file_descriptor.set_nonblocking(nonblocking);
+                }
+            }
+            "FIONREAD" => {
+                let file_descriptor = this.machine.file_handler.handles.get_mut(&fd);
+                let available = match file_descriptor.and_then(|file| file.as_socket_mut()) {
+                    Some(socket) => socket.bytes_available(),
+                    None =>
+                        throw_unsup_format!("`ioctl` request `FIONREAD` is only supported on sockets"),
+                };
+                this.write_scalar(Scalar::from_i32(available as i32), int_place.unwrap().into())?;
             }
+            // `TIOCGWINSZ`: nothing is a terminal, like `isatty` says.
+            _ => {
+                let enotty = this.eval_libc("ENOTTY")?;
+                this.set_last_error(enotty)?;
+                return Ok(-1);
+            }
+        }
+        Ok(0)
+    }
+
+    fn close(&mut self, fd_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let fd = this.read_scalar(fd_op)?.to_i32()?;
+        this.check_fd_isolation("close", fd)?;
+
+        if let Some(file_descriptor) = this.machine.file_handler.handles.remove(&fd) {
+            let result = file_descriptor.close()?;
+            this.try_unwrap_io_result(result)
         } else {
             this.handle_not_found()
         }
@@ -335,9 +994,8 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
     ) -> InterpResult<'tcx, i64> {
         let this = self.eval_context_mut();
 
-        this.check_no_isolation("read")?;
-
         let fd = this.read_scalar(fd_op)?.to_i32()?;
+        this.check_fd_isolation("read", fd)?;
         let buf = this.read_scalar(buf_op)?.not_undef()?;
         let count = this.read_scalar(count_op)?.to_machine_usize(&*this.tcx)?;
 
@@ -352,30 +1010,36 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         // host's and target's `isize`. This saves us from having to handle overflows later.
         let count = count.min(this.isize_max() as u64).min(isize::max_value() as u64);
 
-        if let Some(FileHandle { file, writable: _ }) = this.machine.file_handler.handles.get_mut(&fd) {
+        let file_handler = &mut this.machine.file_handler;
+        if let Some(file_descriptor) = file_handler.handles.get_mut(&fd) {
             // This can never fail because `count` was capped to be smaller than
             // `isize::max_value()`.
             let count = isize::try_from(count).unwrap();
             // We want to read at most `count` bytes. We are sure that `count` is not negative
             // because it was a target's `usize`. Also we are sure that its smaller than
             // `usize::max_value()` because it is a host's `isize`.
-            let mut bytes = vec![0; count as usize];
-            let result = file
-                .read(&mut bytes)
-                // `File::read` never returns a value larger than `count`, so this cannot fail.
-                .map(|c| i64::try_from(c).unwrap());
+            let mut bytes = mem::take(&mut file_handler.read_buffer);
+            bytes.clear();
+            bytes.resize(count as usize, 0);
+            let result = file_descriptor.read(&mut bytes)?;
 
-            match result {
+            let result = match result {
                 Ok(read_bytes) => {
-                    // If reading to `bytes` did not fail, we write those bytes to the buffer.
-                    this.memory.write_bytes(buf, bytes)?;
-                    Ok(read_bytes)
+                    // If reading to `bytes` did not fail, we write the bytes that were read to
+                    // the buffer.
+                    this.memory.write_bytes(buf, bytes[..read_bytes].iter().copied())?;
+                    // `read` never returns a value larger than `count`, so this cannot fail.
+                    Ok(i64::try_from(read_bytes).unwrap())
                 }
                 Err(e) => {
                     this.set_last_error_from_io_error(e)?;
                     Ok(-1)
                 }
+            };
+            if bytes.capacity() <= MAX_KEPT_READ_BUFFER {
+                this.machine.file_handler.read_buffer = bytes;
             }
+            result
         } else {
             this.handle_not_found()
         }
@@ -389,9 +1053,8 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
     ) -> InterpResult<'tcx, i64> {
         let this = self.eval_context_mut();
 
-        this.check_no_isolation("write")?;
-
         let fd = this.read_scalar(fd_op)?.to_i32()?;
+        this.check_fd_isolation("write", fd)?;
         let buf = this.read_scalar(buf_op)?.not_undef()?;
         let count = this.read_scalar(count_op)?.to_machine_usize(&*this.tcx)?;
 
@@ -406,9 +1069,9 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         // host's and target's `isize`. This saves us from having to handle overflows later.
         let count = count.min(this.isize_max() as u64).min(isize::max_value() as u64);
 
-        if let Some(FileHandle { file, writable: _ }) = this.machine.file_handler.handles.get_mut(&fd) {
+        if let Some(file_descriptor) = this.machine.file_handler.handles.get_mut(&fd) {
             let bytes = this.memory.read_bytes(buf, Size::from_bytes(count))?;
-            let result = file.write(&bytes).map(|c| i64::try_from(c).unwrap());
+            let result = file_descriptor.write(&bytes)?.map(|c| i64::try_from(c).unwrap());
             this.try_unwrap_io_result(result)
         } else {
             this.handle_not_found()
@@ -423,38 +1086,80 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
     ) -> InterpResult<'tcx, i64> {
         let this = self.eval_context_mut();
 
-        this.check_no_isolation("lseek64")?;
-
         let fd = this.read_scalar(fd_op)?.to_i32()?;
-        let offset = this.read_scalar(offset_op)?.to_i64()?;
+        this.check_fd_isolation("lseek64", fd)?;
+        // This is also used for `lseek`, whose `off_t` only has 32 bits on 32-bit targets.
+        let offset_size = offset_op.layout.size;
+        let offset = this.read_file_offset(offset_op)?;
         let whence = this.read_scalar(whence_op)?.to_i32()?;
 
-        let seek_from = if whence == this.eval_libc_i32("SEEK_SET")? {
-            SeekFrom::Start(offset as u64)
-        } else if whence == this.eval_libc_i32("SEEK_CUR")? {
-            SeekFrom::Current(offset)
-        } else if whence == this.eval_libc_i32("SEEK_END")? {
-            SeekFrom::End(offset)
-        } else {
-            let einval = this.eval_libc("EINVAL")?;
-            this.set_last_error(einval)?;
-            return Ok(-1);
+        let seek_from = match this.seek_from(offset, whence)? {
+            Some(seek_from) => seek_from,
+            None => return Ok(-1),
         };
 
-        if let Some(FileHandle { file, writable: _ }) = this.machine.file_handler.handles.get_mut(&fd) {
-            let result = file.seek(seek_from).map(|offset| offset as i64);
-            this.try_unwrap_io_result(result)
+        if let Some(file_descriptor) = this.machine.file_handler.handles.get_mut(&fd) {
+            let result = file_descriptor.seek(seek_from)?.map(|offset| offset as i64);
+            // The new offset also has to fit into `off_t`.
+            match result {
+                Ok(offset) if offset_size.bits() < 64 && offset > i64::from(i32::max_value()) => {
+                    let eoverflow = this.eval_libc("EOVERFLOW")?;
+                    this.set_last_error(eoverflow)?;
+                    Ok(-1)
+                }
+                result => this.try_unwrap_io_result(result),
+            }
         } else {
             this.handle_not_found()
         }
     }
 
+    fn umask(&mut self, mask_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, u32> {
+        let this = self.eval_context_mut();
+
+        let mask = this.read_scalar(mask_op)?.to_bits(mask_op.layout.size)? as u32;
+        let fh = &mut this.machine.file_handler;
+        let old_mask = fh.umask();
+        fh.umask = Some(mask & 0o777);
+        Ok(old_mask)
+    }
+
+    fn mkdir(
+        &mut self,
+        path_op: OpTy<'tcx, Tag>,
+        mode_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        this.check_no_isolation("mkdir")?;
+
+        let path = this.read_path_from_c_str(this.read_scalar(path_op)?.not_undef()?)?;
+        let mode = this.read_scalar(mode_op)?.to_bits(mode_op.layout.size)? as u32;
+        let mode = mode & 0o7777 & !this.machine.file_handler.umask();
+
+        let result = create_dir(&path).and_then(|()| set_permissions(&path, mode)).map(|()| 0);
+
+        this.try_unwrap_io_result(result)
+    }
+
+    fn rmdir(&mut self, path_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        this.check_no_isolation("rmdir")?;
+
+        let path = this.read_path_from_c_str(this.read_scalar(path_op)?.not_undef()?)?;
+
+        let result = remove_dir(path).map(|()| 0);
+
+        this.try_unwrap_io_result(result)
+    }
+
     fn unlink(&mut self, path_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, i32> {
         let this = self.eval_context_mut();
 
         this.check_no_isolation("unlink")?;
 
-        let path = this.read_os_str_from_c_str(this.read_scalar(path_op)?.not_undef()?)?;
+        let path = this.read_path_from_c_str(this.read_scalar(path_op)?.not_undef()?)?;
 
         let result = remove_file(path).map(|_| 0);
 
@@ -485,8 +1190,9 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
 
         this.check_no_isolation("symlink")?;
 
+        // The target is only resolved when the link is used, relative to the link.
         let target = this.read_os_str_from_c_str(this.read_scalar(target_op)?.not_undef()?)?.into();
-        let linkpath = this.read_os_str_from_c_str(this.read_scalar(linkpath_op)?.not_undef()?)?.into();
+        let linkpath = this.read_path_from_c_str(this.read_scalar(linkpath_op)?.not_undef()?)?;
 
         this.try_unwrap_io_result(create_link(target, linkpath).map(|_| 0))
     }
@@ -534,6 +1240,42 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         this.macos_stat_write_buf(metadata, buf_op)
     }
 
+    /// `stat` (or `lstat`, if `follow_symlink` is false) on `freebsd` and `android`.
+    fn stat_by_name(
+        &mut self,
+        follow_symlink: bool,
+        path_op: OpTy<'tcx, Tag>,
+        buf_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+        this.check_no_isolation(if follow_symlink { "stat" } else { "lstat" })?;
+
+        let path_scalar = this.read_scalar(path_op)?.not_undef()?;
+        let path = this.read_path_from_c_str(path_scalar)?;
+        let metadata = match FileMetadata::from_path(this, path, follow_symlink)? {
+            Some(metadata) => metadata,
+            None => return Ok(-1),
+        };
+        this.stat_write_buf_by_name(metadata, buf_op)
+    }
+
+    /// `fstat` on `freebsd` and `android`.
+    fn fstat_by_name(
+        &mut self,
+        fd_op: OpTy<'tcx, Tag>,
+        buf_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+        this.check_no_isolation("fstat")?;
+
+        let fd = this.read_scalar(fd_op)?.to_i32()?;
+        let metadata = match FileMetadata::from_fd(this, fd)? {
+            Some(metadata) => metadata,
+            None => return Ok(-1),
+        };
+        this.stat_write_buf_by_name(metadata, buf_op)
+    }
+
     fn linux_statx(
         &mut self,
         dirfd_op: OpTy<'tcx, Tag>,    // Should be an `int`
@@ -544,7 +1286,6 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
     ) -> InterpResult<'tcx, i32> {
         let this = self.eval_context_mut();
 
-        this.check_no_isolation("statx")?;
         this.assert_platform("linux", "statx");
 
         let statxbuf_scalar = this.read_scalar(statxbuf_op)?.not_undef()?;
@@ -574,7 +1315,8 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             this.ref_to_mplace(statxbuf_imm)?
         };
 
-        let path: PathBuf = this.read_os_str_from_c_str(pathname_scalar)?.into();
+        let absolute = Path::new(this.read_os_str_from_c_str(pathname_scalar)?).is_absolute();
+        let path = this.read_path_from_c_str(pathname_scalar)?;
         // `flags` should be a `c_int` but the `syscall` function provides an `isize`.
         let flags: i32 =
             this.read_scalar(flags_op)?.to_machine_isize(&*this.tcx)?.try_into().map_err(|e| {
@@ -586,6 +1328,18 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             this.read_scalar(dirfd_op)?.to_machine_isize(&*this.tcx)?.try_into().map_err(|e| {
                 err_unsup_format!("Failed to convert pointer sized operand to integer: {}", e)
             })?;
+        // Synthetic files do not touch the host, so they can be queried under isolation.
+        let synthetic = path.as_os_str().is_empty()
+            && empty_path_flag
+            && this
+                .machine
+                .file_handler
+                .handles
+                .get(&dirfd)
+                .map_or(false, |file_descriptor| file_descriptor.as_synthetic_file().is_some());
+        if !synthetic {
+            this.check_no_isolation("statx")?;
+        }
         // We only support:
         // * interpreting `path` as an absolute directory,
         // * interpreting `path` as a path relative to `dirfd` when the latter is `AT_FDCWD`, or
@@ -594,7 +1348,7 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         // Other behaviors cannot be tested from `libstd` and thus are not implemented. If you
         // found this error, please open an issue reporting it.
         if !(
-            path.is_absolute() ||
+            absolute ||
             dirfd == this.eval_libc_i32("AT_FDCWD")? ||
             (path.as_os_str().is_empty() && empty_path_flag)
         ) {
@@ -629,9 +1383,8 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         };
 
         // The `mode` field specifies the type of the file and the permissions over the file for
-        // the owner, its group and other users. Given that we can only provide the file type
-        // without using platform specific methods, we only set the bits corresponding to the file
-        // type. This should be an `__u16` but `libc` provides its values as `u32`.
+        // the owner, its group and other users. The permissions are only known on Unix hosts.
+        // This should be an `__u16` but `libc` provides its values as `u32`.
         let mode: u16 = metadata
             .mode
             .to_u32()?
@@ -715,13 +1468,231 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             return Ok(-1);
         }
 
-        let oldpath = this.read_os_str_from_c_str(oldpath_scalar)?;
-        let newpath = this.read_os_str_from_c_str(newpath_scalar)?;
+        let oldpath = this.read_path_from_c_str(oldpath_scalar)?;
+        let mut newpath = this.read_path_from_c_str(newpath_scalar)?;
+        if this.machine.fs_case_insensitive && newpath == oldpath {
+            // Renaming a file to a name that only differs in case changes the case.
+            let name = this.read_os_str_from_c_str(newpath_scalar)?;
+            if let Some(name) = Path::new(name).file_name() {
+                newpath.set_file_name(name);
+            }
+        }
 
         let result = rename(oldpath, newpath).map(|_| 0);
 
         this.try_unwrap_io_result(result)
     }
+
+    fn fopen(
+        &mut self,
+        path_op: OpTy<'tcx, Tag>,
+        mode_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, Scalar<Tag>> {
+        let this = self.eval_context_mut();
+
+        this.check_no_isolation("fopen")?;
+
+        let mode = this.memory.read_c_str(this.read_scalar(mode_op)?.not_undef()?)?.to_owned();
+        let (access, modifiers) = match mode.split_first() {
+            Some((&access, modifiers)) if b"rwa".contains(&access) => (access, modifiers),
+            _ => {
+                let einval = this.eval_libc("EINVAL")?;
+                this.set_last_error(einval)?;
+                return Ok(Scalar::ptr_null(&*this.tcx));
+            }
+        };
+        let update = modifiers.contains(&b'+');
+        let mut options = OpenOptions::new();
+        match access {
+            b'r' => options.read(true).write(update),
+            b'w' => options.write(true).read(update).create(true).truncate(true),
+            _ => options.append(true).read(update).create(true),
+        };
+        for &modifier in modifiers {
+            match modifier {
+                // There is no difference between binary and text streams on POSIX systems.
+                b'+' | b'b' => {}
+                // We do not need to do anything for `O_CLOEXEC`, see `open`.
+                b'e' => {}
+                b'x' if access == b'w' => {
+                    options.create_new(true);
+                }
+                _ => throw_unsup_format!(
+                    "unsupported `fopen` mode {:?}",
+                    String::from_utf8_lossy(&mode)
+                ),
+            }
+        }
+        let readable = access == b'r' || update;
+        let writable = access != b'r' || update;
+
+        let path = this.read_path_from_c_str(this.read_scalar(path_op)?.not_undef()?)?;
+        match options.open(&path) {
+            Ok(file) => {
+                let fh = &mut this.machine.file_handler;
+                let file_handle = FileHandle { file, readable, writable, nonblocking: false };
+                let fd = fh.insert_fd(Box::new(file_handle));
+                // The `FILE` is opaque to the program, the allocation only gives the stream a
+                // unique address that it can be found by.
+                let ptr = this.memory.allocate(
+                    Size::from_bytes(1),
+                    Align::from_bytes(1).unwrap(),
+                    MiriMemoryKind::Env.into(),
+                );
+                this.machine.file_handler.streams.insert(ptr.alloc_id, Stream::new(fd));
+                Ok(ptr.into())
+            }
+            Err(e) => {
+                this.set_last_error_from_io_error(e)?;
+                Ok(Scalar::ptr_null(&*this.tcx))
+            }
+        }
+    }
+
+    fn fread(
+        &mut self,
+        ptr_op: OpTy<'tcx, Tag>,
+        size_op: OpTy<'tcx, Tag>,
+        nmemb_op: OpTy<'tcx, Tag>,
+        stream_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, u64> {
+        let this = self.eval_context_mut();
+
+        let buf = this.read_scalar(ptr_op)?.not_undef()?;
+        let size = this.read_scalar(size_op)?.to_machine_usize(&*this.tcx)?;
+        let nmemb = this.read_scalar(nmemb_op)?.to_machine_usize(&*this.tcx)?;
+        let id = this.stream_id("fread", stream_op)?;
+        let count = size
+            .checked_mul(nmemb)
+            .ok_or_else(|| err_ub_format!("overflow during `fread` size computation"))?;
+        if count == 0 {
+            return Ok(0);
+        }
+
+        // Check that the *entire* buffer is actually valid memory.
+        this.memory.check_ptr_access(
+            buf,
+            Size::from_bytes(count),
+            Align::from_bytes(1).unwrap(),
+        )?;
+
+        // The buffer fits into the program's memory, so its size fits into the host's `usize`.
+        let count = usize::try_from(count).unwrap();
+        let mut bytes = Vec::new();
+        this.with_stream(id, |stream, file| stream.read(file, count, &mut bytes))?;
+        // Even if reading failed, the bytes read before the error are passed on.
+        this.memory.write_bytes(buf, bytes.iter().copied())?;
+        Ok(bytes.len() as u64 / size)
+    }
+
+    fn fwrite(
+        &mut self,
+        ptr_op: OpTy<'tcx, Tag>,
+        size_op: OpTy<'tcx, Tag>,
+        nmemb_op: OpTy<'tcx, Tag>,
+        stream_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, u64> {
+        let this = self.eval_context_mut();
+
+        let buf = this.read_scalar(ptr_op)?.not_undef()?;
+        let size = this.read_scalar(size_op)?.to_machine_usize(&*this.tcx)?;
+        let nmemb = this.read_scalar(nmemb_op)?.to_machine_usize(&*this.tcx)?;
+        let id = this.stream_id("fwrite", stream_op)?;
+        let count = size
+            .checked_mul(nmemb)
+            .ok_or_else(|| err_ub_format!("overflow during `fwrite` size computation"))?;
+        if count == 0 {
+            return Ok(0);
+        }
+
+        let bytes = this.memory.read_bytes(buf, Size::from_bytes(count))?.to_vec();
+        match this.with_stream(id, |stream, file| stream.write(file, &bytes))? {
+            Some(()) => Ok(nmemb),
+            // We do not know how much of the buffer reached the file, so we report nothing.
+            None => Ok(0),
+        }
+    }
+
+    fn fseek(
+        &mut self,
+        stream_op: OpTy<'tcx, Tag>,
+        offset_op: OpTy<'tcx, Tag>,
+        whence_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let id = this.stream_id("fseek", stream_op)?;
+        let offset = this.read_file_offset(offset_op)?;
+        let whence = this.read_scalar(whence_op)?.to_i32()?;
+
+        let seek_from = match this.seek_from(offset, whence)? {
+            Some(seek_from) => seek_from,
+            None => return Ok(-1),
+        };
+        let result = this.with_stream(id, |stream, file| {
+            if let Err(e) = stream.sync(file)? {
+                return Ok(Err(e));
+            }
+            file.seek(seek_from)
+        })?;
+        Ok(if result.is_some() { 0 } else { -1 })
+    }
+
+    /// `offset_size` is the size of the returned `c_long` or `off_t`, which the position has to
+    /// fit into.
+    fn ftell(&mut self, stream_op: OpTy<'tcx, Tag>, offset_size: Size) -> InterpResult<'tcx, i64> {
+        let this = self.eval_context_mut();
+
+        let id = this.stream_id("ftell", stream_op)?;
+        match this.with_stream(id, |stream, file| stream.position(file))? {
+            Some(offset) if offset_size.bits() < 64 && offset > i32::max_value() as u64 => {
+                let eoverflow = this.eval_libc("EOVERFLOW")?;
+                this.set_last_error(eoverflow)?;
+                Ok(-1)
+            }
+            Some(offset) => Ok(offset as i64),
+            None => Ok(-1),
+        }
+    }
+
+    fn fflush(&mut self, stream_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let ids = if this.is_null(this.read_scalar(stream_op)?.not_undef()?)? {
+            // A null stream means all of them.
+            this.machine.file_handler.streams.keys().copied().collect()
+        } else {
+            vec![this.stream_id("fflush", stream_op)?]
+        };
+        let mut result = 0;
+        for id in ids {
+            if this.with_stream(id, |stream, file| stream.sync(file))?.is_none() {
+                result = -1;
+            }
+        }
+        Ok(result)
+    }
+
+    fn fclose(&mut self, stream_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let id = this.stream_id("fclose", stream_op)?;
+        let flushed = this.with_stream(id, |stream, file| stream.sync(file))?.is_some();
+
+        // The stream is gone even if flushing or closing it fails.
+        let stream = this.machine.file_handler.streams.remove(&id).unwrap();
+        let ptr = this.force_ptr(this.read_scalar(stream_op)?.not_undef()?)?;
+        this.memory.deallocate(ptr, None, MiriMemoryKind::Env.into())?;
+        let closed = match this.machine.file_handler.handles.remove(&stream.fd) {
+            Some(file_descriptor) => {
+                let result = file_descriptor.close()?;
+                this.try_unwrap_io_result(result)? == 0
+            }
+            // `with_stream` already reported that the file descriptor is gone.
+            None => false,
+        };
+        Ok(if flushed && closed { 0 } else { -1 })
+    }
 }
 
 /// Extracts the number of seconds and nanoseconds elapsed between `time` and the unix epoch when
@@ -736,6 +1707,31 @@ fn extract_sec_and_nsec<'tcx>(
     }).transpose()
 }
 
+/// Sets the permission bits of `path` to `mode` exactly, without applying the host's umask. Hosts
+/// that do not have Unix permissions ignore them.
+#[cfg(unix)]
+fn set_permissions(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_permissions(_path: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+/// The permission bits of a file, or 0 on hosts that do not have Unix permissions.
+#[cfg(unix)]
+fn host_permissions(metadata: &std::fs::Metadata) -> u128 {
+    use std::os::unix::fs::PermissionsExt;
+    (metadata.permissions().mode() & 0o7777).into()
+}
+
+#[cfg(not(unix))]
+fn host_permissions(_metadata: &std::fs::Metadata) -> u128 {
+    0
+}
+
 /// Stores a file's metadata in order to avoid code duplication in the different metadata related
 /// shims.
 struct FileMetadata {
@@ -767,7 +1763,13 @@ impl FileMetadata {
     ) -> InterpResult<'tcx, Option<FileMetadata>> {
         let option = ecx.machine.file_handler.handles.get(&fd);
         let file = match option {
-            Some(FileHandle { file, writable: _ }) => file,
+            Some(file_descriptor) if file_descriptor.as_synthetic_file().is_some() => {
+                // Like the files in `/proc`, synthetic files are regular files of size 0.
+                let mode = ecx.eval_libc("S_IFREG")?;
+                let (created, accessed, modified) = (None, None, None);
+                return Ok(Some(FileMetadata { mode, size: 0, created, accessed, modified }));
+            }
+            Some(file_descriptor) => &file_descriptor.as_file_handle()?.file,
             None => return ecx.handle_not_found().map(|_: i32| None),
         };
         let metadata = file.metadata();
@@ -797,7 +1799,9 @@ impl FileMetadata {
             "S_IFLNK"
         };
 
-        let mode = ecx.eval_libc(mode_name)?;
+        let mode_t = ecx.libc_ty_layout("mode_t")?;
+        let mode = ecx.eval_libc(mode_name)?.to_bits(mode_t.size)? | host_permissions(&metadata);
+        let mode = Scalar::from_uint(mode, mode_t.size);
 
         let size = metadata.len();
 
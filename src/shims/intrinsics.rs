@@ -4,7 +4,8 @@ use rustc::mir;
 use rustc::mir::interpret::{InterpResult, PointerArithmetic};
 use rustc::ty;
 use rustc::ty::layout::{self, Align, LayoutOf, Size};
-use rustc_apfloat::Float;
+use rustc_apfloat::{Float, Status};
+use syntax::ast::FloatTy;
 use rustc_span::source_map::Span;
 
 use crate::*;
@@ -20,9 +21,6 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         unwind: Option<mir::BasicBlock>,
     ) -> InterpResult<'tcx> {
         let this = self.eval_context_mut();
-        if this.emulate_intrinsic(span, instance, args, ret)? {
-            return Ok(());
-        }
         let tcx = &{ this.tcx.tcx };
         let substs = instance.substs;
 
@@ -31,10 +29,33 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         // that might still hang around!
         let intrinsic_name = &*tcx.item_name(instance.def_id()).as_str();
 
+        // We implement some intrinsics ourselves even though `emulate_intrinsic` supports them,
+        // to give more precise errors.
+        if intrinsic_name == "ptr_offset_from" {
+            let (dest, ret) = ret.unwrap();
+            this.ptr_offset_from(args, substs.type_at(0), dest)?;
+            this.dump_place(*dest);
+            this.go_to_block(ret);
+            return Ok(());
+        }
+
+        if this.emulate_intrinsic(span, instance, args, ret)? {
+            return Ok(());
+        }
+
         // Handle diverging intrinsics.
         let (dest, ret) = match intrinsic_name {
             "abort" => {
-                throw_machine_stop!(TerminationInfo::Abort);
+                // If a panic payload is still waiting to be caught, libstd aborts because of a
+                // panic during unwinding. The panic hook has already printed both messages.
+                if this.machine.panic_payload.is_some() {
+                    throw_machine_stop!(TerminationInfo::Abort(Some(
+                        "the evaluated program aborted execution: panicked while unwinding from \
+                         another panic"
+                            .to_owned()
+                    )));
+                }
+                throw_machine_stop!(TerminationInfo::Abort(None));
             }
             "miri_start_panic" => return this.handle_miri_start_panic(args, unwind),
             _ =>
@@ -64,14 +85,19 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 }
             }
 
+            // Volatile accesses behave exactly like regular accesses: they must be aligned and
+            // in-bounds, they copy uninitialized memory just like any other copy does, and they
+            // are *not* synchronization (for us, as in the Rust memory model).
             "volatile_load" => {
                 let place = this.deref_operand(args[0])?;
                 this.copy_op(place.into(), dest)?;
+                this.track_volatile_spin(place, dest)?;
             }
 
             "volatile_store" => {
                 let place = this.deref_operand(args[0])?;
                 this.copy_op(args[1], place.into())?;
+                this.machine.volatile_spin = None;
             }
 
             #[rustfmt::skip]
@@ -190,6 +216,26 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             | "atomic_xsub_rel"
             | "atomic_xsub_acqrel"
             | "atomic_xsub_relaxed"
+            | "atomic_min"
+            | "atomic_min_acq"
+            | "atomic_min_rel"
+            | "atomic_min_acqrel"
+            | "atomic_min_relaxed"
+            | "atomic_max"
+            | "atomic_max_acq"
+            | "atomic_max_rel"
+            | "atomic_max_acqrel"
+            | "atomic_max_relaxed"
+            | "atomic_umin"
+            | "atomic_umin_acq"
+            | "atomic_umin_rel"
+            | "atomic_umin_acqrel"
+            | "atomic_umin_relaxed"
+            | "atomic_umax"
+            | "atomic_umax_acq"
+            | "atomic_umax_rel"
+            | "atomic_umax_acqrel"
+            | "atomic_umax_relaxed"
             => {
                 let place = this.deref_operand(args[0])?;
                 if !place.layout.ty.is_integral() {
@@ -205,22 +251,43 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 this.memory.check_ptr_access(place.ptr, place.layout.size, align)?;
 
                 this.write_immediate(*old, dest)?; // old value is returned
-                let (op, neg) = match intrinsic_name.split('_').nth(1).unwrap() {
-                    "or" => (mir::BinOp::BitOr, false),
-                    "xor" => (mir::BinOp::BitXor, false),
-                    "and" => (mir::BinOp::BitAnd, false),
-                    "xadd" => (mir::BinOp::Add, false),
-                    "xsub" => (mir::BinOp::Sub, false),
-                    "nand" => (mir::BinOp::BitAnd, true),
-                    _ => bug!(),
+                let op_name = intrinsic_name.split('_').nth(1).unwrap();
+                let val = match op_name {
+                    "min" | "max" | "umin" | "umax" => {
+                        // Compare as signed or unsigned integers depending on the operation,
+                        // not the type.
+                        let size = place.layout.size;
+                        let old_bits = old.to_scalar()?.to_bits(size)?;
+                        let rhs_bits = rhs.to_scalar()?.to_bits(size)?;
+                        let old_is_less = if op_name.starts_with('u') {
+                            old_bits < rhs_bits
+                        } else {
+                            (this.sign_extend(old_bits, place.layout) as i128)
+                                < (this.sign_extend(rhs_bits, place.layout) as i128)
+                        };
+                        let keep_old =
+                            if op_name.ends_with("min") { old_is_less } else { !old_is_less };
+                        if keep_old { old } else { rhs }
+                    }
+                    _ => {
+                        let (op, neg) = match op_name {
+                            "or" => (mir::BinOp::BitOr, false),
+                            "xor" => (mir::BinOp::BitXor, false),
+                            "and" => (mir::BinOp::BitAnd, false),
+                            "xadd" => (mir::BinOp::Add, false),
+                            "xsub" => (mir::BinOp::Sub, false),
+                            "nand" => (mir::BinOp::BitAnd, true),
+                            _ => bug!(),
+                        };
+                        // Atomics wrap around on overflow.
+                        let val = this.binary_op(op, old, rhs)?;
+                        if neg { this.unary_op(mir::UnOp::Not, val)? } else { val }
+                    }
                 };
-                // Atomics wrap around on overflow.
-                let val = this.binary_op(op, old, rhs)?;
-                let val = if neg { this.unary_op(mir::UnOp::Not, val)? } else { val };
                 this.write_immediate(*val, place.into())?;
             }
 
-            "breakpoint" => unimplemented!(), // halt miri
+            "breakpoint" => this.breakpoint_instruction("`intrinsics::breakpoint`")?,
 
             #[rustfmt::skip]
             | "copy"
@@ -239,12 +306,11 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 let dest = this.memory.check_ptr_access(dest, size, elem_align)?;
 
                 if let (Some(src), Some(dest)) = (src, dest) {
-                    this.memory.copy(
-                        src,
-                        dest,
-                        size,
-                        intrinsic_name.ends_with("_nonoverlapping"),
-                    )?;
+                    let nonoverlapping = intrinsic_name.ends_with("_nonoverlapping");
+                    if nonoverlapping {
+                        this.check_no_overlap(intrinsic_name, src, dest, size)?;
+                    }
+                    this.memory.copy(src, dest, size, nonoverlapping)?;
                 }
             }
 
@@ -287,6 +353,7 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     "roundf32" => f.round(),
                     _ => bug!(),
                 };
+                let f = this.float_jitter_f32(intrinsic_name, f);
                 this.write_scalar(Scalar::from_u32(f.to_bits()), dest)?;
             }
 
@@ -323,6 +390,7 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     "roundf64" => f.round(),
                     _ => bug!(),
                 };
+                let f = this.float_jitter_f64(intrinsic_name, f);
                 this.write_scalar(Scalar::from_u64(f.to_bits()), dest)?;
             }
 
@@ -381,6 +449,23 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             "exact_div" =>
                 this.exact_div(this.read_immediate(args[0])?, this.read_immediate(args[1])?, dest)?,
 
+            "float_to_int_approx_unchecked" => {
+                let val = this.read_immediate(args[0])?;
+                let res = match val.layout.ty.kind {
+                    ty::Float(FloatTy::F32) =>
+                        float_to_int_approx_unchecked(val.to_scalar()?.to_f32()?, dest.layout)?,
+                    ty::Float(FloatTy::F64) =>
+                        float_to_int_approx_unchecked(val.to_scalar()?.to_f64()?, dest.layout)?,
+                    _ => bug!(
+                        "`float_to_int_approx_unchecked` called with non-float input type {:?}",
+                        val.layout.ty
+                    ),
+                };
+                this.write_scalar(res, dest)?;
+            }
+
+            "black_box" => this.black_box(args[0], dest)?,
+
             "forget" => {}
 
             #[rustfmt::skip]
@@ -457,14 +542,16 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 // FIXME: Using host floats.
                 let f = f32::from_bits(this.read_scalar(args[0])?.to_u32()?);
                 let f2 = f32::from_bits(this.read_scalar(args[1])?.to_u32()?);
-                this.write_scalar(Scalar::from_u32(f.powf(f2).to_bits()), dest)?;
+                let res = this.float_jitter_f32(intrinsic_name, f.powf(f2));
+                this.write_scalar(Scalar::from_u32(res.to_bits()), dest)?;
             }
 
             "powf64" => {
                 // FIXME: Using host floats.
                 let f = f64::from_bits(this.read_scalar(args[0])?.to_u64()?);
                 let f2 = f64::from_bits(this.read_scalar(args[1])?.to_u64()?);
-                this.write_scalar(Scalar::from_u64(f.powf(f2).to_bits()), dest)?;
+                let res = this.float_jitter_f64(intrinsic_name, f.powf(f2));
+                this.write_scalar(Scalar::from_u64(res.to_bits()), dest)?;
             }
 
             "fmaf32" => {
@@ -487,14 +574,217 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                 // FIXME: Using host floats.
                 let f = f32::from_bits(this.read_scalar(args[0])?.to_u32()?);
                 let i = this.read_scalar(args[1])?.to_i32()?;
-                this.write_scalar(Scalar::from_u32(f.powi(i).to_bits()), dest)?;
+                let res = this.float_jitter_f32(intrinsic_name, f.powi(i));
+                this.write_scalar(Scalar::from_u32(res.to_bits()), dest)?;
             }
 
             "powif64" => {
                 // FIXME: Using host floats.
                 let f = f64::from_bits(this.read_scalar(args[0])?.to_u64()?);
                 let i = this.read_scalar(args[1])?.to_i32()?;
-                this.write_scalar(Scalar::from_u64(f.powi(i).to_bits()), dest)?;
+                let res = this.float_jitter_f64(intrinsic_name, f.powi(i));
+                this.write_scalar(Scalar::from_u64(res.to_bits()), dest)?;
+            }
+
+            // Generic SIMD operations (the `platform-intrinsic` ABI). These operate lane-wise on
+            // vectors; `simd_insert` and `simd_extract` are handled by `emulate_intrinsic`.
+            #[rustfmt::skip]
+            | "simd_add"
+            | "simd_sub"
+            | "simd_mul"
+            | "simd_div"
+            | "simd_rem"
+            | "simd_shl"
+            | "simd_shr"
+            | "simd_and"
+            | "simd_or"
+            | "simd_xor"
+            | "simd_eq"
+            | "simd_ne"
+            | "simd_lt"
+            | "simd_le"
+            | "simd_gt"
+            | "simd_ge"
+            => {
+                use mir::BinOp;
+                let op = match intrinsic_name {
+                    "simd_add" => BinOp::Add,
+                    "simd_sub" => BinOp::Sub,
+                    "simd_mul" => BinOp::Mul,
+                    "simd_div" => BinOp::Div,
+                    "simd_rem" => BinOp::Rem,
+                    "simd_shl" => BinOp::Shl,
+                    "simd_shr" => BinOp::Shr,
+                    "simd_and" => BinOp::BitAnd,
+                    "simd_or" => BinOp::BitOr,
+                    "simd_xor" => BinOp::BitXor,
+                    "simd_eq" => BinOp::Eq,
+                    "simd_ne" => BinOp::Ne,
+                    "simd_lt" => BinOp::Lt,
+                    "simd_le" => BinOp::Le,
+                    "simd_gt" => BinOp::Gt,
+                    "simd_ge" => BinOp::Ge,
+                    _ => bug!(),
+                };
+                let lanes = dest.layout.fields.count() as u64;
+                assert_eq!(lanes, args[0].layout.fields.count() as u64);
+                assert_eq!(lanes, args[1].layout.fields.count() as u64);
+                for i in 0..lanes {
+                    let left = this.read_immediate(this.operand_field(args[0], i)?)?;
+                    let right = this.read_immediate(this.operand_field(args[1], i)?)?;
+                    let dest = this.place_field(dest, i)?;
+                    let is_int = left.layout.ty.is_integral();
+                    if is_int && (op == BinOp::Div || op == BinOp::Rem) {
+                        let r = right.to_scalar()?.to_bits(right.layout.size)?;
+                        if r == 0 {
+                            throw_ub_format!(
+                                "dividing by zero in lane {} of `{}`",
+                                i,
+                                intrinsic_name
+                            );
+                        }
+                    }
+                    let (val, overflowed, ty) = this.overflowing_binary_op(op, left, right)?;
+                    if overflowed && (op == BinOp::Shl || op == BinOp::Shr) {
+                        let r = right.to_scalar()?.to_bits(right.layout.size)?;
+                        throw_ub_format!(
+                            "overflowing shift by {} in lane {} of `{}`",
+                            r,
+                            i,
+                            intrinsic_name
+                        );
+                    }
+                    // Signed division overflow (`MIN / -1`) is UB as well; other arithmetic wraps.
+                    if overflowed && is_int && (op == BinOp::Div || op == BinOp::Rem) {
+                        throw_ub_format!(
+                            "overflow in signed division in lane {} of `{}`",
+                            i,
+                            intrinsic_name
+                        );
+                    }
+                    if ty.is_bool() {
+                        // Comparisons produce a mask: all bits set for `true`.
+                        let mask = if val.to_bool()? { -1 } else { 0 };
+                        this.write_scalar(Scalar::from_int(mask, dest.layout.size), dest)?;
+                    } else {
+                        this.write_scalar(val, dest)?;
+                    }
+                }
+            }
+
+            "simd_select" => {
+                let lanes = dest.layout.fields.count() as u64;
+                for i in 0..lanes {
+                    let src = if simd_mask_lane(this, args[0], i)? { args[1] } else { args[2] };
+                    let val = this.read_immediate(this.operand_field(src, i)?)?;
+                    let dest = this.place_field(dest, i)?;
+                    this.write_immediate(*val, dest)?;
+                }
+            }
+
+            "simd_bitmask" => {
+                let lanes = args[0].layout.fields.count() as u64;
+                assert!(lanes <= 64 && dest.layout.size.bits() >= lanes);
+                let mut bits = 0u128;
+                for i in 0..lanes {
+                    if simd_mask_lane(this, args[0], i)? {
+                        bits |= 1 << i;
+                    }
+                }
+                this.write_scalar(Scalar::from_uint(bits, dest.layout.size), dest)?;
+            }
+
+            name if name.starts_with("simd_shuffle") => {
+                let lanes = args[0].layout.fields.count() as u64;
+                let dest_lanes = dest.layout.fields.count() as u64;
+                for i in 0..dest_lanes {
+                    let idx = this.read_scalar(this.operand_field(args[2], i)?)?.to_u32()? as u64;
+                    let val = if idx < lanes {
+                        this.read_immediate(this.operand_field(args[0], idx)?)?
+                    } else if idx < 2 * lanes {
+                        this.read_immediate(this.operand_field(args[1], idx - lanes)?)?
+                    } else {
+                        throw_ub_format!(
+                            "`{}` index {} is out-of-bounds for two vectors of {} lanes",
+                            name,
+                            idx,
+                            lanes
+                        );
+                    };
+                    let dest = this.place_field(dest, i)?;
+                    this.write_immediate(*val, dest)?;
+                }
+            }
+
+            #[rustfmt::skip]
+            | "simd_reduce_add_ordered"
+            | "simd_reduce_add_unordered"
+            | "simd_reduce_mul_ordered"
+            | "simd_reduce_mul_unordered"
+            | "simd_reduce_and"
+            | "simd_reduce_or"
+            | "simd_reduce_xor"
+            | "simd_reduce_min"
+            | "simd_reduce_max"
+            | "simd_reduce_min_nanless"
+            | "simd_reduce_max_nanless"
+            => {
+                use mir::BinOp;
+                let lanes = args[0].layout.fields.count() as u64;
+                // The ordered float reductions take a start value; integer reductions wrap.
+                let mut acc = if intrinsic_name.ends_with("_ordered") {
+                    this.read_immediate(args[1])?
+                } else {
+                    this.read_immediate(this.operand_field(args[0], 0)?)?
+                };
+                let start = if intrinsic_name.ends_with("_ordered") { 0 } else { 1 };
+                for i in start..lanes {
+                    let lane = this.read_immediate(this.operand_field(args[0], i)?)?;
+                    acc = match intrinsic_name {
+                        "simd_reduce_min" | "simd_reduce_min_nanless"
+                        | "simd_reduce_max" | "simd_reduce_max_nanless" => {
+                            let op = if intrinsic_name.starts_with("simd_reduce_min") {
+                                BinOp::Lt
+                            } else {
+                                BinOp::Gt
+                            };
+                            let lane_wins = this.overflowing_binary_op(op, lane, acc)?.0;
+                            if lane_wins.to_bool()? { lane } else { acc }
+                        }
+                        _ => {
+                            let op = match intrinsic_name {
+                                "simd_reduce_add_ordered" | "simd_reduce_add_unordered" =>
+                                    BinOp::Add,
+                                "simd_reduce_mul_ordered" | "simd_reduce_mul_unordered" =>
+                                    BinOp::Mul,
+                                "simd_reduce_and" => BinOp::BitAnd,
+                                "simd_reduce_or" => BinOp::BitOr,
+                                "simd_reduce_xor" => BinOp::BitXor,
+                                _ => bug!(),
+                            };
+                            let (val, _overflowed, ty) =
+                                this.overflowing_binary_op(op, acc, lane)?;
+                            ImmTy::from_scalar(val, this.layout_of(ty)?)
+                        }
+                    };
+                }
+                this.write_immediate(*acc, dest)?;
+            }
+
+            #[rustfmt::skip]
+            | "simd_reduce_all"
+            | "simd_reduce_any"
+            => {
+                let lanes = args[0].layout.fields.count() as u64;
+                let mut all = true;
+                let mut any = false;
+                for i in 0..lanes {
+                    let mask = simd_mask_lane(this, args[0], i)?;
+                    all &= mask;
+                    any |= mask;
+                }
+                let res = if intrinsic_name == "simd_reduce_all" { all } else { any };
+                this.write_scalar(Scalar::from_bool(res), dest)?;
             }
 
             "size_of_val" => {
@@ -569,4 +859,169 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         this.go_to_block(ret);
         Ok(())
     }
+
+    /// Programs sometimes wait for another thread (or a signal handler, or hardware) by reading
+    /// a location with `read_volatile` until it changes. Nothing else can ever change memory
+    /// here, so such a loop never terminates; once it looks like the program is doing that, we
+    /// explain why (but we do not stop it unless `-Zmiri-deny=volatile-spin` is set, it might
+    /// just be a very long benchmark-style loop).
+    /// `dest` holds the value that was just read from `place`.
+    fn track_volatile_spin(
+        &mut self,
+        place: MPlaceTy<'tcx, Tag>,
+        dest: PlaceTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let ptr = match (place.ptr, &place.layout.abi) {
+            (Scalar::Ptr(ptr), layout::Abi::Scalar(..)) => ptr,
+            _ => {
+                this.machine.volatile_spin = None;
+                return Ok(());
+            }
+        };
+        // Reading `place` again would be a second access to it, so we look at the copy.
+        let val = this.read_scalar(this.place_to_op(dest)?)?;
+        let spin = match this.machine.volatile_spin.take() {
+            Some(spin) if spin.location == (ptr.alloc_id, ptr.offset) && spin.value == val =>
+                VolatileSpin { reads: spin.reads + 1, ..spin },
+            _ => VolatileSpin { location: (ptr.alloc_id, ptr.offset), value: val, reads: 1 },
+        };
+        if spin.reads == VOLATILE_SPIN_THRESHOLD {
+            report_warning(
+                this,
+                WarningCategory::VolatileSpin,
+                format!(
+                    "the program read the same value from {:?} with `read_volatile` {} times \
+                    in a row",
+                    ptr.alloc_id, spin.reads
+                ),
+                vec![
+                    "volatile accesses are not synchronization: they cannot be used to \
+                    communicate between threads"
+                        .to_owned(),
+                    "nothing but the program itself can change memory in Miri, so if this is \
+                    waiting for another thread, a signal handler or hardware, it will spin forever"
+                        .to_owned(),
+                ],
+            )?;
+        }
+        this.machine.volatile_spin = Some(spin);
+        Ok(())
+    }
+
+    /// Implements `ptr_offset_from`: both pointers must point into (or one past the end of) the
+    /// same live allocation, and their distance must be a multiple of the element size.
+    fn ptr_offset_from(
+        &mut self,
+        args: &[OpTy<'tcx, Tag>],
+        pointee_ty: ty::Ty<'tcx>,
+        dest: PlaceTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let a = this.read_scalar(args[0])?.not_undef()?;
+        let b = this.read_scalar(args[1])?.not_undef()?;
+        let pointee_size = i128::from(this.layout_of(pointee_ty)?.size.bytes());
+
+        // Equal non-NULL pointers are always fine, even if they are just integers.
+        let distance = if this.ptr_eq(a, b)? && !this.is_null(a)? {
+            0
+        } else {
+            let a = this.force_ptr(a)?;
+            let b = this.force_ptr(b)?;
+            if a.alloc_id != b.alloc_id {
+                throw_ub_format!(
+                    "`ptr_offset_from` called on pointers into different allocations: {:?} and {:?}",
+                    a.alloc_id,
+                    b.alloc_id
+                );
+            }
+            let (size, _) = this.memory.get_size_and_align(a.alloc_id, AllocCheck::Live)?;
+            for ptr in &[a, b] {
+                if ptr.offset > size {
+                    throw_ub_format!(
+                        "`ptr_offset_from` called on a pointer at offset {} which is outside \
+                        bounds of {:?} with size {}",
+                        ptr.offset.bytes(),
+                        ptr.alloc_id,
+                        size.bytes()
+                    );
+                }
+            }
+            i128::from(a.offset.bytes()) - i128::from(b.offset.bytes())
+        };
+
+        if distance % pointee_size != 0 {
+            throw_ub_format!(
+                "`ptr_offset_from` called on pointers that are {} bytes apart, \
+                which is not a multiple of the element size {}",
+                distance,
+                pointee_size
+            );
+        }
+        this.write_scalar(Scalar::from_int(distance / pointee_size, dest.layout.size), dest)
+    }
+
+    /// Implements `black_box`: an identity function that the program cannot see through. We do
+    /// not optimize anything anyway, but we still validate the value, as if it was passed to
+    /// unknown code.
+    fn black_box(&mut self, arg: OpTy<'tcx, Tag>, dest: PlaceTy<'tcx, Tag>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        this.copy_op(arg, dest)?;
+        this.validate_place(dest)
+    }
+}
+
+/// Interprets a lane of a SIMD mask vector: all bits set means `true`, all bits cleared means
+/// `false`, anything else is UB.
+fn simd_mask_lane<'tcx>(
+    this: &MiriEvalContext<'_, 'tcx>,
+    mask: OpTy<'tcx, Tag>,
+    lane: u64,
+) -> InterpResult<'tcx, bool> {
+    let size = this.operand_field(mask, lane)?.layout.size;
+    let bits = this.read_scalar(this.operand_field(mask, lane)?)?.not_undef()?.to_bits(size)?;
+    if bits == 0 {
+        Ok(false)
+    } else if bits == u128::max_value() >> (128 - size.bits()) {
+        Ok(true)
+    } else {
+        throw_ub_format!(
+            "invalid SIMD mask value {:#x} in lane {}: must be all-zero or all-one",
+            bits,
+            lane
+        )
+    }
+}
+
+/// Implements `float_to_int_approx_unchecked`: rounds towards zero, and reports UB if the result
+/// does not fit into the destination type (or the input is NaN).
+fn float_to_int_approx_unchecked<'tcx, F>(
+    f: F,
+    dest: layout::TyLayout<'tcx>,
+) -> InterpResult<'tcx, Scalar<Tag>>
+where
+    F: Float + std::fmt::Display,
+{
+    let width = dest.size.bits() as usize;
+    let (res, status) = match dest.ty.kind {
+        ty::Uint(_) => {
+            let res = f.to_u128(width);
+            (Scalar::from_uint(res.value, dest.size), res.status)
+        }
+        ty::Int(_) => {
+            let res = f.to_i128(width);
+            (Scalar::from_int(res.value, dest.size), res.status)
+        }
+        _ => bug!("`float_to_int_approx_unchecked` called with non-int output type {:?}", dest.ty),
+    };
+    // `INEXACT` just means that there was a fractional part that got truncated.
+    if status.intersects(Status::INVALID_OP) {
+        throw_ub_format!(
+            "`float_to_int_approx_unchecked` intrinsic called on {} which cannot be represented in \
+             target type `{}`",
+            f,
+            dest.ty
+        );
+    }
+    Ok(res)
 }
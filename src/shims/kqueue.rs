@@ -0,0 +1,362 @@
+//! Emulation of macOS's `kqueue` readiness notifications over Miri's emulated file descriptors.
+//!
+//! Like our `epoll`, this computes readiness on demand from `FileDescriptor::readiness`, so all
+//! notifications are level-triggered; `EV_CLEAR` is accepted and treated the same way (a
+//! spurious wakeup that correct programs must handle anyway). Besides `EVFILT_READ` and
+//! `EVFILT_WRITE`, we support `EVFILT_USER`, which `mio` uses to wake up a blocked thread.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io;
+
+use rustc::ty::layout::Size;
+
+use crate::*;
+
+/// An event registered in a kqueue.
+#[derive(Debug, Clone, Copy)]
+struct Registration {
+    /// The `EV_*` flags the event was registered with.
+    flags: u16,
+    enabled: bool,
+    /// For `EVFILT_USER`: whether the event was triggered.
+    triggered: bool,
+    udata: Scalar<Tag>,
+}
+
+/// A kqueue: its registered events, keyed by `(ident, filter)`.
+#[derive(Debug, Default)]
+pub struct Kqueue {
+    registrations: BTreeMap<(u64, i16), Registration>,
+}
+
+impl FileDescriptor for Kqueue {
+    fn as_kqueue_mut(&mut self) -> Option<&mut Kqueue> {
+        Some(self)
+    }
+
+    fn read<'tcx>(&mut self, _bytes: &mut [u8]) -> InterpResult<'tcx, io::Result<usize>> {
+        Ok(Err(io::ErrorKind::InvalidInput.into()))
+    }
+
+    fn write<'tcx>(&mut self, _bytes: &[u8]) -> InterpResult<'tcx, io::Result<usize>> {
+        Ok(Err(io::ErrorKind::InvalidInput.into()))
+    }
+
+    fn close<'tcx>(self: Box<Self>) -> InterpResult<'tcx, io::Result<i32>> {
+        Ok(Ok(0))
+    }
+
+    fn dup<'tcx>(&mut self) -> InterpResult<'tcx, io::Result<Box<dyn FileDescriptor>>> {
+        throw_unsup_format!("duplicating kqueues is not supported")
+    }
+}
+
+/// The fields of a `struct kevent`.
+#[derive(Debug, Clone, Copy)]
+struct Event {
+    ident: u64,
+    filter: i16,
+    flags: u16,
+    fflags: u32,
+    data: i64,
+    udata: Scalar<Tag>,
+}
+
+impl<'mir, 'tcx> EvalContextExtPrivate<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+trait EvalContextExtPrivate<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    fn kqueue_error<T: From<i32>>(&mut self, errno: &str) -> InterpResult<'tcx, T> {
+        let this = self.eval_context_mut();
+        let errno = this.eval_libc(errno)?;
+        this.set_last_error(errno)?;
+        Ok((-1).into())
+    }
+
+    /// The place of the `i`-th `struct kevent` in the array that `list_op` points to.
+    fn kevent_place(
+        &mut self,
+        list_op: OpTy<'tcx, Tag>,
+        i: usize,
+    ) -> InterpResult<'tcx, MPlaceTy<'tcx, Tag>> {
+        let this = self.eval_context_mut();
+        let list = this.read_scalar(list_op)?.not_undef()?;
+        let first = this.deref_operand(list_op)?;
+        let offset = Size::from_bytes(i as u64 * first.layout.size.bytes());
+        let ptr = list.ptr_offset(offset, this)?;
+        this.ref_to_mplace(ImmTy::from_scalar(ptr, list_op.layout))
+    }
+
+    fn read_kevent(&mut self, list_op: OpTy<'tcx, Tag>, i: usize) -> InterpResult<'tcx, Event> {
+        let this = self.eval_context_mut();
+        let place = this.kevent_place(list_op, i)?;
+        let field = |this: &mut MiriEvalContext<'mir, 'tcx>, i| {
+            let field = this.mplace_field(place, i)?;
+            this.read_scalar(field.into())?.not_undef()
+        };
+        Ok(Event {
+            ident: field(this, 0)?.to_machine_usize(this)?,
+            filter: field(this, 1)?.to_i16()?,
+            flags: field(this, 2)?.to_u16()?,
+            fflags: field(this, 3)?.to_u32()?,
+            data: field(this, 4)?.to_machine_isize(this)?,
+            udata: field(this, 5)?,
+        })
+    }
+
+    fn write_kevent(
+        &mut self,
+        list_op: OpTy<'tcx, Tag>,
+        i: usize,
+        event: Event,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let place = this.kevent_place(list_op, i)?;
+        let ptr_size = this.pointer_size();
+        let fields = [
+            Scalar::from_uint(event.ident, ptr_size),
+            Scalar::from_int(event.filter, Size::from_bytes(2)),
+            Scalar::from_u16(event.flags),
+            Scalar::from_u32(event.fflags),
+            Scalar::from_int(event.data, ptr_size),
+            event.udata,
+        ];
+        for (i, &value) in fields.iter().enumerate() {
+            let field = this.mplace_field(place, i as u64)?;
+            this.write_scalar(value, field.into())?;
+        }
+        Ok(())
+    }
+
+    /// Applies one entry of the change list. Returns the `errno` to report on failure.
+    fn apply_kevent_change(
+        &mut self,
+        kq: i32,
+        change: Event,
+    ) -> InterpResult<'tcx, Result<(), &'static str>> {
+        let this = self.eval_context_mut();
+
+        let ev_add = this.eval_libc("EV_ADD")?.to_u16()?;
+        let ev_delete = this.eval_libc("EV_DELETE")?.to_u16()?;
+        let ev_enable = this.eval_libc("EV_ENABLE")?.to_u16()?;
+        let ev_disable = this.eval_libc("EV_DISABLE")?.to_u16()?;
+        let mut supported = ev_add | ev_delete | ev_enable | ev_disable;
+        for flag in &["EV_CLEAR", "EV_ONESHOT", "EV_RECEIPT"] {
+            supported |= this.eval_libc(flag)?.to_u16()?;
+        }
+        if change.flags & !supported != 0 {
+            throw_unsup_format!("unsupported kevent flags {:#x}", change.flags & !supported);
+        }
+
+        let evfilt_user = this.eval_libc("EVFILT_USER")?.to_i16()?;
+        let trigger = if change.filter == evfilt_user {
+            let note_trigger = this.eval_libc("NOTE_TRIGGER")?.to_u32()?;
+            // The other `fflags` of user events are just stored for the program; we ignore them.
+            change.fflags & note_trigger != 0
+        } else if change.filter == this.eval_libc("EVFILT_READ")?.to_i16()?
+            || change.filter == this.eval_libc("EVFILT_WRITE")?.to_i16()?
+        {
+            let fd = i32::try_from(change.ident).unwrap_or(-1);
+            if !this.machine.file_handler.handles.contains_key(&fd) {
+                return Ok(Err("EBADF"));
+            }
+            false
+        } else {
+            throw_unsup_format!("unsupported kevent filter {}", change.filter);
+        };
+
+        let kqueue = this.machine.file_handler.handles.get_mut(&kq).unwrap();
+        let registrations = &mut kqueue.as_kqueue_mut().unwrap().registrations;
+        let key = (change.ident, change.filter);
+        if change.flags & ev_delete != 0 {
+            if registrations.remove(&key).is_none() {
+                return Ok(Err("ENOENT"));
+            }
+            return Ok(Ok(()));
+        }
+        if change.flags & ev_add != 0 {
+            let registration =
+                Registration { flags: 0, enabled: true, triggered: false, udata: change.udata };
+            registrations.entry(key).or_insert(registration);
+        }
+        let registration = match registrations.get_mut(&key) {
+            Some(registration) => registration,
+            None => return Ok(Err("ENOENT")),
+        };
+        if change.flags & ev_add != 0 {
+            registration.flags = change.flags;
+            registration.udata = change.udata;
+        }
+        if change.flags & ev_enable != 0 {
+            registration.enabled = true;
+        }
+        if change.flags & ev_disable != 0 {
+            registration.enabled = false;
+        }
+        if trigger {
+            registration.triggered = true;
+        }
+        Ok(Ok(()))
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    fn kqueue(&mut self) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+        Ok(this.machine.file_handler.insert_fd(Box::new(Kqueue::default())))
+    }
+
+    fn kevent(
+        &mut self,
+        kq_op: OpTy<'tcx, Tag>,
+        changelist_op: OpTy<'tcx, Tag>,
+        nchanges_op: OpTy<'tcx, Tag>,
+        eventlist_op: OpTy<'tcx, Tag>,
+        nevents_op: OpTy<'tcx, Tag>,
+        timeout_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let kq = this.read_scalar(kq_op)?.to_i32()?;
+        let nchanges = this.read_scalar(nchanges_op)?.to_i32()?;
+        let nevents = this.read_scalar(nevents_op)?.to_i32()?;
+        let timeout = this.read_scalar(timeout_op)?.not_undef()?;
+
+        let (nchanges, nevents) = match (usize::try_from(nchanges), usize::try_from(nevents)) {
+            (Ok(nchanges), Ok(nevents)) => (nchanges, nevents),
+            _ => return this.kqueue_error("EINVAL"),
+        };
+        let is_kqueue = match this.machine.file_handler.handles.get_mut(&kq) {
+            Some(file_descriptor) => file_descriptor.as_kqueue_mut().is_some(),
+            None => return this.kqueue_error("EBADF"),
+        };
+        if !is_kqueue {
+            return this.kqueue_error("EINVAL");
+        }
+
+        let ev_error = this.eval_libc("EV_ERROR")?.to_u16()?;
+        let ev_receipt = this.eval_libc("EV_RECEIPT")?.to_u16()?;
+        let mut written = 0;
+
+        // Apply the changes. Errors, and all results of changes with `EV_RECEIPT`, are reported in
+        // the event list as `EV_ERROR` events; if there is no space left for an error, the whole
+        // call fails.
+        for i in 0..nchanges {
+            let change = this.read_kevent(changelist_op, i)?;
+            let result = this.apply_kevent_change(kq, change)?;
+            if result.is_ok() && change.flags & ev_receipt == 0 {
+                continue;
+            }
+            if written == nevents {
+                return match result {
+                    Ok(()) => continue,
+                    Err(errno) => this.kqueue_error(errno),
+                };
+            }
+            let data = match result {
+                Ok(()) => 0,
+                Err(errno) => this.eval_libc_i32(errno)?.into(),
+            };
+            let receipt = Event { flags: change.flags | ev_error, data, ..change };
+            this.write_kevent(eventlist_op, written, receipt)?;
+            written += 1;
+        }
+        if written > 0 || nevents == 0 {
+            return Ok(written as i32);
+        }
+
+        // Collect the triggered events.
+        let evfilt_read = this.eval_libc("EVFILT_READ")?.to_i16()?;
+        let evfilt_user = this.eval_libc("EVFILT_USER")?.to_i16()?;
+        let ev_eof = this.eval_libc("EV_EOF")?.to_u16()?;
+        let ev_oneshot = this.eval_libc("EV_ONESHOT")?.to_u16()?;
+        let ev_clear = this.eval_libc("EV_CLEAR")?.to_u16()?;
+        let registrations = this
+            .machine
+            .file_handler
+            .handles
+            .get_mut(&kq)
+            .unwrap()
+            .as_kqueue_mut()
+            .unwrap()
+            .registrations
+            .clone();
+        let mut ready = Vec::new();
+        let mut closed = Vec::new();
+        for (&(ident, filter), registration) in &registrations {
+            if ready.len() == nevents {
+                break;
+            }
+            if !registration.enabled {
+                continue;
+            }
+            let mut flags = 0;
+            let triggered = if filter == evfilt_user {
+                registration.triggered
+            } else {
+                let fd = i32::try_from(ident).unwrap();
+                let readiness = match this.machine.file_handler.handles.get(&fd) {
+                    // Regular files are always ready.
+                    Some(file_descriptor) => file_descriptor.readiness().unwrap_or(Readiness {
+                        readable: true,
+                        writable: true,
+                        ..Readiness::default()
+                    }),
+                    // Closing a file descriptor removes its events.
+                    None => {
+                        closed.push((ident, filter));
+                        continue;
+                    }
+                };
+                if filter == evfilt_read {
+                    if readiness.read_hangup {
+                        flags |= ev_eof;
+                    }
+                    readiness.readable
+                } else {
+                    if readiness.hangup {
+                        flags |= ev_eof;
+                    }
+                    readiness.writable || readiness.hangup
+                }
+            };
+            if triggered {
+                let event = Event {
+                    ident,
+                    filter,
+                    flags: registration.flags | flags,
+                    fflags: 0,
+                    data: 0,
+                    udata: registration.udata,
+                };
+                ready.push(event);
+            }
+        }
+        let kqueue = this.machine.file_handler.handles.get_mut(&kq).unwrap();
+        let registrations = &mut kqueue.as_kqueue_mut().unwrap().registrations;
+        for key in closed {
+            registrations.remove(&key);
+        }
+        for event in &ready {
+            let key = (event.ident, event.filter);
+            if event.flags & ev_oneshot != 0 {
+                registrations.remove(&key);
+            } else if event.filter == evfilt_user && event.flags & ev_clear != 0 {
+                registrations.get_mut(&key).unwrap().triggered = false;
+            }
+        }
+
+        if ready.is_empty() && this.is_null(timeout)? {
+            // Nothing can become ready while we are waiting: there are no other threads.
+            throw_machine_stop!(TerminationInfo::Deadlock(
+                "waiting without timeout on a kqueue with no triggered events".to_owned()
+            ));
+        }
+        // With a timeout, waiting would just let the time run out.
+
+        for (i, &event) in ready.iter().enumerate() {
+            this.write_kevent(eventlist_op, i, event)?;
+        }
+        Ok(ready.len() as i32)
+    }
+}
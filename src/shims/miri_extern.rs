@@ -0,0 +1,216 @@
+//! Functions that the interpreted program can declare in an `extern "Rust"` block to interact
+//! with Miri itself, e.g. for debugging or to write targeted tests. None of these exist outside
+//! of Miri, so programs using them should only do so under `cfg(miri)`.
+
+use std::ffi::OsStr;
+
+use rand::RngCore;
+
+use rustc::ty;
+use rustc::ty::layout::{Align, Size};
+use rustc_span::{BytePos, Loc};
+
+use crate::*;
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Emulates calling one of the `miri_*` functions. They all return normally, so the caller
+    /// jumps to the return block.
+    fn emulate_miri_extern(
+        &mut self,
+        link_name: &str,
+        args: &[OpTy<'tcx, Tag>],
+        dest: PlaceTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        match link_name {
+            // `fn miri_get_alloc_id(ptr: *const u8) -> u64`
+            "miri_get_alloc_id" => {
+                let ptr = this.read_scalar(args[0])?.not_undef()?;
+                let ptr = this.force_ptr(ptr).map_err(|_| {
+                    err_ub_format!("`miri_get_alloc_id` called on a pointer without provenance")
+                })?;
+                this.write_scalar(Scalar::from_u64(ptr.alloc_id.0), dest)?;
+            }
+            // `fn miri_print_borrow_stacks(ptr: *const u8)`
+            "miri_print_borrow_stacks" => {
+                let ptr = this.read_scalar(args[0])?.not_undef()?;
+                let ptr = this.force_ptr(ptr)?;
+                let alloc = this.memory.get_raw(ptr.alloc_id)?;
+                match &alloc.extra.stacked_borrows {
+                    Some(stacks) => {
+                        eprintln!("Borrow stacks of {:?}:", ptr.alloc_id);
+                        stacks.print_stacks();
+                    }
+                    None => eprintln!(
+                        "Borrow stacks of {:?} are not tracked (Stacked Borrows is disabled)",
+                        ptr.alloc_id
+                    ),
+                }
+            }
+            // `fn miri_dump_alloc(ptr: *const u8)`
+            "miri_dump_alloc" => {
+                let ptr = this.read_scalar(args[0])?.not_undef()?;
+                let ptr = this.force_ptr(ptr)?;
+                eprint!("{}", this.render_allocation(ptr.alloc_id)?);
+            }
+            // `fn miri_backtrace()`
+            "miri_backtrace" => {
+                report_msg(this, "backtrace requested by `miri_backtrace`".to_owned(), false, vec![]);
+            }
+            // `fn miri_backtrace_size(flags: u64) -> usize`
+            // `fn miri_get_backtrace(flags: u64, buf: *mut *mut ())`
+            // Together, these return the frames of the current backtrace, innermost first, for
+            // the `backtrace` crate. The pointers are only meaningful to `miri_resolve_frame`.
+            "miri_backtrace_size" => {
+                let frames = this.backtrace_frame_ptrs(link_name, args[0])?;
+                this.write_scalar(Scalar::from_uint(frames.len() as u64, dest.layout.size), dest)?;
+            }
+            "miri_get_backtrace" => {
+                let frames = this.backtrace_frame_ptrs(link_name, args[0])?;
+                let buf = this.deref_operand(args[1])?;
+                for (i, frame) in frames.into_iter().enumerate() {
+                    let offset = Size::from_bytes(i as u64 * buf.layout.size.bytes());
+                    let place = buf.offset(offset, MemPlaceMeta::None, buf.layout, &*this.tcx)?;
+                    this.write_scalar(frame, place.into())?;
+                }
+            }
+            // `fn miri_resolve_frame(ptr: *mut (), flags: u64) -> MiriFrame`, where `MiriFrame` is
+            // a struct with the fields `name_len: usize`, `filename_len: usize`, `lineno: u32`,
+            // `colno: u32` and `fn_ptr: *mut ()`.
+            "miri_resolve_frame" => {
+                let (instance, fn_ptr, loc) = this.resolve_frame_ptr(link_name, args[0], args[1])?;
+                let dest = this.force_allocation(dest)?;
+                this.write_int_field(dest, "name_len", instance.to_string().len() as i64)?;
+                this.write_int_field(dest, "filename_len", loc.file.name.to_string().len() as i64)?;
+                this.write_int_field(dest, "lineno", loc.line as i64)?;
+                // Columns are counted from 1, like lines.
+                this.write_int_field(dest, "colno", loc.col.0 as i64 + 1)?;
+                let fn_ptr_place = this.field_by_name(dest, "fn_ptr")?;
+                this.write_scalar(fn_ptr, fn_ptr_place.into())?;
+            }
+            // `fn miri_resolve_frame_names(ptr: *mut (), flags: u64, name_buf: *mut u8, filename_buf: *mut u8)`
+            // Writes the function name and the file name of the frame (without null terminators)
+            // to buffers of the lengths that `miri_resolve_frame` returned.
+            "miri_resolve_frame_names" => {
+                let (instance, _, loc) = this.resolve_frame_ptr(link_name, args[0], args[1])?;
+                let name_buf = this.read_scalar(args[2])?.not_undef()?;
+                let filename_buf = this.read_scalar(args[3])?.not_undef()?;
+                this.memory.write_bytes(name_buf, instance.to_string().into_bytes())?;
+                this.memory.write_bytes(filename_buf, loc.file.name.to_string().into_bytes())?;
+            }
+            // `fn miri_host_to_target_path(path: *const c_char, out: *mut c_char, out_size: usize) -> usize`
+            // Returns 0 on success, or the required buffer size (including the null terminator)
+            // if `out` is too small.
+            "miri_host_to_target_path" => {
+                let path = this.read_scalar(args[0])?.not_undef()?;
+                let out = this.read_scalar(args[1])?.not_undef()?;
+                let out_size = this.read_scalar(args[2])?.to_machine_usize(this)?;
+
+                let path = this.read_os_str_from_c_str(path)?.to_owned();
+                let path = this.convert_path(&path, PathConversion::HostToTarget)?;
+                let path: &OsStr = &path;
+                let result = if this.write_os_str_to_c_str(path, out, out_size)? {
+                    0
+                } else {
+                    path.len() as u64 + 1
+                };
+                this.write_scalar(Scalar::from_uint(result, dest.layout.size), dest)?;
+            }
+            // `fn miri_nondet_bytes(ptr: *mut u8, len: usize)`
+            // Unlike `getrandom`, this always uses the seeded RNG (even with isolation disabled),
+            // so that running with the same `-Zmiri-seed` reproduces the same values.
+            "miri_nondet_bytes" => {
+                let ptr = this.read_scalar(args[0])?.not_undef()?;
+                let len = this.read_scalar(args[1])?.to_machine_usize(this)?;
+                // Check the destination before allocating the buffer for it on the host.
+                let align = Align::from_bytes(1).unwrap();
+                if this.memory.check_ptr_access(ptr, Size::from_bytes(len), align)?.is_some() {
+                    let mut data = vec![0; len as usize];
+                    this.memory.extra.rng.get_mut().fill_bytes(&mut data);
+                    this.memory.write_bytes(ptr, data.iter().copied())?;
+                }
+            }
+            // `fn miri_host_target_info() -> MiriHostTargetInfo`, where `MiriHostTargetInfo` is a
+            // struct with the fields `isolation: bool`, `seed: u64` and `target_len: usize`.
+            "miri_host_target_info" => {
+                let dest = this.force_allocation(dest)?;
+                let isolation = this.field_by_name(dest, "isolation")?;
+                this.write_scalar(Scalar::from_bool(!this.machine.communicate), isolation.into())?;
+                let seed = this.field_by_name(dest, "seed")?;
+                this.write_scalar(Scalar::from_u64(this.machine.seed), seed.into())?;
+                let target_len = this.tcx.sess.opts.target_triple.triple().len();
+                this.write_int_field(dest, "target_len", target_len as i64)?;
+            }
+            // `fn miri_target_triple(buf: *mut u8)`
+            // Writes the target triple (without null terminator) to a buffer of the length that
+            // `miri_host_target_info` returned.
+            "miri_target_triple" => {
+                let buf = this.read_scalar(args[0])?.not_undef()?;
+                let target = this.tcx.sess.opts.target_triple.triple().to_owned();
+                this.memory.write_bytes(buf, target.into_bytes())?;
+            }
+            _ => throw_unsup_format!("can't call foreign function: {}", link_name),
+        }
+
+        Ok(())
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExtPrivate<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+trait EvalContextExtPrivate<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Checks that the `flags` argument of the backtrace function `name` is 0, the only value
+    /// that is defined yet.
+    fn check_backtrace_flags(&self, name: &str, flags_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx> {
+        let this = self.eval_context_ref();
+        let flags = this.read_scalar(flags_op)?.to_u64()?;
+        if flags != 0 {
+            throw_unsup_format!("unknown `{}` flags {}", name, flags);
+        }
+        Ok(())
+    }
+
+    /// The pointers that represent the frames of the current backtrace, innermost first. Each one
+    /// is a function pointer to the function of its frame, offset by the position in the source
+    /// that the frame is executing. It must not be used for anything but `miri_resolve_frame`.
+    fn backtrace_frame_ptrs(
+        &mut self,
+        name: &str,
+        flags_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, Vec<Scalar<Tag>>> {
+        let this = self.eval_context_mut();
+        this.check_backtrace_flags(name, flags_op)?;
+        let mut frames = Vec::new();
+        for frame in this.generate_stacktrace(None) {
+            let fn_ptr = this.memory.create_fn_alloc(FnVal::Instance(frame.instance));
+            let pos = Size::from_bytes(u64::from(frame.call_site.lo().0));
+            frames.push(Scalar::Ptr(fn_ptr.offset(pos, this)?));
+        }
+        Ok(frames)
+    }
+
+    /// The frame that `ptr_op`, a pointer returned by `miri_get_backtrace`, represents: its
+    /// function, a pointer to that function and the position in the source.
+    fn resolve_frame_ptr(
+        &mut self,
+        name: &str,
+        ptr_op: OpTy<'tcx, Tag>,
+        flags_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, (ty::Instance<'tcx>, Scalar<Tag>, Loc)> {
+        let this = self.eval_context_mut();
+        this.check_backtrace_flags(name, flags_op)?;
+        let ptr = this.force_ptr(this.read_scalar(ptr_op)?.not_undef()?)?;
+        let fn_ptr = Pointer::new_with_tag(ptr.alloc_id, Size::ZERO, ptr.tag);
+        let instance = match this.memory.get_fn(fn_ptr.into()) {
+            Ok(FnVal::Instance(instance)) => instance,
+            _ => throw_ub_format!(
+                "`{}` called on {:?}, which is not a frame returned by `miri_get_backtrace`",
+                name,
+                ptr
+            ),
+        };
+        let loc = this.tcx.sess.source_map().lookup_char_pos(BytePos(ptr.offset.bytes() as u32));
+        Ok((instance, fn_ptr.into(), loc))
+    }
+}
@@ -1,14 +1,20 @@
 pub mod dlsym;
 pub mod env;
+pub mod epoll;
 pub mod foreign_items;
 pub mod fs;
 pub mod intrinsics;
+pub mod kqueue;
+pub mod miri_extern;
 pub mod panic;
+pub mod poll;
+pub mod socket;
 pub mod time;
 pub mod tls;
 
 use crate::*;
 use rustc::{mir, ty};
+use rustc_span::symbol::sym;
 
 impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
 pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
@@ -33,6 +39,30 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             return Ok(None);
         }
 
+        // Some library functions are implemented with inline assembly, which we cannot run:
+        // `core::arch`'s `cpuid` support, and `black_box` in `core::hint` and `test`.
+        let def_id = instance.def_id();
+        let krate = this.tcx.crate_name(def_id.krate);
+        if krate == sym::core || krate == sym::test {
+            let name = this.tcx.item_name(def_id);
+            let name = &*name.as_str();
+            match name {
+                "__cpuid_count" | "has_cpuid" if krate == sym::core => {
+                    let (dest, ret) = ret.unwrap();
+                    foreign_items::x86::EvalContextExt::emulate_cpuid_fn(this, name, args, dest)?;
+                    this.go_to_block(ret);
+                    return Ok(None);
+                }
+                "black_box" => {
+                    let (dest, ret) = ret.unwrap();
+                    intrinsics::EvalContextExt::black_box(this, args[0], dest)?;
+                    this.go_to_block(ret);
+                    return Ok(None);
+                }
+                _ => {}
+            }
+        }
+
         // Try to see if we can do something about foreign items.
         if this.tcx.is_foreign_item(instance.def_id()) {
             // An external function call that does not have a MIR body. We either find MIR elsewhere
@@ -41,7 +71,9 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             // to run extra MIR), and Ok(Some(body)) if we found MIR to run for the
             // foreign function
             // Any needed call to `goto_block` will be performed by `emulate_foreign_item`.
-            return this.emulate_foreign_item(instance.def_id(), args, ret, unwind);
+            let res = this.emulate_foreign_item(instance.def_id(), args, ret, unwind);
+            this.record_shim_call(instance.def_id(), &res);
+            return res;
         }
 
         // Otherwise, load the MIR.
@@ -0,0 +1,227 @@
+//! Emulation of `poll` and `select` over Miri's emulated file descriptors.
+//!
+//! Like our `epoll`, this computes readiness from `FileDescriptor::readiness`. Descriptors that do
+//! not support readiness notifications (regular files) are always ready, as they are on Unix.
+//! There are no other threads that could make a descriptor ready, so waiting with a timeout just
+//! lets the time run out, and waiting without one is a deadlock.
+
+use std::convert::TryFrom;
+
+use rustc::ty::layout::{Endian, LayoutOf, Size};
+
+use crate::*;
+
+/// The largest file descriptor that fits into an `fd_set`, plus one.
+const FD_SETSIZE: u64 = 1024;
+
+impl<'mir, 'tcx> EvalContextExtPrivate<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+trait EvalContextExtPrivate<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    fn poll_error<T: From<i32>>(&mut self, errno: &str) -> InterpResult<'tcx, T> {
+        let this = self.eval_context_mut();
+        let errno = this.eval_libc(errno)?;
+        this.set_last_error(errno)?;
+        Ok((-1).into())
+    }
+
+    /// The readiness of `fd`, or `None` if it is not open.
+    fn fd_readiness(&self, fd: i32) -> Option<Readiness> {
+        let this = self.eval_context_ref();
+        let always_ready = Readiness { readable: true, writable: true, ..Readiness::default() };
+        match fd {
+            // Reading from stdin does not wait for Miri's other descriptors.
+            0 => Some(Readiness { readable: true, ..Readiness::default() }),
+            1 | 2 => Some(Readiness { writable: true, ..Readiness::default() }),
+            _ => this
+                .machine
+                .file_handler
+                .handles
+                .get(&fd)
+                .map(|file_descriptor| file_descriptor.readiness().unwrap_or(always_ready)),
+        }
+    }
+
+    /// The position of the bit for `fd` in an `fd_set`: the byte, and the bit in that byte.
+    /// `fd_set` is an array of integers whose size depends on the target.
+    fn fd_set_bit(&self, fd: u64) -> (u64, u8) {
+        let this = self.eval_context_ref();
+        let word_size = if this.tcx.sess.target.target.target_os == "macos" {
+            4
+        } else {
+            this.pointer_size().bytes()
+        };
+        let word = fd / (word_size * 8);
+        let bit = fd % (word_size * 8);
+        let byte = match this.tcx.data_layout.endian {
+            Endian::Little => bit / 8,
+            Endian::Big => word_size - 1 - bit / 8,
+        };
+        (word * word_size + byte, (bit % 8) as u8)
+    }
+
+    /// Reads the descriptors below `nfds` that are in the `fd_set` at `set`, if it is not null.
+    fn read_fd_set(&mut self, set: Scalar<Tag>, nfds: u64) -> InterpResult<'tcx, Vec<i32>> {
+        let this = self.eval_context_mut();
+        if this.is_null(set)? {
+            return Ok(Vec::new());
+        }
+        let size = this.libc_ty_layout("fd_set")?.size;
+        let bytes = this.memory.read_bytes(set, size)?.to_owned();
+        Ok((0..nfds)
+            .filter(|&fd| {
+                let (byte, bit) = this.fd_set_bit(fd);
+                bytes[byte as usize] & (1 << bit) != 0
+            })
+            .map(|fd| fd as i32)
+            .collect())
+    }
+
+    /// Replaces the contents of the `fd_set` at `set`, if it is not null, with `fds`.
+    fn write_fd_set(&mut self, set: Scalar<Tag>, fds: &[i32]) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        if this.is_null(set)? {
+            return Ok(());
+        }
+        let size = this.libc_ty_layout("fd_set")?.size;
+        let mut bytes = vec![0u8; size.bytes() as usize];
+        for &fd in fds {
+            let (byte, bit) = this.fd_set_bit(fd as u64);
+            bytes[byte as usize] |= 1 << bit;
+        }
+        this.memory.write_bytes(set, bytes)
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    fn poll(
+        &mut self,
+        fds_op: OpTy<'tcx, Tag>,
+        nfds_op: OpTy<'tcx, Tag>,
+        timeout_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let fds_ptr = this.read_scalar(fds_op)?.not_undef()?;
+        let nfds = this.read_scalar(nfds_op)?.to_machine_usize(this)?;
+        let timeout = this.read_scalar(timeout_op)?.to_i32()?;
+
+        let pollfd_layout = this.libc_ty_layout("pollfd")?;
+        let pollfd_ptr_layout = this.layout_of(this.tcx.mk_mut_ptr(pollfd_layout.ty))?;
+        // The events are `short`s.
+        let short = Size::from_bytes(2);
+        // `POLLHUP` and `POLLNVAL` are always reported, even if they were not requested.
+        let pollin = this.eval_libc("POLLIN")?.to_bits(short)?;
+        let pollout = this.eval_libc("POLLOUT")?.to_bits(short)?;
+        let pollhup = this.eval_libc("POLLHUP")?.to_bits(short)?;
+        let pollnval = this.eval_libc("POLLNVAL")?.to_bits(short)?;
+        // Only Linux has `POLLRDHUP`.
+        let pollrdhup = match this.eval_path_scalar(&["libc", "POLLRDHUP"])? {
+            Some(pollrdhup) => pollrdhup.not_undef()?.to_bits(short)?,
+            None => 0,
+        };
+
+        let mut ready = 0;
+        for i in 0..nfds {
+            let offset = Size::from_bytes(i * pollfd_layout.size.bytes());
+            let ptr = fds_ptr.ptr_offset(offset, this)?;
+            let pollfd = this.ref_to_mplace(ImmTy::from_scalar(ptr, pollfd_ptr_layout))?;
+            let fd = this.read_scalar(this.mplace_field(pollfd, 0)?.into())?.to_i32()?;
+            let events = this.read_scalar(this.mplace_field(pollfd, 1)?.into())?.to_bits(short)?;
+            // Negative descriptors are ignored.
+            let revents = if fd < 0 {
+                0
+            } else {
+                match this.fd_readiness(fd) {
+                    Some(readiness) => {
+                        let mut revents = 0;
+                        for &(flag, is_ready) in &[
+                            (pollin, readiness.readable),
+                            (pollout, readiness.writable),
+                            (pollrdhup, readiness.read_hangup),
+                        ] {
+                            if is_ready && events & flag != 0 {
+                                revents |= flag;
+                            }
+                        }
+                        if readiness.hangup {
+                            revents |= pollhup;
+                        }
+                        revents
+                    }
+                    None => pollnval,
+                }
+            };
+            let revents_place = this.mplace_field(pollfd, 2)?;
+            this.write_scalar(Scalar::from_uint(revents, short), revents_place.into())?;
+            if revents != 0 {
+                ready += 1;
+            }
+        }
+
+        if ready == 0 && timeout < 0 {
+            // Nothing can become ready while we are waiting: there are no other threads.
+            throw_machine_stop!(TerminationInfo::Deadlock(
+                "`poll` without timeout on file descriptors that are not ready".to_owned()
+            ));
+        }
+        // With a timeout, waiting would just let the time run out.
+        Ok(ready)
+    }
+
+    fn select(
+        &mut self,
+        nfds_op: OpTy<'tcx, Tag>,
+        readfds_op: OpTy<'tcx, Tag>,
+        writefds_op: OpTy<'tcx, Tag>,
+        exceptfds_op: OpTy<'tcx, Tag>,
+        timeout_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let nfds = this.read_scalar(nfds_op)?.to_i32()?;
+        let readfds = this.read_scalar(readfds_op)?.not_undef()?;
+        let writefds = this.read_scalar(writefds_op)?.not_undef()?;
+        let exceptfds = this.read_scalar(exceptfds_op)?.not_undef()?;
+        let timeout = this.read_scalar(timeout_op)?.not_undef()?;
+
+        let nfds = match u64::try_from(nfds) {
+            Ok(nfds) if nfds <= FD_SETSIZE => nfds,
+            _ => return this.poll_error("EINVAL"),
+        };
+        let read = this.read_fd_set(readfds, nfds)?;
+        let write = this.read_fd_set(writefds, nfds)?;
+        let except = this.read_fd_set(exceptfds, nfds)?;
+
+        let mut readiness = Vec::new();
+        for &fd in read.iter().chain(&write).chain(&except) {
+            match this.fd_readiness(fd) {
+                Some(fd_readiness) => readiness.push((fd, fd_readiness)),
+                None => return this.poll_error("EBADF"),
+            }
+        }
+        let is_ready = |fd: i32, ready: fn(&Readiness) -> bool| {
+            readiness.iter().any(|(ready_fd, readiness)| *ready_fd == fd && ready(readiness))
+        };
+        // A hangup makes the descriptor readable, as reading then returns immediately.
+        let read: Vec<i32> = read
+            .into_iter()
+            .filter(|&fd| is_ready(fd, |readiness| readiness.readable || readiness.hangup))
+            .collect();
+        let write: Vec<i32> =
+            write.into_iter().filter(|&fd| is_ready(fd, |readiness| readiness.writable)).collect();
+        let ready = read.len() + write.len();
+
+        if ready == 0 && this.is_null(timeout)? {
+            // Nothing can become ready while we are waiting: there are no other threads.
+            throw_machine_stop!(TerminationInfo::Deadlock(
+                "`select` without timeout on file descriptors that are not ready".to_owned()
+            ));
+        }
+        // With a timeout, waiting would just let the time run out.
+        this.write_fd_set(readfds, &read)?;
+        this.write_fd_set(writefds, &write)?;
+        // Out-of-band data is not supported, so there are never any exceptional conditions.
+        this.write_fd_set(exceptfds, &[])?;
+        Ok(ready as i32)
+    }
+}
@@ -0,0 +1,1093 @@
+//! In-memory emulation of TCP and UDP sockets on the loopback interface.
+//!
+//! Programs can bind, listen, connect and accept within one interpreted process, which is all
+//! that tests talking to themselves over `127.0.0.1` need. UDP datagrams are queued at the port
+//! they are sent to; `-Zmiri-udp-drop-rate` and `-Zmiri-udp-reorder-rate` make some of them get
+//! lost or overtake each other, based on the seed. Nothing ever touches the host network,
+//! so this also works with isolation enabled. All connections are made immediately (if somebody is
+//! listening), and data written on one end becomes readable on the other end right away. Miri
+//! does not support threads, so an operation that would have to wait for another thread to do
+//! something (like `accept` without a pending connection) stops the program with a deadlock.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::TryFrom;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6};
+use std::rc::Rc;
+
+use rand::Rng;
+use rustc::ty::layout::{Align, Size};
+
+use crate::*;
+
+/// The range of ports handed out to sockets that are bound to port 0, or that connect without
+/// being bound (this is the IANA range for dynamic ports).
+const EPHEMERAL_PORTS: std::ops::RangeInclusive<u16> = 49152..=65535;
+
+/// One direction of a connection.
+#[derive(Debug, Default)]
+struct Pipe {
+    /// The bytes that have been written but not read yet.
+    buf: VecDeque<u8>,
+    /// The writing end was shut down: once `buf` is empty, reads report the end of the stream.
+    write_closed: bool,
+    /// The reading end was shut down: writes fail with `EPIPE`.
+    read_closed: bool,
+}
+
+/// A port that a socket is bound to.
+#[derive(Debug, Default)]
+struct Port {
+    /// Whether the socket that holds this port is still open.
+    in_use: bool,
+    /// If the socket is listening, the maximum number of pending connections.
+    listening: Option<usize>,
+    /// The server ends of the connections that have not been accepted yet.
+    pending: VecDeque<Socket>,
+    /// For UDP: the datagrams that have been received but not read yet, with their senders.
+    datagrams: VecDeque<(SocketAddr, Vec<u8>)>,
+}
+
+#[derive(Debug)]
+enum SocketState {
+    /// Freshly created or bound, but neither listening nor connected.
+    Unconnected,
+    /// Waiting for connections; they are queued in the bound port.
+    Listening,
+    Connected { peer: SocketAddr, read: Rc<RefCell<Pipe>>, write: Rc<RefCell<Pipe>> },
+    /// A UDP socket with a default destination.
+    Associated { peer: SocketAddr },
+}
+
+/// An emulated TCP or UDP socket.
+#[derive(Debug)]
+pub struct Socket {
+    ipv6: bool,
+    udp: bool,
+    nonblocking: bool,
+    /// The local address, once the socket is bound or connected.
+    local: Option<SocketAddr>,
+    /// The port this socket owns. Accepted connections share the port of the listening socket and
+    /// do not own it.
+    port: Option<Rc<RefCell<Port>>>,
+    state: SocketState,
+}
+
+impl FileDescriptor for Socket {
+    fn as_socket_mut(&mut self) -> Option<&mut Socket> {
+        Some(self)
+    }
+
+    fn readiness(&self) -> Option<Readiness> {
+        if self.udp {
+            let readable =
+                self.port.as_ref().map_or(false, |port| !port.borrow().datagrams.is_empty());
+            return Some(Readiness { readable, writable: true, ..Readiness::default() });
+        }
+        Some(match &self.state {
+            SocketState::Unconnected =>
+                Readiness { writable: true, hangup: true, ..Readiness::default() },
+            SocketState::Listening => {
+                let pending = !self.port.as_ref().unwrap().borrow().pending.is_empty();
+                Readiness { readable: pending, ..Readiness::default() }
+            }
+            SocketState::Associated { .. } => bug!("TCP socket with a default destination"),
+            SocketState::Connected { read, write, .. } => {
+                let read = read.borrow();
+                let write = write.borrow();
+                let read_hangup = read.write_closed;
+                Readiness {
+                    readable: !read.buf.is_empty() || read_hangup || read.read_closed,
+                    writable: !write.write_closed && !write.read_closed,
+                    read_hangup,
+                    hangup: read_hangup && write.write_closed,
+                }
+            }
+        })
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+
+    fn read<'tcx>(&mut self, bytes: &mut [u8]) -> InterpResult<'tcx, io::Result<usize>> {
+        if self.udp {
+            return Ok(self.recv_datagram(bytes)?.map(|(count, _)| count));
+        }
+        let read = match &self.state {
+            SocketState::Connected { read, .. } => read,
+            _ => return Ok(Err(io::ErrorKind::NotConnected.into())),
+        };
+        let mut read = read.borrow_mut();
+        if read.buf.is_empty() && !read.write_closed && !read.read_closed {
+            if self.nonblocking {
+                return Ok(Err(io::ErrorKind::WouldBlock.into()));
+            }
+            throw_machine_stop!(TerminationInfo::Deadlock(
+                "reading from a socket whose peer never sends any more data".to_owned()
+            ));
+        }
+        let count = bytes.len().min(read.buf.len());
+        for (dest, byte) in bytes.iter_mut().zip(read.buf.drain(..count)) {
+            *dest = byte;
+        }
+        Ok(Ok(count))
+    }
+
+    fn write<'tcx>(&mut self, bytes: &[u8]) -> InterpResult<'tcx, io::Result<usize>> {
+        if self.udp {
+            throw_unsup_format!("`write` on UDP sockets is not supported, use `send`");
+        }
+        let write = match &self.state {
+            SocketState::Connected { write, .. } => write,
+            _ => return Ok(Err(io::ErrorKind::NotConnected.into())),
+        };
+        let mut write = write.borrow_mut();
+        if write.write_closed || write.read_closed {
+            return Ok(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        write.buf.extend(bytes);
+        Ok(Ok(bytes.len()))
+    }
+
+    fn close<'tcx>(self: Box<Self>) -> InterpResult<'tcx, io::Result<i32>> {
+        self.shutdown(true, true);
+        if let Some(port) = self.port {
+            let mut port = port.borrow_mut();
+            port.in_use = false;
+            port.listening = None;
+            port.datagrams.clear();
+            // Connections that were never accepted are closed as well.
+            for pending in port.pending.drain(..) {
+                pending.shutdown(true, true);
+            }
+        }
+        Ok(Ok(0))
+    }
+
+    fn dup<'tcx>(&mut self) -> InterpResult<'tcx, io::Result<Box<dyn FileDescriptor>>> {
+        throw_unsup_format!("duplicating sockets is not supported")
+    }
+}
+
+impl Socket {
+    /// Receives the next datagram on a UDP socket, and returns how much of it fit into `bytes`
+    /// (the rest is discarded) and who sent it.
+    fn recv_datagram<'tcx>(
+        &mut self,
+        bytes: &mut [u8],
+    ) -> InterpResult<'tcx, io::Result<(usize, SocketAddr)>> {
+        let port = self.port.as_ref();
+        match port.and_then(|port| port.borrow_mut().datagrams.pop_front()) {
+            Some((source, datagram)) => {
+                let count = bytes.len().min(datagram.len());
+                bytes[..count].copy_from_slice(&datagram[..count]);
+                Ok(Ok((count, source)))
+            }
+            None if self.nonblocking => Ok(Err(io::ErrorKind::WouldBlock.into())),
+            None =>
+                throw_machine_stop!(TerminationInfo::Deadlock(
+                    "receiving on a UDP socket that nobody sends any datagrams to".to_owned()
+                )),
+        }
+    }
+
+    /// The number of bytes that the next read could return, for `FIONREAD`. On UDP sockets, this
+    /// is the size of the next datagram.
+    pub(crate) fn bytes_available(&self) -> usize {
+        if self.udp {
+            let port = self.port.as_ref().map(|port| port.borrow());
+            return port.and_then(|port| port.datagrams.front().map(|(_, d)| d.len())).unwrap_or(0);
+        }
+        match &self.state {
+            SocketState::Connected { read, .. } => read.borrow().buf.len(),
+            _ => 0,
+        }
+    }
+
+    fn shutdown(&self, read: bool, write: bool) {
+        if let SocketState::Connected { read: read_pipe, write: write_pipe, .. } = &self.state {
+            if read {
+                read_pipe.borrow_mut().read_closed = true;
+            }
+            if write {
+                write_pipe.borrow_mut().write_closed = true;
+            }
+        }
+    }
+}
+
+/// The ports of the emulated loopback interface.
+#[derive(Debug)]
+pub struct SocketHandler {
+    /// The ports in use, keyed by whether they are UDP ports (TCP and UDP ports are separate),
+    /// and the port number.
+    ports: BTreeMap<(bool, u16), Rc<RefCell<Port>>>,
+    next_ephemeral_port: u16,
+    /// The probability that a UDP datagram gets lost.
+    udp_drop_rate: f64,
+    /// The probability that a UDP datagram overtakes one that was sent before it.
+    udp_reorder_rate: f64,
+}
+
+impl SocketHandler {
+    pub fn new(udp_drop_rate: f64, udp_reorder_rate: f64) -> Self {
+        SocketHandler {
+            ports: BTreeMap::new(),
+            next_ephemeral_port: *EPHEMERAL_PORTS.start(),
+            udp_drop_rate,
+            udp_reorder_rate,
+        }
+    }
+}
+
+impl SocketHandler {
+    /// Reserves `port`, or a free ephemeral port if `port` is 0. Returns the `errno` to report on
+    /// failure.
+    fn bind_port(
+        &mut self,
+        udp: bool,
+        port: u16,
+    ) -> Result<(u16, Rc<RefCell<Port>>), &'static str> {
+        let port = if port == 0 {
+            // Hand out ephemeral ports round-robin, so that the same port is not reused right away.
+            let len = usize::from(EPHEMERAL_PORTS.end() - EPHEMERAL_PORTS.start()) + 1;
+            let start = self.next_ephemeral_port;
+            let free = EPHEMERAL_PORTS
+                .chain(EPHEMERAL_PORTS)
+                .skip(usize::from(start - EPHEMERAL_PORTS.start()))
+                .take(len)
+                .find(|port| !self.is_in_use(udp, *port))
+                .ok_or("EADDRINUSE")?;
+            self.next_ephemeral_port =
+                if free == *EPHEMERAL_PORTS.end() { *EPHEMERAL_PORTS.start() } else { free + 1 };
+            free
+        } else if self.is_in_use(udp, port) {
+            return Err("EADDRINUSE");
+        } else {
+            port
+        };
+        let entry = Rc::new(RefCell::new(Port { in_use: true, ..Port::default() }));
+        self.ports.insert((udp, port), entry.clone());
+        Ok((port, entry))
+    }
+
+    fn is_in_use(&self, udp: bool, port: u16) -> bool {
+        self.ports.get(&(udp, port)).map_or(false, |port| port.borrow().in_use)
+    }
+
+    /// Gives `socket` a local address, if it does not have one yet.
+    fn autobind(&mut self, socket: &mut Socket) -> Result<(), &'static str> {
+        if socket.port.is_none() {
+            let (port, entry) = self.bind_port(socket.udp, 0)?;
+            socket.local = Some(SocketAddr::new(loopback(socket.ipv6), port));
+            socket.port = Some(entry);
+        }
+        Ok(())
+    }
+}
+
+/// The address a socket of the given family uses to talk to the loopback interface.
+fn loopback(ipv6: bool) -> IpAddr {
+    if ipv6 { Ipv6Addr::LOCALHOST.into() } else { Ipv4Addr::LOCALHOST.into() }
+}
+
+impl<'mir, 'tcx> EvalContextExtPrivate<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+trait EvalContextExtPrivate<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Runs `f` on the socket behind `fd_op`. Errors (from looking up the socket, or returned by
+    /// `f`) are `errno` names; they are stored in the last OS error and `-1` is returned.
+    fn with_socket<T: From<i32>>(
+        &mut self,
+        fd_op: OpTy<'tcx, Tag>,
+        f: impl FnOnce(
+            &mut Socket,
+            &mut SocketHandler,
+        ) -> InterpResult<'tcx, Result<T, &'static str>>,
+    ) -> InterpResult<'tcx, T> {
+        let this = self.eval_context_mut();
+        let fd = this.read_scalar(fd_op)?.to_i32()?;
+        let machine = &mut this.machine;
+        let result = match machine.file_handler.handles.get_mut(&fd) {
+            Some(file_descriptor) =>
+                match file_descriptor.as_socket_mut() {
+                    Some(socket) => f(socket, &mut machine.socket_handler)?,
+                    None => Err("ENOTSOCK"),
+                },
+            None => Err("EBADF"),
+        };
+        this.socket_result(result)
+    }
+
+    fn socket_result<T: From<i32>>(
+        &mut self,
+        result: Result<T, &'static str>,
+    ) -> InterpResult<'tcx, T> {
+        let this = self.eval_context_mut();
+        match result {
+            Ok(ok) => Ok(ok),
+            Err(errno) => {
+                let errno = this.eval_libc(errno)?;
+                this.set_last_error(errno)?;
+                Ok((-1).into())
+            }
+        }
+    }
+
+    /// Reads the `sockaddr` of length `len` at `addr`.
+    fn read_sockaddr(
+        &mut self,
+        addr: Scalar<Tag>,
+        len: u64,
+    ) -> InterpResult<'tcx, Result<SocketAddr, &'static str>> {
+        let this = self.eval_context_mut();
+        let bytes = this.memory.read_bytes(addr, Size::from_bytes(len))?.to_owned();
+        if bytes.len() < 2 {
+            return Ok(Err("EINVAL"));
+        }
+        let family = this.sockaddr_family(&bytes)?;
+        let port = |bytes: &[u8]| u16::from_be_bytes([bytes[2], bytes[3]]);
+        Ok(if family == this.eval_libc_i32("AF_INET")? && bytes.len() >= 16 {
+            let ip = <[u8; 4]>::try_from(&bytes[4..8]).unwrap();
+            Ok(SocketAddr::new(Ipv4Addr::from(ip).into(), port(&bytes)))
+        } else if family == this.eval_libc_i32("AF_INET6")? && bytes.len() >= 28 {
+            let ip = <[u8; 16]>::try_from(&bytes[8..24]).unwrap();
+            let flowinfo = this.uint_from_target_bytes(&bytes[4..8]) as u32;
+            let scope_id = this.uint_from_target_bytes(&bytes[24..28]) as u32;
+            let addr = SocketAddrV6::new(Ipv6Addr::from(ip), port(&bytes), flowinfo, scope_id);
+            Ok(addr.into())
+        } else if bytes.len() < 16 {
+            Err("EINVAL")
+        } else {
+            Err("EAFNOSUPPORT")
+        })
+    }
+
+    /// Whether `sockaddr` starts with its length, like on the BSDs.
+    fn has_sockaddr_len(&self) -> bool {
+        let target_os = &self.eval_context_ref().tcx.sess.target.target.target_os;
+        target_os == "macos" || target_os == "freebsd"
+    }
+
+    /// The address family stored in the first two bytes of a `sockaddr`.
+    fn sockaddr_family(&self, bytes: &[u8]) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_ref();
+        Ok(if this.has_sockaddr_len() {
+            // `sa_len: u8, sa_family: u8`
+            i32::from(bytes[1])
+        } else {
+            // `sa_family: u16`
+            this.uint_from_target_bytes(&bytes[0..2]) as i32
+        })
+    }
+
+    /// The bytes of the `sockaddr_in` or `sockaddr_in6` for `addr`.
+    fn sockaddr_bytes(&mut self, addr: SocketAddr) -> InterpResult<'tcx, Vec<u8>> {
+        let this = self.eval_context_mut();
+        let (family, mut bytes) = match addr {
+            SocketAddr::V4(addr) => {
+                let mut bytes = vec![0; 16];
+                bytes[4..8].copy_from_slice(&addr.ip().octets());
+                (this.eval_libc_i32("AF_INET")?, bytes)
+            }
+            SocketAddr::V6(addr) => {
+                // `std` keeps the flow information and scope ID in native byte order.
+                let u32_size = Size::from_bytes(4);
+                let flowinfo = this.uint_to_target_bytes(addr.flowinfo().into(), u32_size);
+                let scope_id = this.uint_to_target_bytes(addr.scope_id().into(), u32_size);
+                let mut bytes = vec![0; 28];
+                bytes[4..8].copy_from_slice(&flowinfo);
+                bytes[8..24].copy_from_slice(&addr.ip().octets());
+                bytes[24..28].copy_from_slice(&scope_id);
+                (this.eval_libc_i32("AF_INET6")?, bytes)
+            }
+        };
+        if this.has_sockaddr_len() {
+            bytes[0] = bytes.len() as u8;
+            bytes[1] = family as u8;
+        } else {
+            let family = this.uint_to_target_bytes(family as u128, Size::from_bytes(2));
+            bytes[0..2].copy_from_slice(&family);
+        }
+        bytes[2..4].copy_from_slice(&addr.port().to_be_bytes());
+        Ok(bytes)
+    }
+
+    /// Writes `addr` to the `sockaddr` at `addr_op`, whose size is stored in the `socklen_t` that
+    /// `len_op` points to. Like the real implementation, we truncate the address if the buffer is
+    /// too small, and store the full size in `len_op`. A null `addr_op` is ignored.
+    fn write_sockaddr(
+        &mut self,
+        addr: SocketAddr,
+        addr_op: OpTy<'tcx, Tag>,
+        len_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let addr_ptr = this.read_scalar(addr_op)?.not_undef()?;
+        if this.is_null(addr_ptr)? {
+            return Ok(());
+        }
+        let mut bytes = this.sockaddr_bytes(addr)?;
+        let full_len = bytes.len() as u32;
+
+        let len_place = this.deref_operand(len_op)?;
+        let capacity = this.read_scalar(len_place.into())?.to_u32()?;
+        bytes.truncate(capacity as usize);
+        this.memory.write_bytes(addr_ptr, bytes)?;
+        this.write_scalar(Scalar::from_u32(full_len), len_place.into())
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    fn socket(
+        &mut self,
+        domain_op: OpTy<'tcx, Tag>,
+        type_op: OpTy<'tcx, Tag>,
+        protocol_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let domain = this.read_scalar(domain_op)?.to_i32()?;
+        let mut ty = this.read_scalar(type_op)?.to_i32()?;
+        let protocol = this.read_scalar(protocol_op)?.to_i32()?;
+
+        let ipv6 = if domain == this.eval_libc_i32("AF_INET")? {
+            false
+        } else if domain == this.eval_libc_i32("AF_INET6")? {
+            true
+        } else {
+            throw_unsup_format!("unsupported socket domain {}", domain);
+        };
+        let mut nonblocking = false;
+        let target_os = &this.tcx.sess.target.target.target_os;
+        if target_os == "linux" || target_os == "android" || target_os == "freebsd" {
+            // We ignore `SOCK_CLOEXEC` because `exec` is not supported anyway.
+            ty &= !this.eval_libc_i32("SOCK_CLOEXEC")?;
+            let sock_nonblock = this.eval_libc_i32("SOCK_NONBLOCK")?;
+            if ty & sock_nonblock != 0 {
+                nonblocking = true;
+                ty &= !sock_nonblock;
+            }
+        }
+        let udp = if ty == this.eval_libc_i32("SOCK_STREAM")? {
+            false
+        } else if ty == this.eval_libc_i32("SOCK_DGRAM")? {
+            true
+        } else {
+            throw_unsup_format!("unsupported socket type {:#x}", ty);
+        };
+        let default_protocol = this.eval_libc_i32(if udp { "IPPROTO_UDP" } else { "IPPROTO_TCP" })?;
+        if protocol != 0 && protocol != default_protocol {
+            throw_unsup_format!("unsupported socket protocol {}", protocol);
+        }
+
+        let socket = Socket {
+            ipv6,
+            udp,
+            nonblocking,
+            local: None,
+            port: None,
+            state: SocketState::Unconnected,
+        };
+        Ok(this.machine.file_handler.insert_fd(Box::new(socket)))
+    }
+
+    fn bind(
+        &mut self,
+        fd_op: OpTy<'tcx, Tag>,
+        addr_op: OpTy<'tcx, Tag>,
+        len_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let addr = this.read_scalar(addr_op)?.not_undef()?;
+        let len = this.read_scalar(len_op)?.to_u32()?;
+        let addr = match this.read_sockaddr(addr, len.into())? {
+            Ok(addr) => addr,
+            Err(errno) => return this.socket_result(Err(errno)),
+        };
+
+        this.with_socket(fd_op, |socket, handler| {
+            Ok(if socket.port.is_some() || socket.local.is_some() {
+                Err("EINVAL")
+            } else if addr.is_ipv6() != socket.ipv6 {
+                Err("EAFNOSUPPORT")
+            } else if !addr.ip().is_loopback() && !addr.ip().is_unspecified() {
+                // The loopback interface is the only one we have.
+                Err("EADDRNOTAVAIL")
+            } else {
+                handler.bind_port(socket.udp, addr.port()).map(|(port, entry)| {
+                    socket.local = Some(SocketAddr::new(addr.ip(), port));
+                    socket.port = Some(entry);
+                    0
+                })
+            })
+        })
+    }
+
+    fn listen(
+        &mut self,
+        fd_op: OpTy<'tcx, Tag>,
+        backlog_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let backlog = this.read_scalar(backlog_op)?.to_i32()?;
+        // Like Linux, we treat non-positive backlogs as 1 (and do not limit large ones).
+        let backlog = usize::try_from(backlog).unwrap_or(0).max(1);
+
+        this.with_socket(fd_op, |socket, handler| {
+            if socket.udp {
+                return Ok(Err("EOPNOTSUPP"));
+            }
+            if let SocketState::Connected { .. } = socket.state {
+                return Ok(Err("EINVAL"));
+            }
+            // Listening on an unbound socket binds it to an ephemeral port.
+            if socket.port.is_none() {
+                match handler.bind_port(false, 0) {
+                    Ok((port, entry)) => {
+                        let ip = if socket.ipv6 {
+                            Ipv6Addr::UNSPECIFIED.into()
+                        } else {
+                            Ipv4Addr::UNSPECIFIED.into()
+                        };
+                        socket.local = Some(SocketAddr::new(ip, port));
+                        socket.port = Some(entry);
+                    }
+                    Err(errno) => return Ok(Err(errno)),
+                }
+            }
+            socket.port.as_ref().unwrap().borrow_mut().listening = Some(backlog);
+            socket.state = SocketState::Listening;
+            Ok(Ok(0))
+        })
+    }
+
+    fn connect(
+        &mut self,
+        fd_op: OpTy<'tcx, Tag>,
+        addr_op: OpTy<'tcx, Tag>,
+        len_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let addr = this.read_scalar(addr_op)?.not_undef()?;
+        let len = this.read_scalar(len_op)?.to_u32()?;
+        let addr = match this.read_sockaddr(addr, len.into())? {
+            Ok(addr) => addr,
+            Err(errno) => return this.socket_result(Err(errno)),
+        };
+
+        this.with_socket(fd_op, |socket, handler| {
+            if addr.is_ipv6() != socket.ipv6 {
+                return Ok(Err("EAFNOSUPPORT"));
+            }
+            if !addr.ip().is_loopback() && !addr.ip().is_unspecified() {
+                return Ok(Err("ENETUNREACH"));
+            }
+            if socket.udp {
+                // This just sets the default destination (and binds the socket).
+                if let Err(errno) = handler.autobind(socket) {
+                    return Ok(Err(errno));
+                }
+                socket.state = SocketState::Associated { peer: addr };
+                return Ok(Ok(0));
+            }
+            match socket.state {
+                SocketState::Connected { .. } => return Ok(Err("EISCONN")),
+                SocketState::Listening => return Ok(Err("EINVAL")),
+                SocketState::Unconnected => {}
+                SocketState::Associated { .. } => bug!("TCP socket with a default destination"),
+            }
+            let listener = match handler.ports.get(&(false, addr.port())) {
+                Some(port) if port.borrow().in_use => port.clone(),
+                _ => return Ok(Err("ECONNREFUSED")),
+            };
+            match listener.borrow().listening {
+                Some(backlog) if listener.borrow().pending.len() < backlog => {}
+                _ => return Ok(Err("ECONNREFUSED")),
+            }
+            // Connecting binds the socket to an ephemeral port, if it is not bound yet.
+            if let Err(errno) = handler.autobind(socket) {
+                return Ok(Err(errno));
+            }
+            let local = socket.local.unwrap();
+            // Connecting to the unspecified address connects to the local host.
+            let server_addr = if addr.ip().is_unspecified() {
+                SocketAddr::new(loopback(socket.ipv6), addr.port())
+            } else {
+                addr
+            };
+
+            let to_server = Rc::new(RefCell::new(Pipe::default()));
+            let to_client = Rc::new(RefCell::new(Pipe::default()));
+            listener.borrow_mut().pending.push_back(Socket {
+                ipv6: socket.ipv6,
+                udp: false,
+                nonblocking: false,
+                local: Some(server_addr),
+                port: None,
+                state: SocketState::Connected {
+                    peer: local,
+                    read: to_server.clone(),
+                    write: to_client.clone(),
+                },
+            });
+            socket.state =
+                SocketState::Connected { peer: server_addr, read: to_client, write: to_server };
+            Ok(Ok(0))
+        })
+    }
+
+    fn accept(
+        &mut self,
+        fd_op: OpTy<'tcx, Tag>,
+        addr_op: OpTy<'tcx, Tag>,
+        len_op: OpTy<'tcx, Tag>,
+        flags_op: Option<OpTy<'tcx, Tag>>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let mut nonblocking = false;
+        if let Some(flags_op) = flags_op {
+            let mut flags = this.read_scalar(flags_op)?.to_i32()?;
+            // We ignore `SOCK_CLOEXEC` because `exec` is not supported anyway.
+            flags &= !this.eval_libc_i32("SOCK_CLOEXEC")?;
+            let sock_nonblock = this.eval_libc_i32("SOCK_NONBLOCK")?;
+            if flags & sock_nonblock != 0 {
+                nonblocking = true;
+                flags &= !sock_nonblock;
+            }
+            if flags != 0 {
+                throw_unsup_format!("unsupported flags {:#x} for `accept4`", flags);
+            }
+        }
+
+        let mut accepted = None;
+        let result = this.with_socket(fd_op, |socket, _handler| {
+            match socket.state {
+                SocketState::Listening => {}
+                _ => return Ok(Err("EINVAL")),
+            }
+            let mut port = socket.port.as_ref().unwrap().borrow_mut();
+            match port.pending.pop_front() {
+                Some(mut connection) => {
+                    connection.nonblocking = nonblocking;
+                    accepted = Some(connection);
+                    Ok(Ok(0))
+                }
+                None if socket.nonblocking => Ok(Err("EWOULDBLOCK")),
+                None =>
+                    throw_machine_stop!(TerminationInfo::Deadlock(
+                        "accepting a connection on a socket that nobody connects to".to_owned()
+                    )),
+            }
+        })?;
+        let connection = match accepted {
+            Some(connection) => connection,
+            None => return Ok(result),
+        };
+
+        if let SocketState::Connected { peer, .. } = connection.state {
+            this.write_sockaddr(peer, addr_op, len_op)?;
+        }
+        Ok(this.machine.file_handler.insert_fd(Box::new(connection)))
+    }
+
+    /// Implements `getsockname` (if `peer` is false) and `getpeername` (if `peer` is true).
+    fn getsockname(
+        &mut self,
+        fd_op: OpTy<'tcx, Tag>,
+        addr_op: OpTy<'tcx, Tag>,
+        len_op: OpTy<'tcx, Tag>,
+        peer: bool,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let mut addr = None;
+        let result = this.with_socket(fd_op, |socket, _handler| {
+            Ok(if peer {
+                match socket.state {
+                    SocketState::Connected { peer, .. } | SocketState::Associated { peer } => {
+                        addr = Some(peer);
+                        Ok(0)
+                    }
+                    _ => Err("ENOTCONN"),
+                }
+            } else {
+                // An unbound socket reports the unspecified address with port 0.
+                let unbound = SocketAddr::new(
+                    if socket.ipv6 {
+                        Ipv6Addr::UNSPECIFIED.into()
+                    } else {
+                        Ipv4Addr::UNSPECIFIED.into()
+                    },
+                    0,
+                );
+                addr = Some(socket.local.unwrap_or(unbound));
+                Ok(0)
+            })
+        })?;
+        if let Some(addr) = addr {
+            this.write_sockaddr(addr, addr_op, len_op)?;
+        }
+        Ok(result)
+    }
+
+    fn setsockopt(
+        &mut self,
+        fd_op: OpTy<'tcx, Tag>,
+        level_op: OpTy<'tcx, Tag>,
+        name_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let level = this.read_scalar(level_op)?.to_i32()?;
+        let name = this.read_scalar(name_op)?.to_i32()?;
+        // These options do not make a difference for our emulated sockets, so we accept and
+        // ignore them.
+        let ignored = if level == this.eval_libc_i32("SOL_SOCKET")? {
+            name == this.eval_libc_i32("SO_REUSEADDR")?
+                || (this.tcx.sess.target.target.target_os == "macos"
+                    && name == this.eval_libc_i32("SO_NOSIGPIPE")?)
+        } else if level == this.eval_libc_i32("IPPROTO_TCP")? {
+            name == this.eval_libc_i32("TCP_NODELAY")?
+        } else {
+            false
+        };
+        if !ignored {
+            throw_unsup_format!("unsupported socket option {} at level {}", name, level);
+        }
+        this.with_socket(fd_op, |_socket, _handler| Ok(Ok(0)))
+    }
+
+    fn shutdown(
+        &mut self,
+        fd_op: OpTy<'tcx, Tag>,
+        how_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let how = this.read_scalar(how_op)?.to_i32()?;
+        let (read, write) = if how == this.eval_libc_i32("SHUT_RD")? {
+            (true, false)
+        } else if how == this.eval_libc_i32("SHUT_WR")? {
+            (false, true)
+        } else if how == this.eval_libc_i32("SHUT_RDWR")? {
+            (true, true)
+        } else {
+            return this.socket_result(Err("EINVAL"));
+        };
+
+        this.with_socket(fd_op, |socket, _handler| {
+            Ok(match socket.state {
+                SocketState::Connected { .. } | SocketState::Associated { .. } => {
+                    socket.shutdown(read, write);
+                    Ok(0)
+                }
+                _ => Err("ENOTCONN"),
+            })
+        })
+    }
+
+    /// Resolves host names without asking the host: `localhost` and numeric addresses resolve to
+    /// the corresponding loopback (or unspecified) addresses, everything else fails with
+    /// `EAI_NONAME`. This keeps name resolution deterministic, and we cannot talk to other hosts
+    /// anyway.
+    fn getaddrinfo(
+        &mut self,
+        node_op: OpTy<'tcx, Tag>,
+        service_op: OpTy<'tcx, Tag>,
+        hints_op: OpTy<'tcx, Tag>,
+        res_op: OpTy<'tcx, Tag>,
+    ) -> InterpResult<'tcx, i32> {
+        let this = self.eval_context_mut();
+
+        let node = this.read_scalar(node_op)?.not_undef()?;
+        let service = this.read_scalar(service_op)?.not_undef()?;
+        let hints = this.read_scalar(hints_op)?.not_undef()?;
+
+        let node = if this.is_null(node)? {
+            None
+        } else {
+            Some(this.read_os_str_from_c_str(node)?.to_string_lossy().into_owned())
+        };
+        let service = if this.is_null(service)? {
+            None
+        } else {
+            Some(this.read_os_str_from_c_str(service)?.to_string_lossy().into_owned())
+        };
+
+        let (mut flags, mut family, mut socktype, mut protocol) = (0, 0, 0, 0);
+        if !this.is_null(hints)? {
+            let hints = this.deref_operand(hints_op)?;
+            let read_field = |name: &str| -> InterpResult<'tcx, i32> {
+                let field = this.field_by_name(hints, name)?;
+                this.read_scalar(field.into())?.to_i32()
+            };
+            flags = read_field("ai_flags")?;
+            family = read_field("ai_family")?;
+            socktype = read_field("ai_socktype")?;
+            protocol = read_field("ai_protocol")?;
+        }
+        let af_inet = this.eval_libc_i32("AF_INET")?;
+        let af_inet6 = this.eval_libc_i32("AF_INET6")?;
+        if family != 0 && family != af_inet && family != af_inet6 {
+            return this.eval_libc_i32("EAI_FAMILY");
+        }
+
+        let port = match &service {
+            None => 0,
+            Some(service) =>
+                match service.parse::<u16>() {
+                    Ok(port) => port,
+                    // We do not know any service names.
+                    Err(_) => return this.eval_libc_i32("EAI_SERVICE"),
+                },
+        };
+        let ips: Vec<IpAddr> = match &node {
+            None if service.is_none() => return this.eval_libc_i32("EAI_NONAME"),
+            None if flags & this.eval_libc_i32("AI_PASSIVE")? != 0 =>
+                vec![Ipv4Addr::UNSPECIFIED.into(), Ipv6Addr::UNSPECIFIED.into()],
+            None => vec![loopback(false), loopback(true)],
+            Some(node) =>
+                match node.parse::<IpAddr>() {
+                    Ok(ip) => vec![ip],
+                    Err(_)
+                        if node.eq_ignore_ascii_case("localhost")
+                            && flags & this.eval_libc_i32("AI_NUMERICHOST")? == 0 =>
+                        vec![loopback(false), loopback(true)],
+                    Err(_) => return this.eval_libc_i32("EAI_NONAME"),
+                },
+        };
+        let addrs = ips.into_iter().map(|ip| SocketAddr::new(ip, port)).filter(|addr| {
+            family == 0 || (family == af_inet6) == addr.is_ipv6()
+        });
+
+        // Build the list back to front, in memory that `freeaddrinfo` releases.
+        let addrinfo_layout = this.libc_ty_layout("addrinfo")?;
+        let kind = MiriMemoryKind::C.into();
+        let mut next = Scalar::null_ptr(this);
+        for addr in addrs.collect::<Vec<_>>().into_iter().rev() {
+            let bytes = this.sockaddr_bytes(addr)?;
+            let addrlen = bytes.len() as i64;
+            let size = Size::from_bytes(bytes.len() as u64);
+            let sockaddr_ptr = this.memory.allocate(size, Align::from_bytes(8).unwrap(), kind);
+            this.memory.write_bytes(sockaddr_ptr.into(), bytes)?;
+            let canonname = match &node {
+                Some(node) if flags & this.eval_libc_i32("AI_CANONNAME")? != 0 =>
+                    Scalar::from(this.alloc_os_str_as_c_str(node.as_ref(), kind)),
+                _ => Scalar::null_ptr(this),
+            };
+
+            let addrinfo = this.allocate(addrinfo_layout, kind);
+            this.write_int_field(addrinfo, "ai_flags", 0)?;
+            let family = if addr.is_ipv6() { af_inet6 } else { af_inet };
+            this.write_int_field(addrinfo, "ai_family", family.into())?;
+            this.write_int_field(addrinfo, "ai_socktype", socktype.into())?;
+            this.write_int_field(addrinfo, "ai_protocol", protocol.into())?;
+            this.write_int_field(addrinfo, "ai_addrlen", addrlen)?;
+            for &(name, ptr) in
+                &[("ai_addr", sockaddr_ptr.into()), ("ai_canonname", canonname), ("ai_next", next)]
+            {
+                let field = this.field_by_name(addrinfo, name)?;
+                this.write_scalar(ptr, field.into())?;
+            }
+            next = addrinfo.ptr;
+        }
+        if this.is_null(next)? {
+            // All addresses were filtered out by the requested family.
+            return this.eval_libc_i32("EAI_NONAME");
+        }
+
+        let res = this.deref_operand(res_op)?;
+        this.write_scalar(next, res.into())?;
+        Ok(0)
+    }
+
+    fn freeaddrinfo(&mut self, res_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let mut addrinfo_op = res_op;
+        while !this.is_null(this.read_scalar(addrinfo_op)?.not_undef()?)? {
+            let addrinfo = this.deref_operand(addrinfo_op)?;
+            for name in &["ai_addr", "ai_canonname"] {
+                let field = this.field_by_name(addrinfo, name)?;
+                let ptr = this.read_scalar(field.into())?.not_undef()?;
+                if !this.is_null(ptr)? {
+                    this.memory.deallocate(ptr.assert_ptr(), None, MiriMemoryKind::C.into())?;
+                }
+            }
+            let next = this.field_by_name(addrinfo, "ai_next")?;
+            let next = this.read_immediate(next.into())?;
+            let ptr = addrinfo.ptr.assert_ptr();
+            this.memory.deallocate(ptr, None, MiriMemoryKind::C.into())?;
+            addrinfo_op = next.into();
+        }
+        Ok(())
+    }
+
+    /// Returns a (static) message for a `getaddrinfo` error code.
+    fn gai_strerror(&mut self, code_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, Scalar<Tag>> {
+        let this = self.eval_context_mut();
+
+        let code = this.read_scalar(code_op)?.to_i32()?;
+        let msg = if code == this.eval_libc_i32("EAI_NONAME")? {
+            "Name or service not known"
+        } else if code == this.eval_libc_i32("EAI_SERVICE")? {
+            "Servname not supported for ai_socktype"
+        } else if code == this.eval_libc_i32("EAI_FAMILY")? {
+            "ai_family not supported"
+        } else {
+            "Unknown error"
+        };
+        Ok(this.static_c_str(msg).into())
+    }
+
+    /// Checks that the flags passed to `send` or `recv` are ones we can ignore.
+    fn check_socket_io_flags(&mut self, flags_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        let mut flags = this.read_scalar(flags_op)?.to_i32()?;
+        let target_os = &this.tcx.sess.target.target.target_os;
+        if target_os == "linux" || target_os == "android" || target_os == "freebsd" {
+            // We never raise `SIGPIPE` anyway.
+            flags &= !this.eval_libc_i32("MSG_NOSIGNAL")?;
+        }
+        if flags != 0 {
+            throw_unsup_format!("unsupported flags {:#x} for socket I/O", flags);
+        }
+        Ok(())
+    }
+
+    /// Returns whether `fd_op` refers to a UDP socket.
+    fn is_udp_socket(&mut self, fd_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx, bool> {
+        let this = self.eval_context_mut();
+        let fd = this.read_scalar(fd_op)?.to_i32()?;
+        Ok(match this.machine.file_handler.handles.get_mut(&fd) {
+            Some(file_descriptor) => file_descriptor.as_socket_mut().map_or(false, |s| s.udp),
+            None => false,
+        })
+    }
+
+    /// Implements `send` and `sendto`. `dest` are the address arguments of `sendto`, which are
+    /// ignored for TCP sockets.
+    fn send(
+        &mut self,
+        fd_op: OpTy<'tcx, Tag>,
+        buf_op: OpTy<'tcx, Tag>,
+        len_op: OpTy<'tcx, Tag>,
+        flags_op: OpTy<'tcx, Tag>,
+        dest: Option<(OpTy<'tcx, Tag>, OpTy<'tcx, Tag>)>,
+    ) -> InterpResult<'tcx, i64> {
+        let this = self.eval_context_mut();
+
+        this.check_socket_io_flags(flags_op)?;
+        if !this.is_udp_socket(fd_op)? {
+            return this.write(fd_op, buf_op, len_op);
+        }
+
+        let buf = this.read_scalar(buf_op)?.not_undef()?;
+        let len = this.read_scalar(len_op)?.to_machine_usize(&*this.tcx)?;
+        let dest = match dest {
+            Some((addr_op, addr_len_op)) => {
+                let addr = this.read_scalar(addr_op)?.not_undef()?;
+                if this.is_null(addr)? {
+                    None
+                } else {
+                    let addr_len = this.read_scalar(addr_len_op)?.to_u32()?;
+                    match this.read_sockaddr(addr, addr_len.into())? {
+                        Ok(addr) => Some(addr),
+                        Err(errno) => return this.socket_result(Err(errno)),
+                    }
+                }
+            }
+            None => None,
+        };
+        let bytes = this.memory.read_bytes(buf, Size::from_bytes(len))?.to_owned();
+
+        // Decide the fate of the datagram up front, so that it only depends on the seed and the
+        // order of the `send` calls.
+        let handler = &this.machine.socket_handler;
+        let (drop_rate, reorder_rate) = (handler.udp_drop_rate, handler.udp_reorder_rate);
+        let rng = this.memory.extra.rng.get_mut();
+        let dropped = rng.gen_bool(drop_rate);
+        let reorder = if rng.gen_bool(reorder_rate) { Some(rng.gen::<usize>()) } else { None };
+
+        let result = this.with_socket(fd_op, |socket, handler| {
+            let dest = match (dest, &socket.state) {
+                (Some(dest), _) => dest,
+                (None, SocketState::Associated { peer }) => *peer,
+                (None, _) => return Ok(Err("EDESTADDRREQ")),
+            };
+            if dest.is_ipv6() != socket.ipv6 {
+                return Ok(Err("EAFNOSUPPORT"));
+            }
+            if !dest.ip().is_loopback() && !dest.ip().is_unspecified() {
+                return Ok(Err("ENETUNREACH"));
+            }
+            // Sending on an unbound socket binds it to an ephemeral port.
+            if let Err(errno) = handler.autobind(socket) {
+                return Ok(Err(errno));
+            }
+            let mut source = socket.local.unwrap();
+            if source.ip().is_unspecified() {
+                source.set_ip(loopback(socket.ipv6));
+            }
+            // Like on a real network, datagrams sent to a port nobody is bound to just get lost.
+            let port = handler.ports.get(&(true, dest.port())).filter(|port| port.borrow().in_use);
+            if let (Some(port), false) = (port, dropped) {
+                let datagrams = &mut port.borrow_mut().datagrams;
+                let position = reorder.map_or(datagrams.len(), |r| r % (datagrams.len() + 1));
+                datagrams.insert(position, (source, bytes));
+            }
+            Ok(Ok(i64::try_from(len).unwrap()))
+        })?;
+        Ok(result)
+    }
+
+    /// Implements `recv` and `recvfrom`. `src` are the address arguments of `recvfrom`; without
+    /// them, or on a TCP socket, this is the same as `read`.
+    fn recv(
+        &mut self,
+        fd_op: OpTy<'tcx, Tag>,
+        buf_op: OpTy<'tcx, Tag>,
+        len_op: OpTy<'tcx, Tag>,
+        flags_op: OpTy<'tcx, Tag>,
+        src: Option<(OpTy<'tcx, Tag>, OpTy<'tcx, Tag>)>,
+    ) -> InterpResult<'tcx, i64> {
+        let this = self.eval_context_mut();
+
+        this.check_socket_io_flags(flags_op)?;
+        let (addr_op, addr_len_op) = match src {
+            Some(src) if this.is_udp_socket(fd_op)? => src,
+            _ => return this.read(fd_op, buf_op, len_op),
+        };
+
+        let buf = this.read_scalar(buf_op)?.not_undef()?;
+        let len = this.read_scalar(len_op)?.to_machine_usize(&*this.tcx)?;
+        let mut bytes = vec![0; usize::try_from(len).unwrap()];
+        let mut source = None;
+        let result = this.with_socket(fd_op, |socket, _handler| {
+            Ok(match socket.recv_datagram(&mut bytes)? {
+                Ok((count, addr)) => {
+                    source = Some(addr);
+                    Ok(i64::try_from(count).unwrap())
+                }
+                Err(_) => Err("EWOULDBLOCK"),
+            })
+        })?;
+        if let Some(source) = source {
+            bytes.truncate(usize::try_from(result).unwrap());
+            this.memory.write_bytes(buf, bytes)?;
+            this.write_sockaddr(source, addr_op, addr_len_op)?;
+        }
+        Ok(result)
+    }
+}
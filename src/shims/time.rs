@@ -81,4 +81,25 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
 
         Ok(0)
     }
+
+    // Foreign function used by windows
+    #[allow(non_snake_case)]
+    fn GetSystemTimeAsFileTime(&mut self, filetime_op: OpTy<'tcx, Tag>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+
+        this.check_no_isolation("GetSystemTimeAsFileTime")?;
+
+        // A `FILETIME` counts 100ns intervals since the start of 1601.
+        const SECONDS_1601_TO_1970: u64 = 11_644_473_600;
+        let duration = get_time()? + Duration::from_secs(SECONDS_1601_TO_1970);
+        let ticks = duration.as_secs() * 10_000_000 + u64::from(duration.subsec_nanos() / 100);
+
+        let filetime = this.deref_operand(filetime_op)?;
+        let low = this.mplace_field(filetime, 0)?;
+        this.write_scalar(Scalar::from_u32(ticks as u32), low.into())?;
+        let high = this.mplace_field(filetime, 1)?;
+        this.write_scalar(Scalar::from_u32((ticks >> 32) as u32), high.into())?;
+
+        Ok(())
+    }
 }
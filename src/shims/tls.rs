@@ -5,7 +5,10 @@ use std::collections::BTreeMap;
 use rustc::{ty, ty::layout::HasDataLayout};
 use rustc_target::abi::LayoutOf;
 
-use crate::{HelpersEvalContextExt, InterpResult, MPlaceTy, Scalar, StackPopCleanup, Tag};
+use crate::{
+    AllocCheck, AllocId, HelpersEvalContextExt, InterpResult, MPlaceTy, MiriEvalContext, Scalar,
+    StackPopCleanup, Tag, TerminationInfo,
+};
 
 pub type TlsKey = u128;
 
@@ -16,6 +19,8 @@ pub struct TlsEntry<'tcx> {
     /// Will eventually become a map from thread IDs to `Scalar`s, if we ever support more than one thread.
     data: Option<Scalar<Tag>>,
     dtor: Option<ty::Instance<'tcx>>,
+    /// Whether the destructor ran, and no new value was stored since.
+    destroyed: bool,
 }
 
 #[derive(Debug)]
@@ -25,6 +30,12 @@ pub struct TlsData<'tcx> {
 
     /// pthreads-style thread-local storage.
     keys: BTreeMap<TlsKey, TlsEntry<'tcx>>,
+
+    /// The destructor that is currently running, and the key it was registered for.
+    running_dtor: Option<(TlsKey, ty::Instance<'tcx>)>,
+
+    /// The heap allocations created by destructors, with the destructor that created them.
+    dtor_allocations: Vec<(AllocId, TlsKey, ty::Instance<'tcx>)>,
 }
 
 impl<'tcx> Default for TlsData<'tcx> {
@@ -32,6 +43,8 @@ impl<'tcx> Default for TlsData<'tcx> {
         TlsData {
             next_key: 1, // start with 1 as we must not use 0 on Windows
             keys: Default::default(),
+            running_dtor: None,
+            dtor_allocations: Vec::new(),
         }
     }
 }
@@ -40,7 +53,7 @@ impl<'tcx> TlsData<'tcx> {
     pub fn create_tls_key(&mut self, dtor: Option<ty::Instance<'tcx>>) -> TlsKey {
         let new_key = self.next_key;
         self.next_key += 1;
-        self.keys.insert(new_key, TlsEntry { data: None, dtor }).unwrap_none();
+        self.keys.insert(new_key, TlsEntry { data: None, dtor, destroyed: false }).unwrap_none();
         trace!("New TLS key allocated: {} with dtor {:?}", new_key, dtor);
         new_key
     }
@@ -61,6 +74,19 @@ impl<'tcx> TlsData<'tcx> {
         cx: &impl HasDataLayout,
     ) -> InterpResult<'tcx, Scalar<Tag>> {
         match self.keys.get(&key) {
+            Some(&TlsEntry { destroyed: true, dtor: Some(dtor), .. })
+                if self.running_dtor.is_some() && is_local_dtor(dtor) =>
+            {
+                // Only the destructors of other keys can still run at this point. When they get
+                // `NULL`, they usually create a fresh value, and the state of the destroyed one
+                // is lost.
+                let (running_key, running_dtor) = self.running_dtor.unwrap();
+                throw_machine_stop!(TerminationInfo::Misuse(format!(
+                    "TLS key {} is accessed by the destructor `{}` (of TLS key {}) after its own \
+                    destructor already ran",
+                    key, running_dtor, running_key,
+                )));
+            }
             Some(&TlsEntry { data, .. }) => {
                 trace!("TLS key {} loaded: {:?}", key, data);
                 Ok(data.unwrap_or_else(|| Scalar::ptr_null(cx).into()))
@@ -71,9 +97,13 @@ impl<'tcx> TlsData<'tcx> {
 
     pub fn store_tls(&mut self, key: TlsKey, new_data: Option<Scalar<Tag>>) -> InterpResult<'tcx> {
         match self.keys.get_mut(&key) {
-            Some(&mut TlsEntry { ref mut data, .. }) => {
+            Some(&mut TlsEntry { ref mut data, ref mut destroyed, .. }) => {
                 trace!("TLS key {} stored: {:?}", key, new_data);
                 *data = new_data;
+                if new_data.is_some() {
+                    // The destructor will run again.
+                    *destroyed = false;
+                }
                 Ok(())
             }
             None => throw_unsup!(TlsOutOfBounds),
@@ -109,7 +139,7 @@ impl<'tcx> TlsData<'tcx> {
             Some(key) => Excluded(key),
             None => Unbounded,
         };
-        for (&key, &mut TlsEntry { ref mut data, dtor }) in
+        for (&key, &mut TlsEntry { ref mut data, dtor, .. }) in
             thread_local.range_mut((start, Unbounded))
         {
             if let Some(data_scalar) = *data {
@@ -122,6 +152,45 @@ impl<'tcx> TlsData<'tcx> {
         }
         None
     }
+
+    /// Remembers that the heap allocation `alloc_id` was created by the destructor that is
+    /// currently running, if any.
+    pub fn note_allocation(&mut self, alloc_id: AllocId) {
+        if let Some((key, dtor)) = self.running_dtor {
+            self.dtor_allocations.push((alloc_id, key, dtor));
+        }
+    }
+
+    /// Explains the leaked allocations that were created by destructors: these were probably stored
+    /// in a thread-local that was already destroyed.
+    pub fn dtor_leak_notes(&self, ecx: &MiriEvalContext<'_, 'tcx>) -> Vec<String> {
+        self.dtor_allocations
+            .iter()
+            .filter(|(alloc_id, ..)| {
+                ecx.memory.get_size_and_align(*alloc_id, AllocCheck::Live).is_ok()
+            })
+            .map(|(alloc_id, key, dtor)| {
+                format!(
+                    "{:?} was allocated by the destructor `{}` of TLS key {} and never freed",
+                    alloc_id, dtor, key,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Whether `dtor` destroys values of the local crate: it is defined by the local crate, or it is
+/// generic over a type that involves one of the local crate's types (like the destructors of
+/// `thread_local!`). The standard library's own thread-locals are harmlessly recreated when they
+/// are used after their destruction, so accessing those is not an error.
+fn is_local_dtor(dtor: ty::Instance<'_>) -> bool {
+    dtor.def_id().is_local()
+        || dtor.substs.types().any(|ty| {
+            ty.walk().any(|ty| match ty.kind {
+                ty::Adt(adt, _) => adt.did.is_local(),
+                _ => false,
+            })
+        })
 }
 
 impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
@@ -143,7 +212,16 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
             )?;
 
             // step until out of stackframes
+            this.machine.tls.running_dtor = Some((key, instance));
             this.run()?;
+            this.machine.tls.running_dtor = None;
+            // The destructor may have stored a new value (then it runs again), or even deleted
+            // the key.
+            if let Some(entry) = this.machine.tls.keys.get_mut(&key) {
+                if entry.data.is_none() {
+                    entry.destroyed = true;
+                }
+            }
 
             dtor = match this.machine.tls.fetch_tls_dtor(Some(key)) {
                 dtor @ Some(_) => dtor,
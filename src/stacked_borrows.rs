@@ -2,11 +2,14 @@
 //! for further information.
 
 use std::cell::RefCell;
+use std::cmp;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::num::NonZeroU64;
 use std::rc::Rc;
 
+use smallvec::SmallVec;
+
 use rustc_hir::Mutability;
 use rustc::mir::RetagKind;
 use rustc::ty::{self, layout::Size};
@@ -42,6 +45,10 @@ pub enum Permission {
     SharedReadWrite,
     /// Grants shared read-only access.
     SharedReadOnly,
+    /// A two-phase borrow that was not activated yet: grants unique mutable access, but reads
+    /// through other pointers do not disable it. The first write through it activates it, which
+    /// turns it into `Unique` and removes all `SharedReadOnly`.
+    Reserved,
     /// Grants no access, but separates two groups of SharedReadWrite so they are not
     /// all considered mutually compatible.
     Disabled,
@@ -69,14 +76,25 @@ impl fmt::Debug for Item {
     }
 }
 
+/// The items of a stack. Most stacks only have a few items, which are then stored inline.
+type Items = SmallVec<[Item; 4]>;
+
 /// Extra per-location state.
+///
+/// The items are shared copy-on-write: cloning a stack (when a range of the `RangeMap` gets
+/// split) shares them, and stacks with the same items are interned, so that they share them even
+/// if they came about independently (in different allocations, or by copying memory). Comparing
+/// stacks that share their items is cheap, which helps merging ranges again.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Stack {
     /// Used *mostly* as a stack; never empty.
     /// Invariants:
-    /// * Above a `SharedReadOnly` there can only be more `SharedReadOnly`.
+    /// * Above a `SharedReadOnly` there can only be more `SharedReadOnly`, or a `Reserved` that
+    ///   was created while the `SharedReadOnly` existed (and then only `SharedReadOnly`).
+    ///   Activating the `Reserved` removes *all* `SharedReadOnly` from the stack, including the
+    ///   ones below it, so the stack has no `SharedReadOnly` at all right after an activation.
     /// * Except for `Untagged`, no tag occurs in the stack more than once.
-    borrows: Vec<Item>,
+    borrows: Rc<Items>,
 }
 
 /// Extra per-allocation state.
@@ -103,10 +121,42 @@ pub struct GlobalState {
     active_calls: HashSet<CallId>,
     /// The id to trace in this execution run
     tracked_pointer_tag: Option<PtrId>,
+    /// The id whose creation and uses are reported, for `-Zmiri-break-on-tag`.
+    break_on_tag: Option<PtrId>,
+    /// The items of all stacks, to share them between identical stacks.
+    stack_interner: RefCell<StackInterner>,
 }
 /// Memory extra state gives us interior mutable access to the global state.
 pub type MemoryExtra = Rc<RefCell<GlobalState>>;
 
+/// The distinct items of the stacks. Every stack's items are in here, and each stack that has the
+/// same items as another one shares them with it.
+#[derive(Debug, Default)]
+struct StackInterner {
+    items: HashSet<Rc<Items>>,
+    /// When `items` has this many entries, the ones that no stack uses any more are removed.
+    limit: usize,
+}
+
+impl StackInterner {
+    /// The number of entries at which unused entries are removed for the first time.
+    const MIN_LIMIT: usize = 1024;
+
+    /// Makes `stack` share its items with the other stacks that have the same items.
+    fn intern(&mut self, stack: &mut Stack) {
+        if let Some(items) = self.items.get(&stack.borrows) {
+            stack.borrows = Rc::clone(items);
+            return;
+        }
+        if self.items.len() >= self.limit {
+            // Items that are only referred to from here are not used by any stack.
+            self.items.retain(|items| Rc::strong_count(items) > 1);
+            self.limit = cmp::max(Self::MIN_LIMIT, 2 * self.items.len());
+        }
+        self.items.insert(Rc::clone(&stack.borrows));
+    }
+}
+
 /// Indicates which kind of access is being performed.
 #[derive(Copy, Clone, Hash, PartialEq, Eq)]
 pub enum AccessKind {
@@ -150,22 +200,36 @@ impl fmt::Display for RefKind {
 
 /// Utilities for initialization and ID generation
 impl GlobalState {
-    pub fn new(tracked_pointer_tag: Option<PtrId>) -> Self {
+    pub fn new(tracked_pointer_tag: Option<PtrId>, break_on_tag: Option<PtrId>) -> Self {
         GlobalState {
             next_ptr_id: NonZeroU64::new(1).unwrap(),
             base_ptr_ids: HashMap::default(),
             next_call_id: NonZeroU64::new(1).unwrap(),
             active_calls: HashSet::default(),
             tracked_pointer_tag,
+            break_on_tag,
+            stack_interner: RefCell::new(StackInterner::default()),
         }
     }
 
     fn new_ptr(&mut self) -> PtrId {
         let id = self.next_ptr_id;
         self.next_ptr_id = NonZeroU64::new(id.get() + 1).unwrap();
+        if Some(id) == self.break_on_tag {
+            register_diagnostic(NonHaltingDiagnostic::TouchedTag(id, "created"));
+        }
         id
     }
 
+    /// Reports a use of the tag `-Zmiri-break-on-tag` is about.
+    fn check_break_on_tag(&self, tag: Tag, action: &'static str) {
+        if let Tag::Tagged(id) = tag {
+            if Some(id) == self.break_on_tag {
+                register_diagnostic(NonHaltingDiagnostic::TouchedTag(id, action));
+            }
+        }
+    }
+
     pub fn new_call(&mut self) -> CallId {
         let id = self.next_call_id;
         trace!("new_call: Assigning ID {}", id);
@@ -219,6 +283,19 @@ impl Permission {
 }
 
 /// Core per-location operations: access, dealloc, reborrow.
+impl Stack {
+    fn new(item: Item) -> Self {
+        let mut items = Items::new();
+        items.push(item);
+        Stack { borrows: Rc::new(items) }
+    }
+
+    /// The items for mutation; they are copied first if they are shared with another stack.
+    fn borrows_mut(&mut self) -> &mut Items {
+        Rc::make_mut(&mut self.borrows)
+    }
+}
+
 impl<'tcx> Stack {
     /// Find the item granting the given kind of access to the given tag, and return where
     /// it is on the stack.
@@ -244,7 +321,7 @@ impl<'tcx> Stack {
             Permission::SharedReadOnly => bug!("Cannot use SharedReadOnly for writing"),
             Permission::Disabled => bug!("Cannot use Disabled for anything"),
             // On a write, everything above us is incompatible.
-            Permission::Unique => granting + 1,
+            Permission::Unique | Permission::Reserved => granting + 1,
             Permission::SharedReadWrite => {
                 // The SharedReadWrite *just* above us are compatible, to skip those.
                 let mut idx = granting + 1;
@@ -303,9 +380,25 @@ impl<'tcx> Stack {
             // Remove everything above the write-compatible items, like a proper stack. This makes sure read-only and unique
             // pointers become invalid on write accesses (ensures F2a, and ensures U2 for write accesses).
             let first_incompatible_idx = self.find_first_write_incompatible(granting_idx);
-            for item in self.borrows.drain(first_incompatible_idx..).rev() {
-                trace!("access: popping item {:?}", item);
-                Stack::check_protector(&item, Some(tag), global)?;
+            if first_incompatible_idx < self.borrows.len() {
+                for item in self.borrows_mut().drain(first_incompatible_idx..).rev() {
+                    trace!("access: popping item {:?}", item);
+                    Stack::check_protector(&item, Some(tag), global)?;
+                }
+            }
+            // Writing activates a reservation, which is now on top. The shared references that
+            // still exist from before the reservation become invalid, like creating a `Unique`
+            // would have made them.
+            if self.borrows[granting_idx].perm == Permission::Reserved {
+                let shared = |item: &Item| item.perm == Permission::SharedReadOnly;
+                for item in self.borrows.iter().filter(|item| shared(item)) {
+                    trace!("access: popping item {:?}", item);
+                    Stack::check_protector(item, Some(tag), global)?;
+                }
+                let borrows = self.borrows_mut();
+                borrows.retain(|item| !shared(item));
+                trace!("access: activating item {:?}", borrows.last());
+                borrows.last_mut().unwrap().perm = Permission::Unique;
             }
         } else {
             // On a read, *disable* all `Unique` above the granting item.  This ensures U2 for read accesses.
@@ -317,8 +410,8 @@ impl<'tcx> Stack {
             // reference and use that.
             // We *disable* instead of removing `Unique` to avoid "connecting" two neighbouring blocks of SRWs.
             for idx in ((granting_idx + 1)..self.borrows.len()).rev() {
-                let item = &mut self.borrows[idx];
-                if item.perm == Permission::Unique {
+                if self.borrows[idx].perm == Permission::Unique {
+                    let item = &mut self.borrows_mut()[idx];
                     trace!("access: disabling item {:?}", item);
                     Stack::check_protector(item, Some(tag), global)?;
                     item.perm = Permission::Disabled;
@@ -340,7 +433,7 @@ impl<'tcx> Stack {
         ))))?;
 
         // Step 2: Remove all items.  Also checks for protectors.
-        for item in self.borrows.drain(..).rev() {
+        for item in self.borrows_mut().drain(..).rev() {
             Stack::check_protector(&item, None, global)?;
         }
 
@@ -376,6 +469,12 @@ impl<'tcx> Stack {
             // be popped to (i.e., we insert it above all the write-compatible items).
             // This ensures F2b by adding the new item below any potentially existing `SharedReadOnly`.
             self.find_first_write_incompatible(granting_idx)
+        } else if new.perm == Permission::Reserved {
+            // A reservation only counts as a read access until it is activated, so that the
+            // pointers it is derived from (and the shared references that existed before it)
+            // can still be read from.
+            self.access(AccessKind::Read, derived_from, global)?;
+            self.borrows.len()
         } else {
             // A "safe" reborrow for a pointer that actually expects some aliasing guarantees.
             // Here, creating a reference actually counts as an access.
@@ -395,7 +494,7 @@ impl<'tcx> Stack {
             trace!("reborrow: avoiding adding redundant item {:?}", new);
         } else {
             trace!("reborrow: adding item {:?}", new);
-            self.borrows.insert(new_idx, new);
+            self.borrows_mut().insert(new_idx, new);
         }
 
         Ok(())
@@ -407,8 +506,8 @@ impl<'tcx> Stack {
 impl<'tcx> Stacks {
     /// Creates new stack with initial tag.
     fn new(size: Size, perm: Permission, tag: Tag, extra: MemoryExtra) -> Self {
-        let item = Item { perm, tag, protector: None };
-        let stack = Stack { borrows: vec![item] };
+        let mut stack = Stack::new(Item { perm, tag, protector: None });
+        extra.borrow().stack_interner.borrow_mut().intern(&mut stack);
 
         Stacks { stacks: RefCell::new(RangeMap::new(size, stack)), global: extra }
     }
@@ -423,7 +522,12 @@ impl<'tcx> Stacks {
         let global = self.global.borrow();
         let mut stacks = self.stacks.borrow_mut();
         for stack in stacks.iter_mut(ptr.offset, size) {
+            // The interner shares the items of every stack, so modifying them copies them.
+            let items: *const Items = &*stack.borrows;
             f(stack, &*global)?;
+            if !std::ptr::eq(items, &*stack.borrows) {
+                global.stack_interner.borrow_mut().intern(stack);
+            }
         }
         Ok(())
     }
@@ -460,6 +564,7 @@ impl Stacks {
     #[inline(always)]
     pub fn memory_read<'tcx>(&self, ptr: Pointer<Tag>, size: Size) -> InterpResult<'tcx> {
         trace!("read access with tag {:?}: {:?}, size {}", ptr.tag, ptr.erase_tag(), size.bytes());
+        self.global.borrow().check_break_on_tag(ptr.tag, "used for a read");
         self.for_each(ptr, size, |stack, global| {
             stack.access(AccessKind::Read, ptr.tag, global)?;
             Ok(())
@@ -469,6 +574,7 @@ impl Stacks {
     #[inline(always)]
     pub fn memory_written<'tcx>(&mut self, ptr: Pointer<Tag>, size: Size) -> InterpResult<'tcx> {
         trace!("write access with tag {:?}: {:?}, size {}", ptr.tag, ptr.erase_tag(), size.bytes());
+        self.global.borrow().check_break_on_tag(ptr.tag, "used for a write");
         self.for_each(ptr, size, |stack, global| {
             stack.access(AccessKind::Write, ptr.tag, global)?;
             Ok(())
@@ -482,8 +588,16 @@ impl Stacks {
         size: Size,
     ) -> InterpResult<'tcx> {
         trace!("deallocation with tag {:?}: {:?}, size {}", ptr.tag, ptr.erase_tag(), size.bytes());
+        self.global.borrow().check_break_on_tag(ptr.tag, "used for a deallocation");
         self.for_each(ptr, size, |stack, global| stack.dealloc(ptr.tag, global))
     }
+
+    /// Print the borrow stacks of the entire allocation to stderr, one range per line.
+    pub fn print_stacks(&self) {
+        for (range, stack) in self.stacks.borrow().iter_all() {
+            eprintln!("{:?}: {:?}", range, &stack.borrows[..]);
+        }
+    }
 }
 
 /// Retagging/reborrowing.  There is some policy in here, such as which permissions
@@ -520,18 +634,21 @@ trait EvalContextPrivExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         // There could be existing unique pointers reborrowed from them that should remain valid!
         let perm = match kind {
             RefKind::Unique { two_phase: false } => Permission::Unique,
-            RefKind::Unique { two_phase: true } => Permission::SharedReadWrite,
             RefKind::Raw { mutable: true } => Permission::SharedReadWrite,
-            RefKind::Shared | RefKind::Raw { mutable: false } => {
+            RefKind::Unique { two_phase: true }
+            | RefKind::Shared
+            | RefKind::Raw { mutable: false } => {
                 // Shared references and *const are a whole different kind of game, the
                 // permission is not uniform across the entire range!
                 // We need a frozen-sensitive reborrow.
                 return this.visit_freeze_sensitive(place, size, |cur_ptr, size, frozen| {
-                    // We are only ever `SharedReadOnly` inside the frozen bits.
-                    let perm = if frozen {
-                        Permission::SharedReadOnly
-                    } else {
-                        Permission::SharedReadWrite
+                    // We are only ever `SharedReadOnly` inside the frozen bits. Two-phase
+                    // borrows are reservations there. Inside `UnsafeCell`, other pointers may
+                    // also write while the borrow is reserved, so it is `SharedReadWrite`.
+                    let perm = match kind {
+                        _ if !frozen => Permission::SharedReadWrite,
+                        RefKind::Unique { .. } => Permission::Reserved,
+                        _ => Permission::SharedReadOnly,
                     };
                     let item = Item { perm, tag: new_tag, protector };
                     stacked_borrows.for_each(cur_ptr, size, |stack, global| {
@@ -555,6 +672,8 @@ trait EvalContextPrivExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         let this = self.eval_context_mut();
         // We want a place for where the ptr *points to*, so we get one.
         let place = this.ref_to_mplace(val)?;
+        // The size of unsized places is computed from the metadata, so check it first.
+        this.check_wide_ptr_meta(place)?;
         let size = this
             .size_and_align_of_mplace(place)?
             .map(|(size, _)| size)
@@ -594,7 +713,11 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         // Determine mutability and whether to add a protector.
         // Cannot use `builtin_deref` because that reports *immutable* for `Box`,
         // making it useless.
-        fn qualify(ty: ty::Ty<'_>, kind: RetagKind) -> Option<(RefKind, bool)> {
+        fn qualify(
+            ty: ty::Ty<'_>,
+            kind: RetagKind,
+            box_uniqueness: bool,
+        ) -> Option<(RefKind, bool)> {
             match ty.kind {
                 // References are simple.
                 ty::Ref(_, _, Mutability::Mut) => Some((
@@ -608,7 +731,14 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
                     Some((RefKind::Raw { mutable: tym.mutbl == Mutability::Mut }, false)),
                 // Boxes do not get a protector: protectors reflect that references outlive the call
                 // they were passed in to; that's just not the case for boxes.
-                ty::Adt(..) if ty.is_box() => Some((RefKind::Unique { two_phase: false }, false)),
+                ty::Adt(..) if ty.is_box() =>
+                    if box_uniqueness {
+                        Some((RefKind::Unique { two_phase: false }, false))
+                    } else {
+                        // Like raw pointers, such boxes are untagged and do not assert
+                        // uniqueness.
+                        Some((RefKind::Raw { mutable: true }, false))
+                    },
                 _ => None,
             }
         }
@@ -616,7 +746,8 @@ pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx
         // We only reborrow "bare" references/boxes.
         // Not traversing into fields helps with <https://github.com/rust-lang/unsafe-code-guidelines/issues/125>,
         // but might also cost us optimization and analyses. We will have to experiment more with this.
-        if let Some((mutbl, protector)) = qualify(place.layout.ty, kind) {
+        let box_uniqueness = this.machine.box_uniqueness;
+        if let Some((mutbl, protector)) = qualify(place.layout.ty, kind, box_uniqueness) {
             // Fast path.
             let val = this.read_immediate(this.place_to_op(place)?)?;
             let val = this.retag_reference(val, mutbl, protector)?;
@@ -0,0 +1,135 @@
+//! Support for programs built with libtest's test harness (`--test`, as in `cargo miri test`).
+//!
+//! The harness calls `test::__rust_begin_short_backtrace` with each `#[test]` function, so the
+//! frame of that call delimits the test. Heap allocations are attributed to the test that created
+//! them, and the leak report at the end of the program names the tests that leaked memory. The
+//! leak check itself still runs when the program ends: memory that is live when a test returns
+//! (like the buffer of its captured output) is often freed by the harness afterwards.
+//! With `-Zmiri-stats`, the number of steps is also reported for each test.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use rustc::ty;
+use rustc_hir::def_id::DefId;
+
+use crate::helpers::resolve_did;
+use crate::*;
+
+/// The test that is currently running.
+#[derive(Debug)]
+struct RunningTest {
+    name: Rc<str>,
+    /// The height of the stack without the frame of `__rust_begin_short_backtrace`.
+    depth: usize,
+    /// The number of steps the program executed before the test started.
+    start_step: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct TestHarness {
+    /// `test::__rust_begin_short_backtrace`, once it was looked up, if the program has it.
+    entry: Option<Option<DefId>>,
+    running: Option<RunningTest>,
+    /// The live heap allocations that were created by tests, with the name of the test.
+    allocations: HashMap<AllocId, Rc<str>>,
+}
+
+impl TestHarness {
+    /// Remembers that the heap allocation `alloc_id` was created by the test that is currently
+    /// running, if any.
+    pub fn note_allocation(&mut self, alloc_id: AllocId) {
+        if let Some(test) = &self.running {
+            self.allocations.insert(alloc_id, test.name.clone());
+        }
+    }
+
+    /// Forgets the heap allocation `alloc_id`, which was deallocated.
+    pub fn note_free(&mut self, alloc_id: AllocId) {
+        self.allocations.remove(&alloc_id);
+    }
+
+    /// Explains which of the leaked allocations were created by which test.
+    pub fn leak_notes(&self, ecx: &MiriEvalContext<'_, '_>) -> Vec<String> {
+        let mut leaks: Vec<(&AllocId, &Rc<str>)> = self
+            .allocations
+            .iter()
+            .filter(|(alloc_id, _)| {
+                ecx.memory.get_size_and_align(**alloc_id, AllocCheck::Live).is_ok()
+            })
+            .collect();
+        leaks.sort_by_key(|(alloc_id, _)| **alloc_id);
+        leaks
+            .into_iter()
+            .map(|(alloc_id, test)| {
+                format!("{:?} was allocated by test `{}` and never freed", alloc_id, test)
+            })
+            .collect()
+    }
+}
+
+/// The name of the test whose function is `f`, as the harness displays it: the test functions
+/// are closures (calling the `#[test]` function) inside the constant that describes the test.
+fn test_name(tcx: ty::TyCtxt<'_>, f: ty::Instance<'_>) -> String {
+    let path = tcx.def_path_str(f.def_id());
+    match path.find("::{{closure}}") {
+        Some(closure) => path[..closure].to_owned(),
+        None => path,
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Called for every function call: if this one starts a test, the test starts running.
+    fn begin_test(
+        &mut self,
+        instance: ty::Instance<'tcx>,
+        args: &[OpTy<'tcx, Tag>],
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let tcx = this.tcx.tcx;
+        let entry = *this
+            .machine
+            .test_harness
+            .entry
+            .get_or_insert_with(|| resolve_did(tcx, &["test", "__rust_begin_short_backtrace"]));
+        if entry != Some(instance.def_id()) || this.machine.test_harness.running.is_some() {
+            return Ok(());
+        }
+        // Tests are `fn()`s; benchmarks and dynamic tests are not scoped.
+        let f = match args {
+            [f] => match f.layout.ty.kind {
+                ty::FnPtr(_) => this.read_scalar(*f)?.not_undef()?,
+                _ => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+        let name = match this.memory.get_fn(f)? {
+            FnVal::Instance(f) => test_name(tcx, f),
+            FnVal::Other(_) => return Ok(()),
+        };
+        this.machine.test_harness.running = Some(RunningTest {
+            name: name.into(),
+            depth: this.stack().len(),
+            start_step: this.machine.steps,
+        });
+        Ok(())
+    }
+
+    /// Called after a frame was popped: if it was the frame of the running test, the test ends.
+    fn end_test(&mut self) {
+        let this = self.eval_context_mut();
+        match &this.machine.test_harness.running {
+            Some(test) if test.depth == this.stack().len() => {}
+            _ => return,
+        }
+        let test = this.machine.test_harness.running.take().unwrap();
+        if this.machine.stats {
+            eprintln!(
+                "miri: test `{}` executed {} steps",
+                test.name,
+                this.machine.steps - test.start_step
+            );
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Logging function entries and exits to a file, for `-Zmiri-trace`.
+//!
+//! Every line names the step (the number of statements and terminators executed so far) and the
+//! thread, so that the traces of two runs (e.g. with different seeds) can be diffed to find where
+//! they first diverge. The file is gzip-compressed, as traces get large quickly.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use rustc::ty;
+
+use crate::*;
+
+/// Miri only runs the main thread, and `pthread_self` reports it as thread 0.
+const MAIN_THREAD: u64 = 0;
+
+pub struct Trace<'tcx> {
+    out: GzEncoder<BufWriter<File>>,
+    /// Whether every terminator is logged as well.
+    terminators: bool,
+    /// The number of steps executed so far.
+    step: u64,
+    /// The functions of the stack frames at the end of the last step.
+    stack: Vec<ty::Instance<'tcx>>,
+    /// The first error writing the trace; later writes are skipped.
+    error: Option<io::Error>,
+}
+
+impl<'tcx> Trace<'tcx> {
+    pub fn new(file: &Path, terminators: bool) -> io::Result<Self> {
+        let out = GzEncoder::new(BufWriter::new(File::create(file)?), Compression::default());
+        Ok(Trace { out, terminators, step: 0, stack: Vec::new(), error: None })
+    }
+
+    fn log(&mut self, event: std::fmt::Arguments<'_>) {
+        if self.error.is_none() {
+            if let Err(err) = writeln!(self.out, "{} {} {}", self.step, MAIN_THREAD, event) {
+                self.error = Some(err);
+            }
+        }
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Logs the function calls and returns since the last step, and the terminator that is about
+    /// to be executed if terminators are traced.
+    fn trace_step(&mut self) {
+        let this = self.eval_context_mut();
+        // Take the trace out of the machine, so that it can be updated while looking at the stack.
+        let mut trace = match this.machine.trace.take() {
+            Some(trace) => trace,
+            None => return,
+        };
+        let stack = this.stack();
+        // Frames that were popped: everything above the first frame that changed.
+        let unchanged = trace
+            .stack
+            .iter()
+            .zip(stack)
+            .take_while(|(instance, frame)| **instance == frame.instance)
+            .count();
+        while trace.stack.len() > unchanged {
+            let instance = trace.stack.pop().unwrap();
+            trace.log(format_args!("exit `{}`", instance));
+        }
+        for frame in &stack[unchanged..] {
+            trace.log(format_args!("enter `{}`", frame.instance));
+            trace.stack.push(frame.instance);
+        }
+        if trace.terminators {
+            if let Some(frame) = stack.last() {
+                // Frames that are unwinding have no current block.
+                if let Some(block) = frame.block {
+                    let data = &frame.body.basic_blocks()[block];
+                    if frame.stmt == data.statements.len() {
+                        let kind = &data.terminator().kind;
+                        trace.log(format_args!("terminator `{:?}` in `{}`", kind, frame.instance));
+                    }
+                }
+            }
+        }
+        trace.step += 1;
+        this.machine.trace = Some(trace);
+    }
+
+    /// Logs the exits of the functions that are still running, and finishes the trace file.
+    fn finish_trace(&mut self) -> io::Result<()> {
+        let this = self.eval_context_mut();
+        let mut trace = match this.machine.trace.take() {
+            Some(trace) => trace,
+            None => return Ok(()),
+        };
+        while let Some(instance) = trace.stack.pop() {
+            trace.log(format_args!("exit `{}`", instance));
+        }
+        if let Some(err) = trace.error {
+            return Err(err);
+        }
+        trace.out.finish()?.flush()
+    }
+}
@@ -0,0 +1,271 @@
+//! Configurable validation of assigned values, for `-Zmiri-recursive-validation` and
+//! `-Zmiri-ignore-validity`.
+//!
+//! By default, the engine validates every typed copy itself. That validation does not look
+//! behind references and cannot skip any invariant, so when the configuration asks for either,
+//! Miri turns it off and instead validates, after each step, what that step wrote: the
+//! destination of an assignment or call, the arguments of a call, or a return value.
+//!
+//! With recursive validation, values in immutable memory (constants and promoteds) that only
+//! reach immutable memory through their references are only validated once per type: such
+//! memory can never change, so the result stays valid.
+
+use rustc::mir;
+use rustc::ty::Ty;
+use rustc_hir::Mutability;
+use rustc_mir::interpret::{PathElem, RefTracking};
+
+use crate::*;
+
+/// How deeply values are validated when they are assigned.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ValidationMode {
+    /// Values are not validated (Stacked Borrows is still enforced).
+    Off,
+    /// The value itself is validated, but not the memory its references point to.
+    Shallow,
+    /// Everything that can be reached through references is validated as well.
+    Full,
+}
+
+impl ValidationMode {
+    pub fn from_str(mode: &str) -> Option<ValidationMode> {
+        Some(match mode {
+            "off" => ValidationMode::Off,
+            "shallow" => ValidationMode::Shallow,
+            "full" => ValidationMode::Full,
+            _ => return None,
+        })
+    }
+}
+
+/// The kinds of validity invariants that `-Zmiri-ignore-validity` can turn off.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ValidityCheck {
+    /// Enums must have a valid discriminant.
+    EnumDiscriminants,
+    /// References and boxes must be non-null, aligned and dereferenceable.
+    References,
+    /// Scalars must be in their valid range: `bool`, `char`, `NonNull`, `NonZero*` and so on.
+    ScalarRanges,
+}
+
+impl ValidityCheck {
+    pub fn from_str(check: &str) -> Option<ValidityCheck> {
+        Some(match check {
+            "discriminants" => ValidityCheck::EnumDiscriminants,
+            "references" => ValidityCheck::References,
+            "ranges" => ValidityCheck::ScalarRanges,
+            _ => return None,
+        })
+    }
+
+    /// Determines which invariant the validation failure `msg` is about. The message has the
+    /// form "encountered <value>[ at <path>][, but expected <expectation>]". The path is made of
+    /// the program's field names, so it must not be looked at.
+    fn of_failure(msg: &str) -> Option<ValidityCheck> {
+        let (found, expected) = match msg.find(", but expected ") {
+            Some(pos) => (&msg[..pos], &msg[pos + ", but expected ".len()..]),
+            None => (msg, ""),
+        };
+        // Paths do not contain spaces.
+        let found = found.rfind(" at ").map_or(found, |pos| &found[..pos]);
+        if expected.contains("enum discriminant") {
+            Some(ValidityCheck::EnumDiscriminants)
+        } else if expected.starts_with("a boolean")
+            || expected.starts_with("a valid unicode codepoint")
+            || expected.starts_with("something")
+        {
+            Some(ValidityCheck::ScalarRanges)
+        } else if found.contains("enum discriminant") {
+            Some(ValidityCheck::EnumDiscriminants)
+        } else if found.contains("reference") || found.contains("box") {
+            Some(ValidityCheck::References)
+        } else {
+            None
+        }
+    }
+}
+
+/// A value in immutable memory: where it is, its metadata if it is unsized, and its type.
+pub type KnownValid<'tcx> = (Pointer, Option<Scalar>, Ty<'tcx>);
+
+/// What a step is about to write, so that it can be validated after the step.
+pub struct PendingValidation<'tcx> {
+    stack_len: usize,
+    /// The location of the step, to report errors there.
+    location: (mir::BasicBlock, usize),
+    /// The destination, if the step is an assignment or a call.
+    dest: Option<mir::Place<'tcx>>,
+    /// The place that receives the return value, if the step returns from the current frame.
+    return_place: Option<PlaceTy<'tcx, Tag>>,
+}
+
+impl<'mir, 'tcx> EvalContextExtPrivate<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+trait EvalContextExtPrivate<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Validates `place` and everything reachable from it through references.
+    fn validate_recursively(&mut self, place: PlaceTy<'tcx, Tag>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let mplace = this.force_allocation(place)?;
+        // Only remember values as valid once everything was validated successfully.
+        let mut known_valid = Vec::new();
+        this.validate_reachable(mplace, vec![], &mut known_valid)?;
+        this.machine.known_valid.extend(known_valid);
+        Ok(())
+    }
+
+    /// Validates `root` and everything reachable from it through references. Every value in
+    /// immutable memory is validated separately, and added to `known_valid` if all memory it
+    /// reaches is immutable as well. Returns whether all memory reachable from `root`, including
+    /// `root` itself, is immutable.
+    fn validate_reachable(
+        &mut self,
+        root: MPlaceTy<'tcx, Tag>,
+        path: Vec<PathElem>,
+        known_valid: &mut Vec<KnownValid<'tcx>>,
+    ) -> InterpResult<'tcx, bool> {
+        let this = self.eval_context_mut();
+        let key = this.known_valid_key(root);
+        if let Some(key) = key {
+            if this.machine.known_valid.contains(&key) || known_valid.contains(&key) {
+                return Ok(true);
+            }
+            // Assume the value is valid while validating it, so that cycles of references
+            // terminate. If it turns out to reach mutable memory, everything that relied on that
+            // is removed again below.
+            known_valid.push(key);
+        }
+        let known_valid_len = known_valid.len();
+        let mut immutable = key.is_some();
+        let mut ref_tracking = RefTracking::new(root);
+        ref_tracking.todo = vec![(root, path)];
+        let mut is_root = true;
+        while let Some((mplace, path)) = ref_tracking.todo.pop() {
+            if !is_root && this.known_valid_key(mplace).is_some() {
+                immutable &= this.validate_reachable(mplace, path, known_valid)?;
+                continue;
+            }
+            immutable &= is_root && key.is_some();
+            is_root = false;
+            this.validate_operand(mplace.into(), path, Some(&mut ref_tracking))?;
+        }
+        if !immutable && key.is_some() {
+            known_valid.truncate(known_valid_len - 1);
+        }
+        Ok(immutable)
+    }
+
+    /// Identifies `mplace` if it is a value in immutable memory, which can never change.
+    /// Values with interior mutability do not count.
+    fn known_valid_key(&self, mplace: MPlaceTy<'tcx, Tag>) -> Option<KnownValid<'tcx>> {
+        let this = self.eval_context_ref();
+        let ptr = match mplace.ptr {
+            Scalar::Ptr(ptr) => ptr,
+            _ => return None,
+        };
+        // Dangling pointers are left to the validation to report.
+        let alloc = this.memory.get_raw(ptr.alloc_id).ok()?;
+        if alloc.mutability != Mutability::Not || !this.type_is_freeze(mplace.layout.ty) {
+            return None;
+        }
+        Some((ptr.erase_tag(), mplace.meta.map(|meta| meta.erase_tag()), mplace.layout.ty))
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Whether Miri validates assigned values itself, instead of leaving it to the engine.
+    fn validates_itself(&self) -> bool {
+        let this = self.eval_context_ref();
+        match this.machine.validation {
+            ValidationMode::Off => false,
+            ValidationMode::Shallow => !this.machine.ignored_validity.is_empty(),
+            ValidationMode::Full => true,
+        }
+    }
+
+    /// Validates the value at `place` as configured, ignoring failures of the invariants that
+    /// should not be checked.
+    fn validate_place(&mut self, place: PlaceTy<'tcx, Tag>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let result = match this.machine.validation {
+            ValidationMode::Off => return Ok(()),
+            ValidationMode::Shallow => {
+                let op = this.place_to_op(place)?;
+                this.validate_operand(op, vec![], None)
+            }
+            ValidationMode::Full => this.validate_recursively(place),
+        };
+        if let Err(err) = &result {
+            if let err_unsup!(ValidationFailure(msg)) = &err.kind {
+                let check = ValidityCheck::of_failure(msg);
+                if check.map_or(false, |check| this.machine.ignored_validity.contains(&check)) {
+                    return Ok(());
+                }
+            }
+        }
+        result
+    }
+
+    /// Remembers what the next step is going to write, if Miri has to validate that.
+    fn prepare_validation(&self) -> Option<PendingValidation<'tcx>> {
+        let this = self.eval_context_ref();
+        if !this.validates_itself() {
+            return None;
+        }
+        let frame = this.stack().last()?;
+        // Frames that are unwinding have no current block.
+        let block = frame.block?;
+        let data = &frame.body.basic_blocks()[block];
+        let mut dest = None;
+        let mut return_place = None;
+        match data.statements.get(frame.stmt) {
+            Some(statement) =>
+                if let mir::StatementKind::Assign(assign) = &statement.kind {
+                    dest = Some(assign.0.clone());
+                },
+            None => match &data.terminator().kind {
+                mir::TerminatorKind::Call { destination: Some((place, _)), .. } =>
+                    dest = Some(place.clone()),
+                mir::TerminatorKind::Return => return_place = frame.return_place,
+                _ => {}
+            },
+        }
+        Some(PendingValidation {
+            stack_len: this.stack().len(),
+            location: (block, frame.stmt),
+            dest,
+            return_place,
+        })
+    }
+
+    /// After a step, validates what it wrote: the destination of an assignment or of a call that
+    /// was handled by a shim, the arguments of a call, or the return value.
+    fn finish_validation(&mut self, pending: PendingValidation<'tcx>) -> InterpResult<'tcx> {
+        let this = self.eval_context_mut();
+        let stack_len = this.stack().len();
+        if stack_len == pending.stack_len + 1 {
+            // A call: validate the arguments of the new frame. The destination is validated when
+            // the callee returns.
+            for local in this.frame().body.args_iter() {
+                let place = this.eval_place(&mir::Place::from(local))?;
+                this.validate_place(place)?;
+            }
+        } else if stack_len + 1 == pending.stack_len {
+            if let Some(return_place) = pending.return_place {
+                this.validate_place(return_place)?;
+            }
+        } else if let Some(dest) = pending.dest {
+            let place = this.eval_place(&dest)?;
+            if let Err(err) = this.validate_place(place) {
+                // The step is done, but the error should point to it.
+                let (block, stmt) = pending.location;
+                let frame = this.frame_mut();
+                frame.block = Some(block);
+                frame.stmt = stmt;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+//! Checks of the metadata of wide pointers that go beyond what the engine's validation checks.
+//!
+//! The engine only checks that a vtable pointer points to memory that starts with a drop function,
+//! a size and an alignment. Miri also checks that it points to a vtable that was created for the
+//! trait of the pointer: using a vtable of another trait calls whatever function happens to be in
+//! the slot of the method, or reads past the end of the vtable. Slice lengths are checked to not
+//! describe a slice that is larger than the address space.
+//!
+//! The checks run when a reference or box is retagged, which happens whenever the program creates
+//! one; raw pointers are not checked.
+
+use rustc::traits::supertrait_def_ids;
+use rustc::ty::{
+    self,
+    layout::{LayoutOf, Size},
+    Ty, TyCtxt,
+};
+use rustc_hir::def_id::DefId;
+
+use crate::*;
+
+/// The trait that the method `def_id` belongs to: the trait that declares it, or the trait of the
+/// impl that defines it. Closures do not belong to a trait.
+fn trait_of_method(tcx: TyCtxt<'_>, def_id: DefId) -> Option<DefId> {
+    tcx.trait_of_item(def_id)
+        .or_else(|| tcx.impl_of_method(def_id).and_then(|impl_did| tcx.trait_id_of_impl(impl_did)))
+}
+
+/// Reports a wide pointer with invalid metadata. That makes the pointer an invalid value.
+fn invalid_wide_ptr<'tcx>(msg: String, notes: Vec<String>) -> InterpResult<'tcx> {
+    let kind = UbKind::InvalidValue;
+    throw_machine_stop!(TerminationInfo::UndefinedBehavior { kind, msg, notes })
+}
+
+impl<'mir, 'tcx> EvalContextExtPrivate<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+trait EvalContextExtPrivate<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Checks that `vtable` is a vtable for `principal`, the trait of `dyn_ty`. Without a
+    /// principal trait, the vtable only has the drop function, size and alignment.
+    fn check_vtable(
+        &self,
+        vtable: Scalar<Tag>,
+        dyn_ty: Ty<'tcx>,
+        principal: Option<DefId>,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_ref();
+        let tcx = this.tcx.tcx;
+        // Integers that are not pointers are left to the engine.
+        let vtable = match this.memory.force_ptr(vtable) {
+            Ok(vtable) => vtable,
+            Err(_) => return Ok(()),
+        };
+        // The vtables created while the program runs are known. Vtables in constants are in
+        // global memory, which can also hold other data.
+        let is_vtable = this.memory.extra.vtables.borrow().contains(&vtable.alloc_id)
+            || match tcx.alloc_map.lock().get(vtable.alloc_id) {
+                Some(GlobalAlloc::Memory(_)) => true,
+                _ => false,
+            };
+        if !is_vtable || vtable.offset != Size::ZERO {
+            let msg = format!(
+                "the vtable pointer in a wide pointer to `{}` does not point to a vtable",
+                dyn_ty
+            );
+            return invalid_wide_ptr(msg, vec![]);
+        }
+        let principal = match principal {
+            Some(principal) => principal,
+            None => return Ok(()),
+        };
+
+        // The vtable has a slot for every method of the trait and its supertraits, including the
+        // methods that cannot be called on trait objects. Traits that are supertraits with
+        // different generic arguments have several sets of slots, so there may be more.
+        let traits: Vec<DefId> = supertrait_def_ids(tcx, principal).collect();
+        let methods: usize = traits
+            .iter()
+            .map(|&def_id| {
+                tcx.associated_items(def_id)
+                    .filter(|item| item.kind == ty::AssocKind::Method)
+                    .count()
+            })
+            .sum();
+        let alloc = this.memory.get_raw(vtable.alloc_id)?;
+        let ptr_size = this.pointer_size();
+        let slots = alloc.size.bytes() / ptr_size.bytes();
+        if slots < 3 + methods as u64 {
+            let msg = format!(
+                "the vtable in a wide pointer to `{}` is not a vtable for `{}`: it has {} method \
+                 slots, but the trait has {} methods",
+                dyn_ty,
+                tcx.def_path_str(principal),
+                slots.saturating_sub(3),
+                methods
+            );
+            return invalid_wide_ptr(msg, vec![]);
+        }
+        // The slots of the methods that cannot be called on trait objects are empty.
+        let mut foreign_method = None;
+        for slot in 3..slots {
+            let fn_ptr = match alloc.read_ptr_sized(this, vtable.offset(ptr_size * slot, this)?)? {
+                ScalarMaybeUndef::Scalar(Scalar::Ptr(fn_ptr)) => fn_ptr,
+                _ => continue,
+            };
+            let method = match this.memory.get_fn(fn_ptr.into()) {
+                Ok(FnVal::Instance(method)) => method.def_id(),
+                _ => continue,
+            };
+            match trait_of_method(tcx, method) {
+                Some(method_trait) if !traits.contains(&method_trait) => {
+                    foreign_method = Some((method, method_trait));
+                    break;
+                }
+                _ => {}
+            }
+        }
+        if let Some((method, method_trait)) = foreign_method {
+            let notes = vec![format!(
+                "the vtable contains `{}`, a method of `{}`",
+                tcx.def_path_str(method),
+                tcx.def_path_str(method_trait)
+            )];
+            let msg = format!(
+                "the vtable in a wide pointer to `{}` is a vtable for another trait",
+                dyn_ty
+            );
+            return invalid_wide_ptr(msg, notes);
+        }
+        Ok(())
+    }
+
+    /// Checks that a slice of `len` elements of `elem_size` bytes after `prefix` bytes (the sized
+    /// fields of a struct whose last field is the slice) fits into the address space.
+    fn check_slice_len(
+        &self,
+        len: Scalar<Tag>,
+        ty: Ty<'tcx>,
+        prefix: Size,
+        elem_size: Size,
+    ) -> InterpResult<'tcx> {
+        let this = self.eval_context_ref();
+        // Lengths that are not integers are left to the engine.
+        let len = match len.to_machine_usize(this) {
+            Ok(len) => len,
+            Err(_) => return Ok(()),
+        };
+        let max = this.isize_max() as u64;
+        let fits = elem_size
+            .bytes()
+            .checked_mul(len)
+            .and_then(|size| size.checked_add(prefix.bytes()))
+            .map_or(false, |size| size <= max);
+        if !fits {
+            let msg = format!(
+                "the slice in a wide pointer to `{}` has {} elements of {} bytes each, which do \
+                 not fit into the address space (objects can be at most {} bytes large)",
+                ty,
+                len,
+                elem_size.bytes(),
+                max
+            );
+            return invalid_wide_ptr(msg, vec![]);
+        }
+        Ok(())
+    }
+}
+
+impl<'mir, 'tcx> EvalContextExt<'mir, 'tcx> for crate::MiriEvalContext<'mir, 'tcx> {}
+pub trait EvalContextExt<'mir, 'tcx: 'mir>: crate::MiriEvalContextExt<'mir, 'tcx> {
+    /// Checks the metadata of the wide pointer that points to `place`: its vtable, if it points
+    /// to a trait object, or its length, if it points to a slice. Sized places have no metadata.
+    fn check_wide_ptr_meta(&self, place: MPlaceTy<'tcx, Tag>) -> InterpResult<'tcx> {
+        let this = self.eval_context_ref();
+        let meta = match place.meta {
+            Some(meta) => meta,
+            None => return Ok(()),
+        };
+        let tail =
+            this.tcx.struct_tail_erasing_lifetimes(place.layout.ty, ty::ParamEnv::reveal_all());
+        match tail.kind {
+            ty::Dynamic(predicates, _) =>
+                this.check_vtable(meta, tail, predicates.principal_def_id()),
+            ty::Slice(_) | ty::Str => {
+                let elem = this.layout_of(tail)?.field(this, 0)?;
+                this.check_slice_len(meta, place.layout.ty, place.layout.size, elem.size)
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -61,3 +61,13 @@ fn fail_index_check() {
     #[cfg(not(windows))]
     [][42]
 }
+
+// FIXME: see above
+#[test]
+#[cfg_attr(not(windows), should_panic(expected="formatted panic 42"))]
+fn panic_with_cleanup() {
+    // This must be dropped during unwinding, or the leak checker complains.
+    let _guard = vec![1, 2, 3];
+    #[cfg(not(windows))]
+    panic!("formatted panic {}", 42);
+}
@@ -0,0 +1,12 @@
+fn covered(x: bool) -> u32 {
+    if x { 1 } else { 2 }
+}
+
+fn uncalled() {}
+
+fn main() {
+    covered(true);
+    if false {
+        uncalled();
+    }
+}
@@ -0,0 +1,26 @@
+// Run by `tests/compiletest.rs`, once for each kind of error, to check Miri's exit codes.
+
+extern "C" {
+    fn no_such_function();
+}
+
+fn recurse(n: u64) -> u64 {
+    if n == 0 { 0 } else { recurse(n - 1) + 1 }
+}
+
+fn main() {
+    match std::env::args().nth(1).unwrap().as_str() {
+        "abort" => std::process::abort(),
+        "unsupported" => unsafe { no_such_function() },
+        "ub" => {
+            let b = Box::new(42);
+            let ptr: *const i32 = &*b;
+            drop(b);
+            let _x = unsafe { *ptr };
+        }
+        "stack-overflow" => {
+            recurse(u64::max_value());
+        }
+        mode => panic!("unknown mode {}", mode),
+    }
+}
@@ -0,0 +1,13 @@
+extern "C" {
+    fn strlen(s: *const u8) -> usize;
+    fn memcmp(s1: *const u8, s2: *const u8, n: usize) -> i32;
+}
+
+fn main() {
+    unsafe {
+        // The embedder's hook handles `strlen` instead of the built-in shim.
+        assert_eq!(strlen(b"four\0".as_ptr()), 42);
+        // No hook handles `memcmp`, so the built-in shim runs.
+        assert_eq!(memcmp(b"ab".as_ptr(), b"ac".as_ptr(), 2).signum(), -1);
+    }
+}
@@ -0,0 +1,20 @@
+// Run with `-Zmiri-many-seeds` by `tests/compiletest.rs`: only seed 3 fails.
+
+#[repr(C)]
+struct MiriHostTargetInfo {
+    isolation: bool,
+    seed: u64,
+    target_len: usize,
+}
+
+extern "Rust" {
+    fn miri_host_target_info() -> MiriHostTargetInfo;
+}
+
+fn main() {
+    let info = unsafe { miri_host_target_info() };
+    println!("seed {}", info.seed);
+    if info.seed == 3 {
+        std::process::exit(42);
+    }
+}
@@ -0,0 +1,15 @@
+extern "Rust" {
+    fn miri_print_borrow_stacks(ptr: *const u8);
+    fn miri_backtrace();
+}
+
+fn helper() {
+    unsafe { miri_backtrace() };
+}
+
+fn main() {
+    let x = Box::new(0u8);
+    let r = &*x;
+    unsafe { miri_print_borrow_stacks(r) };
+    helper();
+}
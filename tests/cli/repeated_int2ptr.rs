@@ -0,0 +1,10 @@
+// Run with `-Zmiri-warn=int2ptr` by `tests/compiletest.rs`: the warning is shown once, and the
+// other 4 are summarized at the end.
+
+fn main() {
+    let x = 42u8;
+    let addr = &x as *const u8 as usize;
+    for _ in 0..5 {
+        let _val = unsafe { *(addr as *const u8) };
+    }
+}
@@ -0,0 +1,10 @@
+// Run with `-Zmiri-trace` by `tests/compiletest.rs`.
+
+#[inline(never)]
+fn helper(x: u32) -> u32 {
+    x + 1
+}
+
+fn main() {
+    assert_eq!(helper(1), 2);
+}
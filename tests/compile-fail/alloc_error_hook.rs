@@ -0,0 +1,10 @@
+// error-pattern: the evaluated program aborted execution
+#![feature(alloc_error_hook)]
+
+use std::alloc::{handle_alloc_error, set_alloc_error_hook, Layout};
+
+fn main() {
+    // The handler of `std` runs the hook, and then aborts.
+    set_alloc_error_hook(|layout| eprintln!("failed to allocate {} bytes", layout.size()));
+    handle_alloc_error(Layout::new::<u64>());
+}
@@ -1,4 +1,4 @@
-//error-pattern: copy_nonoverlapping called on overlapping ranges
+//error-pattern: copy_nonoverlapping called on overlapping ranges: source 0..2 and destination 1..3
 #![feature(intrinsics)]
 
 // Directly call intrinsic to avoid debug assertions in libstd
@@ -0,0 +1,15 @@
+#![feature(intrinsics)]
+
+// Directly call intrinsic to avoid debug assertions in libstd
+extern "rust-intrinsic" {
+    fn copy_nonoverlapping<T>(src: *const T, dst: *mut T, count: usize);
+}
+
+fn main() {
+    let mut data = [0u8; 16];
+    unsafe {
+        let a = data.as_mut_ptr();
+        // Copying a range onto itself is an overlap as well.
+        copy_nonoverlapping(a.wrapping_add(4), a.wrapping_add(4), 1); //~ ERROR copy_nonoverlapping called on overlapping ranges: source 4..5 and destination 4..5
+    }
+}
@@ -0,0 +1,14 @@
+// ignore-windows: No libc on Windows
+// error-pattern: was allocated with the C heap (`malloc`), but it is deallocated with the Rust allocator
+// error-pattern: was allocated here
+
+#![feature(rustc_private)]
+
+extern crate libc;
+
+fn main() {
+    unsafe {
+        let ptr = libc::malloc(4) as *mut u32;
+        drop(Box::from_raw(ptr));
+    }
+}
@@ -5,7 +5,7 @@ extern crate alloc;
 use alloc::alloc::Global;
 use std::alloc::{AllocRef, Layout};
 
-// error-pattern: incorrect alloc info: expected size 1 and align 2, got size 1 and align 1
+// error-pattern: has size 1 and alignment 1, but it was deallocated with size 1 and alignment 2
 
 fn main() {
     unsafe {
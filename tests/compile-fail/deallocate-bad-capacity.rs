@@ -0,0 +1,9 @@
+// error-pattern: has size 4 and alignment 1, but it was deallocated with size 8 and alignment 1
+
+fn main() {
+    let mut v = vec![0u8; 4];
+    let ptr = v.as_mut_ptr();
+    std::mem::forget(v);
+    // Wrong capacity: the deallocation uses a different layout than the allocation.
+    unsafe { drop(Vec::from_raw_parts(ptr, 4, 8)) };
+}
@@ -0,0 +1,9 @@
+// compile-flags: -Zmiri-deny=int2ptr
+// error-pattern: an integer was used as a pointer
+
+fn main() {
+    let x = 42u8;
+    let addr = &x as *const u8 as usize;
+    let ptr = addr as *const u8;
+    assert_eq!(unsafe { *ptr }, 42);
+}
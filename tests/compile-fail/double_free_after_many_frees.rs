@@ -0,0 +1,19 @@
+// ignore-windows: Uses POSIX APIs
+// error-pattern: double free of alloc
+// compile-flags: -Zmiri-dealloc-backtraces
+
+#![feature(rustc_private)]
+
+extern crate libc;
+
+fn main() {
+    unsafe {
+        let p = libc::malloc(8);
+        libc::free(p);
+        // More deallocations than are remembered with their full backtrace.
+        for _ in 0..5000 {
+            libc::free(libc::malloc(8));
+        }
+        libc::free(p);
+    }
+}
@@ -1,4 +1,4 @@
- // error-pattern: the evaluated program aborted
+ // error-pattern: the evaluated program aborted execution: panicked while unwinding from another panic
 struct Foo;
 impl Drop for Foo {
     fn drop(&mut self) {
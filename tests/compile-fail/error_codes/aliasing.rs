@@ -0,0 +1,7 @@
+fn main() {
+    let mut x = 42;
+    let raw = &mut x as *mut i32;
+    let r = &mut x;
+    *r = 0;
+    let _val = unsafe { *raw }; //~ ERROR found in borrow stack. [MIRIUB0002]
+}
@@ -0,0 +1,6 @@
+fn main() {
+    let b = Box::new(42);
+    let ptr: *const i32 = &*b;
+    drop(b);
+    let _x = unsafe { *ptr }; //~ ERROR dangling pointer was dereferenced [MIRIUB0001]
+}
@@ -0,0 +1,7 @@
+// Dangling references are invalid values, even though they are never dereferenced.
+
+fn main() {
+    let val = 14;
+    let ptr = (&val as *const i32).wrapping_offset(1);
+    let _x: &i32 = unsafe { std::mem::transmute(ptr) }; //~ ERROR dangling reference (not entirely in bounds) [MIRIUB0003]
+}
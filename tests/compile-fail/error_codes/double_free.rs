@@ -0,0 +1,14 @@
+// ignore-windows: Uses POSIX APIs
+// error-pattern: error[MIRIUB0006]: double free of alloc
+
+#![feature(rustc_private)]
+
+extern crate libc;
+
+fn main() {
+    unsafe {
+        let p = libc::malloc(8);
+        libc::free(p);
+        libc::free(p);
+    }
+}
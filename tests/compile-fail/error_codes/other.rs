@@ -0,0 +1,5 @@
+#![feature(core_intrinsics)]
+
+fn main() {
+    unsafe { std::intrinsics::unchecked_add(40000u16, 30000); } //~ ERROR Overflow executing `unchecked_add` [MIRIUB0008]
+}
@@ -0,0 +1,7 @@
+// compile-flags: -Zmiri-disable-validation
+
+fn main() {
+    let x = [2u32, 3];
+    let x = (x.as_ptr() as *const u8).wrapping_offset(3) as *const u32;
+    let _x = unsafe { *x }; //~ ERROR tried to access memory with alignment 1, but alignment 4 is required [MIRIUB0005]
+}
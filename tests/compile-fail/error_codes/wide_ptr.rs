@@ -0,0 +1,8 @@
+use std::fmt;
+
+fn main() {
+    let x = 42u32;
+    let debug: &dyn fmt::Debug = &x;
+    let display: &dyn fmt::Display = unsafe { std::mem::transmute(debug) }; //~ ERROR is a vtable for another trait [MIRIUB0003]
+    println!("{}", display);
+}
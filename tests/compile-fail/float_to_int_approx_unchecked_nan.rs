@@ -0,0 +1,7 @@
+#![feature(core_intrinsics)]
+
+fn main() {
+    unsafe {
+        std::intrinsics::float_to_int_approx_unchecked::<f32, i32>(std::f32::NAN); //~ ERROR cannot be represented in target type `i32`
+    }
+}
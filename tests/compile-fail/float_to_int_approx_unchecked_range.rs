@@ -0,0 +1,7 @@
+#![feature(core_intrinsics)]
+
+fn main() {
+    unsafe {
+        std::intrinsics::float_to_int_approx_unchecked::<f64, u8>(256.0); //~ ERROR which cannot be represented in target type `u8`
+    }
+}
@@ -0,0 +1,9 @@
+#![feature(asm)]
+
+fn main() {
+    let x: u64;
+    unsafe {
+        asm!("mov $1, $0" : "=r"(x) : "r"(5u64)); //~ ERROR inline assembly is not supported
+    }
+    println!("{}", x);
+}
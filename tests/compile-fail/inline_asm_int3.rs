@@ -0,0 +1,9 @@
+#![feature(asm)]
+
+// error-pattern: executed a breakpoint instruction (`int3`) without a debugger attached
+
+fn main() {
+    unsafe {
+        asm!("int3");
+    }
+}
@@ -0,0 +1,13 @@
+// ignore-windows: No libc on Windows
+
+#![feature(rustc_private)]
+
+extern crate libc;
+
+fn main() {
+    let mut buf = [0u8; 8];
+    let ptr = buf.as_mut_ptr();
+    unsafe {
+        libc::memcpy(ptr.add(2).cast(), ptr.cast(), 4); //~ ERROR memcpy called on overlapping ranges: source 0..4 and destination 2..6
+    }
+}
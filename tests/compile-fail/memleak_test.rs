@@ -0,0 +1,14 @@
+// ignore-windows: We do not check leaks on Windows
+// ignore-macos: We do not check leaks on macOS
+// compile-flags: --test
+// error-pattern: was allocated by test `leaks_a_box` and never freed
+
+#[test]
+fn no_leak() {
+    let _b = Box::new(0u32);
+}
+
+#[test]
+fn leaks_a_box() {
+    std::mem::forget(Box::new(42u64));
+}
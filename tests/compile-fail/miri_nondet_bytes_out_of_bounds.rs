@@ -0,0 +1,9 @@
+extern "Rust" {
+    fn miri_nondet_bytes(ptr: *mut u8, len: usize);
+}
+
+fn main() {
+    let mut buf = [0u8; 4];
+    // The length is checked against the allocation before any bytes are generated.
+    unsafe { miri_nondet_bytes(buf.as_mut_ptr(), isize::max_value() as usize) }; //~ ERROR outside bounds
+}
@@ -0,0 +1,24 @@
+// ignore-windows: No libc on Windows
+// error-pattern: are inaccessible
+
+#![feature(rustc_private)]
+extern crate libc;
+
+use std::alloc::{alloc, Layout};
+
+const PAGE_SIZE: usize = 4096;
+
+fn main() {
+    unsafe {
+        let layout = Layout::from_size_align(3 * PAGE_SIZE, PAGE_SIZE).unwrap();
+        let ptr = alloc(layout);
+        // Turn the middle page into a guard page.
+        let guard = ptr.add(PAGE_SIZE);
+        assert_eq!(libc::mprotect(guard as *mut libc::c_void, PAGE_SIZE, libc::PROT_NONE), 0);
+        // The other pages can still be used.
+        *ptr = 1;
+        *ptr.add(2 * PAGE_SIZE) = 2;
+        // Running from the first page into the guard page is caught.
+        let _x = (guard.sub(2) as *const u32).read_unaligned();
+    }
+}
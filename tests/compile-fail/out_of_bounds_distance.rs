@@ -0,0 +1,7 @@
+// error-pattern: the access ends 4 byte(s) past the end of
+
+fn main() {
+    let v = [0u32; 2];
+    let p = v.as_ptr().wrapping_add(2);
+    let _x = unsafe { *p };
+}
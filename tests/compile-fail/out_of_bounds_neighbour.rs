@@ -0,0 +1,11 @@
+// error-pattern: which is offset 0 of alloc
+
+fn main() {
+    let a = Box::new([0u8; 16]);
+    let b = Box::new([1u8; 16]);
+    let a_ptr = &*a as *const [u8; 16] as *const u8;
+    let b_ptr = &*b as *const [u8; 16] as *const u8;
+    // Going from `a` to `b` is fine on real hardware, but is out of bounds of `a`.
+    let distance = (b_ptr as usize).wrapping_sub(a_ptr as usize);
+    let _x = unsafe { *a_ptr.wrapping_add(distance) };
+}
@@ -1,4 +1,3 @@
-// ignore-test: Abort panics are not yet supported
 // error-pattern: the evaluated program panicked
 // compile-flags: -C panic=abort
 
@@ -0,0 +1,17 @@
+// ignore-windows: TLS destructors of Windows go through `std`
+// error-pattern: panicked inside of its `#[panic_handler]`
+#![feature(lang_items, start)]
+#![no_std]
+
+#[start]
+fn start(_argc: isize, _argv: *const *const u8) -> isize {
+    panic!("first")
+}
+
+#[panic_handler]
+fn panic_handler(_: &core::panic::PanicInfo) -> ! {
+    panic!("second")
+}
+
+#[lang = "eh_personality"]
+fn eh_personality() {}
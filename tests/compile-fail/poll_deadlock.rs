@@ -0,0 +1,16 @@
+// ignore-windows: Uses POSIX APIs
+// ignore-macos: `std` uses `ioctl` to set up sockets on macOS
+
+#![feature(rustc_private)]
+extern crate libc;
+
+use std::net::TcpListener;
+use std::os::unix::io::AsRawFd;
+
+fn main() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut fds = [libc::pollfd { fd: listener.as_raw_fd(), events: libc::POLLIN, revents: 0 }];
+    unsafe {
+        libc::poll(fds.as_mut_ptr(), 1, -1); //~ ERROR `poll` without timeout
+    }
+}
@@ -0,0 +1,9 @@
+// compile-flags: -Zmiri-ptr-comparison=error
+
+fn main() {
+    let a = [0u8; 4];
+    let b = 0u8;
+    // `end` could be the address of `b`, or not.
+    let end = a.as_ptr().wrapping_add(4);
+    let _eq = end == &b as *const u8; //~ ERROR the result depends on where the allocations are placed
+}
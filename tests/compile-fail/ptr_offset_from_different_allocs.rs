@@ -0,0 +1,8 @@
+// error-pattern: `ptr_offset_from` called on pointers into different allocations
+#![feature(ptr_offset_from)]
+
+fn main() {
+    let a = [0u8; 4];
+    let b = [0u8; 4];
+    let _val = unsafe { b.as_ptr().offset_from(a.as_ptr()) };
+}
@@ -0,0 +1,9 @@
+// error-pattern: 3 bytes apart, which is not a multiple of the element size 2
+#![feature(ptr_offset_from)]
+
+fn main() {
+    let a = [0u16; 4];
+    let start = a.as_ptr();
+    let odd = (start as *const u8).wrapping_add(3) as *const u16;
+    let _val = unsafe { odd.offset_from(start) };
+}
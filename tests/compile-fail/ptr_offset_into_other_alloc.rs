@@ -0,0 +1,13 @@
+// error-pattern: the resulting pointer would point into
+
+fn main() {
+    let a = [0u8; 4];
+    let b = [0u8; 4];
+    let a_ptr = a.as_ptr();
+    // Give both allocations an address, so we can tell where the offset pointer would end up.
+    // Addresses are randomized, so `b` may be below `a`.
+    let a_addr = a_ptr as usize;
+    let b_addr = b.as_ptr() as usize;
+    let distance = b_addr.wrapping_sub(a_addr) as isize;
+    let _val = unsafe { a_ptr.offset(distance) };
+}
@@ -0,0 +1,10 @@
+// ignore-windows: No libc on Windows
+
+#![feature(rustc_private)]
+extern crate libc;
+
+fn main() {
+    unsafe {
+        libc::raise(libc::SIGUSR1); //~ ERROR Miri does not support sending signals
+    }
+}
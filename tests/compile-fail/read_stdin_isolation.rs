@@ -0,0 +1,10 @@
+// ignore-windows: Uses POSIX APIs
+
+#![feature(rustc_private)]
+extern crate libc;
+
+fn main() {
+    // Only the descriptors that Miri emulates in memory can be used under isolation.
+    let mut buf = [0u8; 4];
+    unsafe { libc::read(0, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) }; //~ ERROR `read` not available when isolation is enabled
+}
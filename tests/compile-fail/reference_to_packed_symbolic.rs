@@ -0,0 +1,18 @@
+// compile-flags: -Zmiri-symbolic-alignment-check
+
+#![allow(dead_code, unused_variables)]
+
+#[repr(packed)]
+struct Foo {
+    x: i32,
+    y: i32,
+}
+
+fn main() {
+    let foo = Foo {
+        x: 42,
+        y: 99,
+    };
+    // The reference is never used, but creating it is UB already.
+    let p = unsafe { &foo.y }; //~ ERROR creating a reference to field `y` of packed struct `Foo`
+}
@@ -0,0 +1,17 @@
+// ignore-windows: No libc on Windows
+
+#![feature(rustc_private)]
+extern crate libc;
+
+extern "C" {
+    // The size of `jmp_buf` differs between targets, so this just uses a buffer that is large
+    // enough for all of them.
+    fn setjmp(env: *mut u64) -> libc::c_int;
+}
+
+fn main() {
+    let mut env = [0u64; 64];
+    unsafe {
+        setjmp(env.as_mut_ptr()); //~ ERROR Miri does not support setjmp/longjmp
+    }
+}
@@ -0,0 +1,7 @@
+// ignore-windows: File handling is not implemented yet
+// compile-flags: -Zmiri-shim-report
+// error-pattern: rmdir: 1 call, rejected by isolation
+
+fn main() {
+    let _ = std::fs::remove_dir("miri_test_shim_report");
+}
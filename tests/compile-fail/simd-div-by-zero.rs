@@ -0,0 +1,18 @@
+#![feature(repr_simd, platform_intrinsics)]
+#![allow(non_camel_case_types)]
+
+#[repr(simd)]
+#[derive(Copy, Clone)]
+struct i32x2(i32, i32);
+
+extern "platform-intrinsic" {
+    fn simd_div<T>(x: T, y: T) -> T;
+}
+
+fn main() {
+    unsafe {
+        let x = i32x2(1, 1);
+        let y = i32x2(1, 0);
+        simd_div(x, y); //~ ERROR dividing by zero in lane 1 of `simd_div`
+    }
+}
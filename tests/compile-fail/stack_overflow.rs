@@ -0,0 +1,14 @@
+// compile-flags: -Zmiri-stack-limit=40
+// error-pattern: stack overflow in interpreted program: reached the limit of 40 stack frames
+
+fn even(n: u64) -> bool {
+    if n == 0 { true } else { odd(n - 1) }
+}
+
+fn odd(n: u64) -> bool {
+    if n == 0 { false } else { even(n - 1) }
+}
+
+fn main() {
+    even(1000);
+}
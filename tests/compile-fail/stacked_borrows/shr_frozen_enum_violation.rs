@@ -0,0 +1,14 @@
+use std::cell::Cell;
+
+#[allow(dead_code)]
+enum E {
+    A(Cell<u8>),
+    B(u32),
+}
+
+fn main() {
+    let x = E::B(0);
+    // Only the `Cell` of `E::A` may be written through a shared reference, not the discriminant.
+    let r = &x;
+    unsafe { *(r as *const E as *mut E) = E::A(Cell::new(1)); } //~ ERROR borrow stack
+}
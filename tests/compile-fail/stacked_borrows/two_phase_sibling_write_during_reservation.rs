@@ -0,0 +1,19 @@
+// error-pattern: does not have an appropriate item in the borrow stack
+// A reservation goes on top of the stack, so writing through a raw pointer that was created
+// before it invalidates it. (When two-phase borrows were `SharedReadWrite`, they were inserted
+// next to the `SharedReadWrite` of `raw`, and the write kept them.)
+
+trait S: Sized {
+    fn tpb(&mut self, _s: i32) {}
+}
+
+impl S for i32 {}
+
+fn main() {
+    let mut x = 0;
+    let raw = &mut x as *mut i32;
+    x.tpb(unsafe {
+        *raw = 1;
+        2
+    });
+}
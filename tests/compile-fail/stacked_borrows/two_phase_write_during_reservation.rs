@@ -0,0 +1,19 @@
+// error-pattern: does not have an appropriate item in the borrow stack
+// A two-phase borrow is only a reservation until it is used: writing to the borrowed place in the
+// meantime invalidates it.
+
+trait S: Sized {
+    fn tpb(&mut self, _s: i32) {}
+}
+
+impl S for i32 {}
+
+fn main() {
+    let mut x = 0;
+    let raw = &mut x as *mut i32;
+    let r = unsafe { &mut *raw };
+    r.tpb(unsafe {
+        *raw = 1;
+        2
+    });
+}
@@ -0,0 +1,16 @@
+// ignore-windows: No libc on Windows
+
+#![feature(rustc_private)]
+
+extern crate libc;
+
+use std::mem::MaybeUninit;
+
+fn main() {
+    let mut buf = [MaybeUninit::<u8>::uninit(); 4];
+    buf[0] = MaybeUninit::new(b'a');
+    buf[3] = MaybeUninit::new(0);
+    unsafe {
+        libc::strlen(buf.as_ptr().cast()); //~ ERROR `strlen` read uninitialized memory at offset 1
+    }
+}
@@ -0,0 +1,10 @@
+// ignore-windows: TCP sockets are only emulated on Unix targets
+// ignore-macos: `std` uses `ioctl` to set up sockets on macOS
+// error-pattern: deadlocked: accepting a connection on a socket that nobody connects to
+
+use std::net::TcpListener;
+
+fn main() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.accept().unwrap();
+}
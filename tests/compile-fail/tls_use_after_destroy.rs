@@ -0,0 +1,33 @@
+// ignore-windows: Uses POSIX APIs
+// error-pattern: after its own destructor already ran
+
+#![feature(rustc_private)]
+extern crate libc;
+
+use std::mem;
+
+static mut KEYS: [libc::pthread_key_t; 2] = [0; 2];
+static mut VALUE: u32 = 42;
+
+unsafe fn create(dtor: unsafe extern "C" fn(*mut u8)) -> libc::pthread_key_t {
+    let mut key = 0;
+    assert_eq!(libc::pthread_key_create(&mut key, mem::transmute(Some(dtor))), 0);
+    key
+}
+
+unsafe extern "C" fn first_dtor(_ptr: *mut u8) {}
+
+unsafe extern "C" fn second_dtor(_ptr: *mut u8) {
+    // The first key was destroyed before this destructor runs, so this gets `NULL`.
+    assert!(libc::pthread_getspecific(KEYS[0]).is_null());
+}
+
+fn main() {
+    unsafe {
+        KEYS[0] = create(first_dtor);
+        KEYS[1] = create(second_dtor);
+        let value = &mut VALUE as *mut u32 as *mut libc::c_void;
+        assert_eq!(libc::pthread_setspecific(KEYS[0], value), 0);
+        assert_eq!(libc::pthread_setspecific(KEYS[1], value), 0);
+    }
+}
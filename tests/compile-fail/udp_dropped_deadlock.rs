@@ -0,0 +1,14 @@
+// ignore-windows: UDP sockets are only emulated on Unix targets
+// ignore-macos: `std` uses `ioctl` to set up sockets on macOS
+// compile-flags: -Zmiri-udp-drop-rate=1
+// error-pattern: deadlocked: receiving on a UDP socket that nobody sends any datagrams to
+
+use std::net::UdpSocket;
+
+fn main() {
+    let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+    a.send_to(b"lost", b.local_addr().unwrap()).unwrap();
+    let mut buf = [0; 4];
+    b.recv(&mut buf).unwrap();
+}
@@ -0,0 +1,13 @@
+// compile-flags: -Zmiri-dealloc-backtraces
+// error-pattern: was deallocated here
+
+fn free(b: Box<i32>) {
+    drop(b);
+}
+
+fn main() {
+    let b = Box::new(42);
+    let ptr: *const i32 = &*b;
+    free(b);
+    let _x = unsafe { *ptr };
+}
@@ -0,0 +1,8 @@
+// error-pattern: was deallocated here
+
+fn main() {
+    let b = Box::new(42);
+    let ptr: *const i32 = &*b;
+    drop(b);
+    let _x = unsafe { *ptr };
+}
@@ -0,0 +1,10 @@
+// error-pattern: was deallocated here
+
+use std::mem;
+
+fn main() {
+    let b = Box::new(42);
+    let ptr: *const i32 = &*b;
+    drop(b);
+    let _x: &i32 = unsafe { mem::transmute(ptr) };
+}
@@ -0,0 +1,11 @@
+// compile-flags: -Zmiri-ignore-validity=references,ranges
+// error-pattern: but expected a valid enum discriminant
+
+#[repr(C)]
+pub enum Foo {
+    A, B, C, D
+}
+
+fn main() {
+    let _f = unsafe { std::mem::transmute::<i32, Foo>(42) };
+}
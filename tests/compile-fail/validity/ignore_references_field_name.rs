@@ -0,0 +1,13 @@
+// compile-flags: -Zmiri-ignore-validity=references
+// Field names that mention references or boxes do not make a failure about a reference.
+
+#[allow(dead_code)]
+#[repr(C)]
+struct Message {
+    inbox: bool,
+    reference: u8,
+}
+
+fn main() {
+    let _m = unsafe { std::mem::transmute::<[u8; 2], Message>([3, 0]) }; //~ ERROR encountered 3 at .inbox, but expected something less or equal to 1
+}
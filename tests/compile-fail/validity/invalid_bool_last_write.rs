@@ -0,0 +1,9 @@
+// compile-flags: -Zmiri-track-last-write
+// error-pattern: bytes 1..2 of alloc
+// error-pattern: were last written at
+
+fn main() {
+    let mut buf = [0u8; 4];
+    buf[1] = 3;
+    let _b: bool = unsafe { *(buf.as_ptr().add(1) as *const bool) };
+}
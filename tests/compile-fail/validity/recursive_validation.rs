@@ -0,0 +1,8 @@
+// compile-flags: -Zmiri-recursive-validation=full
+// error-pattern: but expected something less or equal to 1
+
+fn main() {
+    let x = 2u8;
+    // The reference itself is fine, but it points to an invalid `bool`.
+    let _r: &bool = unsafe { &*(&x as *const u8 as *const bool) };
+}
@@ -0,0 +1,7 @@
+// Without the engine's validation, the reference is first checked when it is retagged.
+// compile-flags: -Zmiri-recursive-validation=off
+
+fn main() {
+    let x = 0u32;
+    let _s: &[u32] = unsafe { std::mem::transmute((&x, usize::max_value() / 2)) }; //~ ERROR do not fit into the address space
+}
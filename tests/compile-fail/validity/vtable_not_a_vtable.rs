@@ -0,0 +1,21 @@
+use std::fmt;
+
+// Looks like the vtable of `Debug` for `u8`, but is not one.
+#[repr(C)]
+struct Vtable {
+    drop: unsafe fn(*mut u8),
+    size: usize,
+    align: usize,
+    fmt: fn(&u8, &mut fmt::Formatter<'_>) -> fmt::Result,
+}
+
+fn main() {
+    let vtable = Vtable {
+        drop: std::ptr::drop_in_place::<u8>,
+        size: 1,
+        align: 1,
+        fmt: <u8 as fmt::Debug>::fmt,
+    };
+    let x = 0u8;
+    let _debug: &dyn fmt::Debug = unsafe { std::mem::transmute((&x, &vtable)) }; //~ ERROR does not point to a vtable
+}
@@ -0,0 +1,9 @@
+use std::fmt;
+
+fn main() {
+    let x = 42u32;
+    let debug: &dyn fmt::Debug = &x;
+    // The vtable of `Debug` has the same size as the one of `Display`.
+    let display: &dyn fmt::Display = unsafe { std::mem::transmute(debug) }; //~ ERROR is a vtable for another trait
+    println!("{}", display);
+}
@@ -0,0 +1,8 @@
+// compile-flags: -Zmiri-deny=volatile-spin
+// error-pattern: with `read_volatile` 65536 times in a row
+
+fn main() {
+    let flag = 0u32;
+    // Nothing can ever change `flag`, so this loop never ends.
+    while unsafe { std::ptr::read_volatile(&flag) } == 0 {}
+}
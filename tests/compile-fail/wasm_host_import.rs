@@ -0,0 +1,15 @@
+// ignore-linux: Uses wasm-only APIs
+// ignore-macos: Uses wasm-only APIs
+// ignore-windows: Uses wasm-only APIs
+// ignore-freebsd: Uses wasm-only APIs
+// ignore-android: Uses wasm-only APIs
+// error-pattern: imported from the wasm host
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn host_log(x: i32);
+}
+
+fn main() {
+    unsafe { host_log(42) };
+}
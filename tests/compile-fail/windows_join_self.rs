@@ -0,0 +1,17 @@
+// ignore-linux: Uses Windows-only APIs
+// ignore-macos: Uses Windows-only APIs
+// error-pattern: deadlocked: the current thread is waiting for itself to terminate
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetCurrentThread() -> isize;
+    fn WaitForSingleObject(handle: isize, timeout: u32) -> u32;
+}
+
+const INFINITE: u32 = 0xFFFFFFFF;
+
+fn main() {
+    unsafe {
+        WaitForSingleObject(GetCurrentThread(), INFINITE);
+    }
+}
@@ -3,10 +3,14 @@
 #![test_runner(test_runner)]
 
 use std::env;
+use std::fs;
+use std::io::Read;
 use std::path::PathBuf;
+use std::process::{Command, Output};
 
 use colored::*;
 use compiletest_rs as compiletest;
+use flate2::read::GzDecoder;
 
 fn miri_path() -> PathBuf {
     if rustc_test_suite().is_some() {
@@ -120,6 +124,181 @@ fn compile_fail_miri(opt: bool) {
     compile_fail("tests/compile-fail", &get_target(), opt);
 }
 
+/// Runs the Miri binary directly, for the features of its command line that compiletest cannot
+/// check.
+fn run_miri(args: &[&str]) -> Output {
+    Command::new(miri_path()).args(args).output().expect("failed to run miri")
+}
+
+/// Runs Miri on the program `tests/cli/<name>.rs`, with the Miri flags `flags`.
+fn run_miri_on(name: &str, flags: &[&str]) -> Output {
+    let mut cmd = Command::new(miri_path());
+    cmd.arg(format!("tests/cli/{}.rs", name));
+    cmd.args(&["--edition", "2018", "--target", &get_target()]);
+    if let Ok(sysroot) = env::var("MIRI_SYSROOT") {
+        cmd.arg("--sysroot").arg(sysroot);
+    }
+    cmd.args(flags);
+    cmd.output().expect("failed to run miri")
+}
+
+/// A path for a file that Miri writes in a test, which is removed if it exists.
+fn temp_file(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("miri-test-{}-{}", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn explain_miri() {
+    eprintln!("{}", "## Running `miri --explain` tests".green().bold());
+
+    let output = run_miri(&["--explain", "miriub0003"]);
+    assert!(output.status.success(), "`miri --explain` failed: {:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("MIRIUB0003: invalid value\n\n"), "unexpected output: {}", stdout);
+
+    // Unknown codes fail, and list the known ones.
+    let output = run_miri(&["--explain", "MIRIUB9999"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("    MIRIUB0001: dangling or out-of-bounds pointer\n"), "{}", stderr);
+}
+
+fn exit_codes_miri() {
+    eprintln!("{}", "## Running exit code tests".green().bold());
+
+    for &(mode, code) in &[("abort", 1), ("unsupported", 2), ("ub", 3), ("stack-overflow", 4)] {
+        let output = run_miri_on("exit_codes", &["-Zmiri-stack-limit=20", "--", mode]);
+        let status = output.status.code();
+        assert_eq!(status, Some(code), "unexpected exit status for {}: {:?}", mode, output);
+    }
+}
+
+fn many_seeds_miri() {
+    eprintln!("{}", "## Running `-Zmiri-many-seeds` tests".green().bold());
+
+    // The seeds run in order, and the first failing one ends the run with its exit code.
+    let output = run_miri_on("many_seeds_exit", &["-Zmiri-many-seeds=0..10"]);
+    assert_eq!(output.status.code(), Some(42), "unexpected exit status: {:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, "seed 0\nseed 1\nseed 2\nseed 3\n");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("the program returned 42 with seed 3"), "{}", stderr);
+}
+
+fn miri_extern_miri() {
+    eprintln!("{}", "## Running `miri_*` extern function tests".green().bold());
+
+    let output = run_miri_on("miri_extern_debug", &[]);
+    assert!(output.status.success(), "miri failed: {:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    // The stack of the box has its `Unique` item, and the shared reference on top of it.
+    assert!(stderr.contains("Borrow stacks of alloc"), "{}", stderr);
+    let stack = stderr.lines().find(|line| line.starts_with("0..1: ")).expect(&stderr);
+    assert!(stack.contains("[Unique for ") && stack.contains("[SharedReadOnly for "), "{}", stack);
+    // The backtrace is a note with the frames of the call.
+    assert!(stderr.contains("backtrace requested by `miri_backtrace`"), "{}", stderr);
+    assert!(stderr.contains("inside call to `helper`"), "{}", stderr);
+}
+
+fn coverage_miri() {
+    eprintln!("{}", "## Running `-Zmiri-coverage` tests".green().bold());
+
+    let file = temp_file("coverage.txt");
+    let output = run_miri_on("coverage", &[&format!("-Zmiri-coverage={}", file.display())]);
+    assert!(output.status.success(), "miri failed: {:?}", output);
+    let report = fs::read_to_string(&file).expect("the coverage report was not written");
+    fs::remove_file(&file).unwrap();
+
+    // Each function has a line with its counts, followed by a line with the executed locations.
+    let counts = |name: &str| {
+        let mut lines = report.lines().skip_while(|line| !line.starts_with(&format!("{}: ", name)));
+        let line = lines.next()?;
+        let counts = line[name.len() + 2..].trim_end_matches(" statements executed");
+        let mut counts = counts.split('/').map(|count| count.parse::<usize>().expect(line));
+        let locations = lines.next().expect(&report);
+        assert!(locations.starts_with("    bb0[0]"), "{}", report);
+        Some((counts.next().unwrap(), counts.next().unwrap()))
+    };
+    // Only one branch of each `if` is taken.
+    let (executed, total) = counts("covered").expect(&report);
+    assert!(0 < executed && executed < total, "{}", report);
+    let (executed, total) = counts("main").expect(&report);
+    assert!(0 < executed && executed < total, "{}", report);
+    assert_eq!(counts("uncalled"), None, "{}", report);
+}
+
+fn trace_miri() {
+    eprintln!("{}", "## Running `-Zmiri-trace` tests".green().bold());
+
+    let file = temp_file("trace.gz");
+    let output = run_miri_on("trace", &[&format!("-Zmiri-trace={}", file.display())]);
+    assert!(output.status.success(), "miri failed: {:?}", output);
+    let mut trace = String::new();
+    GzDecoder::new(fs::File::open(&file).unwrap()).read_to_string(&mut trace).unwrap();
+    fs::remove_file(&file).unwrap();
+
+    // Every line starts with the step, which never decreases, and the thread.
+    let mut last_step = 0;
+    for line in trace.lines() {
+        let mut fields = line.splitn(3, ' ');
+        let step: u64 = fields.next().unwrap().parse().expect(line);
+        assert!(step >= last_step, "steps out of order: {}", line);
+        last_step = step;
+        assert_eq!(fields.next(), Some("0"), "{}", line);
+    }
+    let position = |event: &str| {
+        trace
+            .lines()
+            .position(|line| line.contains(event) && line.ends_with("helper`"))
+            .unwrap_or_else(|| panic!("no `{}` of `helper` in the trace:\n{}", event, trace))
+    };
+    assert!(position(" enter `") < position(" exit `"));
+}
+
+fn repeated_diagnostics_miri() {
+    eprintln!("{}", "## Running repeated diagnostics tests".green().bold());
+
+    let output = run_miri_on("repeated_int2ptr", &["-Zmiri-warn=int2ptr"]);
+    assert!(output.status.success(), "miri failed: {:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    // Once as the warning, and once in the summary.
+    assert_eq!(stderr.matches("an integer was used as a pointer to").count(), 2, "{}", stderr);
+    assert!(stderr.contains("(repeated 4 more times)"), "{}", stderr);
+}
+
+fn log_file_miri() {
+    eprintln!("{}", "## Running `-Zmiri-log-file` tests".green().bold());
+
+    // `{crate}` is replaced by the name of the crate.
+    let file = temp_file("log-log_file.txt");
+    let path = file.to_str().unwrap().replace("log_file", "{crate}");
+    let output = run_miri_on("log_file", &[&format!("-Zmiri-log-file={}", path)]);
+    assert_eq!(output.status.code(), Some(3), "unexpected exit status: {:?}", output);
+    let log = fs::read_to_string(&file).expect("the log file was not written");
+    fs::remove_file(&file).unwrap();
+    assert!(log.starts_with("error[MIRIUB0001]: dangling pointer was dereferenced\n"), "{}", log);
+    assert!(log.contains("\n  --> tests/cli/log_file.rs:7:"), "{}", log);
+
+    let file = temp_file("log.json");
+    let output = run_miri_on(
+        "log_file",
+        &[&format!("-Zmiri-log-file={}", file.display()), "-Zmiri-log-format=json"],
+    );
+    assert_eq!(output.status.code(), Some(3), "unexpected exit status: {:?}", output);
+    let log = fs::read_to_string(&file).expect("the log file was not written");
+    fs::remove_file(&file).unwrap();
+    let error = log.lines().next().unwrap();
+    assert!(
+        error.starts_with(
+            r#"{"level":"error","code":"MIRIUB0001","message":"dangling pointer was dereferenced","#
+        ),
+        "{}",
+        log
+    );
+    assert!(log.lines().all(|line| line.starts_with('{') && line.ends_with('}')), "{}", log);
+}
+
 fn test_runner(_tests: &[&()]) {
     // Add a test env var to do environment communication tests
     std::env::set_var("MIRI_ENV_VAR_TEST", "0");
@@ -129,4 +308,13 @@ fn test_runner(_tests: &[&()]) {
 
     compile_fail_miri(false);
     compile_fail_miri(true);
+
+    explain_miri();
+    exit_codes_miri();
+    many_seeds_miri();
+    miri_extern_miri();
+    coverage_miri();
+    trace_miri();
+    repeated_diagnostics_miri();
+    log_file_miri();
 }
@@ -0,0 +1,92 @@
+//! Runs a program with `ForeignItemHook`s registered, as a tool embedding Miri would.
+#![feature(rustc_private)]
+
+extern crate miri;
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_interface;
+
+use std::env;
+use std::sync::{Arc, Mutex};
+
+use rustc_driver::Compilation;
+use rustc_hir::def_id::LOCAL_CRATE;
+use rustc_interface::{interface, Queries};
+
+use miri::{ForeignItemHook, InterpResult, MiriConfig, MiriEvalContext, OpTy, PlaceTy, Scalar, Tag};
+
+struct HookCompilerCalls {
+    miri_config: MiriConfig,
+    return_code: Option<i64>,
+}
+
+impl rustc_driver::Callbacks for HookCompilerCalls {
+    fn after_analysis<'tcx>(
+        &mut self,
+        compiler: &interface::Compiler,
+        queries: &'tcx Queries<'tcx>,
+    ) -> Compilation {
+        compiler.session().abort_if_errors();
+        queries.global_ctxt().unwrap().peek_mut().enter(|tcx| {
+            let (entry_def_id, _) = tcx.entry_fn(LOCAL_CRATE).expect("no main function found!");
+            let outcome = miri::eval_main(tcx, entry_def_id, self.miri_config.clone());
+            self.return_code = outcome.return_code;
+        });
+        Compilation::Stop
+    }
+}
+
+/// Turns `f` into a hook. Passing the closure through this function gives it the signature of a
+/// hook, which closures only infer from their use otherwise.
+fn hook<F>(f: F) -> ForeignItemHook
+where
+    F: for<'mir, 'tcx> Fn(
+            &mut MiriEvalContext<'mir, 'tcx>,
+            &str,
+            &[OpTy<'tcx, Tag>],
+            PlaceTy<'tcx, Tag>,
+        ) -> InterpResult<'tcx, bool>
+        + Send
+        + Sync
+        + 'static,
+{
+    Arc::new(f)
+}
+
+#[test]
+fn foreign_item_hooks() {
+    let called = Arc::new(Mutex::new(Vec::new()));
+    let mut miri_config = MiriConfig::default();
+    // The first hook only records the calls; it handles none of them, so the next hook is tried.
+    let record = called.clone();
+    miri_config.foreign_item_hooks.push(hook(move |_, link_name, _, _| {
+        record.lock().unwrap().push(link_name.to_owned());
+        Ok(false)
+    }));
+    // The second hook handles `strlen`, which Miri also has a built-in shim for.
+    miri_config.foreign_item_hooks.push(hook(|this, link_name, _, dest| {
+        if link_name != "strlen" {
+            return Ok(false);
+        }
+        this.write_scalar(Scalar::from_uint(42u64, dest.layout.size), dest)?;
+        Ok(true)
+    }));
+
+    let mut args: Vec<String> = vec!["miri".into(), "tests/cli/foreign_item_hooks.rs".into()];
+    args.extend(miri::miri_default_args().iter().map(ToString::to_string));
+    args.extend(vec!["--edition".into(), "2018".into()]);
+    if let Ok(target) = env::var("MIRI_TEST_TARGET") {
+        args.extend(vec!["--target".into(), target]);
+    }
+    let sysroot = env::var("MIRI_SYSROOT").expect("set MIRI_SYSROOT to a Miri-enabled sysroot");
+    args.extend(vec!["--sysroot".into(), sysroot]);
+
+    let mut calls = HookCompilerCalls { miri_config, return_code: None };
+    rustc_driver::catch_fatal_errors(|| rustc_driver::run_compiler(&args, &mut calls, None, None))
+        .expect("compilation failed")
+        .expect("compilation failed");
+    assert_eq!(calls.return_code, Some(0), "the program failed");
+    let called = called.lock().unwrap();
+    assert!(called.iter().any(|name| name == "strlen"), "{:?}", called);
+    assert!(called.iter().any(|name| name == "memcmp"), "{:?}", called);
+}
@@ -0,0 +1,23 @@
+// ignore-wasm32: the linear memory gets compact addresses
+
+fn addr<T>(x: &T) -> usize {
+    x as *const T as usize
+}
+
+fn main() {
+    // Allocations are exactly as aligned as they have to be, never more.
+    let byte = Box::new(0u8);
+    assert_eq!(addr(&*byte) % 2, 1);
+    let word = Box::new(0u32);
+    assert_eq!(addr(&*word) % 8, 4);
+    let local = [0u16; 4];
+    assert_eq!(addr(&local) % 4, 2);
+
+    // Allocations do not overlap.
+    let boxes: Vec<Box<[u8; 64]>> = (0..32).map(|_| Box::new([0; 64])).collect();
+    let mut addrs: Vec<usize> = boxes.iter().map(|b| addr(&**b)).collect();
+    addrs.sort();
+    for pair in addrs.windows(2) {
+        assert!(pair[0] + 64 <= pair[1]);
+    }
+}
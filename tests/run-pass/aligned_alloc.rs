@@ -0,0 +1,46 @@
+// ignore-windows: Uses POSIX APIs
+
+#![feature(rustc_private)]
+
+extern crate libc;
+
+use std::ptr;
+
+extern "C" {
+    // Not available in all versions of the `libc` crate.
+    fn aligned_alloc(align: libc::size_t, size: libc::size_t) -> *mut libc::c_void;
+}
+
+fn main() {
+    unsafe {
+        // posix_memalign
+        for &align in &[8, 16, 64, 4096] {
+            let mut p = ptr::null_mut();
+            assert_eq!(libc::posix_memalign(&mut p, align, 100), 0);
+            assert!(!p.is_null());
+            assert_eq!(p as usize % align, 0);
+            libc::free(p);
+        }
+        // Invalid alignments are reported as errors, and leave the pointer alone.
+        let mut p = ptr::null_mut();
+        assert_eq!(libc::posix_memalign(&mut p, 24, 100), libc::EINVAL);
+        assert_eq!(libc::posix_memalign(&mut p, 2, 100), libc::EINVAL);
+        assert!(p.is_null());
+
+        // aligned_alloc
+        let p = aligned_alloc(256, 512);
+        assert!(!p.is_null());
+        assert_eq!(p as usize % 256, 0);
+        libc::free(p);
+        assert!(aligned_alloc(3, 12).is_null());
+
+        // memalign
+        #[cfg(target_os = "linux")]
+        {
+            let p = libc::memalign(32, 5);
+            assert!(!p.is_null());
+            assert_eq!(p as usize % 32, 0);
+            libc::free(p);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+// ignore-linux: Uses Android-only APIs
+// ignore-macos: Uses Android-only APIs
+// ignore-windows: Uses Android-only APIs
+// ignore-freebsd: Uses Android-only APIs
+// compile-flags: -Zmiri-disable-isolation
+
+#![feature(rustc_private)]
+extern crate libc;
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::{remove_file, File};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+fn main() {
+    // Environment
+    env::set_var("MIRI_ANDROID_TEST", "1");
+    assert_eq!(env::var("MIRI_ANDROID_TEST").unwrap(), "1");
+    let mut value = [1 as libc::c_char; 92]; // PROP_VALUE_MAX
+    let name = b"ro.build.version.sdk\0";
+    let len = unsafe { libc::__system_property_get(name.as_ptr().cast(), value.as_mut_ptr()) };
+    assert_eq!(len, 0);
+    assert_eq!(value[0], 0);
+
+    // Randomness (via the `getrandom` syscall)
+    let mut map = HashMap::new();
+    map.insert(1, 2);
+    assert_eq!(map[&1], 2);
+
+    // Files, and `errno` (via `__errno`)
+    assert_eq!(File::open("/does/not/exist").unwrap_err().kind(), ErrorKind::NotFound);
+    // `temp_dir` would be the Android default, which the host does not have.
+    let tmp = env::var_os("TMPDIR").map_or_else(|| PathBuf::from("/tmp"), PathBuf::from);
+    let path = tmp.join("miri_test_android.txt");
+    let mut file = File::create(&path).unwrap();
+    file.write_all(b"hello").unwrap();
+    assert_eq!(file.metadata().unwrap().len(), 5);
+    assert!(path.metadata().unwrap().is_file());
+    remove_file(&path).unwrap();
+
+    // Time
+    let now = SystemTime::now();
+    assert!(now.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() > 0);
+}
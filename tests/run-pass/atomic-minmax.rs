@@ -0,0 +1,43 @@
+#![feature(atomic_min_max, core_intrinsics)]
+
+use std::intrinsics;
+use std::sync::atomic::{AtomicI32, AtomicU64, Ordering::*};
+
+fn min_max() {
+    let x = AtomicI32::new(-5);
+    assert_eq!(x.fetch_max(3, SeqCst), -5);
+    assert_eq!(x.fetch_min(-10, Relaxed), 3);
+    assert_eq!(x.fetch_max(-20, AcqRel), -10);
+    assert_eq!(x.load(SeqCst), -10);
+
+    let y = AtomicU64::new(5);
+    assert_eq!(y.fetch_max(u64::max_value(), Acquire), 5);
+    assert_eq!(y.fetch_min(7, Release), u64::max_value());
+    assert_eq!(y.load(SeqCst), 7);
+
+    // The unsigned operations compare unsigned, whatever the type.
+    let mut z = -1i32;
+    unsafe {
+        assert_eq!(intrinsics::atomic_umin(&mut z, 1), -1);
+    }
+    assert_eq!(z, 1);
+}
+
+fn u128_ops() {
+    #[repr(align(16))]
+    struct Aligned(u128);
+    let mut x = Aligned(1 << 100);
+    let ptr = &mut x.0 as *mut u128;
+    unsafe {
+        assert_eq!(intrinsics::atomic_cxchg(ptr, 1 << 100, 42), (1 << 100, true));
+        assert_eq!(intrinsics::atomic_cxchg(ptr, 0, 1), (42, false));
+        assert_eq!(intrinsics::atomic_xadd(ptr, u128::max_value()), 42);
+        assert_eq!(intrinsics::atomic_umax(ptr, 1 << 127), 41);
+        assert_eq!(intrinsics::atomic_load(ptr), 1 << 127);
+    }
+}
+
+fn main() {
+    min_max();
+    u128_ops();
+}
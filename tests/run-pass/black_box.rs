@@ -0,0 +1,25 @@
+#![feature(test)]
+extern crate test;
+
+use test::black_box;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Op {
+    Add(i32),
+    Neg,
+}
+
+fn main() {
+    assert_eq!(black_box(42u8), 42);
+    assert_eq!(black_box("hello"), "hello");
+    assert_eq!(black_box(Op::Add(3)), Op::Add(3));
+    assert_eq!(black_box(Op::Neg), Op::Neg);
+    assert_eq!(black_box(vec![1, 2, 3]), [1, 2, 3]);
+
+    // A typical benchmark loop.
+    let mut sum = 0u64;
+    for i in 0..100 {
+        sum += black_box(i);
+    }
+    assert_eq!(black_box(sum), 4950);
+}
@@ -0,0 +1,9 @@
+// compile-flags: -Zmiri-break-on-alloc=1 -Zmiri-break-on-tag=1
+// Without the debugger, the breakpoints only log, and the program runs to completion.
+
+fn main() {
+    let mut x = Box::new(1u8);
+    let r = &mut *x;
+    *r += 1;
+    assert_eq!(*x, 2);
+}
@@ -8,19 +8,33 @@ extern crate libc;
 
 fn main() {
     unsafe {
+        // Zero-sized allocations return unique pointers that must be freed.
         let p1 = libc::calloc(0, 0);
-        assert!(p1.is_null());
+        assert!(!p1.is_null());
 
         let p2 = libc::calloc(20, 0);
-        assert!(p2.is_null());
+        assert!(!p2.is_null());
+        assert_ne!(p1, p2);
 
         let p3 = libc::calloc(0, 20);
-        assert!(p3.is_null());
+        assert!(!p3.is_null());
+        libc::free(p1);
+        libc::free(p2);
+        libc::free(p3);
 
         let p4 = libc::calloc(4, 8);
         assert!(!p4.is_null());
         let slice = slice::from_raw_parts(p4 as *const u8, 4 * 8);
         assert_eq!(&slice, &[0_u8; 4 * 8]);
         libc::free(p4);
+
+        // Huge zeroed buffers are fine as long as only a small part of them is used.
+        let size = 1 << 30;
+        let p5 = libc::calloc(size, 1) as *mut u8;
+        assert!(!p5.is_null());
+        assert_eq!(*p5.add(size / 2), 0);
+        *p5.add(size - 1) = 42;
+        assert_eq!(*p5.add(size - 1), 42);
+        libc::free(p5 as *mut libc::c_void);
     }
 }
@@ -0,0 +1,21 @@
+#![feature(intrinsics)]
+
+// Directly call intrinsic to avoid debug assertions in libstd
+extern "rust-intrinsic" {
+    fn copy_nonoverlapping<T>(src: *const T, dst: *mut T, count: usize);
+}
+
+fn main() {
+    let mut data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+    unsafe {
+        let a = data.as_mut_ptr();
+        // Ranges that are directly adjacent do not overlap, in both directions.
+        copy_nonoverlapping(a, a.wrapping_add(4), 4);
+        assert_eq!(data, [1, 2, 3, 4, 1, 2, 3, 4]);
+        let a = data.as_mut_ptr();
+        copy_nonoverlapping(a.wrapping_add(2), a, 2);
+        assert_eq!(data, [3, 4, 3, 4, 1, 2, 3, 4]);
+        // Zero-sized copies never overlap.
+        copy_nonoverlapping(a, a, 0);
+    }
+}
@@ -0,0 +1,13 @@
+// compile-flags: -Zmiri-cpu-features=sse,sse2,avx,avx2
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn main() {
+    assert!(is_x86_feature_detected!("sse2"));
+    assert!(is_x86_feature_detected!("avx"));
+    assert!(is_x86_feature_detected!("avx2"));
+    assert!(!is_x86_feature_detected!("sse4.2"));
+    assert!(!is_x86_feature_detected!("avx512f"));
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn main() {}
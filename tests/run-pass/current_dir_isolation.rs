@@ -0,0 +1,15 @@
+// ignore-windows: TODO the windows hook is not done yet
+use std::env;
+use std::path::Path;
+
+fn main() {
+    // Under isolation, the program starts in the root directory...
+    assert_eq!(env::current_dir().unwrap(), Path::new("/"));
+    // ... and can change to any directory, as there is no file system to check.
+    env::set_current_dir("/miri/test/../dir").unwrap();
+    assert_eq!(env::current_dir().unwrap(), Path::new("/miri/dir"));
+    env::set_current_dir("./sub").unwrap();
+    assert_eq!(env::current_dir().unwrap(), Path::new("/miri/dir/sub"));
+    env::set_current_dir("../../../..").unwrap();
+    assert_eq!(env::current_dir().unwrap(), Path::new("/"));
+}
@@ -0,0 +1,7 @@
+// compile-flags: -Zmiri-debug
+// Without commands on stdin, the debugger lets the program run to completion.
+
+fn main() {
+    let v = vec![1, 2, 3];
+    println!("{}", v.iter().sum::<i32>());
+}
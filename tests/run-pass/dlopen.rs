@@ -0,0 +1,45 @@
+// ignore-windows: Uses POSIX APIs
+
+#![feature(rustc_private)]
+extern crate libc;
+
+use std::ffi::CStr;
+use std::mem;
+
+fn dlerror() -> Option<String> {
+    let error = unsafe { libc::dlerror() };
+    if error.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(error) }.to_string_lossy().into_owned())
+    }
+}
+
+fn main() {
+    unsafe {
+        // Loading a library fails gracefully.
+        let lib = libc::dlopen(b"libfoo.so\0".as_ptr().cast(), libc::RTLD_NOW);
+        assert!(lib.is_null());
+        assert!(dlerror().unwrap().starts_with("libfoo.so: "));
+        // The error is only reported once.
+        assert_eq!(dlerror(), None);
+
+        // The program itself can be opened, and symbols that Miri implements can be found in it.
+        let handle = libc::dlopen(std::ptr::null(), libc::RTLD_NOW);
+        assert!(!handle.is_null());
+        let getentropy = libc::dlsym(handle, b"getentropy\0".as_ptr().cast());
+        assert!(!getentropy.is_null());
+        let getentropy: unsafe extern "C" fn(*mut libc::c_void, libc::size_t) -> libc::c_int =
+            mem::transmute(getentropy);
+        let mut buf = [0u8; 16];
+        assert_eq!(getentropy(buf.as_mut_ptr().cast(), buf.len()), 0);
+
+        // Other symbols do not exist.
+        let missing = libc::dlsym(handle, b"foo_init\0".as_ptr().cast());
+        assert!(missing.is_null());
+        assert_eq!(dlerror().unwrap(), "undefined symbol: foo_init");
+        assert!(libc::dlsym(libc::RTLD_DEFAULT, b"foo_init\0".as_ptr().cast()).is_null());
+
+        assert_eq!(libc::dlclose(handle), 0);
+    }
+}
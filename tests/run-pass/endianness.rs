@@ -0,0 +1,66 @@
+// ignore-windows: File handling and networking are not implemented yet
+// ignore-macos: `std` uses `ioctl` to set up sockets on macOS
+// compile-flags: -Zmiri-disable-isolation
+
+//! Checks that the shims agree with the target about byte order. CI also runs this on a
+//! big-endian target.
+
+use std::fs::{remove_file, File};
+use std::io::Write;
+use std::net::{Ipv6Addr, SocketAddr, SocketAddrV6, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+fn test_integers() {
+    let x = 0x0102_0304u32;
+    #[cfg(target_endian = "little")]
+    assert_eq!(x.to_ne_bytes(), [4, 3, 2, 1]);
+    #[cfg(target_endian = "big")]
+    assert_eq!(x.to_ne_bytes(), [1, 2, 3, 4]);
+    assert_eq!(x.to_be_bytes(), [1, 2, 3, 4]);
+    assert_eq!(u32::from_ne_bytes(x.to_ne_bytes()), x);
+    assert_eq!(x.to_be(), u32::from_be_bytes(x.to_ne_bytes()));
+}
+
+fn test_atomics() {
+    let a = AtomicU32::new(0x0102_0304);
+    a.fetch_add(0x10, Ordering::SeqCst);
+    let bytes: [u8; 4] = unsafe { std::mem::transmute(a) };
+    assert_eq!(u32::from_ne_bytes(bytes), 0x0102_0314);
+}
+
+fn test_file_size() {
+    let path = std::env::temp_dir().join("miri_test_endianness.txt");
+    remove_file(&path).ok();
+    let mut file = File::create(&path).unwrap();
+    file.write_all(&[0; 258]).unwrap();
+    // A size whose bytes differ, so that swapping them would be noticed.
+    assert_eq!(file.metadata().unwrap().len(), 258);
+    drop(file);
+    remove_file(&path).unwrap();
+}
+
+fn test_sockaddr() {
+    // Ports are stored in network byte order, the address family in native byte order.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    assert!(addr.is_ipv4());
+    let stream = TcpStream::connect(addr).unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), addr);
+
+    let v6 = SocketAddrV6::new(Ipv6Addr::LOCALHOST, 0, 0, 0);
+    let socket = UdpSocket::bind(v6).unwrap();
+    let local = socket.local_addr().unwrap();
+    assert!(local.is_ipv6());
+    assert_ne!(local.port(), 0);
+    let target: SocketAddr = SocketAddrV6::new(Ipv6Addr::LOCALHOST, local.port(), 7, 3).into();
+    assert_eq!(socket.send_to(b"ping", target).unwrap(), 4);
+    let mut buf = [0; 4];
+    assert_eq!(socket.recv_from(&mut buf).unwrap(), (4, local));
+}
+
+fn main() {
+    test_integers();
+    test_atomics();
+    test_file_size();
+    test_sockaddr();
+}
@@ -0,0 +1,64 @@
+// ignore-windows: epoll is Linux-specific
+// ignore-macos: epoll is Linux-specific
+
+#![feature(rustc_private)]
+extern crate libc;
+
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::io::AsRawFd;
+
+fn add(epfd: i32, fd: i32, events: libc::c_int, data: u64) {
+    let mut event = libc::epoll_event { events: events as u32, u64: data };
+    assert_eq!(unsafe { libc::epoll_ctl(epfd, libc::EPOLL_CTL_ADD, fd, &mut event) }, 0);
+}
+
+fn wait(epfd: i32) -> Vec<(u32, u64)> {
+    let mut events = [libc::epoll_event { events: 0, u64: 0 }; 8];
+    let n = unsafe { libc::epoll_wait(epfd, events.as_mut_ptr(), events.len() as i32, 0) };
+    assert!(n >= 0);
+    events[..n as usize].iter().map(|e| (e.events, e.u64)).collect()
+}
+
+fn main() {
+    let epfd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+    assert!(epfd >= 0);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    add(epfd, listener.as_raw_fd(), libc::EPOLLIN, 1);
+    assert!(wait(epfd).is_empty());
+
+    // A pending connection makes the listener readable.
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    assert_eq!(wait(epfd), [(libc::EPOLLIN as u32, 1)]);
+    let (server, _) = listener.accept().unwrap();
+    assert!(wait(epfd).is_empty());
+
+    add(epfd, server.as_raw_fd(), libc::EPOLLIN | libc::EPOLLRDHUP | libc::EPOLLET, 2);
+    add(epfd, client.as_raw_fd(), libc::EPOLLOUT, 3);
+    assert_eq!(wait(epfd), [(libc::EPOLLOUT as u32, 3)]);
+
+    client.write_all(b"ping").unwrap();
+    let mut ready = wait(epfd);
+    ready.sort_by_key(|&(_, data)| data);
+    assert_eq!(ready, [(libc::EPOLLIN as u32, 2), (libc::EPOLLOUT as u32, 3)]);
+
+    // Registering twice fails, and so does removing something that is not registered.
+    let mut event = libc::epoll_event { events: libc::EPOLLIN as u32, u64: 0 };
+    unsafe {
+        assert_eq!(libc::epoll_ctl(epfd, libc::EPOLL_CTL_ADD, client.as_raw_fd(), &mut event), -1);
+        assert_eq!(*libc::__errno_location(), libc::EEXIST);
+        let res = libc::epoll_ctl(epfd, libc::EPOLL_CTL_DEL, client.as_raw_fd(), &mut event);
+        assert_eq!(res, 0);
+        let res = libc::epoll_ctl(epfd, libc::EPOLL_CTL_DEL, client.as_raw_fd(), &mut event);
+        assert_eq!(res, -1);
+        assert_eq!(*libc::__errno_location(), libc::ENOENT);
+    }
+
+    // Closing the client is reported as a read hangup.
+    drop(client);
+    let hangup = (libc::EPOLLIN | libc::EPOLLRDHUP) as u32;
+    assert_eq!(wait(epfd), [(hangup, 2)]);
+
+    assert_eq!(unsafe { libc::close(epfd) }, 0);
+}
@@ -0,0 +1,35 @@
+// ignore-windows: Uses POSIX APIs
+
+#![feature(rustc_private)]
+extern crate libc;
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::io::AsRawFd;
+
+fn main() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+    let fd = server.as_raw_fd();
+
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    assert_eq!(flags & libc::O_ACCMODE, libc::O_RDWR);
+    assert_eq!(flags & libc::O_NONBLOCK, 0);
+
+    // Setting `O_NONBLOCK` makes reads fail instead of waiting.
+    assert_eq!(unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) }, 0);
+    assert_ne!(unsafe { libc::fcntl(fd, libc::F_GETFL) } & libc::O_NONBLOCK, 0);
+    let mut buf = [0; 8];
+    assert_eq!(server.read(&mut buf).unwrap_err().kind(), ErrorKind::WouldBlock);
+    client.write_all(b"hello").unwrap();
+    assert_eq!(server.read(&mut buf).unwrap(), 5);
+
+    // `set_nonblocking` is visible through `F_GETFL`, and can be undone.
+    client.set_nonblocking(true).unwrap();
+    let flags = unsafe { libc::fcntl(client.as_raw_fd(), libc::F_GETFL) };
+    assert_ne!(flags & libc::O_NONBLOCK, 0);
+    assert_eq!(client.read(&mut buf).unwrap_err().kind(), ErrorKind::WouldBlock);
+    server.set_nonblocking(false).unwrap();
+    assert_eq!(unsafe { libc::fcntl(fd, libc::F_GETFL) } & libc::O_NONBLOCK, 0);
+}
@@ -0,0 +1,13 @@
+// ignore-windows: Uses POSIX APIs
+// compile-flags: -Zmiri-disable-isolation
+
+#![feature(rustc_private)]
+extern crate libc;
+
+fn main() {
+    assert_eq!(unsafe { libc::fcntl(0, libc::F_GETFL) } & libc::O_ACCMODE, libc::O_RDONLY);
+    assert_eq!(unsafe { libc::fcntl(1, libc::F_GETFL) } & libc::O_ACCMODE, libc::O_WRONLY);
+
+    assert_eq!(unsafe { libc::fcntl(1000, libc::F_GETFL) }, -1);
+    assert_eq!(std::io::Error::last_os_error().raw_os_error(), Some(libc::EBADF));
+}
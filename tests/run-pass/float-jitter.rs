@@ -0,0 +1,17 @@
+// compile-flags: -Zmiri-float-jitter
+
+fn main() {
+    let x = 1.0f64;
+    let results: Vec<u64> = (0..100).map(|_| x.sin().to_bits()).collect();
+    let min = *results.iter().min().unwrap();
+    let max = *results.iter().max().unwrap();
+    // All results are within one ULP of the exact result, but they are not all the same.
+    assert!(max - min <= 2);
+    assert!(max != min);
+
+    // Exact operations are not affected.
+    for _ in 0..10 {
+        assert_eq!(16.0f64.sqrt(), 4.0);
+        assert_eq!(2.5f32.floor(), 2.0);
+    }
+}
@@ -0,0 +1,20 @@
+#![feature(core_intrinsics)]
+
+use std::intrinsics::float_to_int_approx_unchecked;
+
+fn main() {
+    unsafe {
+        assert_eq!(float_to_int_approx_unchecked::<f32, i32>(-1.9), -1);
+        assert_eq!(float_to_int_approx_unchecked::<f32, u8>(255.9), 255);
+        // Truncated to 0, which is in range.
+        assert_eq!(float_to_int_approx_unchecked::<f64, u32>(-0.99), 0);
+        assert_eq!(
+            float_to_int_approx_unchecked::<f64, i64>(-9223372036854775808.0),
+            i64::min_value()
+        );
+        assert_eq!(
+            float_to_int_approx_unchecked::<f64, u128>(1e30),
+            1000000000000000019884624838656
+        );
+    }
+}
@@ -0,0 +1,33 @@
+// ignore-linux: Uses FreeBSD-only APIs
+// ignore-macos: Uses FreeBSD-only APIs
+// ignore-windows: Uses FreeBSD-only APIs
+// compile-flags: -Zmiri-disable-isolation
+
+#![feature(rustc_private)]
+extern crate libc;
+
+use std::collections::HashMap;
+use std::fs::{remove_file, File};
+use std::io::{ErrorKind, Write};
+
+fn main() {
+    // `HashMap` gets its random keys from `sysctl`.
+    let mut map = HashMap::new();
+    map.insert(1, 2);
+    assert_eq!(map[&1], 2);
+
+    let mut id = 0;
+    assert_eq!(unsafe { libc::thr_self(&mut id) }, 0);
+    assert_ne!(id, 0);
+
+    // `errno` is found via `__error`.
+    assert_eq!(File::open("/does/not/exist").unwrap_err().kind(), ErrorKind::NotFound);
+
+    let path = std::env::temp_dir().join("miri_test_freebsd.txt");
+    let mut file = File::create(&path).unwrap();
+    file.write_all(b"hello").unwrap();
+    assert_eq!(file.metadata().unwrap().len(), 5);
+    assert!(path.metadata().unwrap().is_file());
+    assert!(path.symlink_metadata().unwrap().modified().is_ok());
+    remove_file(&path).unwrap();
+}
@@ -0,0 +1,38 @@
+// ignore-windows: File handling is not implemented yet
+// compile-flags: -Zmiri-disable-isolation -Zmiri-fs-case-insensitive
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+
+fn main() {
+    let tmp = std::env::temp_dir();
+    let path = tmp.join("Miri_Test_Case.txt");
+    fs::remove_file(&path).ok();
+    File::create(&path).unwrap().write_all(b"first").unwrap();
+
+    // The same file can be opened with a name of a different case...
+    let mut contents = String::new();
+    File::open(tmp.join("miri_test_case.txt")).unwrap().read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "first");
+    assert_eq!(fs::metadata(tmp.join("MIRI_TEST_CASE.TXT")).unwrap().len(), 5);
+
+    // ... so it cannot be created a second time.
+    let err = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(tmp.join("miri_test_case.txt"))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    File::create(tmp.join("miri_test_case.TXT")).unwrap().write_all(b"second").unwrap();
+    contents.clear();
+    File::open(&path).unwrap().read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "second");
+
+    // Changing the case of the name keeps the file.
+    let renamed = tmp.join("miri_test_case.txt");
+    fs::rename(&path, &renamed).unwrap();
+    assert!(fs::metadata(&path).is_ok());
+
+    fs::remove_file(tmp.join("MIRI_TEST_CASE.txt")).unwrap();
+    assert_eq!(fs::metadata(&renamed).unwrap_err().kind(), ErrorKind::NotFound);
+}
@@ -0,0 +1,45 @@
+// ignore-windows: File handling is not implemented yet
+// compile-flags: -Zmiri-disable-isolation
+
+#![feature(rustc_private)]
+extern crate libc;
+
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+fn mode(path: &std::path::Path) -> u32 {
+    fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+fn main() {
+    let tmp = std::env::temp_dir();
+    let secret = tmp.join("miri_test_fs_umask_secret.txt");
+    let public = tmp.join("miri_test_fs_umask_public.txt");
+    let dir = tmp.join("miri_test_fs_umask_dir");
+    fs::remove_file(&secret).ok();
+    fs::remove_file(&public).ok();
+    fs::remove_dir(&dir).ok();
+
+    // The default mask removes the write permission for others.
+    assert_eq!(unsafe { libc::umask(0o077) }, 0o022);
+    assert_eq!(unsafe { libc::umask(0o077) }, 0o077);
+
+    // `File::create` asks for 0o666, of which the mask leaves 0o600.
+    File::create(&secret).unwrap();
+    assert_eq!(mode(&secret), 0o600);
+    // Opening an existing file does not change its mode.
+    OpenOptions::new().write(true).create(true).mode(0o777).open(&secret).unwrap();
+    assert_eq!(mode(&secret), 0o600);
+
+    assert_eq!(unsafe { libc::umask(0o022) }, 0o077);
+    OpenOptions::new().write(true).create_new(true).mode(0o640).open(&public).unwrap();
+    assert_eq!(mode(&public), 0o640);
+    fs::create_dir(&dir).unwrap();
+    assert_eq!(mode(&dir), 0o755);
+    assert!(fs::metadata(&dir).unwrap().is_dir());
+
+    fs::remove_file(&secret).unwrap();
+    fs::remove_file(&public).unwrap();
+    fs::remove_dir(&dir).unwrap();
+    assert!(fs::metadata(&dir).is_err());
+}
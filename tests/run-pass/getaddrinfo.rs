@@ -0,0 +1,18 @@
+// ignore-windows: name resolution is only emulated on Unix targets
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+
+fn main() {
+    let addrs: Vec<SocketAddr> = ("localhost", 8080).to_socket_addrs().unwrap().collect();
+    assert_eq!(addrs, [
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8080),
+        SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 8080),
+    ]);
+
+    // Numeric addresses do not even reach `getaddrinfo` in `std`, but they resolve anyway.
+    let addrs: Vec<SocketAddr> = "127.0.0.1:80".to_socket_addrs().unwrap().collect();
+    assert_eq!(addrs, [SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80)]);
+
+    // Anything else cannot be resolved.
+    assert!("example.com:80".to_socket_addrs().is_err());
+}
@@ -0,0 +1,19 @@
+// ignore-windows: Uses POSIX APIs
+#![feature(rustc_private)]
+
+extern crate libc;
+
+extern "C" {
+    fn getentropy(buf: *mut libc::c_void, buflen: libc::size_t) -> libc::c_int;
+}
+
+fn main() {
+    let mut buf = [0u8; 257];
+    unsafe {
+        assert_eq!(getentropy(buf.as_mut_ptr() as *mut libc::c_void, 0), 0);
+        assert_eq!(getentropy(buf.as_mut_ptr() as *mut libc::c_void, 256), 0);
+        // At most 256 bytes can be requested at once.
+        assert_eq!(getentropy(buf.as_mut_ptr() as *mut libc::c_void, 257), -1);
+        assert_eq!(std::io::Error::last_os_error().raw_os_error(), Some(libc::EIO));
+    }
+}
@@ -0,0 +1,39 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts the bytes that are currently allocated.
+struct Counter;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counter {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counter = Counter;
+
+fn main() {
+    let before = ALLOCATED.load(Ordering::SeqCst);
+    let b = Box::new([0u8; 100]);
+    assert_eq!(ALLOCATED.load(Ordering::SeqCst), before + 100);
+    drop(b);
+    assert_eq!(ALLOCATED.load(Ordering::SeqCst), before);
+
+    // Growing goes through the default `GlobalAlloc::realloc`, i.e., `alloc` and `dealloc`.
+    let mut v = Vec::with_capacity(4);
+    v.extend(0u32..4);
+    assert_eq!(ALLOCATED.load(Ordering::SeqCst), before + 16);
+    v.push(4);
+    assert_eq!(ALLOCATED.load(Ordering::SeqCst), before + 4 * v.capacity());
+    drop(v);
+    assert_eq!(ALLOCATED.load(Ordering::SeqCst), before);
+}
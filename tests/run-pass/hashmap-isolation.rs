@@ -0,0 +1,10 @@
+// `HashMap::new()` needs random keys; under isolation they come from the seeded RNG.
+use std::collections::HashMap;
+
+fn main() {
+    let mut map = HashMap::new();
+    for i in 0..10 {
+        map.insert(i, i * i);
+    }
+    assert_eq!(map.values().sum::<i32>(), 285);
+}
@@ -0,0 +1,23 @@
+// compile-flags: -Zmiri-disable-isolation -Zmiri-deterministic-hashmap
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::hash::{BuildHasher, Hasher};
+
+fn hash(state: &RandomState, value: u64) -> u64 {
+    let mut hasher = state.build_hasher();
+    hasher.write_u64(value);
+    hasher.finish()
+}
+
+fn main() {
+    let set: HashSet<u64> = (0..100).collect();
+    assert_eq!(set.len(), 100);
+    assert!((0..100).all(|i| set.contains(&i)));
+
+    // The keys are generated once per thread and then incremented for every `RandomState`, so
+    // states still hash differently from each other.
+    let (a, b) = (RandomState::new(), RandomState::new());
+    assert_ne!(hash(&a, 42), hash(&b, 42));
+    assert_eq!(hash(&a, 42), hash(&a.clone(), 42));
+}
@@ -0,0 +1,14 @@
+#![feature(asm)]
+
+fn main() {
+    let mut x = 0;
+    for _ in 0..3 {
+        // Some crates use these to keep a loop from being optimized away.
+        unsafe {
+            asm!("nop");
+            asm!("" :::: "volatile");
+        }
+        x += 1;
+    }
+    assert_eq!(x, 3);
+}
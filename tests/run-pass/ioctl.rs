@@ -0,0 +1,37 @@
+// ignore-windows: Uses POSIX APIs
+
+#![feature(rustc_private)]
+extern crate libc;
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::io::AsRawFd;
+
+fn main() {
+    // Nothing is a terminal.
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    assert_eq!(unsafe { libc::ioctl(1, libc::TIOCGWINSZ, &mut size) }, -1);
+    assert_eq!(std::io::Error::last_os_error().raw_os_error(), Some(libc::ENOTTY));
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+    let fd = server.as_raw_fd();
+
+    // `FIONBIO` makes reads fail instead of waiting.
+    let mut on: libc::c_int = 1;
+    assert_eq!(unsafe { libc::ioctl(fd, libc::FIONBIO, &mut on) }, 0);
+    let mut buf = [0; 8];
+    assert_eq!(server.read(&mut buf).unwrap_err().kind(), ErrorKind::WouldBlock);
+
+    // `FIONREAD` reports how much can be read.
+    client.write_all(b"hello").unwrap();
+    let mut available: libc::c_int = 0;
+    assert_eq!(unsafe { libc::ioctl(fd, libc::FIONREAD, &mut available) }, 0);
+    assert_eq!(available, 5);
+    assert_eq!(server.read(&mut buf).unwrap(), 5);
+
+    assert_eq!(unsafe { libc::ioctl(fd, libc::FIOCLEX) }, 0);
+    assert_eq!(unsafe { libc::ioctl(1000, libc::FIOCLEX) }, -1);
+    assert_eq!(std::io::Error::last_os_error().raw_os_error(), Some(libc::EBADF));
+}
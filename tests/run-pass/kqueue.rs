@@ -0,0 +1,47 @@
+// ignore-windows: kqueue is macOS-specific
+// ignore-linux: kqueue is macOS-specific
+
+#![feature(rustc_private)]
+extern crate libc;
+
+use std::ptr;
+
+fn kevent(ident: usize, filter: i16, flags: u16, fflags: u32, udata: usize) -> libc::kevent {
+    libc::kevent { ident, filter, flags, fflags, data: 0, udata: udata as *mut libc::c_void }
+}
+
+fn main() {
+    let kq = unsafe { libc::kqueue() };
+    assert!(kq >= 0);
+    let zero = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    let mut events = [kevent(0, 0, 0, 0, 0); 4];
+
+    // Register a user event, with a receipt.
+    let flags = libc::EV_ADD | libc::EV_CLEAR | libc::EV_RECEIPT;
+    let change = kevent(7, libc::EVFILT_USER, flags, 0, 42);
+    let n = unsafe { libc::kevent(kq, &change, 1, events.as_mut_ptr(), 4, &zero) };
+    assert_eq!(n, 1);
+    assert!(events[0].flags & libc::EV_ERROR != 0);
+    assert_eq!(events[0].data, 0);
+
+    // Nothing happened yet.
+    let n = unsafe { libc::kevent(kq, ptr::null(), 0, events.as_mut_ptr(), 4, &zero) };
+    assert_eq!(n, 0);
+
+    // Trigger it; it is reported once because of `EV_CLEAR`.
+    let change = kevent(7, libc::EVFILT_USER, 0, libc::NOTE_TRIGGER, 0);
+    let n = unsafe { libc::kevent(kq, &change, 1, ptr::null_mut(), 0, &zero) };
+    assert_eq!(n, 0);
+    let n = unsafe { libc::kevent(kq, ptr::null(), 0, events.as_mut_ptr(), 4, &zero) };
+    assert_eq!(n, 1);
+    assert_eq!(events[0].ident, 7);
+    assert_eq!(events[0].udata as usize, 42);
+    let n = unsafe { libc::kevent(kq, ptr::null(), 0, events.as_mut_ptr(), 4, &zero) };
+    assert_eq!(n, 0);
+
+    // Deleting an unknown event fails.
+    let change = kevent(8, libc::EVFILT_USER, libc::EV_DELETE, 0, 0);
+    let n = unsafe { libc::kevent(kq, &change, 1, ptr::null_mut(), 0, &zero) };
+    assert_eq!(n, -1);
+    assert_eq!(unsafe { *libc::__error() }, libc::ENOENT);
+}
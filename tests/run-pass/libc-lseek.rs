@@ -0,0 +1,46 @@
+// ignore-windows: No libc on Windows
+// compile-flags: -Zmiri-disable-isolation
+
+#![feature(rustc_private)]
+
+extern crate libc;
+
+use std::env::temp_dir;
+use std::fs::{remove_file, File};
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+
+fn main() {
+    let path = temp_dir().join("miri_test_libc_lseek.txt");
+    // Cleanup before test
+    remove_file(&path).ok();
+
+    let mut file = File::create(&path).unwrap();
+    file.write_all(b"Hello, World!\n").unwrap();
+    let fd = file.as_raw_fd();
+
+    unsafe {
+        // `off_t` is only 32 bits wide on 32-bit targets, we must not read it as 64 bits.
+        assert_eq!(libc::lseek(fd, 0, libc::SEEK_END), 14);
+        assert_eq!(libc::lseek(fd, -7, libc::SEEK_CUR), 7);
+        assert_eq!(libc::lseek(fd, 3, libc::SEEK_SET), 3);
+
+        #[cfg(target_os = "linux")]
+        {
+            // `lseek64` always takes a 64-bit offset.
+            assert_eq!(libc::lseek64(fd, 1 << 32, libc::SEEK_SET), 1 << 32);
+            assert_eq!(libc::lseek64(fd, 5, libc::SEEK_SET), 5);
+        }
+
+        // A position that does not fit into a 32-bit `off_t` is an error.
+        #[cfg(all(target_pointer_width = "32", target_os = "linux"))]
+        {
+            assert_eq!(libc::lseek(fd, i32::max_value().into(), libc::SEEK_SET), i32::max_value());
+            assert_eq!(libc::lseek(fd, 1, libc::SEEK_CUR), -1);
+            assert_eq!(*libc::__errno_location(), libc::EOVERFLOW);
+        }
+    }
+
+    drop(file);
+    remove_file(&path).unwrap();
+}
@@ -37,7 +37,33 @@ fn test_posix_fadvise() {
     assert_eq!(result, 0);
 }
 
+/// `read` only writes the bytes it read, and leaves the rest of the buffer alone.
+fn test_short_read() {
+    use std::env::temp_dir;
+    use std::fs::{remove_file, File};
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+
+    let path = temp_dir().join("miri_test_libc_short_read.txt");
+    remove_file(&path).ok();
+    File::create(&path).unwrap().write_all(b"abc").unwrap();
+
+    let file = File::open(&path).unwrap();
+    let mut buf = [b'x'; 6];
+    let n = unsafe { libc::read(file.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+    assert_eq!(n, 3);
+    assert_eq!(&buf, b"abcxxx");
+    // Nothing is left to read.
+    let n = unsafe { libc::read(file.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+    assert_eq!(n, 0);
+    assert_eq!(&buf, b"abcxxx");
+    drop(file);
+    remove_file(&path).unwrap();
+}
+
 fn main() {
     #[cfg(not(target_os = "macos"))]
     test_posix_fadvise();
+
+    test_short_read();
 }
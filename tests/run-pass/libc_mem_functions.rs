@@ -0,0 +1,40 @@
+// ignore-windows: No libc on Windows
+
+#![feature(rustc_private)]
+
+extern crate libc;
+
+fn main() {
+    unsafe {
+        // Copies keep pointers intact.
+        let x = 42u64;
+        let src: [&u64; 4] = [&x; 4];
+        let mut dst: [&u64; 4] = [&0; 4];
+        let ret = libc::memcpy(dst.as_mut_ptr().cast(), src.as_ptr().cast(), 4 * 8);
+        assert_eq!(ret, dst.as_mut_ptr().cast());
+        assert!(dst.iter().all(|&r| *r == 42));
+
+        // A large copy.
+        let big: Vec<u8> = (0..1 << 16).map(|i| i as u8).collect();
+        let mut copy = vec![0u8; 1 << 16];
+        libc::memcpy(copy.as_mut_ptr().cast(), big.as_ptr().cast(), big.len());
+        assert_eq!(big, copy);
+
+        // `memmove` allows the ranges to overlap.
+        let mut buf = [1u8, 2, 3, 4, 5, 6];
+        libc::memmove(buf.as_mut_ptr().add(2).cast(), buf.as_ptr().cast(), 4);
+        assert_eq!(buf, [1, 2, 1, 2, 3, 4]);
+        libc::memmove(buf.as_mut_ptr().cast(), buf.as_ptr().add(1).cast(), 5);
+        assert_eq!(buf, [2, 1, 2, 3, 4, 4]);
+
+        // `memset` only uses the lowest byte of the value.
+        let ret = libc::memset(buf.as_mut_ptr().cast(), 0x1ff, 3);
+        assert_eq!(ret, buf.as_mut_ptr().cast());
+        assert_eq!(buf, [0xff, 0xff, 0xff, 3, 4, 4]);
+
+        // Nothing happens for empty ranges.
+        libc::memcpy(buf.as_mut_ptr().cast(), buf.as_ptr().cast(), 0);
+        libc::memset(buf.as_mut_ptr().cast(), 0, 0);
+        assert_eq!(buf, [0xff, 0xff, 0xff, 3, 4, 4]);
+    }
+}
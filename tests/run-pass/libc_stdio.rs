@@ -0,0 +1,62 @@
+// ignore-windows: No libc on Windows
+// compile-flags: -Zmiri-disable-isolation
+
+#![feature(rustc_private)]
+
+extern crate libc;
+
+use std::env::temp_dir;
+use std::ffi::CString;
+use std::fs::{read, remove_file};
+use std::os::unix::ffi::OsStrExt;
+
+fn main() {
+    let path = temp_dir().join("miri_test_libc_stdio.txt");
+    // Cleanup before test
+    remove_file(&path).ok();
+    let c_path = CString::new(path.as_os_str().as_bytes()).unwrap();
+
+    unsafe {
+        // Opening a file that does not exist for reading fails.
+        let file = libc::fopen(c_path.as_ptr(), b"r\0".as_ptr().cast());
+        assert!(file.is_null());
+        assert_eq!(std::io::Error::last_os_error().raw_os_error(), Some(libc::ENOENT));
+
+        let file = libc::fopen(c_path.as_ptr(), b"w+\0".as_ptr().cast());
+        assert!(!file.is_null());
+        let bytes = b"Hello, World!\n";
+        assert_eq!(libc::fwrite(bytes.as_ptr().cast(), 1, bytes.len(), file), bytes.len());
+        // The write is buffered, but the position already includes it.
+        assert_eq!(libc::ftell(file), 14);
+        assert_eq!(read(&path).unwrap(), b"");
+        assert_eq!(libc::fflush(file), 0);
+        assert_eq!(read(&path).unwrap(), bytes);
+
+        // Reading from the start of the file, in items of two bytes.
+        assert_eq!(libc::fseek(file, 0, libc::SEEK_SET), 0);
+        let mut buf = [0u8; 6];
+        assert_eq!(libc::fread(buf.as_mut_ptr().cast(), 2, 3, file), 3);
+        assert_eq!(&buf, b"Hello,");
+        // Reads are buffered as well, the position only counts what the program got.
+        assert_eq!(libc::ftell(file), 6);
+
+        // Writing after reading continues at the position of the program.
+        assert_eq!(libc::fwrite(b" WORLD".as_ptr().cast(), 6, 1, file), 1);
+        assert_eq!(libc::fseek(file, -3, libc::SEEK_END), 0);
+        let mut buf = [0u8; 8];
+        // Only one complete item is left.
+        assert_eq!(libc::fread(buf.as_mut_ptr().cast(), 2, 4, file), 1);
+        assert_eq!(&buf[..3], b"D!\n");
+        assert_eq!(libc::fclose(file), 0);
+        assert_eq!(read(&path).unwrap(), b"Hello, WORLD!\n");
+
+        // Appending, and flushing all streams at once.
+        let file = libc::fopen(c_path.as_ptr(), b"ab\0".as_ptr().cast());
+        assert_eq!(libc::fwrite(b"Bye\n".as_ptr().cast(), 1, 4, file), 4);
+        assert_eq!(libc::fflush(std::ptr::null_mut()), 0);
+        assert_eq!(read(&path).unwrap(), b"Hello, WORLD!\nBye\n");
+        assert_eq!(libc::fclose(file), 0);
+    }
+
+    remove_file(&path).unwrap();
+}
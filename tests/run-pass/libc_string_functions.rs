@@ -0,0 +1,69 @@
+// ignore-windows: No libc on Windows
+
+#![feature(rustc_private)]
+
+extern crate libc;
+
+use std::ffi::CStr;
+
+extern "C" {
+    // Not declared by the `libc` crate.
+    fn stpcpy(dest: *mut libc::c_char, src: *const libc::c_char) -> *mut libc::c_char;
+}
+
+fn main() {
+    unsafe {
+        let hello = b"hello\0";
+        let s = hello.as_ptr().cast::<libc::c_char>();
+        assert_eq!(libc::strlen(s), 5);
+        assert_eq!(libc::strnlen(s, 3), 3);
+        assert_eq!(libc::strnlen(s, 10), 5);
+        // With a limit, the string does not have to be terminated.
+        let unterminated = [b'a'; 4];
+        assert_eq!(libc::strnlen(unterminated.as_ptr().cast(), 4), 4);
+
+        // Comparisons stop at the first difference or the end of the shorter string.
+        assert_eq!(libc::strcmp(s, b"hello\0".as_ptr().cast()), 0);
+        assert!(libc::strcmp(s, b"help\0".as_ptr().cast()) < 0);
+        assert!(libc::strcmp(s, b"hell\0".as_ptr().cast()) > 0);
+        assert!(libc::strcmp(b"\0".as_ptr().cast(), s) < 0);
+        assert_eq!(libc::strncmp(s, b"help\0".as_ptr().cast(), 3), 0);
+        assert_eq!(libc::strncmp(s, unterminated.as_ptr().cast(), 0), 0);
+        // The right string is only read as far as the left one goes.
+        let short = [b'h', b'x'];
+        assert!(libc::strcmp(b"h\0".as_ptr().cast(), short.as_ptr().cast()) < 0);
+
+        // Searching.
+        assert_eq!(libc::strchr(s, b'l' as i32), s.add(2) as *mut _);
+        assert_eq!(libc::strrchr(s, b'l' as i32), s.add(3) as *mut _);
+        assert_eq!(libc::strchr(s, 0), s.add(5) as *mut _);
+        assert!(libc::strchr(s, b'z' as i32).is_null());
+        assert_eq!(libc::strstr(s, b"llo\0".as_ptr().cast()), s.add(2) as *mut _);
+        assert_eq!(libc::strstr(s, b"\0".as_ptr().cast()), s as *mut _);
+        assert!(libc::strstr(s, b"lol\0".as_ptr().cast()).is_null());
+
+        // Copying.
+        let mut buf = [0xffu8; 12];
+        let b = buf.as_mut_ptr().cast::<libc::c_char>();
+        assert_eq!(libc::strcpy(b, s), b);
+        assert_eq!(&buf[..7], b"hello\0\xff");
+        assert_eq!(libc::strcat(b, b" you\0".as_ptr().cast()), b);
+        assert_eq!(CStr::from_ptr(b).to_bytes(), b"hello you");
+        assert_eq!(stpcpy(b, b"hi\0".as_ptr().cast()), b.add(2));
+        assert_eq!(CStr::from_ptr(b).to_bytes(), b"hi");
+        let mut buf = [0xffu8; 6];
+        let b = buf.as_mut_ptr().cast::<libc::c_char>();
+        libc::strncpy(b, b"ab\0".as_ptr().cast(), 5);
+        assert_eq!(buf, *b"ab\0\0\0\xff");
+        libc::strncpy(b, s, 3);
+        assert_eq!(buf, *b"hel\0\0\xff");
+
+        // Duplicating.
+        let copy = libc::strdup(s);
+        assert_eq!(CStr::from_ptr(copy).to_bytes(), b"hello");
+        libc::free(copy.cast());
+        let copy = libc::strndup(s, 2);
+        assert_eq!(CStr::from_ptr(copy).to_bytes(), b"he");
+        libc::free(copy.cast());
+    }
+}
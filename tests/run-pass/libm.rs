@@ -0,0 +1,38 @@
+// Calls the C math functions directly (rather than through the float methods, which mostly
+// use intrinsics).
+
+extern "C" {
+    fn sin(x: f64) -> f64;
+    fn cos(x: f64) -> f64;
+    fn exp(x: f64) -> f64;
+    fn log(x: f64) -> f64;
+    fn pow(x: f64, y: f64) -> f64;
+    fn fmod(x: f64, y: f64) -> f64;
+    fn fdim(x: f64, y: f64) -> f64;
+    fn sqrtf(x: f32) -> f32;
+    fn powf(x: f32, y: f32) -> f32;
+    fn fmodf(x: f32, y: f32) -> f32;
+}
+
+fn assert_approx_eq(a: f64, b: f64) {
+    assert!((a - b).abs() < 1e-10, "{} is not approximately equal to {}", a, b);
+}
+
+fn main() {
+    unsafe {
+        assert_approx_eq(sin(0.0), 0.0);
+        assert_approx_eq(cos(0.0), 1.0);
+        assert_approx_eq(exp(1.0), std::f64::consts::E);
+        assert_approx_eq(log(std::f64::consts::E), 1.0);
+        assert_eq!(pow(2.0, 10.0), 1024.0);
+        assert_eq!(fmod(7.5, 2.0), 1.5);
+        assert_eq!(fmod(-7.5, 2.0), -1.5);
+        assert!(fmod(1.0, 0.0).is_nan());
+        assert_eq!(fdim(3.0, 1.0), 2.0);
+        assert_eq!(fdim(1.0, 3.0), 0.0);
+        assert!(fdim(std::f64::NAN, 1.0).is_nan());
+        assert_eq!(sqrtf(16.0), 4.0);
+        assert_eq!(powf(3.0, 2.0), 9.0);
+        assert_eq!(fmodf(5.0, 3.0), 2.0);
+    }
+}
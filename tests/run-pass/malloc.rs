@@ -50,4 +50,20 @@ fn main() {
 
         libc::free(p1);
     }
+
+    unsafe {
+        // `malloc(0)` returns unique pointers that can be freed (also via `realloc`).
+        let p1 = libc::malloc(0);
+        let p2 = libc::malloc(0);
+        assert!(!p1.is_null() && !p2.is_null());
+        assert_ne!(p1, p2);
+        libc::free(p1);
+        let p3 = libc::realloc(p2, 8);
+        assert!(!p3.is_null());
+        libc::free(p3);
+
+        let p4 = libc::realloc(ptr::null_mut(), 0);
+        assert!(!p4.is_null());
+        libc::free(p4);
+    }
 }
@@ -0,0 +1,17 @@
+// compile-flags: -Zmiri-many-seeds=0..3
+
+#[repr(C)]
+struct MiriHostTargetInfo {
+    isolation: bool,
+    seed: u64,
+    target_len: usize,
+}
+
+extern "Rust" {
+    fn miri_host_target_info() -> MiriHostTargetInfo;
+}
+
+fn main() {
+    let info = unsafe { miri_host_target_info() };
+    println!("seed {}", info.seed);
+}
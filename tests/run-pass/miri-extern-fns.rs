@@ -0,0 +1,33 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+extern "Rust" {
+    fn miri_get_alloc_id(ptr: *const u8) -> u64;
+    fn miri_dump_alloc(ptr: *const u8);
+    fn miri_host_to_target_path(path: *const c_char, out: *mut c_char, out_size: usize) -> usize;
+}
+
+fn main() {
+    let a = Box::new([0u8; 4]);
+    let b = Box::new(0u8);
+    unsafe {
+        // Pointers into the same allocation share an id, different allocations differ.
+        let id = miri_get_alloc_id(a.as_ptr());
+        assert_eq!(id, miri_get_alloc_id(a.as_ptr().add(3)));
+        assert_ne!(id, miri_get_alloc_id(&*b));
+    }
+
+    // Dumping works with uninitialized bytes and pointers in the allocation.
+    let mut parts = [std::mem::MaybeUninit::<&u8>::uninit(); 2];
+    parts[0] = std::mem::MaybeUninit::new(&*b);
+    unsafe { miri_dump_alloc(parts.as_ptr() as *const u8) };
+
+    let path = CString::new("foo").unwrap();
+    let mut out = [0 as c_char; 16];
+    unsafe {
+        // A too small buffer reports the needed size.
+        assert_eq!(miri_host_to_target_path(path.as_ptr(), out.as_mut_ptr(), 2), 4);
+        assert_eq!(miri_host_to_target_path(path.as_ptr(), out.as_mut_ptr(), out.len()), 0);
+        assert_eq!(CStr::from_ptr(out.as_ptr()).to_str().unwrap(), "foo");
+    }
+}
@@ -0,0 +1,14 @@
+extern "Rust" {
+    fn miri_nondet_bytes(ptr: *mut u8, len: usize);
+}
+
+fn main() {
+    let mut buf = [0u8; 64];
+    unsafe {
+        miri_nondet_bytes(buf.as_mut_ptr(), buf.len());
+        // Zero-sized requests are fine, even with a dangling pointer.
+        miri_nondet_bytes(std::ptr::NonNull::dangling().as_ptr(), 0);
+    }
+    // 64 zero bytes in a row are astronomically unlikely.
+    assert!(buf.iter().any(|&b| b != 0));
+}
@@ -0,0 +1,62 @@
+#[repr(C)]
+struct MiriFrame {
+    name_len: usize,
+    filename_len: usize,
+    lineno: u32,
+    colno: u32,
+    fn_ptr: *mut (),
+}
+
+extern "Rust" {
+    fn miri_backtrace_size(flags: u64) -> usize;
+    fn miri_get_backtrace(flags: u64, buf: *mut *mut ());
+    fn miri_resolve_frame(ptr: *mut (), flags: u64) -> MiriFrame;
+    fn miri_resolve_frame_names(ptr: *mut (), flags: u64, name_buf: *mut u8, filename_buf: *mut u8);
+}
+
+struct Frame {
+    name: String,
+    filename: String,
+    lineno: u32,
+    colno: u32,
+}
+
+#[inline(never)]
+fn backtrace() -> Vec<Frame> {
+    unsafe {
+        let mut buf = vec![std::ptr::null_mut(); miri_backtrace_size(0)];
+        miri_get_backtrace(0, buf.as_mut_ptr());
+        buf.into_iter()
+            .map(|ptr| {
+                let frame = miri_resolve_frame(ptr, 0);
+                let mut name = vec![0; frame.name_len];
+                let mut filename = vec![0; frame.filename_len];
+                miri_resolve_frame_names(ptr, 0, name.as_mut_ptr(), filename.as_mut_ptr());
+                assert!(!frame.fn_ptr.is_null());
+                Frame {
+                    name: String::from_utf8(name).unwrap(),
+                    filename: String::from_utf8(filename).unwrap(),
+                    lineno: frame.lineno,
+                    colno: frame.colno,
+                }
+            })
+            .collect()
+    }
+}
+
+fn nested() -> Vec<Frame> {
+    backtrace()
+}
+
+fn main() {
+    let frames = nested();
+    for (frame, name) in frames.iter().zip(&["backtrace", "nested", "main"]) {
+        assert!(frame.name.ends_with(name), "{}", frame.name);
+    }
+    for frame in &frames[..3] {
+        assert!(frame.filename.ends_with("miri_backtrace_api.rs"), "{}", frame.filename);
+        assert!(frame.lineno > 0 && frame.colno > 0);
+    }
+    // `nested` calls `backtrace` on line 48.
+    assert_eq!(frames[1].lineno, 48);
+}
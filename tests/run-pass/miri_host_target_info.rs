@@ -0,0 +1,27 @@
+// compile-flags: -Zmiri-seed=000000000000002a
+
+#[repr(C)]
+struct MiriHostTargetInfo {
+    isolation: bool,
+    seed: u64,
+    target_len: usize,
+}
+
+extern "Rust" {
+    fn miri_host_target_info() -> MiriHostTargetInfo;
+    fn miri_target_triple(buf: *mut u8);
+}
+
+fn main() {
+    let info = unsafe { miri_host_target_info() };
+    assert!(info.isolation);
+    assert_eq!(info.seed, 42);
+
+    let mut target = vec![0u8; info.target_len];
+    unsafe { miri_target_triple(target.as_mut_ptr()) };
+    let target = String::from_utf8(target).unwrap();
+    assert!(target.split('-').count() >= 3, "{}", target);
+    if cfg!(target_arch = "x86_64") {
+        assert!(target.starts_with("x86_64-"), "{}", target);
+    }
+}
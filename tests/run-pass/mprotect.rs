@@ -0,0 +1,25 @@
+// ignore-windows: No libc on Windows
+
+#![feature(rustc_private)]
+extern crate libc;
+
+use std::alloc::{alloc, dealloc, Layout};
+
+const PAGE_SIZE: usize = 4096;
+
+fn main() {
+    unsafe {
+        let layout = Layout::from_size_align(2 * PAGE_SIZE, PAGE_SIZE).unwrap();
+        let ptr = alloc(layout);
+        let page = ptr as *mut libc::c_void;
+        // Only page-aligned addresses can be protected.
+        assert_eq!(libc::mprotect(ptr.add(1) as *mut libc::c_void, 1, libc::PROT_NONE), -1);
+        // Protecting a page and making it accessible again.
+        assert_eq!(libc::mprotect(page, PAGE_SIZE, libc::PROT_NONE), 0);
+        *ptr.add(PAGE_SIZE) = 1;
+        assert_eq!(libc::mprotect(page, PAGE_SIZE, libc::PROT_READ | libc::PROT_WRITE), 0);
+        *ptr = 2;
+        assert_eq!(*ptr + *ptr.add(PAGE_SIZE), 3);
+        dealloc(ptr, layout);
+    }
+}
@@ -0,0 +1,17 @@
+// compile-flags: -Zmiri-no-address-randomization
+
+fn addr<T>(x: &T) -> usize {
+    x as *const T as usize
+}
+
+fn main() {
+    // Addresses are handed out in increasing order.
+    let a = Box::new(0u64);
+    let b = Box::new(0u64);
+    let c = [0u8; 3];
+    let addr_a = addr(&*a);
+    let addr_b = addr(&*b);
+    let addr_c = addr(&c);
+    assert!(addr_a < addr_b);
+    assert!(addr_b < addr_c);
+}
@@ -0,0 +1,23 @@
+// ignore-windows: TLS destructors of Windows go through `std`
+#![feature(lang_items, start)]
+#![no_std]
+
+#[start]
+fn start(argc: isize, _argv: *const *const u8) -> isize {
+    let mut sum = 0;
+    for i in 0..10 {
+        sum += i;
+    }
+    assert_eq!(sum, 45);
+    // Miri passes the name of the crate as the only argument.
+    assert_eq!(argc, 1);
+    0
+}
+
+#[panic_handler]
+fn panic_handler(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
+
+#[lang = "eh_personality"]
+fn eh_personality() {}
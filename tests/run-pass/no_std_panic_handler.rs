@@ -0,0 +1,23 @@
+// ignore-windows: No libc on Windows
+#![feature(lang_items, start)]
+#![no_std]
+
+extern "C" {
+    fn exit(code: i32) -> !;
+}
+
+#[start]
+fn start(argc: isize, _argv: *const *const u8) -> isize {
+    let v: &[isize] = &[];
+    // `argc` is 1, so this panics, and our handler ends the program successfully.
+    v[argc as usize]
+}
+
+#[panic_handler]
+fn panic_handler(info: &core::panic::PanicInfo) -> ! {
+    assert!(info.location().is_some());
+    unsafe { exit(0) }
+}
+
+#[lang = "eh_personality"]
+fn eh_personality() {}
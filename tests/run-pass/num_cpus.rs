@@ -0,0 +1,27 @@
+// ignore-macos: Uses Linux-only APIs
+// ignore-windows: Uses Linux-only APIs
+// compile-flags: -Zmiri-num-cpus=256
+
+#![feature(rustc_private)]
+extern crate libc;
+
+use std::mem;
+
+fn main() {
+    assert_eq!(unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) }, 256);
+
+    unsafe {
+        let mut set: libc::cpu_set_t = mem::zeroed();
+        assert_eq!(libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut set), 0);
+        let cpus = 0..libc::CPU_SETSIZE as usize;
+        let count = cpus.filter(|&cpu| libc::CPU_ISSET(cpu, &set)).count();
+        assert_eq!(count, 256);
+        assert!(libc::CPU_ISSET(255, &set));
+        assert!(!libc::CPU_ISSET(256, &set));
+
+        // The mask must have room for all CPUs.
+        let mut word = 0u64;
+        assert_eq!(libc::sched_getaffinity(0, 8, (&mut word as *mut u64).cast()), -1);
+        assert_eq!(std::io::Error::last_os_error().raw_os_error(), Some(libc::EINVAL));
+    }
+}
@@ -0,0 +1,27 @@
+// compile-flags: -Zmiri-symbolic-alignment-check
+
+#[repr(packed)]
+struct S {
+    a: u8,
+    b: u32,
+    c: [u8; 2],
+}
+
+#[repr(C, packed(4))]
+struct T {
+    a: u8,
+    b: u32,
+}
+
+fn main() {
+    let s = S { a: 1, b: 2, c: [3, 4] };
+    // Fields with an alignment of 1 can be borrowed.
+    assert_eq!(&s.a, &1);
+    assert_eq!(&s.c[1], &4);
+    // Others can be copied out.
+    assert_eq!({ s.b }, 2);
+    // `packed(4)` still guarantees the alignment of `u32`.
+    let t = T { a: 5, b: 6 };
+    assert_eq!(&t.b, &6);
+    assert_eq!(t.a, 5);
+}
@@ -0,0 +1,48 @@
+// ignore-windows: Unwind panicking does not currently work on Windows
+use std::cell::RefCell;
+use std::panic::{self, catch_unwind, resume_unwind, AssertUnwindSafe};
+
+thread_local! {
+    static LOG: RefCell<Vec<&'static str>> = RefCell::new(Vec::new());
+}
+
+fn log(s: &'static str) {
+    LOG.with(|log| log.borrow_mut().push(s));
+}
+
+struct Guard(&'static str);
+impl Drop for Guard {
+    fn drop(&mut self) {
+        log(self.0);
+    }
+}
+
+fn inner() {
+    let _g = Guard("inner");
+    panic!("inner panic");
+}
+
+fn middle() {
+    let _g = Guard("middle");
+    // Catch the panic, then continue unwinding with the same payload.
+    let payload = catch_unwind(inner).unwrap_err();
+    log("caught");
+    resume_unwind(payload);
+}
+
+fn main() {
+    // Keep stderr clean.
+    panic::set_hook(Box::new(|_| {}));
+
+    let payload = catch_unwind(AssertUnwindSafe(|| {
+        let _g = Guard("outer");
+        middle();
+    })).unwrap_err();
+    assert_eq!(*payload.downcast_ref::<&str>().unwrap(), "inner panic");
+    LOG.with(|log| assert_eq!(*log.borrow(), ["inner", "caught", "middle", "outer"]));
+
+    // Catching a panic does not affect later, non-panicking calls.
+    assert_eq!(catch_unwind(|| 42).unwrap(), 42);
+
+    drop(panic::take_hook());
+}
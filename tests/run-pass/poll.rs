@@ -0,0 +1,76 @@
+// ignore-windows: Uses POSIX APIs
+// ignore-macos: `std` uses `ioctl` to set up sockets on macOS
+
+#![feature(rustc_private)]
+extern crate libc;
+
+use std::io::Write;
+use std::mem;
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::io::AsRawFd;
+
+fn poll(fds: &mut [libc::pollfd]) -> i32 {
+    unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, 10) }
+}
+
+fn pollfd(fd: i32, events: libc::c_short) -> libc::pollfd {
+    libc::pollfd { fd, events, revents: 0 }
+}
+
+fn select(read: &[i32], write: &[i32]) -> (Vec<i32>, Vec<i32>) {
+    unsafe {
+        let mut read_set: libc::fd_set = mem::zeroed();
+        let mut write_set: libc::fd_set = mem::zeroed();
+        for &fd in read {
+            libc::FD_SET(fd, &mut read_set);
+        }
+        for &fd in write {
+            libc::FD_SET(fd, &mut write_set);
+        }
+        let nfds = read.iter().chain(write).max().unwrap() + 1;
+        let mut timeout = libc::timeval { tv_sec: 0, tv_usec: 0 };
+        let except = std::ptr::null_mut();
+        let n = libc::select(nfds, &mut read_set, &mut write_set, except, &mut timeout);
+        let read: Vec<i32> =
+            read.iter().copied().filter(|&fd| libc::FD_ISSET(fd, &read_set)).collect();
+        let write: Vec<i32> =
+            write.iter().copied().filter(|&fd| libc::FD_ISSET(fd, &write_set)).collect();
+        assert_eq!(n as usize, read.len() + write.len());
+        (read, write)
+    }
+}
+
+fn main() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let listener_fd = listener.as_raw_fd();
+
+    // Nothing to accept yet: the timeout just runs out.
+    let mut fds = [pollfd(listener_fd, libc::POLLIN)];
+    assert_eq!(poll(&mut fds), 0);
+    assert_eq!(fds[0].revents, 0);
+
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    assert_eq!(poll(&mut fds), 1);
+    assert_eq!(fds[0].revents, libc::POLLIN);
+    let (server, _) = listener.accept().unwrap();
+    let server_fd = server.as_raw_fd();
+
+    // The connection is writable, and readable once there is data.
+    let mut fds = [pollfd(server_fd, libc::POLLIN | libc::POLLOUT), pollfd(-1, libc::POLLIN)];
+    assert_eq!(poll(&mut fds), 1);
+    assert_eq!(fds[0].revents, libc::POLLOUT);
+    assert_eq!(fds[1].revents, 0);
+    assert_eq!(select(&[server_fd], &[server_fd]), (vec![], vec![server_fd]));
+
+    client.write_all(b"ping").unwrap();
+    assert_eq!(poll(&mut fds), 1);
+    assert_eq!(fds[0].revents, libc::POLLIN | libc::POLLOUT);
+    assert_eq!(select(&[server_fd], &[]), (vec![server_fd], vec![]));
+
+    // Closed descriptors are reported as invalid.
+    drop(client);
+    drop(server);
+    let mut fds = [pollfd(server_fd, libc::POLLIN)];
+    assert_eq!(poll(&mut fds), 1);
+    assert_eq!(fds[0].revents, libc::POLLNVAL);
+}
@@ -0,0 +1,32 @@
+// ignore-macos: Uses Linux-only files
+// ignore-windows: Uses Linux-only files
+// compile-flags: -Zmiri-num-cpus=2
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+
+fn main() {
+    // These files can be read under isolation.
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").unwrap();
+    assert_eq!(cpuinfo.matches("processor\t: ").count(), 2);
+
+    let status = fs::read_to_string("/proc/self/status").unwrap();
+    let name = status.lines().next().unwrap();
+    assert_eq!(name, "Name:\tproc_files");
+    assert!(status.lines().any(|line| line.starts_with("VmRSS:")));
+
+    let maps = fs::read_to_string("/proc/self/maps").unwrap();
+    assert!(maps.ends_with("[stack]\n"));
+
+    // They are the same every time, and support seeking.
+    let mut file = File::open("/proc/cpuinfo").unwrap();
+    file.seek(SeekFrom::Start(13)).unwrap();
+    let mut rest = String::new();
+    file.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, cpuinfo[13..]);
+    assert_eq!(file.metadata().unwrap().len(), 0);
+
+    // They cannot be written to.
+    let err = OpenOptions::new().write(true).open("/proc/cpuinfo").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+}
@@ -0,0 +1,47 @@
+// compile-flags: -Zmiri-ptr-comparison=random -Zmiri-seed=42
+
+fn main() {
+    // Comparisons with a determined result are not affected.
+    let a = Box::new(0u32);
+    let b = Box::new(0u32);
+    let pa = &*a as *const u32;
+    let pb = &*b as *const u32;
+    assert!(pa != pb);
+    assert!(pa == pa);
+
+    let arr = [0u8; 4];
+    let start = arr.as_ptr();
+    let end = start.wrapping_add(4);
+    assert!(start < end);
+    assert!(end != start);
+    assert!(start.wrapping_add(1) <= end);
+
+    // Comparisons depending on the placement of allocations may go either way, but they do not
+    // stop the program.
+    let _ = end == &*a as *const u32 as *const u8;
+    let _ = pa < pb;
+
+    // The placement is picked once: comparisons are consistent with each other and do not change.
+    let c = Box::new(0u32);
+    let pc = &*c as *const u32;
+    let ptrs = [pa, pb, pc];
+    for &x in &ptrs {
+        for &y in &ptrs {
+            if x != y {
+                assert_eq!(x < y, !(y < x));
+                assert_eq!(x < y, x <= y);
+                assert_eq!(x > y, y < x);
+                for &z in &ptrs {
+                    if x < y && y < z {
+                        assert!(x < z);
+                    }
+                }
+            }
+        }
+    }
+    let eq = end == &*a as *const u32 as *const u8;
+    for _ in 0..10 {
+        assert_eq!(end == &*a as *const u32 as *const u8, eq);
+        assert_eq!(end != &*a as *const u32 as *const u8, !eq);
+    }
+}
@@ -0,0 +1,43 @@
+// compile-flags: -Zmiri-recursive-validation=full
+// Values in constant memory are only validated once; this makes sure that reusing those results
+// works for tables of references, cycles of references and references to mutable memory.
+
+use std::cell::Cell;
+
+static TABLE: &[(&str, u32)] = &[("one", 1), ("two", 2), ("three", 3), ("four", 4)];
+
+struct Node {
+    next: &'static Node,
+    value: u8,
+}
+static A: Node = Node { next: &B, value: 1 };
+static B: Node = Node { next: &A, value: 2 };
+
+struct Shared {
+    counter: &'static Counter,
+}
+struct Counter(Cell<u8>);
+unsafe impl Sync for Counter {}
+static COUNTER: Counter = Counter(Cell::new(0));
+static SHARED: Shared = Shared { counter: &COUNTER };
+
+fn main() {
+    let mut sum = 0;
+    for _ in 0..10 {
+        let table = TABLE;
+        sum += table.iter().map(|&(_, n)| n).sum::<u32>();
+    }
+    assert_eq!(sum, 100);
+
+    let mut node = &A;
+    for _ in 0..5 {
+        node = node.next;
+    }
+    assert_eq!(node.value, 2);
+
+    for i in 0..3 {
+        let shared = &SHARED;
+        assert_eq!(shared.counter.0.get(), i);
+        shared.counter.0.set(i + 1);
+    }
+}
@@ -0,0 +1,29 @@
+// ignore-windows: No libc on Windows
+
+#![feature(rustc_private)]
+extern crate libc;
+
+use std::{mem, ptr};
+
+extern "C" fn handler(_signum: libc::c_int) {
+    unreachable!("Miri never delivers signals");
+}
+
+fn main() {
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = handler as libc::sighandler_t;
+        let mut old: libc::sigaction = mem::zeroed();
+        old.sa_flags = 42;
+        assert_eq!(libc::sigaction(libc::SIGINT, &action, &mut old), 0);
+        // The previous action is the default one.
+        assert_eq!(old.sa_sigaction, libc::SIG_DFL);
+        assert_eq!(old.sa_flags, 0);
+
+        let mut set: libc::sigset_t = mem::zeroed();
+        assert_eq!(libc::sigemptyset(&mut set), 0);
+        let mut old_set: libc::sigset_t = mem::zeroed();
+        assert_eq!(libc::pthread_sigmask(libc::SIG_SETMASK, &set, &mut old_set), 0);
+        assert_eq!(libc::sigprocmask(libc::SIG_BLOCK, &set, ptr::null_mut()), 0);
+    }
+}
@@ -0,0 +1,61 @@
+#![feature(repr_simd, platform_intrinsics)]
+#![allow(non_camel_case_types)]
+
+#[repr(simd)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct i32x4(i32, i32, i32, i32);
+
+#[repr(simd)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct f32x4(f32, f32, f32, f32);
+
+extern "platform-intrinsic" {
+    fn simd_add<T>(x: T, y: T) -> T;
+    fn simd_mul<T>(x: T, y: T) -> T;
+    fn simd_shl<T>(x: T, y: T) -> T;
+    fn simd_xor<T>(x: T, y: T) -> T;
+    fn simd_eq<T, U>(x: T, y: T) -> U;
+    fn simd_lt<T, U>(x: T, y: T) -> U;
+    fn simd_select<M, T>(m: M, a: T, b: T) -> T;
+    fn simd_shuffle4<T, U>(x: T, y: T, idx: [u32; 4]) -> U;
+    fn simd_bitmask<T, U>(x: T) -> U;
+    fn simd_reduce_add_unordered<T, U>(x: T) -> U;
+    fn simd_reduce_add_ordered<T, U>(x: T, acc: U) -> U;
+    fn simd_reduce_max<T, U>(x: T) -> U;
+    fn simd_reduce_any<T>(x: T) -> bool;
+    fn simd_reduce_all<T>(x: T) -> bool;
+}
+
+fn main() {
+    let a = i32x4(1, 2, 3, 4);
+    let b = i32x4(10, 2, 30, i32::max_value());
+    unsafe {
+        assert_eq!(simd_add(a, a), i32x4(2, 4, 6, 8));
+        // Arithmetic wraps.
+        assert_eq!(simd_add(a, b), i32x4(11, 4, 33, i32::min_value() + 3));
+        assert_eq!(simd_mul(a, a), i32x4(1, 4, 9, 16));
+        assert_eq!(simd_shl(a, i32x4(1, 2, 3, 4)), i32x4(2, 8, 24, 64));
+        assert_eq!(simd_xor(a, a), i32x4(0, 0, 0, 0));
+
+        let eq: i32x4 = simd_eq(a, b);
+        assert_eq!(eq, i32x4(0, -1, 0, 0));
+        let lt: i32x4 = simd_lt(a, b);
+        assert_eq!(lt, i32x4(-1, 0, -1, -1));
+        assert_eq!(simd_select(eq, a, b), i32x4(10, 2, 30, i32::max_value()));
+        assert_eq!(simd_select(lt, a, b), i32x4(1, 2, 3, 4));
+        assert_eq!(simd_bitmask::<_, u8>(lt), 0b1101);
+
+        let shuffled: i32x4 = simd_shuffle4(a, b, [0, 4, 7, 3]);
+        assert_eq!(shuffled, i32x4(1, 10, i32::max_value(), 4));
+
+        assert_eq!(simd_reduce_add_unordered::<_, i32>(a), 10);
+        assert_eq!(simd_reduce_max::<_, i32>(b), i32::max_value());
+        assert!(simd_reduce_any(eq));
+        assert!(!simd_reduce_all(eq));
+        assert!(simd_reduce_all(i32x4(-1, -1, -1, -1)));
+
+        let f = f32x4(1.0, 2.0, 3.0, 4.0);
+        assert_eq!(simd_add(f, f), f32x4(2.0, 4.0, 6.0, 8.0));
+        assert_eq!(simd_reduce_add_ordered::<_, f32>(f, 0.5), 10.5);
+    }
+}
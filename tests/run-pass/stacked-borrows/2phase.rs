@@ -56,6 +56,38 @@ fn two_phase_overlapping2() {
     x.add_assign(x + *l);
 }
 
+// Reads through other pointers do not invalidate a reserved borrow, and reading through the
+// borrowed place itself again is also fine.
+fn two_phase_reserved_reads() {
+    let mut v = vec![1];
+    let shr = &v;
+    let raw = &v as *const Vec<usize>;
+    v.push(shr.len() + unsafe { (*raw).len() } + v[0] + v.len());
+    assert_eq!(v, [1, 4]);
+}
+
+// A reservation inside the arguments of another one.
+fn two_phase_nested() {
+    let mut v = vec![];
+    v.push({
+        let mut w = vec![0];
+        w.push(w.len() + v.len());
+        w.len()
+    });
+    assert_eq!(v, [2]);
+}
+
+// After the activation, the borrow is unique again: it can be reborrowed and written through.
+fn two_phase_activated() {
+    let mut x = vec![0];
+    for _ in 0..3 {
+        x.push(x.len());
+    }
+    let r = &mut x;
+    r.push(r.len());
+    assert_eq!(x, [0, 1, 2, 3, 4]);
+}
+
 fn with_interior_mutability() {
     use std::cell::Cell;
 
@@ -86,4 +118,7 @@ fn main() {
     with_interior_mutability();
     two_phase_overlapping1();
     two_phase_overlapping2();
+    two_phase_reserved_reads();
+    two_phase_nested();
+    two_phase_activated();
 }
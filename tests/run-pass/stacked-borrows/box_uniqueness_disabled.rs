@@ -0,0 +1,30 @@
+// compile-flags: -Zmiri-disable-box-uniqueness
+// Raw pointers derived from a box stay usable when the box is moved or written to.
+
+use std::ptr;
+
+static mut LEAK: *mut i32 = ptr::null_mut();
+
+fn write_through_box(mut b: Box<i32>) -> Box<i32> {
+    *b = 5;
+    b
+}
+
+fn main() {
+    let mut b = Box::new(0);
+    let raw = &mut *b as *mut i32;
+    unsafe { LEAK = raw };
+    // Moving the box does not invalidate `raw`.
+    let b = write_through_box(b);
+    unsafe {
+        assert_eq!(*raw, 5);
+        *LEAK = 7;
+    }
+    assert_eq!(*b, 7);
+
+    // References are still checked: this `&mut` is unique as usual.
+    let mut x = 0;
+    let r = &mut x;
+    *r = 1;
+    assert_eq!(x, 1);
+}
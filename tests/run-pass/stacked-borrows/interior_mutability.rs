@@ -7,6 +7,8 @@ fn main() {
     aliasing_frz_and_shr();
     into_interior_mutability();
     unsafe_cell_2phase();
+    raw_round_trip_through_cell();
+    cells_in_enum_variants();
 }
 
 fn aliasing_mut_and_shr() {
@@ -66,3 +68,59 @@ fn unsafe_cell_2phase() { unsafe {
     (*x.get()).push(0);
     let _val = (*x2.get()).get(0);
 } }
+
+// Raw pointers to the `UnsafeCell`s of a value can be turned back into shared references and
+// written through, while the other bytes stay frozen.
+fn raw_round_trip_through_cell() {
+    struct Mixed {
+        frozen: u32,
+        cell: Cell<u32>,
+        cells: [Cell<u8>; 4],
+        mixed: [(u8, UnsafeCell<u8>); 2],
+    }
+
+    let m = Mixed {
+        frozen: 1,
+        cell: Cell::new(0),
+        cells: [Cell::new(0), Cell::new(0), Cell::new(0), Cell::new(0)],
+        mixed: [(0, UnsafeCell::new(0)), (0, UnsafeCell::new(0))],
+    };
+    let frozen = &m.frozen;
+    let ptr = &m as *const Mixed;
+    unsafe {
+        let shr = &*ptr;
+        shr.cell.set(2);
+        (*(&shr.cells[2] as *const Cell<u8>)).set(3);
+        *(*ptr).mixed[1].1.get() = 4;
+    }
+    assert_eq!(*frozen, 1);
+    assert_eq!(m.cell.get(), 2);
+    assert_eq!(m.cells[2].get(), 3);
+    assert_eq!(unsafe { *m.mixed[1].1.get() }, 4);
+}
+
+// The `UnsafeCell`s of all variants of an enum are found without reading its discriminant.
+fn cells_in_enum_variants() {
+    enum E {
+        A(u16, Cell<u16>),
+        B(Cell<u32>),
+        C(u64),
+    }
+
+    let e = E::A(1, Cell::new(0));
+    let ptr = &e as *const E;
+    if let E::A(frozen, cell) = unsafe { &*ptr } {
+        unsafe { (*(cell as *const Cell<u16>)).set(5) };
+        assert_eq!(*frozen, 1);
+    }
+    match &e {
+        E::A(_, cell) => assert_eq!(cell.get(), 5),
+        _ => unreachable!(),
+    }
+    let e = E::B(Cell::new(0));
+    if let E::B(cell) = &e {
+        cell.set(6);
+        assert_eq!(cell.get(), 6);
+    }
+    let _unused = E::C(0);
+}
@@ -0,0 +1,53 @@
+// ignore-windows: TCP sockets are only emulated on Unix targets
+// ignore-macos: `std` uses `ioctl` to set up sockets on macOS
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+
+fn main() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    assert!(addr.ip().is_loopback());
+    assert_ne!(addr.port(), 0);
+
+    // The port is taken now.
+    assert_eq!(TcpListener::bind(addr).unwrap_err().kind(), ErrorKind::AddrInUse);
+
+    let mut client = TcpStream::connect(addr).unwrap();
+    let (mut server, peer) = listener.accept().unwrap();
+    assert_eq!(peer, client.local_addr().unwrap());
+    assert_eq!(server.local_addr().unwrap(), addr);
+    assert_eq!(client.peer_addr().unwrap(), addr);
+    client.set_nodelay(true).unwrap();
+
+    client.write_all(b"hello").unwrap();
+    let mut buf = [0; 5];
+    server.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hello");
+
+    server.write_all(b"world!").unwrap();
+    let mut buf = [0; 3];
+    client.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"wor");
+    client.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"ld!");
+
+    // Shutting down writing signals the end of the stream to the peer.
+    client.shutdown(Shutdown::Write).unwrap();
+    assert_eq!(server.read(&mut buf).unwrap(), 0);
+    assert_eq!(client.write(b"x").unwrap_err().kind(), ErrorKind::BrokenPipe);
+
+    // Closing the server end has the same effect.
+    drop(server);
+    assert_eq!(client.read(&mut buf).unwrap(), 0);
+    drop(client);
+
+    // Nobody listens here anymore.
+    drop(listener);
+    assert_eq!(TcpStream::connect(addr).unwrap_err().kind(), ErrorKind::ConnectionRefused);
+
+    // The port can be used again.
+    let listener = TcpListener::bind(addr).unwrap();
+    let _client = TcpStream::connect(addr).unwrap();
+    listener.accept().unwrap();
+}
@@ -0,0 +1,20 @@
+// ignore-windows: TLS destructors are not run on Windows
+// ignore-macos: TLS destructors are not run on macOS
+
+struct Printer;
+
+impl Drop for Printer {
+    fn drop(&mut self) {
+        // Printing uses the standard library's own thread-locals, which may already be destroyed.
+        println!("dropping the printer");
+    }
+}
+
+thread_local! {
+    static PRINTER: Printer = Printer;
+}
+
+fn main() {
+    println!("hello");
+    PRINTER.with(|_| {});
+}
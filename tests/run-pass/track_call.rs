@@ -0,0 +1,8 @@
+// compile-flags: -Zmiri-track-call=std::ptr::read
+// Tracking calls only reports them; the program runs as usual.
+
+fn main() {
+    let x = (1u32, 2u8);
+    let y = unsafe { std::ptr::read(&x) };
+    assert_eq!(x, y);
+}
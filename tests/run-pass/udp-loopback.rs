@@ -0,0 +1,46 @@
+// ignore-windows: UDP sockets are only emulated on Unix targets
+// ignore-macos: `std` uses `ioctl` to set up sockets on macOS
+
+use std::io::ErrorKind;
+use std::net::UdpSocket;
+
+fn main() {
+    let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let a_addr = a.local_addr().unwrap();
+    let b_addr = b.local_addr().unwrap();
+    assert_ne!(a_addr, b_addr);
+
+    // The port is taken now.
+    assert_eq!(UdpSocket::bind(a_addr).unwrap_err().kind(), ErrorKind::AddrInUse);
+
+    assert_eq!(a.send_to(b"hello", b_addr).unwrap(), 5);
+    assert_eq!(a.send_to(b"world!", b_addr).unwrap(), 6);
+    let mut buf = [0; 16];
+    assert_eq!(b.recv_from(&mut buf).unwrap(), (5, a_addr));
+    assert_eq!(&buf[..5], b"hello");
+    // Whatever does not fit into the buffer is lost.
+    let mut small = [0; 3];
+    assert_eq!(b.recv_from(&mut small).unwrap(), (3, a_addr));
+    assert_eq!(&small, b"wor");
+
+    // Connected sockets have a default destination.
+    assert_eq!(b.peer_addr().unwrap_err().kind(), ErrorKind::NotConnected);
+    b.connect(a_addr).unwrap();
+    assert_eq!(b.peer_addr().unwrap(), a_addr);
+    assert_eq!(b.send(b"pong").unwrap(), 4);
+    assert_eq!(a.recv(&mut buf).unwrap(), 4);
+    assert_eq!(&buf[..4], b"pong");
+
+    // Sockets bound to the unspecified address send from the loopback address.
+    let c = UdpSocket::bind("0.0.0.0:0").unwrap();
+    c.send_to(b"!", a_addr).unwrap();
+    let (len, c_addr) = a.recv_from(&mut buf).unwrap();
+    assert_eq!(len, 1);
+    assert!(c_addr.ip().is_loopback());
+    assert_eq!(c_addr.port(), c.local_addr().unwrap().port());
+
+    // Datagrams sent to a port that nobody is bound to are lost.
+    drop(b);
+    a.send_to(b"lost", b_addr).unwrap();
+}
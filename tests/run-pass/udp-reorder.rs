@@ -0,0 +1,24 @@
+// ignore-windows: UDP sockets are only emulated on Unix targets
+// ignore-macos: `std` uses `ioctl` to set up sockets on macOS
+// compile-flags: -Zmiri-udp-reorder-rate=1 -Zmiri-seed=0
+
+use std::net::UdpSocket;
+
+fn main() {
+    let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let b_addr = b.local_addr().unwrap();
+    for i in 0..8u8 {
+        a.send_to(&[i], b_addr).unwrap();
+    }
+
+    // Every datagram arrives exactly once, but not necessarily in order.
+    let mut received = Vec::new();
+    for _ in 0..8 {
+        let mut buf = [0; 1];
+        b.recv(&mut buf).unwrap();
+        received.push(buf[0]);
+    }
+    received.sort();
+    assert_eq!(received, (0..8).collect::<Vec<_>>());
+}
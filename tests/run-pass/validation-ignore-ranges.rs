@@ -0,0 +1,17 @@
+// compile-flags: -Zmiri-ignore-validity=ranges
+
+use std::mem::transmute;
+
+fn pass(b: bool) -> bool {
+    b
+}
+
+fn main() {
+    // Invalid `bool`s and `char`s may be copied around, but not used.
+    let b = unsafe { transmute::<u8, bool>(2) };
+    let b = pass(b);
+    assert_eq!(unsafe { transmute::<bool, u8>(b) }, 2);
+    let c = unsafe { transmute::<u32, char>(0xD800) };
+    let c2 = c;
+    assert_eq!(unsafe { transmute::<char, u32>(c2) }, 0xD800);
+}
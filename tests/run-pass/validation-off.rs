@@ -0,0 +1,16 @@
+// compile-flags: -Zmiri-recursive-validation=off
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+enum Foo {
+    A,
+}
+
+fn main() {
+    // Without validation, invalid values can be copied around.
+    let b = unsafe { std::mem::transmute::<u8, bool>(2) };
+    let f = unsafe { std::mem::transmute::<u8, Foo>(42) };
+    let (b2, f2) = (b, f);
+    assert_eq!(unsafe { std::mem::transmute::<bool, u8>(b2) }, 2);
+    assert_eq!(unsafe { std::mem::transmute::<Foo, u8>(f2) }, 42);
+}
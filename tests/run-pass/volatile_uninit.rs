@@ -0,0 +1,17 @@
+use std::mem::MaybeUninit;
+use std::ptr;
+
+fn main() {
+    let mut x = 0u32;
+    unsafe {
+        ptr::write_volatile(&mut x, 42);
+        assert_eq!(ptr::read_volatile(&x), 42);
+    }
+
+    // Volatile accesses copy (partially) uninitialized data like any other copy.
+    let uninit = MaybeUninit::<(u8, u32)>::uninit();
+    let mut copy = MaybeUninit::<(u8, u32)>::uninit();
+    unsafe {
+        ptr::write_volatile(copy.as_mut_ptr(), ptr::read_volatile(uninit.as_ptr()));
+    }
+}
@@ -0,0 +1,8 @@
+// compile-flags: -Zmiri-deny=warnings -Zmiri-allow=int2ptr
+
+fn main() {
+    // Later flags override earlier ones, so this does not stop the program.
+    let x = 42u8;
+    let addr = &x as *const u8 as usize;
+    assert_eq!(unsafe { *(addr as *const u8) }, 42);
+}
@@ -0,0 +1,35 @@
+// ignore-linux: Uses wasm-only APIs
+// ignore-macos: Uses wasm-only APIs
+// ignore-windows: Uses wasm-only APIs
+// ignore-freebsd: Uses wasm-only APIs
+// ignore-android: Uses wasm-only APIs
+
+use std::arch::wasm32;
+
+const PAGE_SIZE: usize = 64 * 1024;
+
+fn main() {
+    // Growing by zero pages just reports the current size.
+    let pages = wasm32::memory_size(0);
+    assert_eq!(wasm32::memory_grow(0, 0), pages);
+
+    // New pages start where the memory used to end, and are zeroed.
+    let prev = wasm32::memory_grow(0, 2);
+    assert!(prev >= pages);
+    assert_eq!(wasm32::memory_size(0), prev + 2);
+    let base = (prev * PAGE_SIZE) as *mut u8;
+    unsafe {
+        assert_eq!(*base, 0);
+        assert_eq!(*base.add(2 * PAGE_SIZE - 1), 0);
+        *base = 42;
+        *base.add(2 * PAGE_SIZE - 1) = 1;
+        assert_eq!(*base, 42);
+    }
+
+    // Growing beyond 4 GiB fails.
+    assert_eq!(wasm32::memory_grow(0, 64 * 1024), usize::max_value());
+
+    // The standard library still works.
+    let v: Vec<i32> = (0..10).collect();
+    assert_eq!(v.iter().sum::<i32>(), 45);
+}
@@ -0,0 +1,82 @@
+// Wide pointers whose metadata is valid, but not created in the most obvious way.
+use std::any::Any;
+use std::fmt::{self, Debug};
+
+trait Named: Debug {
+    fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn sized_only(self) -> Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+}
+
+impl Named for u8 {}
+
+struct Tail<T: ?Sized> {
+    len: usize,
+    data: T,
+}
+
+const DEBUG: &dyn Debug = &7u16;
+
+fn vtable_in_constant() {
+    assert_eq!(format!("{:?}", DEBUG), "7");
+}
+
+fn subtrait() {
+    let named: Box<dyn Named> = Box::new(3u8);
+    assert_eq!(named.name(), "3");
+    assert_eq!(format!("{:?}", named), "3");
+}
+
+fn closures() {
+    let mut count = 0;
+    {
+        let mut f: Box<dyn FnMut() + '_> = Box::new(|| count += 1);
+        f();
+        f();
+    }
+    assert_eq!(count, 2);
+    let g: &dyn Fn(u32) -> u32 = &|x| x + 1;
+    assert_eq!(g(1), 2);
+}
+
+fn auto_traits_only() {
+    let x: &(dyn Send + Sync) = &5u64;
+    let _y = x;
+    let any: Box<dyn Any + Send> = Box::new(5i32);
+    assert_eq!(any.downcast_ref::<i32>(), Some(&5));
+}
+
+fn slice_tail() {
+    let tail: &Tail<[u8]> = &Tail { len: 3, data: [1, 2, 3] };
+    assert_eq!(tail.data.len(), tail.len);
+    let s: &str = "hello";
+    assert_eq!(s.len(), 5);
+}
+
+struct Formatted<'a>(&'a dyn fmt::Display);
+
+impl fmt::Display for Formatted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}]", self.0)
+    }
+}
+
+fn nested() {
+    assert_eq!(Formatted(&Formatted(&1)).to_string(), "[[1]]");
+}
+
+fn main() {
+    vtable_in_constant();
+    subtrait();
+    closures();
+    auto_traits_only();
+    slice_tail();
+    nested();
+}
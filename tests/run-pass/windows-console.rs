@@ -0,0 +1,54 @@
+// ignore-linux: Uses Windows-only APIs
+// ignore-macos: Uses Windows-only APIs
+
+use std::ptr;
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetStdHandle(which: u32) -> isize;
+    fn WriteFile(
+        handle: isize,
+        buf: *const u8,
+        len: u32,
+        written: *mut u32,
+        overlapped: *mut u8,
+    ) -> i32;
+    fn WriteConsoleW(
+        handle: isize,
+        buf: *const u16,
+        len: u32,
+        written: *mut u32,
+        reserved: *mut u8,
+    ) -> i32;
+    fn GetLastError() -> u32;
+}
+
+const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+const ERROR_INVALID_HANDLE: u32 = 6;
+
+fn main() {
+    unsafe {
+        let stdout = GetStdHandle(STD_OUTPUT_HANDLE);
+
+        let mut written = 0;
+        let text = b"written with WriteFile\n";
+        let len = text.len() as u32;
+        assert_eq!(WriteFile(stdout, text.as_ptr(), len, &mut written, ptr::null_mut()), 1);
+        assert_eq!(written as usize, text.len());
+
+        let text: Vec<u16> =
+            "written with WriteConsoleW: \u{e9}\u{1f600}\n".encode_utf16().collect();
+        let len = text.len() as u32;
+        assert_eq!(WriteConsoleW(stdout, text.as_ptr(), len, &mut written, ptr::null_mut()), 1);
+        assert_eq!(written as usize, text.len());
+        // The number of characters written is optional.
+        assert_eq!(WriteConsoleW(stdout, text.as_ptr(), 0, ptr::null_mut(), ptr::null_mut()), 1);
+
+        // There are no other handles.
+        assert_eq!(GetStdHandle(42), -1);
+        assert_eq!(GetLastError(), ERROR_INVALID_HANDLE);
+        assert_eq!(WriteFile(42, text.as_ptr().cast(), 0, &mut written, ptr::null_mut()), 0);
+        assert_eq!(GetLastError(), ERROR_INVALID_HANDLE);
+    }
+    println!("written with println!");
+}
@@ -0,0 +1,25 @@
+// ignore-linux: Uses Windows-only APIs
+// ignore-macos: Uses Windows-only APIs
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetCurrentThread() -> isize;
+    fn GetCurrentThreadId() -> u32;
+    fn WaitForSingleObject(handle: isize, timeout: u32) -> u32;
+    fn CloseHandle(handle: isize) -> i32;
+}
+
+const WAIT_TIMEOUT: u32 = 0x00000102;
+
+fn main() {
+    unsafe {
+        assert_eq!(GetCurrentThreadId(), GetCurrentThreadId());
+        assert_ne!(GetCurrentThreadId(), 0);
+
+        let thread = GetCurrentThread();
+        // The current thread does not terminate while we wait for it.
+        assert_eq!(WaitForSingleObject(thread, 0), WAIT_TIMEOUT);
+        // Closing the pseudo-handle succeeds, but does nothing.
+        assert_eq!(CloseHandle(thread), 1);
+    }
+}
@@ -0,0 +1,69 @@
+// ignore-linux: Uses Windows-only APIs
+// ignore-macos: Uses Windows-only APIs
+// compile-flags: -Zmiri-disable-isolation
+
+use std::mem;
+
+#[repr(C)]
+#[derive(Default)]
+struct FILETIME {
+    low: u32,
+    high: u32,
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetModuleHandleW(name: *const u16) -> isize;
+    fn GetProcAddress(module: isize, name: *const u8) -> *const u8;
+    fn GetSystemTimeAsFileTime(time: *mut FILETIME);
+    fn GetLastError() -> u32;
+}
+
+const ERROR_PROC_NOT_FOUND: u32 = 127;
+const ERROR_TIMEOUT: u32 = 1460;
+
+type GetSystemTimePreciseAsFileTime = unsafe extern "system" fn(*mut FILETIME);
+type WaitOnAddress = unsafe extern "system" fn(*const u8, *const u8, usize, u32) -> i32;
+type WakeByAddressSingle = unsafe extern "system" fn(*const u8);
+
+unsafe fn lookup(module: isize, name: &str) -> *const u8 {
+    GetProcAddress(module, name.as_ptr())
+}
+
+fn main() {
+    unsafe {
+        let name: Vec<u16> = "kernel32\0".encode_utf16().collect();
+        let kernel32 = GetModuleHandleW(name.as_ptr());
+        assert_ne!(kernel32, 0);
+
+        assert!(lookup(kernel32, "NoSuchFunction\0").is_null());
+        assert_eq!(GetLastError(), ERROR_PROC_NOT_FOUND);
+
+        let precise = lookup(kernel32, "GetSystemTimePreciseAsFileTime\0");
+        assert!(!precise.is_null());
+        let precise: GetSystemTimePreciseAsFileTime = mem::transmute(precise);
+        let mut before = FILETIME::default();
+        GetSystemTimeAsFileTime(&mut before);
+        let mut after = FILETIME::default();
+        precise(&mut after);
+        let ticks = |t: &FILETIME| u64::from(t.high) << 32 | u64::from(t.low);
+        assert!(ticks(&before) <= ticks(&after));
+        // Later than 2020.
+        assert!(ticks(&after) > 132_223_104_000_000_000);
+
+        let synch = GetModuleHandleW(
+            "api-ms-win-core-synch-l1-2-0\0".encode_utf16().collect::<Vec<u16>>().as_ptr(),
+        );
+        let wait: WaitOnAddress = mem::transmute(lookup(synch, "WaitOnAddress\0"));
+        let wake: WakeByAddressSingle = mem::transmute(lookup(synch, "WakeByAddressSingle\0"));
+        let value = 1u32;
+        let other = 2u32;
+        let value_ptr = &value as *const u32 as *const u8;
+        // The value is not the one we would wait for.
+        assert_eq!(wait(value_ptr, &other as *const u32 as *const u8, 4, 0xFFFFFFFF), 1);
+        // The value does not change before the timeout.
+        assert_eq!(wait(value_ptr, value_ptr, 4, 10), 0);
+        assert_eq!(GetLastError(), ERROR_TIMEOUT);
+        wake(value_ptr);
+    }
+}
@@ -0,0 +1,50 @@
+// ignore-linux: Uses Windows-only APIs
+// ignore-macos: Uses Windows-only APIs
+
+use std::ptr;
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetProcessHeap() -> isize;
+    fn HeapAlloc(heap: isize, flags: u32, size: usize) -> *mut u8;
+    fn HeapReAlloc(heap: isize, flags: u32, ptr: *mut u8, size: usize) -> *mut u8;
+    fn HeapFree(heap: isize, flags: u32, ptr: *mut u8) -> i32;
+}
+
+const HEAP_ZERO_MEMORY: u32 = 0x00000008;
+const HEAP_REALLOC_IN_PLACE_ONLY: u32 = 0x00000010;
+
+fn main() {
+    unsafe {
+        let heap = GetProcessHeap();
+
+        // Zero-sized allocations are unique, aligned pointers.
+        let a = HeapAlloc(heap, 0, 0);
+        let b = HeapAlloc(heap, 0, 0);
+        assert!(!a.is_null() && !b.is_null());
+        assert_ne!(a, b);
+        assert_eq!(a as usize % 8, 0);
+        assert_eq!(HeapFree(heap, 0, a), 1);
+        assert_eq!(HeapFree(heap, 0, b), 1);
+        // Freeing `NULL` succeeds.
+        assert_eq!(HeapFree(heap, 0, ptr::null_mut()), 1);
+
+        let p = HeapAlloc(heap, HEAP_ZERO_MEMORY, 4);
+        assert_eq!(*p.cast::<[u8; 4]>(), [0; 4]);
+        p.write_bytes(1, 4);
+
+        // Only the added bytes are zeroed.
+        let p = HeapReAlloc(heap, HEAP_ZERO_MEMORY, p, 8);
+        assert_eq!(*p.cast::<[u8; 8]>(), [1, 1, 1, 1, 0, 0, 0, 0]);
+
+        // Allocations cannot grow in place.
+        assert!(HeapReAlloc(heap, HEAP_REALLOC_IN_PLACE_ONLY, p, 16).is_null());
+        let p = HeapReAlloc(heap, HEAP_REALLOC_IN_PLACE_ONLY, p, 2);
+        assert_eq!(*p.cast::<[u8; 2]>(), [1, 1]);
+
+        // Shrinking to zero keeps a block around.
+        let p = HeapReAlloc(heap, 0, p, 0);
+        assert!(!p.is_null());
+        assert_eq!(HeapFree(heap, 0, p), 1);
+    }
+}
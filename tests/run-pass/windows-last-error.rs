@@ -0,0 +1,29 @@
+// ignore-linux: Uses Windows-only APIs
+// ignore-macos: Uses Windows-only APIs
+// compile-flags: -Zmiri-disable-isolation
+
+use std::io::ErrorKind;
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn SetCurrentDirectoryW(path: *const u16) -> i32;
+    fn GetLastError() -> u32;
+    fn SetLastError(code: u32);
+}
+
+const ERROR_FILE_NOT_FOUND: u32 = 2;
+
+fn main() {
+    let path: Vec<u16> = "this-directory-does-not-exist\0".encode_utf16().collect();
+    unsafe {
+        SetLastError(0);
+        assert_eq!(SetCurrentDirectoryW(path.as_ptr()), 0);
+        // The host error is turned into the Win32 error code.
+        assert_eq!(GetLastError(), ERROR_FILE_NOT_FOUND);
+    }
+
+    // `std` turns the code back into the same kind.
+    let err = std::env::set_current_dir("this-directory-does-not-exist").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert_eq!(err.raw_os_error(), Some(ERROR_FILE_NOT_FOUND as i32));
+}
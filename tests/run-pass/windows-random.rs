@@ -0,0 +1,27 @@
+// ignore-linux: Uses Windows-only APIs
+// ignore-macos: Uses Windows-only APIs
+
+#[link(name = "bcrypt")]
+extern "system" {
+    fn BCryptGenRandom(
+        algorithm: *mut u8,
+        buffer: *mut u8,
+        buffer_len: u32,
+        flags: u32,
+    ) -> i32;
+}
+
+const BCRYPT_USE_SYSTEM_PREFERRED_RNG: u32 = 2;
+
+fn main() {
+    let mut buf = [0u8; 16];
+    let status = unsafe {
+        BCryptGenRandom(
+            std::ptr::null_mut(),
+            buf.as_mut_ptr(),
+            buf.len() as u32,
+            BCRYPT_USE_SYSTEM_PREFERRED_RNG,
+        )
+    };
+    assert_eq!(status, 0);
+}
@@ -0,0 +1,50 @@
+// ignore-linux: Uses Windows-only APIs
+// ignore-macos: Uses Windows-only APIs
+
+use std::mem;
+use std::ptr;
+
+#[repr(C)]
+struct SYSTEM_INFO {
+    processor_architecture: u16,
+    reserved: u16,
+    page_size: u32,
+    minimum_application_address: *mut u8,
+    maximum_application_address: *mut u8,
+    active_processor_mask: usize,
+    number_of_processors: u32,
+    processor_type: u32,
+    allocation_granularity: u32,
+    processor_level: u16,
+    processor_revision: u16,
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetSystemInfo(info: *mut SYSTEM_INFO);
+    fn GetModuleHandleW(name: *const u16) -> isize;
+    fn GetCommandLineW() -> *const u16;
+    fn ExitProcess(code: u32) -> !;
+}
+
+fn main() {
+    unsafe {
+        let mut info: SYSTEM_INFO = mem::zeroed();
+        GetSystemInfo(&mut info);
+        assert_eq!(info.page_size, 4096);
+        assert_eq!(info.number_of_processors, 1);
+        assert_eq!(info.active_processor_mask, 1);
+        assert_eq!(info.allocation_granularity, 64 * 1024);
+
+        // The handle of the executable itself.
+        assert_ne!(GetModuleHandleW(ptr::null()), 0);
+
+        // The command line starts with the program name, just like `args`.
+        let cmd_line = GetCommandLineW();
+        let len = (0..).take_while(|&i| *cmd_line.add(i) != 0).count();
+        let cmd_line = String::from_utf16(std::slice::from_raw_parts(cmd_line, len)).unwrap();
+        assert!(cmd_line.starts_with(&std::env::args().next().unwrap()));
+
+        ExitProcess(0);
+    }
+}
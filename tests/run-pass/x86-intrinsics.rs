@@ -0,0 +1,34 @@
+#[cfg(target_arch = "x86_64")]
+fn test_sse2() {
+    use std::arch::x86_64::*;
+
+    let bytes: [u8; 17] = *b"hello, miri world";
+    unsafe {
+        // Unaligned load, compare and movemask, as used by `memchr`-style code.
+        let haystack = _mm_loadu_si128(bytes[1..].as_ptr() as *const __m128i);
+        let needle = _mm_set1_epi8(b'l' as i8);
+        let eq = _mm_cmpeq_epi8(haystack, needle);
+        assert_eq!(_mm_movemask_epi8(eq), 0b0100_0000_0000_0110);
+
+        let mut out = [0u8; 17];
+        _mm_storeu_si128(out[1..].as_mut_ptr() as *mut __m128i, haystack);
+        assert_eq!(&out[1..], &bytes[1..]);
+
+        let max = _mm_max_epu8(haystack, needle);
+        let mut out = [0u8; 16];
+        _mm_storeu_si128(out.as_mut_ptr() as *mut __m128i, max);
+        assert_eq!(&out, b"lllollmlrllworll");
+
+        let shifted = _mm_srli_epi16(_mm_set1_epi16(0x1234), 4);
+        assert_eq!(_mm_extract_epi16(shifted, 0), 0x0123);
+        assert_eq!(_mm_extract_epi16(_mm_srli_epi16(shifted, 16), 3), 0);
+
+        let cmp = _mm_cmplt_pd(_mm_set_pd(1.0, 2.0), _mm_set_pd(3.0, 0.0));
+        assert_eq!(_mm_movemask_pd(cmp), 0b10);
+    }
+}
+
+fn main() {
+    #[cfg(target_arch = "x86_64")]
+    test_sse2();
+}
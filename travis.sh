@@ -4,6 +4,7 @@ set -euo pipefail
 # Determine configuration
 if [ "$TRAVIS_OS_NAME" == linux ]; then
   FOREIGN_TARGET=i686-unknown-linux-gnu
+  BIG_ENDIAN_TARGET=s390x-unknown-linux-gnu
 fi
 export CARGO_EXTRA_FLAGS="--all-features"
 export RUSTC_EXTRA_FLAGS="-D warnings"
@@ -31,3 +32,10 @@ if [ -n "${FOREIGN_TARGET+exists}" ]; then
   MIRI_TEST_TARGET="$FOREIGN_TARGET" run_tests
   echo
 fi
+
+if [ -n "${BIG_ENDIAN_TARGET+exists}" ]; then
+  echo "Test big-endian architecture ($BIG_ENDIAN_TARGET)"
+  # Only the tests that are about byte order.
+  MIRI_TEST_TARGET="$BIG_ENDIAN_TARGET" ./miri test --locked -- endian
+  echo
+fi